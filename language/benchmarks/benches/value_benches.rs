@@ -0,0 +1,491 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use move_core_types::{
+    account_address::AccountAddress,
+    ident_str,
+    identifier::{IdentStr, Identifier, IdentifierPool},
+    language_storage::TypeTagCache,
+    move_resource::MoveStructType,
+    value::{
+        serialize_values, serialize_values_into, MoveFieldLayout, MoveStruct, MoveStructLayout,
+        MoveTypeLayout, MoveValue, SharedMoveValue, StructTagRegistry,
+    },
+};
+use serde::de::DeserializeSeed;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+//
+// MoveValue serialization benchmarks
+//
+
+/// Counts allocations and allocated bytes so the benchmarks can report how many allocations each
+/// serialization strategy makes, and how much memory values take.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// The change in heap memory over `f`, negative if `f` frees more than it allocates, along with
+/// its result
+fn resident_bytes<T>(f: impl FnOnce() -> T) -> (T, isize) {
+    let before = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let result = f();
+    let after = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    (result, after.wrapping_sub(before) as isize)
+}
+
+/// Whether to report the heap memory that values take, which criterion does not measure. Set
+/// `MOVE_BENCH_HEAP` to have the benchmarks print it to stderr before they run.
+fn report_heap() -> bool {
+    std::env::var_os("MOVE_BENCH_HEAP").is_some()
+}
+
+/// A block's worth of small values, roughly the shape of transaction arguments and events.
+fn values() -> Vec<MoveValue> {
+    (0..10_000u64)
+        .map(|i| {
+            MoveValue::Struct(MoveStruct::Runtime(vec![
+                MoveValue::U64(i),
+                MoveValue::Address(AccountAddress::ONE),
+                MoveValue::vector_u8(i.to_le_bytes().to_vec()),
+            ]))
+        })
+        .collect()
+}
+
+fn serialize<M: criterion::measurement::Measurement + 'static>(c: &mut Criterion<M>) {
+    let values = values();
+    let mut buf = vec![];
+    // warm up the buffer so that reuse is measured rather than its initial growth
+    serialize_values_into(&values, &mut buf).unwrap();
+
+    let separate = count_allocations(|| {
+        serialize_values(&values);
+    });
+    let reused = count_allocations(|| {
+        buf.clear();
+        serialize_values_into(&values, &mut buf).unwrap();
+    });
+    println!(
+        "allocations for {} values: serialize_values {}, serialize_values_into {}",
+        values.len(),
+        separate,
+        reused
+    );
+
+    c.bench_function("serialize_values", |b| b.iter(|| serialize_values(&values)));
+    c.bench_function("serialize_values_into", |b| {
+        b.iter(|| {
+            buf.clear();
+            serialize_values_into(&values, &mut buf).unwrap()
+        })
+    });
+}
+
+/// The resource `undecorated_vector` holds
+struct AccountResource;
+
+impl MoveStructType for AccountResource {
+    const MODULE_NAME: &'static IdentStr = ident_str!("Account");
+    const STRUCT_NAME: &'static IdentStr = ident_str!("Account");
+}
+
+/// A vector of 100k undecorated 5-field structs and the `WithTypes` layout to decorate it with,
+/// the shape of a resource table scanned by an indexer.
+fn undecorated_vector() -> (MoveValue, MoveTypeLayout) {
+    let fields = ["id", "owner", "balance", "frozen", "sequence_number"];
+    let layouts = vec![
+        MoveTypeLayout::U64,
+        MoveTypeLayout::Address,
+        MoveTypeLayout::U128,
+        MoveTypeLayout::Bool,
+        MoveTypeLayout::U64,
+    ];
+    let layout = MoveStructLayout::with_types(
+        AccountResource::struct_tag(),
+        fields
+            .iter()
+            .zip(layouts)
+            .map(|(name, layout)| MoveFieldLayout::new(Identifier::new(*name).unwrap(), layout))
+            .collect(),
+    );
+    let value = MoveValue::Vector(
+        (0..100_000u64)
+            .map(|i| {
+                MoveValue::Struct(MoveStruct::Runtime(vec![
+                    MoveValue::U64(i),
+                    MoveValue::Address(AccountAddress::ONE),
+                    MoveValue::U128(i.into()),
+                    MoveValue::Bool(i % 2 == 0),
+                    MoveValue::U64(0),
+                ]))
+            })
+            .collect(),
+    );
+    (
+        value,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(layout))),
+    )
+}
+
+fn decorate<M: criterion::measurement::Measurement + 'static>(c: &mut Criterion<M>) {
+    let (value, layout) = undecorated_vector();
+    let allocations = count_allocations(|| {
+        value.clone().decorate(&layout);
+    }) - count_allocations(|| {
+        let _ = value.clone();
+    });
+    println!("allocations for decorating 100000 structs: {}", allocations);
+
+    let cache = TypeTagCache::new(1024);
+    if report_heap() {
+        let (undecorated, undecorated_bytes) = resident_bytes(|| value.clone());
+        let (decorated, decorated_bytes) = resident_bytes(|| undecorated.decorate(&layout));
+        drop(decorated);
+        let (undecorated, _) = resident_bytes(|| value.clone());
+        let (interned, interned_bytes) = resident_bytes(|| {
+            undecorated.decorate_interned(&layout, &mut StructTagRegistry::new())
+        });
+        drop(interned);
+        let (undecorated, _) = resident_bytes(|| value.clone());
+        let (cached, cached_bytes) =
+            resident_bytes(|| undecorated.decorate_cached(&layout, Some(&cache)));
+        drop(cached);
+        eprintln!(
+            "heap bytes for 100000 structs: undecorated {}, added by decorate {}, added by \
+             decorate_interned {}, added by decorate_cached {}",
+            undecorated_bytes, decorated_bytes, interned_bytes, cached_bytes
+        );
+    }
+    let allocations = count_allocations(|| {
+        value.clone().decorate_cached(&layout, Some(&cache));
+    }) - count_allocations(|| {
+        let _ = value.clone();
+    });
+    println!(
+        "allocations for decorating 100000 structs through a warm tag cache: {}",
+        allocations
+    );
+
+    c.bench_function("decorate", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| value.decorate(&layout),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("decorate_interned", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| value.decorate_interned(&layout, &mut StructTagRegistry::new()),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("decorate_cached", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| value.decorate_cached(&layout, Some(&cache)),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+}
+
+/// The field names of the structs of `hand_built_vector`
+const COIN_STORE_FIELDS: [&str; 8] = [
+    "value",
+    "coin",
+    "balance",
+    "frozen",
+    "deposit_events",
+    "withdraw_events",
+    "sequence_number",
+    "owner",
+];
+
+/// 100k 8-field structs whose field names were each created on their own, as by code building
+/// values by hand, and the `WithTypes` layout to decorate them with
+fn hand_built_vector() -> (MoveValue, MoveTypeLayout) {
+    let layout = MoveStructLayout::with_types(
+        AccountResource::struct_tag(),
+        COIN_STORE_FIELDS
+            .iter()
+            .map(|name| MoveFieldLayout::new(Identifier::new(*name).unwrap(), MoveTypeLayout::U64))
+            .collect(),
+    );
+    let value = MoveValue::Vector(
+        (0..100_000u64)
+            .map(|i| {
+                MoveValue::Struct(MoveStruct::with_fields(
+                    COIN_STORE_FIELDS
+                        .iter()
+                        .map(|name| (Identifier::new(*name).unwrap(), MoveValue::U64(i)))
+                        .collect(),
+                ))
+            })
+            .collect(),
+    );
+    (
+        value,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(layout))),
+    )
+}
+
+fn decorate_pooled<M: criterion::measurement::Measurement + 'static>(c: &mut Criterion<M>) {
+    let (value, layout) = hand_built_vector();
+    let pool = IdentifierPool::new(1024);
+    for name in &COIN_STORE_FIELDS {
+        pool.get_or_intern(name).unwrap();
+    }
+    if report_heap() {
+        // fresh values each time, since clones would share their names with `value`
+        let (decorated, decorated_bytes) =
+            resident_bytes(|| hand_built_vector().0.decorate(&layout));
+        drop(decorated);
+        let (pooled, pooled_bytes) = resident_bytes(|| {
+            hand_built_vector()
+                .0
+                .decorate_pooled(&layout, None, Some(&pool))
+        });
+        drop(pooled);
+        eprintln!(
+            "heap bytes for 100000 hand-built structs of 8 fields: decorate {}, decorate_pooled {}",
+            decorated_bytes, pooled_bytes
+        );
+    }
+
+    c.bench_function("decorate_hand_built", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| value.decorate(&layout),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("decorate_pooled_hand_built", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| value.decorate_pooled(&layout, None, Some(&pool)),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+}
+
+/// A resource holding a 1 MB byte field and the layout to deserialize it with
+fn large_resource() -> (Vec<u8>, MoveTypeLayout) {
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::new(vec![
+        MoveTypeLayout::U64,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+    ]));
+    let value = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::U64(0),
+        MoveValue::vector_u8(vec![0xab; 1 << 20]),
+    ]));
+    (value.simple_serialize().unwrap(), layout)
+}
+
+fn deserialize_large<M: criterion::measurement::Measurement + 'static>(c: &mut Criterion<M>) {
+    let (blob, layout) = large_resource();
+    let owned = count_allocations(|| {
+        MoveValue::simple_deserialize(&blob, &layout).unwrap();
+    });
+    let borrowed = count_allocations(|| {
+        MoveValue::simple_deserialize_borrowed(&blob, &layout).unwrap();
+    });
+    println!(
+        "allocations for a 1 MB byte field: simple_deserialize {}, simple_deserialize_borrowed {}",
+        owned, borrowed
+    );
+
+    c.bench_function("simple_deserialize_1mb", |b| {
+        b.iter(|| MoveValue::simple_deserialize(&blob, &layout).unwrap())
+    });
+    c.bench_function("simple_deserialize_borrowed_1mb", |b| {
+        b.iter(|| MoveValue::simple_deserialize_borrowed(&blob, &layout).unwrap())
+    });
+}
+
+/// A resource with a 1 MB byte field before a `u64` field, and the layout to read it with
+fn resource_with_leading_bytes() -> (Vec<u8>, MoveTypeLayout) {
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::new(vec![
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+        MoveTypeLayout::U64,
+    ]));
+    let value = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::vector_u8(vec![0xab; 1 << 20]),
+        MoveValue::U64(7),
+    ]));
+    (value.simple_serialize().unwrap(), layout)
+}
+
+fn read_last_field<M: criterion::measurement::Measurement + 'static>(c: &mut Criterion<M>) {
+    let (blob, layout) = resource_with_leading_bytes();
+    let read_lazily = || {
+        MoveValue::lazy(&blob, &layout)
+            .unwrap()
+            .get_field("1")
+            .unwrap()
+            .decode()
+            .unwrap()
+    };
+    let (_, decoded_bytes) = resident_bytes(|| MoveValue::simple_deserialize(&blob, &layout));
+    let (_, lazy_bytes) = resident_bytes(read_lazily);
+    println!(
+        "heap bytes for reading the field after a 1 MB byte field: simple_deserialize {}, lazy {}",
+        decoded_bytes, lazy_bytes
+    );
+
+    c.bench_function("read_last_field_decoded", |b| {
+        b.iter(|| MoveValue::simple_deserialize(&blob, &layout).unwrap())
+    });
+    c.bench_function("read_last_field_lazy", |b| b.iter(read_lazily));
+}
+
+fn deserialize_many<M: criterion::measurement::Measurement + 'static>(c: &mut Criterion<M>) {
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::new(vec![
+        MoveTypeLayout::U64,
+        MoveTypeLayout::Address,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+    ]));
+    let blobs = serialize_values(&values());
+    let per_blob = count_allocations(|| {
+        for blob in &blobs {
+            MoveValue::simple_deserialize(blob, &layout).unwrap();
+        }
+    });
+    let batched = count_allocations(|| {
+        MoveValue::simple_deserialize_many(blobs.iter().map(Vec::as_slice), &layout).unwrap();
+    });
+    println!(
+        "allocations for {} blobs: simple_deserialize {}, simple_deserialize_many {}",
+        blobs.len(),
+        per_blob,
+        batched
+    );
+
+    c.bench_function("simple_deserialize_per_blob", |b| {
+        b.iter(|| {
+            blobs
+                .iter()
+                .map(|blob| MoveValue::simple_deserialize(blob, &layout).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    c.bench_function("simple_deserialize_many", |b| {
+        b.iter(|| {
+            MoveValue::simple_deserialize_many(blobs.iter().map(Vec::as_slice), &layout).unwrap()
+        })
+    });
+}
+
+/// 10k decorated structs, as BCS and as JSON (where the fields are keyed by name), and the layout
+/// to deserialize them with
+fn decorated_vector() -> (Vec<u8>, String, MoveTypeLayout) {
+    let layout = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(
+        MoveStructLayout::with_fields(vec![
+            MoveFieldLayout::new(ident_str!("id").to_owned(), MoveTypeLayout::U64),
+            MoveFieldLayout::new(ident_str!("owner").to_owned(), MoveTypeLayout::Address),
+            MoveFieldLayout::new(ident_str!("frozen").to_owned(), MoveTypeLayout::Bool),
+        ]),
+    )));
+    let value = MoveValue::Vector(
+        (0..10_000u64)
+            .map(|i| {
+                MoveValue::Struct(MoveStruct::Runtime(vec![
+                    MoveValue::U64(i),
+                    MoveValue::Address(AccountAddress::ONE),
+                    MoveValue::Bool(i % 2 == 0),
+                ]))
+            })
+            .collect(),
+    )
+    .decorate(&layout);
+    (
+        value.simple_serialize().unwrap(),
+        serde_json::to_string(&value).unwrap(),
+        layout,
+    )
+}
+
+fn deserialize_decorated<M: criterion::measurement::Measurement + 'static>(c: &mut Criterion<M>) {
+    let (blob, json, layout) = decorated_vector();
+    let from_json = || {
+        (&layout)
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .unwrap()
+    };
+    let bcs_allocations = count_allocations(|| {
+        MoveValue::simple_deserialize(&blob, &layout).unwrap();
+    });
+    let json_allocations = count_allocations(|| {
+        from_json();
+    });
+    println!(
+        "allocations for 10000 decorated structs of 3 fields: BCS {}, JSON {}",
+        bcs_allocations, json_allocations
+    );
+
+    let names = bcs::to_bytes(&vec![ident_str!("owner").to_owned(); 10_000]).unwrap();
+    let owned = count_allocations(|| {
+        bcs::from_bytes::<Vec<Identifier>>(&names).unwrap();
+    });
+    let borrowed = count_allocations(|| {
+        bcs::from_bytes::<Vec<&IdentStr>>(&names).unwrap();
+    });
+    println!(
+        "allocations for 10000 identifiers: Identifier {}, &IdentStr {}",
+        owned, borrowed
+    );
+
+    c.bench_function("deserialize_decorated_bcs", |b| {
+        b.iter(|| MoveValue::simple_deserialize(&blob, &layout).unwrap())
+    });
+    c.bench_function("deserialize_decorated_json", |b| b.iter(from_json));
+}
+
+fn clone_large<M: criterion::measurement::Measurement + 'static>(c: &mut Criterion<M>) {
+    let value = MoveValue::Vector((0..1_000_000u64).map(MoveValue::U64).collect());
+    let shared = SharedMoveValue::new(value.clone());
+
+    c.bench_function("clone_1m_elements", |b| b.iter(|| value.clone()));
+    c.bench_function("clone_shared_1m_elements", |b| b.iter(|| shared.clone()));
+}
+
+criterion_group!(
+    value_benches,
+    serialize,
+    decorate,
+    decorate_pooled,
+    deserialize_large,
+    read_last_field,
+    deserialize_many,
+    deserialize_decorated,
+    clone_large
+);
+
+criterion_main!(value_benches);
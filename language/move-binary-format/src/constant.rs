@@ -30,7 +30,9 @@ fn ty_to_sig(ty: &MoveTypeLayout) -> Option<SignatureToken> {
         MoveTypeLayout::U64 => Some(SignatureToken::U64),
         MoveTypeLayout::U128 => Some(SignatureToken::U128),
         MoveTypeLayout::Vector(v) => Some(SignatureToken::Vector(Box::new(ty_to_sig(v.as_ref())?))),
-        MoveTypeLayout::Struct(_) => None,
+        MoveTypeLayout::Struct(_) | MoveTypeLayout::Enum(_) | MoveTypeLayout::TypeParameter(_) => {
+            None
+        }
         MoveTypeLayout::Bool => Some(SignatureToken::Bool),
     }
 }
@@ -45,6 +47,6 @@ impl Constant {
 
     pub fn deserialize_constant(&self) -> Option<MoveValue> {
         let ty = sig_to_ty(&self.type_)?;
-        MoveValue::simple_deserialize(&self.data, &ty).ok()
+        MoveValue::deserialize_constant(&ty, &self.data)
     }
 }
@@ -728,10 +728,15 @@ fn load_identifiers(
                 return Err(PartialVMError::new(StatusCode::MALFORMED)
                     .with_message("Bad Identifier pool size".to_string()));
             }
-            let s = Identifier::from_utf8(buffer).map_err(|_| {
-                PartialVMError::new(StatusCode::MALFORMED)
-                    .with_message("Invalid Identifier".to_string())
-            })?;
+            // Identifiers in bytecode are only limited by the binary format, not by the
+            // `MAX_IDENTIFIER_LENGTH` that applies to parsed identifiers
+            let s = String::from_utf8(buffer)
+                .map_err(anyhow::Error::from)
+                .and_then(|s| Identifier::new_with_max_length(s, IDENTIFIER_SIZE_MAX as usize))
+                .map_err(|_| {
+                    PartialVMError::new(StatusCode::MALFORMED)
+                        .with_message("Invalid Identifier".to_string())
+                })?;
             identifiers.push(s);
         }
     }
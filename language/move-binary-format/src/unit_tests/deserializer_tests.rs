@@ -2,10 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    file_format::{CompiledModule, CompiledScript},
+    file_format::{basic_test_module, CompiledModule, CompiledScript},
     file_format_common::*,
 };
-use move_core_types::vm_status::StatusCode;
+use move_core_types::{identifier::Identifier, vm_status::StatusCode};
 
 fn malformed_simple_versioned_test(version: u32) {
     // bad uleb (more than allowed for table count)
@@ -241,3 +241,14 @@ fn deserialize_invalid_script_no_signature() {
         StatusCode::INDEX_OUT_OF_BOUNDS
     );
 }
+
+// Identifiers longer than parsed identifiers may be are still fine in bytecode
+#[test]
+fn deserialize_long_identifier() {
+    let mut m = basic_test_module();
+    m.identifiers[1] =
+        Identifier::new_with_max_length("f".repeat(300), IDENTIFIER_SIZE_MAX as usize).unwrap();
+    let mut binary = vec![];
+    m.serialize(&mut binary).unwrap();
+    assert_eq!(CompiledModule::deserialize(&binary).unwrap(), m);
+}
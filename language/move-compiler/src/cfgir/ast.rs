@@ -289,6 +289,7 @@ impl AstDebug for MoveValue {
             }
             V::Struct(_) => panic!("ICE struct constants not supported"),
             V::Signer(_) => panic!("ICE signer constants not supported"),
+            V::Variant { .. } => panic!("ICE enum constants not supported"),
         }
     }
 }
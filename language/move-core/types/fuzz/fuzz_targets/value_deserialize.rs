@@ -0,0 +1,31 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use move_core_types::value::{MoveTypeLayout, MoveValue};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let (len, rest) = data.split_at(2);
+    let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+    if len > rest.len() {
+        return;
+    }
+    let (layout, blob) = rest.split_at(len);
+    let layout: MoveTypeLayout = match bcs::from_bytes(layout) {
+        Ok(layout) => layout,
+        Err(_) => return,
+    };
+
+    if let Ok(value) = MoveValue::simple_deserialize(blob, &layout) {
+        // decorated values serialize their field names, so compare the undecorated encoding.
+        // Values too deep for the serializer are skipped.
+        if let Some(bytes) = value.canonicalize().simple_serialize() {
+            assert_eq!(bytes, blob);
+        }
+    }
+});
@@ -1,10 +1,18 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(feature = "bech32")]
+use anyhow::bail;
+#[cfg(any(feature = "bech32", feature = "base58"))]
+use anyhow::Result as AResult;
 use hex::FromHex;
 use rand::{rngs::OsRng, Rng};
 use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
-use std::{convert::TryFrom, fmt, str::FromStr};
+use sha3::{Digest, Sha3_256};
+use std::{cmp::Ordering, convert::TryFrom, fmt, str::FromStr};
+
+#[cfg(all(feature = "address20", feature = "address32"))]
+compile_error!("the `address20` and `address32` features are mutually exclusive");
 
 /// A struct that represents an account address.
 #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy)]
@@ -17,18 +25,42 @@ impl AccountAddress {
     }
 
     /// The number of bytes in an address.
-    /// Default to 16 bytes, can be set to 20 bytes with address20 feature.
-    pub const LENGTH: usize = if cfg!(feature = "address20") { 20 } else { 16 };
+    /// Default to 16 bytes, can be set to 20 bytes with the address20 feature or to 32 bytes with
+    /// the address32 feature. Everything else about addresses, such as the width of their hex
+    /// forms, follows from it.
+    pub const LENGTH: usize = if cfg!(feature = "address20") {
+        20
+    } else if cfg!(feature = "address32") {
+        32
+    } else {
+        16
+    };
 
     /// Hex address: 0x0
     pub const ZERO: Self = Self([0u8; Self::LENGTH]);
 
     /// Hex address: 0x1
-    pub const ONE: Self = Self::get_hex_address_one();
-
-    const fn get_hex_address_one() -> Self {
-        let mut addr = [0u8; AccountAddress::LENGTH];
-        addr[AccountAddress::LENGTH - 1] = 1u8;
+    pub const ONE: Self = Self::from_u64(1);
+
+    /// Hex address: 0x2
+    pub const TWO: Self = Self::from_u64(2);
+
+    /// The largest address, with every byte `0xff`
+    pub const MAX: Self = Self([0xff; Self::LENGTH]);
+
+    /// The addresses below this one are special: reserved for the framework, see `is_special`
+    pub const SPECIAL_LIMIT: u64 = 0x10;
+
+    /// The address with `n` in its last 8 bytes, big-endian, and zeros before, so that
+    /// `from_u64(0xcafe)` is `0xcafe`
+    pub const fn from_u64(n: u64) -> Self {
+        let mut addr = [0u8; Self::LENGTH];
+        let bytes = n.to_be_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            addr[Self::LENGTH - bytes.len() + i] = bytes[i];
+            i += 1;
+        }
         Self(addr)
     }
 
@@ -38,6 +70,106 @@ impl AccountAddress {
         Self(buf)
     }
 
+    /// A random address drawn from `rng`, e.g. a seeded one for reproducible tests
+    #[cfg(any(test, feature = "testing"))]
+    pub fn random_with(rng: &mut impl Rng) -> Self {
+        Self(rng.gen())
+    }
+
+    /// Whether this is one of the low addresses reserved for the framework, those below
+    /// `SPECIAL_LIMIT` (`0x0` to `0xf`)
+    pub fn is_special(&self) -> bool {
+        self.is_special_with_limit(Self::SPECIAL_LIMIT)
+    }
+
+    /// Whether this address is below `limit`, for chains that reserve a different range than
+    /// `is_special`
+    pub fn is_special_with_limit(&self, limit: u64) -> bool {
+        *self < Self::from_u64(limit)
+    }
+
+    /// The address after this one, taking addresses as big-endian integers, or `None` for `MAX`
+    pub fn next(&self) -> Option<Self> {
+        self.checked_add(1)
+    }
+
+    /// The address `n` after this one, taking addresses as big-endian integers, or `None` if that
+    /// is past `MAX`
+    pub fn checked_add(&self, n: u128) -> Option<Self> {
+        let n = n.to_be_bytes();
+        let mut sum = [0u8; Self::LENGTH];
+        let mut carry = 0u16;
+        for i in 1..=Self::LENGTH {
+            let addend = if i <= n.len() { n[n.len() - i] } else { 0 };
+            let digit = self.0[Self::LENGTH - i] as u16 + addend as u16 + carry;
+            sum[Self::LENGTH - i] = digit as u8;
+            carry = digit >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+        Some(Self(sum))
+    }
+
+    /// How many `next` steps lead from this address to `other`, or `None` if `other` is before
+    /// this address or the distance does not fit in a `u128` (possible only for addresses longer
+    /// than 16 bytes)
+    pub fn distance_to(&self, other: &Self) -> Option<u128> {
+        if other < self {
+            return None;
+        }
+        let mut diff = [0u8; Self::LENGTH];
+        let mut borrow = false;
+        for i in (0..Self::LENGTH).rev() {
+            let (digit, borrow1) = other.0[i].overflowing_sub(self.0[i]);
+            let (digit, borrow2) = digit.overflowing_sub(borrow as u8);
+            diff[i] = digit;
+            borrow = borrow1 || borrow2;
+        }
+        let (high, low) = diff.split_at(Self::LENGTH - 16);
+        if high.iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(low);
+        Some(u128::from_be_bytes(bytes))
+    }
+
+    /// The first and last addresses, inclusive, that start with the bytes `prefix`. An empty
+    /// prefix covers all addresses from `ZERO` to `MAX`, and a full-length one only itself.
+    ///
+    /// Panics if `prefix` is longer than `LENGTH`.
+    pub fn prefix_range(prefix: &[u8]) -> (Self, Self) {
+        assert!(
+            prefix.len() <= Self::LENGTH,
+            "address prefix of {} bytes is longer than an address",
+            prefix.len()
+        );
+        let mut first = [0u8; Self::LENGTH];
+        let mut last = [0xffu8; Self::LENGTH];
+        first[..prefix.len()].copy_from_slice(prefix);
+        last[..prefix.len()].copy_from_slice(prefix);
+        (Self(first), Self(last))
+    }
+
+    /// Derive a new address from `creator` and `seed`, for the addresses of accounts or objects
+    /// created by `creator`: the last `LENGTH` bytes of the SHA3-256 hash of the preimage
+    ///
+    /// `creator` (its `LENGTH` bytes) || `seed` (as is, without a length) || `scheme` (one byte)
+    ///
+    /// The scheme byte comes last so that the derivations of different schemes never share a
+    /// preimage. The derivation is part of the protocol: never change it.
+    pub fn derive(creator: &AccountAddress, seed: &[u8], scheme: DeriveScheme) -> AccountAddress {
+        let mut hasher = Sha3_256::new();
+        hasher.update(creator.as_ref());
+        hasher.update(seed);
+        hasher.update([scheme as u8]);
+        let hash = hasher.finalize();
+        let mut address = [0u8; Self::LENGTH];
+        address.copy_from_slice(&hash[hash.len() - Self::LENGTH..]);
+        Self(address)
+    }
+
     pub fn short_str_lossless(&self) -> String {
         let hex_str = hex::encode(&self.0).trim_start_matches('0').to_string();
         if hex_str.is_empty() {
@@ -55,45 +187,207 @@ impl AccountAddress {
         self.0
     }
 
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Parse an address written as `0x` followed by at most `2 * LENGTH` hex digits, with any
+    /// missing digits taken to be leading zeros: `0x1`, `0x01` and the full form all denote the
+    /// same address.
     pub fn from_hex_literal(literal: &str) -> Result<Self, AccountAddressParseError> {
-        if !literal.starts_with("0x") {
-            return Err(AccountAddressParseError);
-        }
+        let digits = literal
+            .strip_prefix("0x")
+            .ok_or(AccountAddressParseError::MissingPrefix)?;
+        Self::from_short_hex(digits)
+    }
 
-        let hex_len = literal.len() - 2;
+    /// Parse an address in the canonical form of `to_canonical_string` only: all `2 * LENGTH`
+    /// hex digits, in lowercase, without a `0x` prefix. For consensus-critical paths, where each
+    /// address must have a single spelling; `from_str` accepts the shorter forms as well.
+    pub fn from_str_strict(s: &str) -> Result<Self, AccountAddressParseError> {
+        let address = Self::from_hex(s)?;
+        if s.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err(AccountAddressParseError::NotCanonical);
+        }
+        Ok(address)
+    }
 
-        // If the string is too short, pad it
-        if hex_len < Self::LENGTH * 2 {
-            let mut hex_str = String::with_capacity(Self::LENGTH * 2);
-            for _ in 0..Self::LENGTH * 2 - hex_len {
-                hex_str.push('0');
-            }
-            hex_str.push_str(&literal[2..]);
-            AccountAddress::from_hex(hex_str)
-        } else {
-            AccountAddress::from_hex(&literal[2..])
+    /// At most `2 * LENGTH` hex digits, padded with leading zeros
+    fn from_short_hex(digits: &str) -> Result<Self, AccountAddressParseError> {
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(AccountAddressParseError::InvalidHex);
+        }
+        if digits.len() > Self::LENGTH * 2 {
+            return Err(AccountAddressParseError::TooLong {
+                digits: digits.len(),
+            });
         }
+        let mut padded = "0".repeat(Self::LENGTH * 2 - digits.len());
+        padded.push_str(digits);
+        Self::from_hex(padded)
+    }
+
+    /// All `2 * LENGTH` hex digits of this address, in lowercase and without a `0x` prefix, e.g.
+    /// `00000000000000000000000000000001`; the only form `from_str_strict` accepts
+    pub fn to_canonical_string(&self) -> String {
+        self.to_hex()
     }
 
+    /// The shortest form of this address as a literal: `0x` followed by its hex digits in
+    /// lowercase, without leading zeros, e.g. `0x1`. The zero address is `0x0`.
     pub fn to_hex_literal(&self) -> String {
         format!("0x{}", self.short_str_lossless())
     }
 
+    /// This address with a checksum in the case of its hex digits, in the manner of Ethereum's
+    /// EIP-55, to catch mistyped and mis-pasted addresses: `0x` followed by all `2 * LENGTH` hex
+    /// digits, where a letter digit is in uppercase if and only if the nibble at its position in
+    /// the SHA3-256 hash of the lowercase digits (as ASCII, without `0x`) is 8 or more. Nibbles
+    /// are taken high before low, from the first byte of the hash on. For example, `0xcafe` is
+    /// `0x0000000000000000000000000000cAfE`.
+    pub fn to_checksum_string(&self) -> String {
+        let digits = self.to_hex();
+        let hash = Sha3_256::digest(digits.as_bytes());
+        let mut checksummed = String::with_capacity(digits.len() + 2);
+        checksummed.push_str("0x");
+        for (i, digit) in digits.chars().enumerate() {
+            let nibble = if i % 2 == 0 {
+                hash[i / 2] >> 4
+            } else {
+                hash[i / 2] & 0xf
+            };
+            checksummed.push(if nibble >= 8 {
+                digit.to_ascii_uppercase()
+            } else {
+                digit
+            });
+        }
+        checksummed
+    }
+
+    /// Parse an address in the form of `to_checksum_string`, rejecting it if the case of its
+    /// digits does not match the checksum. Digits all in lowercase or all in uppercase carry no
+    /// checksum, and are accepted as they are.
+    pub fn from_checksum_string(s: &str) -> Result<Self, AccountAddressParseError> {
+        let digits = s
+            .strip_prefix("0x")
+            .ok_or(AccountAddressParseError::MissingPrefix)?;
+        let address = Self::from_hex(digits)?;
+        let mixed_case = digits.bytes().any(|b| b.is_ascii_lowercase())
+            && digits.bytes().any(|b| b.is_ascii_uppercase());
+        if mixed_case && address.to_checksum_string()[2..] != *digits {
+            return Err(AccountAddressParseError::InvalidChecksum);
+        }
+        Ok(address)
+    }
+
+    /// Parse exactly `2 * LENGTH` hex digits, without a `0x` prefix
     pub fn from_hex<T: AsRef<[u8]>>(hex: T) -> Result<Self, AccountAddressParseError> {
-        <[u8; Self::LENGTH]>::from_hex(hex)
-            .map_err(|_| AccountAddressParseError)
-            .map(Self)
+        let hex = hex.as_ref();
+        if !hex.iter().all(u8::is_ascii_hexdigit) {
+            return Err(AccountAddressParseError::InvalidHex);
+        }
+        match hex.len().cmp(&(Self::LENGTH * 2)) {
+            Ordering::Greater => Err(AccountAddressParseError::TooLong { digits: hex.len() }),
+            Ordering::Less => Err(AccountAddressParseError::TooShort { digits: hex.len() }),
+            Ordering::Equal => <[u8; Self::LENGTH]>::from_hex(hex)
+                .map_err(|_| AccountAddressParseError::InvalidHex)
+                .map(Self),
+        }
     }
 
     pub fn to_hex(&self) -> String {
         format!("{:x}", self)
     }
 
+    /// The address of exactly `LENGTH` bytes, failing with the actual length otherwise
     pub fn from_bytes<T: AsRef<[u8]>>(bytes: T) -> Result<Self, AccountAddressParseError> {
-        <[u8; Self::LENGTH]>::try_from(bytes.as_ref())
-            .map_err(|_| AccountAddressParseError)
+        let bytes = bytes.as_ref();
+        <[u8; Self::LENGTH]>::try_from(bytes)
+            .map_err(|_| AccountAddressParseError::InvalidLength { bytes: bytes.len() })
             .map(Self)
     }
+
+    /// The address of at most `LENGTH` bytes, with zeros in front of any missing ones, e.g. for
+    /// addresses from chains with shorter ones
+    pub fn from_bytes_left_padded<T: AsRef<[u8]>>(
+        bytes: T,
+    ) -> Result<Self, AccountAddressParseError> {
+        let bytes = bytes.as_ref();
+        if bytes.len() > Self::LENGTH {
+            return Err(AccountAddressParseError::InvalidLength { bytes: bytes.len() });
+        }
+        let mut address = [0u8; Self::LENGTH];
+        address[Self::LENGTH - bytes.len()..].copy_from_slice(bytes);
+        Ok(Self(address))
+    }
+}
+
+/// Encodings of addresses for user-facing products. They are behind the `bech32` and `base58`
+/// features, so that the crate does not otherwise depend on their implementations.
+#[cfg(feature = "bech32")]
+impl AccountAddress {
+    /// This address in bech32 (BIP-173) with the human-readable part `hrp`, e.g. `move1...`: its
+    /// bytes in 5-bit groups, followed by the bech32 checksum. Fails if `hrp` is not a valid
+    /// human-readable part.
+    pub fn to_bech32(&self, hrp: &str) -> AResult<String> {
+        use bech32::ToBase32;
+
+        Ok(bech32::encode(
+            hrp,
+            self.0.to_base32(),
+            bech32::Variant::Bech32,
+        )?)
+    }
+
+    /// Parse an address in the form of `to_bech32`, along with its human-readable part. Fails on
+    /// a bad checksum, on the bech32m variant, and on data of other than `LENGTH` bytes.
+    pub fn from_bech32(s: &str) -> AResult<(String, AccountAddress)> {
+        use bech32::FromBase32;
+
+        let (hrp, data, variant) = bech32::decode(s)?;
+        if variant != bech32::Variant::Bech32 {
+            bail!("expected bech32, found bech32m: {}", s);
+        }
+        let bytes = Vec::<u8>::from_base32(&data)?;
+        Ok((hrp, Self::from_bytes(bytes)?))
+    }
+}
+
+#[cfg(feature = "base58")]
+impl AccountAddress {
+    /// This address in base58check: the base58 encoding, in the Bitcoin alphabet, of its bytes
+    /// followed by the first 4 bytes of their double SHA-256. There is no version byte.
+    pub fn to_base58check(&self) -> String {
+        bs58::encode(self.0).with_check().into_string()
+    }
+
+    /// Parse an address in the form of `to_base58check`. Fails on a bad checksum and on data of
+    /// other than `LENGTH` bytes.
+    pub fn from_base58check(s: &str) -> AResult<Self> {
+        let bytes = bs58::decode(s).with_check(None).into_vec()?;
+        Ok(Self::from_bytes(bytes)?)
+    }
+}
+
+#[cfg(feature = "subtle")]
+impl AccountAddress {
+    /// Compare two addresses in time independent of where they first differ. Use this instead of
+    /// `==` when either side is derived from a secret (e.g. an authentication key compared against
+    /// a user-supplied one) and the timing of the comparison is observable; `==` is fine for
+    /// public addresses and is what `Eq`, `Ord` and `Hash` use.
+    pub fn ct_eq(&self, other: &Self) -> bool {
+        subtle::ConstantTimeEq::ct_eq(&self.0[..], &other.0[..]).into()
+    }
+}
+
+/// Clears the address to `ZERO`. `AccountAddress` is `Copy`, so this cannot run on drop and does
+/// not reach copies; callers holding a secret-derived address should clear it explicitly.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for AccountAddress {
+    fn zeroize(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0[..]);
+    }
 }
 
 impl AsRef<[u8]> for AccountAddress {
@@ -110,6 +404,9 @@ impl std::ops::Deref for AccountAddress {
     }
 }
 
+/// All `2 * LENGTH` hex digits of the address, in uppercase and without a `0x` prefix, e.g.
+/// `0000000000000000000000000000CAFE`. This form is relied on by logs and stored outputs and will
+/// not change; use `to_hex_literal` or `to_canonical_string` for the other forms.
 impl fmt::Display for AccountAddress {
     fn fmt(&self, f: &mut fmt::Formatter) -> std::fmt::Result {
         write!(f, "{:X}", self)
@@ -208,18 +505,34 @@ impl TryFrom<String> for AccountAddress {
     type Error = AccountAddressParseError;
 
     fn try_from(s: String) -> Result<AccountAddress, AccountAddressParseError> {
-        Self::from_hex(s)
+        Self::from_str(&s)
     }
 }
 
 impl FromStr for AccountAddress {
     type Err = AccountAddressParseError;
 
+    /// Parse an address from at most `2 * LENGTH` hex digits, with or without leading zeros:
+    /// `0x1`, `0x01` and the full form, with or without `0x`, are all accepted. Fewer digits
+    /// than the full form require the `0x` prefix, so that `10` is not taken for `0x10`. See
+    /// `from_str_strict` for the canonical form alone.
     fn from_str(s: &str) -> Result<Self, AccountAddressParseError> {
-        Self::from_hex(s)
+        match s.strip_prefix("0x") {
+            Some(digits) => Self::from_short_hex(digits),
+            None => {
+                let address = Self::from_short_hex(s)?;
+                if s.len() < Self::LENGTH * 2 {
+                    return Err(AccountAddressParseError::MissingPrefix);
+                }
+                Ok(address)
+            }
+        }
     }
 }
 
+/// Human-readable formats such as JSON take an address as a hex string in any of the forms of
+/// `from_str`, so `"0x1"` as well as the canonical form that `Serialize` produces. Other formats
+/// take its raw bytes.
 impl<'de> Deserialize<'de> for AccountAddress {
     fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
     where
@@ -227,7 +540,7 @@ impl<'de> Deserialize<'de> for AccountAddress {
     {
         if deserializer.is_human_readable() {
             let s = <String>::deserialize(deserializer)?;
-            AccountAddress::from_hex(s).map_err(D::Error::custom)
+            AccountAddress::from_str(&s).map_err(D::Error::custom)
         } else {
             // In order to preserve the Serde data model and help analysis tools,
             // make sure to wrap our value in a container with the same name
@@ -242,13 +555,15 @@ impl<'de> Deserialize<'de> for AccountAddress {
     }
 }
 
+/// Human-readable formats represent an address as its canonical string (see
+/// `to_canonical_string`), other formats such as BCS as its raw bytes.
 impl Serialize for AccountAddress {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
         if serializer.is_human_readable() {
-            self.to_hex().serialize(serializer)
+            self.to_canonical_string().serialize(serializer)
         } else {
             // See comment in deserialize.
             serializer.serialize_newtype_struct("AccountAddress", &self.0)
@@ -256,12 +571,58 @@ impl Serialize for AccountAddress {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct AccountAddressParseError;
+/// The domain of a derived address, see `AccountAddress::derive`. The discriminants are the
+/// scheme bytes of the preimage, and must never change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum DeriveScheme {
+    /// The address of an object created by an account
+    Object = 0xFE,
+    /// The address of a resource account, one controlled by the account that created it
+    ResourceAccount = 0xFF,
+}
+
+/// Why a string or byte string is not an address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountAddressParseError {
+    /// Empty, or containing characters other than hex digits
+    InvalidHex,
+    /// More hex digits than an address has
+    TooLong { digits: usize },
+    /// Fewer hex digits than the full form that was required
+    TooShort { digits: usize },
+    /// Missing the `0x` prefix that was required
+    MissingPrefix,
+    /// Not in the lowercase that `from_str_strict` requires
+    NotCanonical,
+    /// A byte string of the wrong length
+    InvalidLength { bytes: usize },
+    /// Mixed-case digits that do not match the checksum of `to_checksum_string`
+    InvalidChecksum,
+}
 
 impl fmt::Display for AccountAddressParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> std::fmt::Result {
-        write!(f, "unable to parse AccoutAddress")
+        let digits = AccountAddress::LENGTH * 2;
+        write!(f, "unable to parse AccountAddress: ")?;
+        match self {
+            Self::InvalidHex => write!(f, "expected hex digits"),
+            Self::TooLong { digits: found } => {
+                write!(f, "expected at most {} hex digits, found {}", digits, found)
+            }
+            Self::TooShort { digits: found } => {
+                write!(f, "expected {} hex digits, found {}", digits, found)
+            }
+            Self::MissingPrefix => write!(f, "expected a `0x` prefix"),
+            Self::NotCanonical => write!(f, "expected lowercase hex digits"),
+            Self::InvalidChecksum => write!(f, "the case of the hex digits fails the checksum"),
+            Self::InvalidLength { bytes } => write!(
+                f,
+                "expected {} bytes, found {}",
+                AccountAddress::LENGTH,
+                bytes
+            ),
+        }
     }
 }
 
@@ -269,7 +630,7 @@ impl std::error::Error for AccountAddressParseError {}
 
 #[cfg(test)]
 mod tests {
-    use super::AccountAddress;
+    use super::{AccountAddress, AccountAddressParseError, DeriveScheme};
     use hex::FromHex;
     use proptest::prelude::*;
     use std::{
@@ -278,6 +639,7 @@ mod tests {
     };
 
     #[test]
+    #[cfg(not(any(feature = "address20", feature = "address32")))]
     fn test_display_impls() {
         let hex = "ca843279e3427144cead5e4d5999a3d0";
         let upper_hex = "CA843279E3427144CEAD5E4D5999A3D0";
@@ -294,6 +656,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(any(feature = "address20", feature = "address32")))]
     fn test_short_str_lossless() {
         let address = AccountAddress::from_hex("00c0f1f95c5b1c5f0eda533eff269000").unwrap();
 
@@ -304,6 +667,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(any(feature = "address20", feature = "address32")))]
     fn test_short_str_lossless_zero() {
         let address = AccountAddress::from_hex("00000000000000000000000000000000").unwrap();
 
@@ -311,6 +675,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(any(feature = "address20", feature = "address32")))]
     fn test_address() {
         let hex = "ca843279e3427144cead5e4d5999a3d0";
         let bytes = Vec::from_hex(hex).expect("You must provide a valid Hex format");
@@ -330,6 +695,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(any(feature = "address20", feature = "address32")))]
     fn test_from_hex_literal() {
         let hex_literal = "0x1";
         let hex = "00000000000000000000000000000001";
@@ -346,6 +712,577 @@ mod tests {
         AccountAddress::from_hex_literal("0x100000000000000000000000000000001").unwrap_err();
     }
 
+    #[test]
+    fn test_from_str_forms() {
+        let width = AccountAddress::LENGTH * 2;
+        let full = format!("{}1", "0".repeat(width - 1));
+        let accepted = [
+            "0x1".to_string(),
+            "0x01".to_string(),
+            format!("0x{}", full),
+            full.clone(),
+        ];
+        for s in &accepted {
+            assert_eq!(
+                AccountAddress::from_str(s),
+                Ok(AccountAddress::ONE),
+                "{}",
+                s
+            );
+            assert_eq!(
+                AccountAddress::try_from(s.clone()),
+                Ok(AccountAddress::ONE),
+                "{}",
+                s
+            );
+        }
+        let cafe = AccountAddress::from_str("0xCaFe").unwrap();
+        assert_eq!(cafe, AccountAddress::from_str("0xcafe").unwrap());
+        assert_eq!(cafe.short_str_lossless(), "cafe");
+        assert_eq!(
+            AccountAddress::from_str(&"f".repeat(width)).unwrap(),
+            AccountAddress::new([0xff; AccountAddress::LENGTH])
+        );
+
+        let rejected = [
+            ("", AccountAddressParseError::InvalidHex),
+            ("0x", AccountAddressParseError::InvalidHex),
+            ("0x0x1", AccountAddressParseError::InvalidHex),
+            ("0X1", AccountAddressParseError::InvalidHex),
+            (" 0x1", AccountAddressParseError::InvalidHex),
+            ("0x1 ", AccountAddressParseError::InvalidHex),
+            ("0xg", AccountAddressParseError::InvalidHex),
+            ("-1", AccountAddressParseError::InvalidHex),
+            ("1", AccountAddressParseError::MissingPrefix),
+            ("10", AccountAddressParseError::MissingPrefix),
+            ("cafe", AccountAddressParseError::MissingPrefix),
+            (&full[1..], AccountAddressParseError::MissingPrefix),
+        ];
+        for (s, err) in &rejected {
+            assert_eq!(AccountAddress::from_str(s), Err(*err), "{}", s);
+        }
+        let too_long = format!("1{}", full);
+        for s in &[too_long.clone(), format!("0x{}", too_long)] {
+            let err = AccountAddress::from_str(s).unwrap_err();
+            assert_eq!(err, AccountAddressParseError::TooLong { digits: width + 1 });
+            assert!(err
+                .to_string()
+                .contains(&format!("expected at most {} hex digits", width)));
+        }
+    }
+
+    #[test]
+    fn test_from_str_strict() {
+        let width = AccountAddress::LENGTH * 2;
+        let canonical = AccountAddress::ONE.to_canonical_string();
+        assert_eq!(canonical, format!("{}1", "0".repeat(width - 1)));
+        assert_eq!(
+            AccountAddress::from_str_strict(&canonical),
+            Ok(AccountAddress::ONE)
+        );
+        let cafe = AccountAddress::from_str("0xcafe").unwrap();
+        assert_eq!(
+            AccountAddress::from_str_strict(&cafe.to_canonical_string()),
+            Ok(cafe)
+        );
+
+        let rejected = [
+            (
+                "1".to_string(),
+                AccountAddressParseError::TooShort { digits: 1 },
+            ),
+            ("0x1".to_string(), AccountAddressParseError::InvalidHex),
+            (
+                format!("0x{}", canonical),
+                AccountAddressParseError::InvalidHex,
+            ),
+            (cafe.to_string(), AccountAddressParseError::NotCanonical),
+            (
+                format!("{}0", canonical),
+                AccountAddressParseError::TooLong { digits: width + 1 },
+            ),
+            (
+                format!("{}g", &canonical[1..]),
+                AccountAddressParseError::InvalidHex,
+            ),
+            (
+                "".to_string(),
+                AccountAddressParseError::TooShort { digits: 0 },
+            ),
+        ];
+        for (s, err) in &rejected {
+            assert_eq!(AccountAddress::from_str_strict(s), Err(*err), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_string_forms() {
+        let width = AccountAddress::LENGTH * 2;
+        let zero = AccountAddress::ZERO;
+        assert_eq!(zero.to_hex_literal(), "0x0");
+        assert_eq!(zero.to_canonical_string(), "0".repeat(width));
+        assert_eq!(zero.to_string(), "0".repeat(width));
+        assert_eq!(format!("{:#x}", zero), format!("0x{}", "0".repeat(width)));
+
+        let max = AccountAddress::new([0xff; AccountAddress::LENGTH]);
+        assert_eq!(max.to_hex_literal(), format!("0x{}", "f".repeat(width)));
+        assert_eq!(max.to_canonical_string(), "f".repeat(width));
+        assert_eq!(max.to_string(), "F".repeat(width));
+        assert_eq!(format!("{:#X}", max), format!("0x{}", "F".repeat(width)));
+
+        for address in &[zero, AccountAddress::ONE, max] {
+            assert_string_forms_roundtrip(address);
+        }
+    }
+
+    fn assert_string_forms_roundtrip(address: &AccountAddress) {
+        assert_eq!(
+            AccountAddress::from_hex_literal(&address.to_hex_literal()),
+            Ok(*address)
+        );
+        assert_eq!(
+            AccountAddress::from_str_strict(&address.to_canonical_string()),
+            Ok(*address)
+        );
+        for s in &[
+            address.to_hex_literal(),
+            address.to_canonical_string(),
+            address.to_string(),
+            format!("{:#x}", address),
+            format!("{:#X}", address),
+        ] {
+            assert_eq!(AccountAddress::from_str(s), Ok(*address), "{}", s);
+        }
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "address20", feature = "address32")))]
+    fn test_checksum_known_answers() {
+        let known = [
+            ("0x1", "0x00000000000000000000000000000001"),
+            ("0xcafe", "0x0000000000000000000000000000cAfE"),
+            ("0xa550c18", "0x0000000000000000000000000A550c18"),
+            (
+                "0xca843279e3427144cead5e4d5999a3d0",
+                "0xcA843279e3427144CeaD5E4d5999A3D0",
+            ),
+            (
+                "0xffffffffffffffffffffffffffffffff",
+                "0xFfFFFFFfffffFFFffFfFfFffFFFFffFf",
+            ),
+        ];
+        for (literal, checksummed) in &known {
+            let address = AccountAddress::from_hex_literal(literal).unwrap();
+            assert_eq!(address.to_checksum_string(), *checksummed);
+            assert_eq!(
+                AccountAddress::from_checksum_string(checksummed),
+                Ok(address)
+            );
+        }
+    }
+
+    #[test]
+    fn test_checksum_verification() {
+        let address = AccountAddress::from_hex_literal("0xcafe").unwrap();
+        let checksummed = address.to_checksum_string();
+        // unchecksummed forms are accepted
+        for s in &[
+            checksummed.to_lowercase(),
+            checksummed.to_uppercase().replacen("0X", "0x", 1),
+        ] {
+            assert_eq!(
+                AccountAddress::from_checksum_string(s),
+                Ok(address),
+                "{}",
+                s
+            );
+        }
+        // flipping the case of any one letter breaks the checksum
+        for (i, c) in checksummed.char_indices().skip(2) {
+            if !c.is_ascii_alphabetic() {
+                continue;
+            }
+            let mut flipped = checksummed.clone();
+            let c = if c.is_ascii_uppercase() {
+                c.to_ascii_lowercase()
+            } else {
+                c.to_ascii_uppercase()
+            };
+            flipped.replace_range(i..=i, &c.to_string());
+            assert_eq!(
+                AccountAddress::from_checksum_string(&flipped),
+                Err(AccountAddressParseError::InvalidChecksum),
+                "{}",
+                flipped
+            );
+        }
+        assert_eq!(
+            AccountAddress::from_checksum_string(&checksummed[2..]),
+            Err(AccountAddressParseError::MissingPrefix)
+        );
+        assert_eq!(
+            AccountAddress::from_checksum_string("0xcAfE"),
+            Err(AccountAddressParseError::TooShort { digits: 4 })
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_length() {
+        assert_eq!(
+            AccountAddress::from_bytes([1u8; 3]),
+            Err(AccountAddressParseError::InvalidLength { bytes: 3 })
+        );
+        assert_eq!(
+            AccountAddressParseError::InvalidLength { bytes: 3 }.to_string(),
+            format!(
+                "unable to parse AccountAddress: expected {} bytes, found 3",
+                AccountAddress::LENGTH
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_u64_layout() {
+        let mut expected = [0u8; AccountAddress::LENGTH];
+        expected[AccountAddress::LENGTH - 8..]
+            .copy_from_slice(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+        assert_eq!(
+            AccountAddress::from_u64(0x0123_4567_89ab_cdef).into_bytes(),
+            expected
+        );
+        assert_eq!(AccountAddress::from_u64(0), AccountAddress::ZERO);
+        assert_eq!(AccountAddress::from_u64(1), AccountAddress::ONE);
+        assert_eq!(AccountAddress::TWO.to_hex_literal(), "0x2");
+        assert_eq!(
+            AccountAddress::from_u64(0xcafe),
+            AccountAddress::from_hex_literal("0xcafe").unwrap()
+        );
+        assert_eq!(
+            AccountAddress::from_u64(u64::MAX).to_hex_literal(),
+            "0xffffffffffffffff"
+        );
+        assert_eq!(
+            AccountAddress::MAX.into_bytes(),
+            [0xff; AccountAddress::LENGTH]
+        );
+    }
+
+    #[test]
+    fn test_random_with_seed() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let a = AccountAddress::random_with(&mut StdRng::seed_from_u64(42));
+        let b = AccountAddress::random_with(&mut StdRng::seed_from_u64(42));
+        assert_eq!(a, b);
+        let mut rng = StdRng::seed_from_u64(42);
+        AccountAddress::random_with(&mut rng);
+        assert_ne!(AccountAddress::random_with(&mut rng), a);
+    }
+
+    #[test]
+    fn test_is_special() {
+        for n in 0..AccountAddress::SPECIAL_LIMIT {
+            assert!(AccountAddress::from_u64(n).is_special(), "{}", n);
+        }
+        assert!(!AccountAddress::from_u64(0x10).is_special());
+        assert!(!AccountAddress::from_u64(0xa550c18).is_special());
+        assert!(!AccountAddress::MAX.is_special());
+        // an address with any high byte set is not special, whatever its low bytes
+        let mut high = [0u8; AccountAddress::LENGTH];
+        high[0] = 1;
+        assert!(!AccountAddress::new(high).is_special());
+
+        assert!(AccountAddress::from_u64(0x10).is_special_with_limit(0x11));
+        assert!(!AccountAddress::ONE.is_special_with_limit(1));
+        assert!(!AccountAddress::ZERO.is_special_with_limit(0));
+    }
+
+    #[test]
+    fn test_next() {
+        assert_eq!(AccountAddress::ZERO.next(), Some(AccountAddress::ONE));
+        assert_eq!(AccountAddress::ONE.next(), Some(AccountAddress::TWO));
+        assert_eq!(AccountAddress::MAX.next(), None);
+        // the carry crosses byte boundaries
+        assert_eq!(
+            AccountAddress::from_u64(0xff).next(),
+            Some(AccountAddress::from_u64(0x100))
+        );
+        let mut above_u64 = [0u8; AccountAddress::LENGTH];
+        above_u64[AccountAddress::LENGTH - 9] = 1;
+        assert_eq!(
+            AccountAddress::from_u64(u64::MAX).next(),
+            Some(AccountAddress::new(above_u64))
+        );
+        assert_eq!(
+            AccountAddress::ZERO.checked_add(1 << 64),
+            Some(AccountAddress::new(above_u64))
+        );
+        let mut below_top = [0xffu8; AccountAddress::LENGTH];
+        below_top[0] = 0xfe;
+        let mut top = [0u8; AccountAddress::LENGTH];
+        top[0] = 0xff;
+        assert_eq!(
+            AccountAddress::new(below_top).next(),
+            Some(AccountAddress::new(top))
+        );
+    }
+
+    #[test]
+    fn test_checked_add_and_distance() {
+        assert_eq!(
+            AccountAddress::ZERO.checked_add(0xcafe),
+            Some(AccountAddress::from_u64(0xcafe))
+        );
+        assert_eq!(
+            AccountAddress::MAX.checked_add(0),
+            Some(AccountAddress::MAX)
+        );
+        assert_eq!(AccountAddress::MAX.checked_add(1), None);
+        assert_eq!(
+            AccountAddress::TWO.checked_add(u128::MAX).is_some(),
+            AccountAddress::LENGTH > 16
+        );
+
+        assert_eq!(
+            AccountAddress::ZERO.distance_to(&AccountAddress::ZERO),
+            Some(0)
+        );
+        assert_eq!(
+            AccountAddress::ONE.distance_to(&AccountAddress::TWO),
+            Some(1)
+        );
+        assert_eq!(AccountAddress::TWO.distance_to(&AccountAddress::ONE), None);
+        assert_eq!(
+            AccountAddress::from_u64(0xff).distance_to(&AccountAddress::from_u64(0x1_00ff)),
+            Some(0x1_0000)
+        );
+        let max_distance = AccountAddress::ZERO.distance_to(&AccountAddress::MAX);
+        if AccountAddress::LENGTH == 16 {
+            assert_eq!(max_distance, Some(u128::MAX));
+        } else {
+            assert_eq!(max_distance, None);
+        }
+    }
+
+    #[test]
+    fn test_prefix_range() {
+        assert_eq!(
+            AccountAddress::prefix_range(&[]),
+            (AccountAddress::ZERO, AccountAddress::MAX)
+        );
+        let full = AccountAddress::from_u64(0xcafe);
+        assert_eq!(AccountAddress::prefix_range(full.as_slice()), (full, full));
+
+        let (first, last) = AccountAddress::prefix_range(&[0xab, 0xff]);
+        assert_eq!(&first.as_slice()[..2], &[0xab, 0xff]);
+        assert!(first.as_slice()[2..].iter().all(|b| *b == 0));
+        assert_eq!(&last.as_slice()[..2], &[0xab, 0xff]);
+        assert!(last.as_slice()[2..].iter().all(|b| *b == 0xff));
+        // the next range starts right after this one, with the carry out of the prefix
+        let (next_first, _) = AccountAddress::prefix_range(&[0xac, 0x00]);
+        assert_eq!(last.next(), Some(next_first));
+        // the last prefix ends at MAX
+        assert_eq!(AccountAddress::prefix_range(&[0xff]).1, AccountAddress::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_prefix_range_too_long() {
+        AccountAddress::prefix_range(&[0u8; AccountAddress::LENGTH + 1]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_checked_add_distance_roundtrip(addr in any::<AccountAddress>(), n in any::<u64>()) {
+            if let Some(later) = addr.checked_add(n as u128) {
+                prop_assert_eq!(addr.distance_to(&later), Some(n as u128));
+                prop_assert!(later >= addr);
+            }
+        }
+
+        #[test]
+        fn test_prefix_range_contains(addr in any::<AccountAddress>(), len in 0..=AccountAddress::LENGTH) {
+            let (first, last) = AccountAddress::prefix_range(&addr.as_slice()[..len]);
+            prop_assert!(first <= addr && addr <= last);
+        }
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "address20", feature = "address32")))]
+    fn test_derive_known_answers() {
+        let cafe = AccountAddress::from_u64(0xcafe);
+        let known = [
+            (
+                AccountAddress::ONE,
+                &b""[..],
+                DeriveScheme::Object,
+                "f636893b5aaa1539ff14aeee6a0a9356",
+            ),
+            (
+                AccountAddress::ONE,
+                &b""[..],
+                DeriveScheme::ResourceAccount,
+                "157fcf885fb93d2711660c8b15071bba",
+            ),
+            (
+                cafe,
+                &b"seed"[..],
+                DeriveScheme::Object,
+                "bb52d672d3da0bea978d44644c63a023",
+            ),
+            (
+                cafe,
+                &b"seed"[..],
+                DeriveScheme::ResourceAccount,
+                "4c75a19ba4db17b92b6710f7aed9cb90",
+            ),
+        ];
+        for (creator, seed, scheme, derived) in &known {
+            assert_eq!(
+                AccountAddress::derive(creator, seed, *scheme).to_hex(),
+                *derived
+            );
+        }
+    }
+
+    #[test]
+    fn test_derive_preimage() {
+        use sha3::{Digest, Sha3_256};
+
+        let creator = AccountAddress::from_u64(0xcafe);
+        let mut preimage = creator.to_vec();
+        preimage.extend_from_slice(b"seed");
+        preimage.push(0xFE);
+        let hash = Sha3_256::digest(&preimage);
+        assert_eq!(
+            AccountAddress::derive(&creator, b"seed", DeriveScheme::Object).as_slice(),
+            &hash[32 - AccountAddress::LENGTH..]
+        );
+        assert_ne!(
+            AccountAddress::derive(&creator, b"seed", DeriveScheme::Object),
+            AccountAddress::derive(&creator, b"seed", DeriveScheme::ResourceAccount)
+        );
+        assert_ne!(
+            AccountAddress::derive(&creator, b"seed", DeriveScheme::Object),
+            AccountAddress::derive(&AccountAddress::ONE, b"seed", DeriveScheme::Object)
+        );
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "bech32",
+        not(any(feature = "address20", feature = "address32"))
+    ))]
+    fn test_bech32_vectors() {
+        // generated with the BIP-173 reference implementation
+        let vectors = [
+            ("0x1", "move1qqqqqqqqqqqqqqqqqqqqqqqqqyhln5kt"),
+            ("0xcafe", "move1qqqqqqqqqqqqqqqqqqqqqqx2lcmlmktw"),
+            (
+                "0xca843279e3427144cead5e4d5999a3d0",
+                "move1e2zry70rgfc5fn4dtex4nxdr6qnjcjj3",
+            ),
+        ];
+        for (literal, encoded) in &vectors {
+            let address = AccountAddress::from_hex_literal(literal).unwrap();
+            assert_eq!(address.to_bech32("move").unwrap(), *encoded);
+            assert_eq!(
+                AccountAddress::from_bech32(encoded).unwrap(),
+                ("move".to_string(), address)
+            );
+        }
+
+        // a changed character breaks the checksum
+        AccountAddress::from_bech32("move1qqqqqqqqqqqqqqqqqqqqqqqqqyhln5kq").unwrap_err();
+        // too few bytes for an address
+        let short = bech32::encode(
+            "move",
+            vec![bech32::u5::try_from_u8(0).unwrap(); 8],
+            bech32::Variant::Bech32,
+        )
+        .unwrap();
+        AccountAddress::from_bech32(&short).unwrap_err();
+        AccountAddress::ONE.to_bech32("").unwrap_err();
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "base58",
+        not(any(feature = "address20", feature = "address32"))
+    ))]
+    fn test_base58check_vectors() {
+        // generated with a reference base58check implementation
+        let vectors = [
+            ("0x1", "1111111111111118k14cG"),
+            ("0xcafe", "111111111111112k5xix6F6"),
+            (
+                "0xca843279e3427144cead5e4d5999a3d0",
+                "3peAnKG2XRFQg9wCXC987SkTXfoE",
+            ),
+        ];
+        for (literal, encoded) in &vectors {
+            let address = AccountAddress::from_hex_literal(literal).unwrap();
+            assert_eq!(address.to_base58check(), *encoded);
+            assert_eq!(AccountAddress::from_base58check(encoded).unwrap(), address);
+        }
+
+        AccountAddress::from_base58check("1111111111111118k14cH").unwrap_err();
+        let short = bs58::encode([1u8; 4]).with_check().into_string();
+        AccountAddress::from_base58check(&short).unwrap_err();
+    }
+
+    #[cfg(feature = "bech32")]
+    proptest! {
+        #[test]
+        fn test_bech32_roundtrip(addr in any::<AccountAddress>()) {
+            let encoded = addr.to_bech32("move").unwrap();
+            prop_assert_eq!(AccountAddress::from_bech32(&encoded).unwrap(), ("move".to_string(), addr));
+        }
+    }
+
+    #[cfg(feature = "base58")]
+    proptest! {
+        #[test]
+        fn test_base58check_roundtrip(addr in any::<AccountAddress>()) {
+            prop_assert_eq!(AccountAddress::from_base58check(&addr.to_base58check()).unwrap(), addr);
+        }
+    }
+
+    #[cfg(feature = "subtle")]
+    const _: fn(&AccountAddress, &AccountAddress) -> bool = AccountAddress::ct_eq;
+
+    #[test]
+    #[cfg(feature = "subtle")]
+    fn test_ct_eq() {
+        assert!(AccountAddress::ONE.ct_eq(&AccountAddress::ONE));
+        assert!(AccountAddress::ZERO.ct_eq(&AccountAddress::ZERO));
+        assert!(!AccountAddress::ONE.ct_eq(&AccountAddress::TWO));
+        assert!(!AccountAddress::ZERO.ct_eq(&AccountAddress::MAX));
+        // differing only in the first byte
+        let mut bytes = [0u8; AccountAddress::LENGTH];
+        bytes[0] = 1;
+        assert!(!AccountAddress::new(bytes).ct_eq(&AccountAddress::ZERO));
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn test_zeroize() {
+        use zeroize::Zeroize;
+
+        let mut address = AccountAddress::MAX;
+        address.zeroize();
+        assert_eq!(address, AccountAddress::ZERO);
+    }
+
+    #[cfg(feature = "subtle")]
+    proptest! {
+        #[test]
+        fn test_ct_eq_matches_eq(a in any::<AccountAddress>(), b in any::<AccountAddress>()) {
+            prop_assert_eq!(a.ct_eq(&b), a == b);
+            prop_assert!(a.ct_eq(&a));
+        }
+    }
+
     #[test]
     fn test_ref() {
         let address = AccountAddress::new([1u8; AccountAddress::LENGTH]);
@@ -358,6 +1295,46 @@ mod tests {
         AccountAddress::from_bytes(bytes).unwrap_err();
     }
 
+    #[test]
+    fn test_from_bytes_lengths() {
+        let exact: Vec<u8> = (1..=AccountAddress::LENGTH as u8).collect();
+        let address = AccountAddress::from_bytes(&exact).unwrap();
+        assert_eq!(address.as_slice(), &exact[..]);
+        assert_eq!(address.into_bytes().to_vec(), exact);
+        assert_eq!(AccountAddress::try_from(&exact[..]), Ok(address));
+        assert_eq!(AccountAddress::try_from(exact.clone()), Ok(address));
+        assert_eq!(AccountAddress::from_bytes_left_padded(&exact), Ok(address));
+
+        let short = &exact[..AccountAddress::LENGTH - 1];
+        let mut long = exact.clone();
+        long.push(0);
+        let wrong_lengths: [&[u8]; 3] = [short, &long, &[]];
+        for bytes in &wrong_lengths {
+            let err = AccountAddressParseError::InvalidLength { bytes: bytes.len() };
+            assert_eq!(AccountAddress::from_bytes(bytes), Err(err));
+            assert_eq!(AccountAddress::try_from(*bytes), Err(err));
+            assert_eq!(AccountAddress::try_from(bytes.to_vec()), Err(err));
+        }
+
+        assert_eq!(
+            AccountAddress::from_bytes_left_padded([1u8]),
+            Ok(AccountAddress::ONE)
+        );
+        assert_eq!(
+            AccountAddress::from_bytes_left_padded([0u8; 0]),
+            Ok(AccountAddress::ZERO)
+        );
+        let padded = AccountAddress::from_bytes_left_padded(short).unwrap();
+        assert_eq!(padded.as_slice()[0], 0);
+        assert_eq!(&padded.as_slice()[1..], short);
+        assert_eq!(
+            AccountAddress::from_bytes_left_padded(&long),
+            Err(AccountAddressParseError::InvalidLength {
+                bytes: AccountAddress::LENGTH + 1
+            })
+        );
+    }
+
     #[test]
     fn test_deserialize_from_json_value() {
         let address = AccountAddress::random();
@@ -368,6 +1345,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(any(feature = "address20", feature = "address32")))]
     fn test_serde_json() {
         let hex = "ca843279e3427144cead5e4d5999a3d0";
         let json_hex = "\"ca843279e3427144cead5e4d5999a3d0\"";
@@ -381,6 +1359,43 @@ mod tests {
         assert_eq!(address, json_address);
     }
 
+    #[test]
+    fn test_serde_json_forms() {
+        let address = AccountAddress::from_hex_literal("0xcafe").unwrap();
+        assert_eq!(
+            serde_json::to_value(address).unwrap(),
+            serde_json::json!(address.to_canonical_string())
+        );
+        for s in &[
+            "0xcafe".to_string(),
+            "0xCAFE".to_string(),
+            address.to_canonical_string(),
+            format!("{:#x}", address),
+        ] {
+            let parsed: AccountAddress = serde_json::from_value(serde_json::json!(s)).unwrap();
+            assert_eq!(parsed, address, "{}", s);
+        }
+        for s in &["", "0x", "cafe", "0xcafg", "0x0xcafe"] {
+            serde_json::from_value::<AccountAddress>(serde_json::json!(s)).unwrap_err();
+        }
+        let too_long = format!("0x1{}", address.to_canonical_string());
+        let err =
+            serde_json::from_value::<AccountAddress>(serde_json::json!(too_long)).unwrap_err();
+        assert!(err.to_string().contains("expected at most"), "{}", err);
+        // the byte array of non-human-readable formats is not accepted
+        serde_json::from_value::<AccountAddress>(serde_json::json!(address.to_vec())).unwrap_err();
+    }
+
+    #[test]
+    fn test_bcs_is_raw_bytes() {
+        let address = AccountAddress::from_hex_literal("0xcafe").unwrap();
+        assert_eq!(bcs::to_bytes(&address).unwrap(), address.to_vec());
+        assert_eq!(
+            bcs::from_bytes::<AccountAddress>(&address.to_vec()).unwrap(),
+            address
+        );
+    }
+
     #[test]
     fn test_address_from_empty_string() {
         assert!(AccountAddress::try_from("".to_string()).is_err());
@@ -395,6 +1410,18 @@ mod tests {
             prop_assert_eq!(addr, addr2);
         }
 
+        #[test]
+        fn test_address_string_forms_roundtrip(addr in any::<AccountAddress>()) {
+            assert_string_forms_roundtrip(&addr);
+        }
+
+        #[test]
+        fn test_address_checksum_roundtrip(addr in any::<AccountAddress>()) {
+            let checksummed = addr.to_checksum_string();
+            prop_assert_eq!(AccountAddress::from_checksum_string(&checksummed), Ok(addr));
+            prop_assert_eq!(checksummed.to_lowercase(), format!("{:#x}", addr));
+        }
+
         #[test]
         fn test_address_protobuf_roundtrip(addr in any::<AccountAddress>()) {
             let bytes = addr.to_vec();
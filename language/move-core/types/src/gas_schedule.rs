@@ -6,6 +6,7 @@
 //! It is important to note that the cost schedule defined in this file does not track hashing
 //! operations or other native operations; the cost of each native operation will be returned by the
 //! native function itself.
+use crate::account_address::AccountAddress;
 use mirai_annotations::*;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -151,6 +152,33 @@ pub const REFERENCE_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(8)
 /// The size of a struct in bytes
 pub const STRUCT_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(2);
 
+/// The size in bytes of an address on the stack
+pub const ADDRESS_SIZE: AbstractMemorySize<GasCarrier> =
+    AbstractMemorySize(AccountAddress::LENGTH as GasCarrier);
+
+/// The size in bytes of an element of a `vector<u8>`
+pub const VEC_U8_ELEMENT_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(1);
+
+/// The size in bytes of an element of a `vector<u64>`
+pub const VEC_U64_ELEMENT_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(8);
+
+/// The size in bytes of an element of a `vector<u128>`
+pub const VEC_U128_ELEMENT_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(16);
+
+/// The size in bytes of an element of a `vector<bool>`
+pub const VEC_BOOL_ELEMENT_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(1);
+
+/// The size in bytes of an element of a `vector<address>`
+pub const VEC_ADDRESS_ELEMENT_SIZE: AbstractMemorySize<GasCarrier> =
+    AbstractMemorySize(AccountAddress::LENGTH as GasCarrier);
+
+/// The abstract size of every node of a type tag, see `TypeTag::abstract_size`
+pub const TYPE_TAG_NODE_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(8);
+
+/// The abstract size of every byte of the module and struct names in a type tag, see
+/// `TypeTag::abstract_size`
+pub const TYPE_TAG_IDENTIFIER_BYTE_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(1);
+
 /// For V1 all accounts will be ~800 bytes
 pub const DEFAULT_ACCOUNT_SIZE: AbstractMemorySize<GasCarrier> = AbstractMemorySize(800);
 
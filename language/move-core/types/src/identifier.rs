@@ -30,8 +30,15 @@ use anyhow::{bail, Result};
 #[cfg(any(test, feature = "fuzzing"))]
 use proptest::prelude::*;
 use ref_cast::RefCast;
-use serde::{Deserialize, Serialize};
-use std::{borrow::Borrow, fmt, ops::Deref, str::FromStr};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    fmt,
+    ops::Deref,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 /// Return true if this character can appear in a Move identifier.
 ///
@@ -42,36 +49,173 @@ pub const fn is_valid_identifier_char(c: char) -> bool {
     matches!(c, '_' | 'a'..='z' | 'A'..='Z' | '0'..='9')
 }
 
-/// Returns `true` if all bytes in `b` after the offset `start_offset` are valid
-/// ASCII identifier characters.
-const fn all_bytes_valid(b: &[u8], start_offset: usize) -> bool {
-    let mut i = start_offset;
-    // TODO(philiphayes): use for loop instead of while loop when it's stable in const fn's.
-    while i < b.len() {
-        if !is_valid_identifier_char(b[i] as char) {
-            return false;
+/// The maximum length of an identifier in bytes. It applies wherever identifiers are created from
+/// untrusted input, including deserialization, even though the bytecode format could store longer
+/// ones: nothing needs them, and they make formatting and comparing names needlessly expensive.
+pub const MAX_IDENTIFIER_LENGTH: usize = 255;
+
+/// Why a string is not a valid identifier, see `validity_error`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidIdentifierKind {
+    /// The string is empty
+    Empty,
+    /// The string starts with a digit
+    StartsWithDigit,
+    /// The string is a lone underscore, which is reserved
+    Reserved,
+    /// The character starting at byte `index` is not an ASCII letter, digit or underscore
+    InvalidChar { index: usize },
+    /// The string is longer than the maximum of `max` bytes, by default `MAX_IDENTIFIER_LENGTH`
+    TooLong { len: usize, max: usize },
+}
+
+impl fmt::Display for InvalidIdentifierKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "identifier is empty"),
+            Self::StartsWithDigit => write!(f, "identifier starts with a digit"),
+            Self::InvalidChar { index } => write!(f, "invalid character at byte {}", index),
+            Self::TooLong { len, max } => write!(
+                f,
+                "identifier of {} bytes is longer than the maximum of {}",
+                len, max
+            ),
+            Self::Reserved => write!(f, "identifier is reserved"),
         }
-        i += 1;
     }
-    true
 }
 
-/// Describes what identifiers are allowed.
+impl std::error::Error for InvalidIdentifierKind {}
+
+/// Describes what identifiers are allowed: `None` if `s` is a valid identifier, and otherwise the
+/// first rule that it breaks, checked in the order of the `InvalidIdentifierKind` variants.
 ///
 /// For now this is deliberately restrictive -- we would like to evolve this in the future.
 // TODO: "<SELF>" is coded as an exception. It should be removed once CompiledScript goes away.
-// Note: needs to be pub as it's used in the `ident_str!` macro.
-pub const fn is_valid(s: &str) -> bool {
+pub const fn validity_error(s: &str) -> Option<InvalidIdentifierKind> {
+    validity_error_with_max_length(s, MAX_IDENTIFIER_LENGTH)
+}
+
+/// Like `validity_error`, but with a maximum length of `max_length` bytes instead of
+/// `MAX_IDENTIFIER_LENGTH`, for callers with limits of their own, such as the binary format
+pub const fn validity_error_with_max_length(
+    s: &str,
+    max_length: usize,
+) -> Option<InvalidIdentifierKind> {
     // Rust const fn's don't currently support slicing or indexing &str's, so we
     // have to operate on the underlying byte slice. This is not a problem as
     // valid identifiers are (currently) ASCII-only.
     let b = s.as_bytes();
     match b {
-        b"<SELF>" => true,
-        [b'a'..=b'z', ..] | [b'A'..=b'Z', ..] => all_bytes_valid(b, 1),
-        [b'_', ..] if b.len() > 1 => all_bytes_valid(b, 1),
-        _ => false,
+        b"<SELF>" => return None,
+        [] => return Some(InvalidIdentifierKind::Empty),
+        [b'0'..=b'9', ..] => return Some(InvalidIdentifierKind::StartsWithDigit),
+        [b'_'] => return Some(InvalidIdentifierKind::Reserved),
+        _ => (),
     }
+    let mut i = 0;
+    // TODO(philiphayes): use for loop instead of while loop when it's stable in const fn's.
+    while i < b.len() {
+        if !is_valid_identifier_char(b[i] as char) {
+            return Some(InvalidIdentifierKind::InvalidChar { index: i });
+        }
+        i += 1;
+    }
+    if b.len() > max_length {
+        return Some(InvalidIdentifierKind::TooLong {
+            len: b.len(),
+            max: max_length,
+        });
+    }
+    None
+}
+
+/// Returns `true` if `s` is a valid identifier, see `validity_error`.
+// Note: needs to be pub as it's used in `IdentStr::const_new`.
+pub const fn is_valid(s: &str) -> bool {
+    validity_error(s).is_none()
+}
+
+/// The leading underscores of `s`, and the words of the rest: runs of ASCII letters and digits,
+/// split where a lowercase letter or digit is followed by an uppercase letter (`fooBar`) and before
+/// the last uppercase letter of an acronym followed by a lowercase one (`BCSBytes`). Underscores
+/// and any other characters only separate words.
+fn words(s: &str) -> (&str, Vec<&str>) {
+    let rest = s.trim_start_matches('_');
+    let leading = &s[..s.len() - rest.len()];
+    let b = rest.as_bytes();
+    let mut words = vec![];
+    let mut start = None;
+    for i in 0..b.len() {
+        if !b[i].is_ascii_alphanumeric() {
+            if let Some(start) = start.take() {
+                words.push(&rest[start..i]);
+            }
+            continue;
+        }
+        let boundary = i > 0
+            && b[i].is_ascii_uppercase()
+            && (b[i - 1].is_ascii_lowercase()
+                || b[i - 1].is_ascii_digit()
+                || (b[i - 1].is_ascii_uppercase()
+                    && b.get(i + 1).map_or(false, u8::is_ascii_lowercase)));
+        match start {
+            Some(word_start) if boundary => {
+                words.push(&rest[word_start..i]);
+                start = Some(i);
+            }
+            Some(_) => (),
+            None => start = Some(i),
+        }
+    }
+    if let Some(start) = start {
+        words.push(&rest[start..]);
+    }
+    (leading, words)
+}
+
+/// `s` in snake case: its words (see `IdentStr::to_snake_case`) in lowercase, joined by
+/// underscores, after its leading underscores
+pub fn to_snake_case(s: &str) -> String {
+    let (leading, words) = words(s);
+    let mut snake = leading.to_string();
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            snake.push('_');
+        }
+        snake.push_str(&word.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// `s` in upper camel case: its words (see `IdentStr::to_snake_case`) with their first letter in
+/// uppercase and the rest in lowercase, after its leading underscores
+pub fn to_upper_camel_case(s: &str) -> String {
+    let (leading, words) = words(s);
+    let mut camel = leading.to_string();
+    for word in words {
+        camel.push_str(&word[..1].to_ascii_uppercase());
+        camel.push_str(&word[1..].to_ascii_lowercase());
+    }
+    camel
+}
+
+/// Whether `s` is a valid identifier in snake case: without uppercase letters
+pub fn is_snake(s: &str) -> bool {
+    is_valid(s) && !s.bytes().any(|b| b.is_ascii_uppercase())
+}
+
+/// Whether `s` is a valid identifier in upper camel case: starting with an uppercase letter after
+/// any leading underscores, and without underscores after it. Acronyms such as `BCSBytes` are
+/// allowed, as by Rust's naming lints.
+pub fn is_upper_camel(s: &str) -> bool {
+    let rest = s.trim_start_matches('_');
+    is_valid(s)
+        && rest
+            .bytes()
+            .next()
+            .map_or(false, |b| b.is_ascii_uppercase())
+        && !rest.contains('_')
 }
 
 /// A regex describing what identifiers are allowed. Used for proptests.
@@ -87,21 +231,41 @@ pub(crate) static ALLOWED_NO_SELF_IDENTIFIERS: &str =
 /// An owned identifier.
 ///
 /// For more details, see the module level documentation.
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
-pub struct Identifier(Box<str>);
-// An identifier cannot be mutated so use Box<str> instead of String -- it is 1 word smaller.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Identifier(Arc<str>);
+// An identifier cannot be mutated, so it can be an Arc<str> rather than a String: clones are a
+// reference count increment that shares the string instead of copying it. That keeps the same
+// names cheap to attach to many values (see `MoveValue::decorate`) and lets `IdentifierPool` hand
+// out one shared copy of each name.
 
 impl Identifier {
     /// Creates a new `Identifier` instance.
-    pub fn new(s: impl Into<Box<str>>) -> Result<Self> {
+    pub fn new(s: impl Into<Arc<str>>) -> Result<Self> {
         let s = s.into();
-        if Self::is_valid(&s) {
-            Ok(Self(s))
-        } else {
-            bail!("Invalid identifier '{}'", s);
+        match validity_error(&s) {
+            None => Ok(Self(s)),
+            Some(kind) => bail!("Invalid identifier '{}': {}", s, kind),
         }
     }
 
+    /// Like `new`, but with a maximum length of `max_length` bytes instead of
+    /// `MAX_IDENTIFIER_LENGTH`
+    pub fn new_with_max_length(s: impl Into<Arc<str>>, max_length: usize) -> Result<Self> {
+        let s = s.into();
+        match validity_error_with_max_length(&s, max_length) {
+            None => Ok(Self(s)),
+            Some(kind) => bail!("Invalid identifier '{}': {}", s, kind),
+        }
+    }
+
+    /// Creates a new `Identifier` without checking its validity, for trusted VM-internal names
+    /// only. The caller must guarantee that `s` is a valid identifier, including its length:
+    /// nothing else checks it, and invalid identifiers break the invariants of code handling them.
+    /// Never pass it anything derived from untrusted input.
+    pub fn new_unchecked(s: impl Into<Arc<str>>) -> Self {
+        Self(s.into())
+    }
+
     /// Returns true if this string is a valid identifier.
     pub fn is_valid(s: impl AsRef<str>) -> bool {
         is_valid(s.as_ref())
@@ -129,7 +293,7 @@ impl Identifier {
     /// This is not implemented as a `From` trait to discourage automatic conversions -- these
     /// conversions should not typically happen.
     pub fn into_string(self) -> String {
-        self.0.into()
+        self.0.to_string()
     }
 
     /// Converts this `Identifier` into a UTF-8-encoded byte sequence.
@@ -168,12 +332,105 @@ impl Deref for Identifier {
     }
 }
 
+// Serialized as a newtype around the string, like a derived implementation for `Box<str>` would
+impl Serialize for Identifier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct("Identifier", &*self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Identifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "Identifier")]
+        struct Value(Box<str>);
+
+        let value = Value::deserialize(deserializer)?;
+        // the same rules as `Identifier::new`, so that untrusted input cannot bypass them
+        if let Some(kind) = validity_error(&value.0) {
+            return Err(D::Error::custom(format!(
+                "Invalid identifier '{}': {}",
+                value.0, kind
+            )));
+        }
+        Ok(Self(value.0.into()))
+    }
+}
+
 impl fmt::Display for Identifier {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", &self.0)
     }
 }
 
+/// A bounded, thread-safe pool of shared `Identifier`s. Interning equal identifiers gives clones
+/// of one `Identifier`, which share its string, so that the same few names created over and over
+/// (`value`, `coin`, ...) are kept once.
+///
+/// The pool holds at most `capacity` identifiers. Once it is full, identifiers not already in it
+/// are returned unshared rather than evicting others, so the names interned first, which tend to
+/// be the most common, stay shared. Interned identifiers are ordinary `Identifier`s: they compare,
+/// hash and serialize the same as any other.
+#[derive(Debug)]
+pub struct IdentifierPool {
+    capacity: usize,
+    identifiers: Mutex<HashSet<Identifier>>,
+}
+
+impl IdentifierPool {
+    /// An empty pool holding at most `capacity` identifiers
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            identifiers: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// The shared identifier `s`, adding it if there is none and the pool is not full. Fails if
+    /// `s` is not a valid identifier.
+    pub fn get_or_intern(&self, s: &str) -> Result<Identifier> {
+        Ok(self.intern(IdentStr::new(s)?))
+    }
+
+    /// The shared copy of `ident`, adding one if there is none and the pool is not full
+    pub fn intern(&self, ident: &IdentStr) -> Identifier {
+        let mut identifiers = self.identifiers.lock().unwrap();
+        if let Some(shared) = identifiers.get(ident) {
+            return shared.clone();
+        }
+        let ident = ident.to_owned();
+        if identifiers.len() < self.capacity {
+            identifiers.insert(ident.clone());
+        }
+        ident
+    }
+
+    /// Like `intern`, but keeps `ident` itself if it is not in the pool, instead of copying it
+    pub fn intern_owned(&self, ident: Identifier) -> Identifier {
+        let mut identifiers = self.identifiers.lock().unwrap();
+        if let Some(shared) = identifiers.get(ident.as_ident_str()) {
+            return shared.clone();
+        }
+        if identifiers.len() < self.capacity {
+            identifiers.insert(ident.clone());
+        }
+        ident
+    }
+
+    /// The number of identifiers in the pool
+    pub fn len(&self) -> usize {
+        self.identifiers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
 /// A borrowed identifier.
 ///
 /// For more details, see the module level documentation.
@@ -183,10 +440,32 @@ pub struct IdentStr(str);
 
 impl IdentStr {
     pub fn new(s: &str) -> Result<&IdentStr> {
-        if Self::is_valid(s) {
-            Ok(IdentStr::ref_cast(s))
-        } else {
-            bail!("Invalid identifier '{}'", s);
+        match validity_error(s) {
+            None => Ok(IdentStr::ref_cast(s)),
+            Some(kind) => bail!("Invalid identifier '{}': {}", s, kind),
+        }
+    }
+
+    /// Creates a `&'static IdentStr` in const contexts, for well-known identifiers defined as
+    /// consts or statics. Panics if `s` is not a valid identifier, which in a const context is a
+    /// compile error; `ident_str!` always evaluates it in one.
+    pub const fn const_new(s: &'static str) -> &'static IdentStr {
+        // Note: Work-around hack to panic in a const fn.
+        ["String is not a valid Move identifier"][!is_valid(s) as usize];
+
+        // SAFETY: the following transmute is safe because
+        // (1) it's equivalent to the unsafe-reborrow inside IdentStr::ref_cast()
+        //     (which we can't use b/c it's not const).
+        // (2) we assert below that IdentStr impls RefCast<From = str>, which already guarantees
+        //     the transmute is safe (RefCast checks that IdentStr(str) is #[repr(transparent)]).
+        // (3) both in and out lifetimes are 'static, so we're not widening the lifetime.
+        // (4) we've just asserted that the IdentStr passes the is_valid check.
+        //
+        // Note: this lint is unjustified and no longer checked. See issue:
+        // https://github.com/rust-lang/rust-clippy/issues/6372
+        #[allow(clippy::transmute_ptr_to_ptr)]
+        unsafe {
+            std::mem::transmute::<&'static str, &'static IdentStr>(s)
         }
     }
 
@@ -217,6 +496,31 @@ impl IdentStr {
     pub fn as_bytes(&self) -> &[u8] {
         self.0.as_bytes()
     }
+
+    /// This identifier in snake case, e.g. `bcs_bytes` for `BCSBytes`. Words are split at
+    /// underscores, where a lowercase letter or digit is followed by an uppercase letter, and
+    /// before the last letter of an acronym followed by a lowercase letter. Leading underscores are
+    /// kept. Fails only if the result is longer than `MAX_IDENTIFIER_LENGTH`.
+    pub fn to_snake_case(&self) -> Result<Identifier> {
+        Identifier::new(to_snake_case(self.as_str()))
+    }
+
+    /// This identifier in upper camel case, e.g. `BcsBytes` for `bcs_bytes` or `BCSBytes`, with
+    /// words split as by `to_snake_case`. Leading underscores are kept. The result is never
+    /// longer than this identifier, so this fails only if the rules for identifiers change.
+    pub fn to_upper_camel_case(&self) -> Result<Identifier> {
+        Identifier::new(to_upper_camel_case(self.as_str()))
+    }
+
+    /// Whether this identifier is in snake case, see `identifier::is_snake`
+    pub fn is_snake(&self) -> bool {
+        is_snake(self.as_str())
+    }
+
+    /// Whether this identifier is in upper camel case, see `identifier::is_upper_camel`
+    pub fn is_upper_camel(&self) -> bool {
+        is_upper_camel(self.as_str())
+    }
 }
 
 impl Borrow<IdentStr> for Identifier {
@@ -233,19 +537,43 @@ impl ToOwned for IdentStr {
     }
 }
 
+/// Deserializes without allocating, from formats that can lend strings from their input (such as
+/// BCS or JSON without escapes); other formats fail with an invalid type error.
+impl<'de: 'a, 'a> Deserialize<'de> for &'a IdentStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "Identifier")]
+        struct Value<'a>(&'a str);
+
+        let value = Value::deserialize(deserializer)?;
+        IdentStr::new(value.0).map_err(D::Error::custom)
+    }
+}
+
 impl fmt::Display for IdentStr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", &self.0)
     }
 }
 
+/// The maximum length of the identifiers generated by `any::<Identifier>()`
+#[cfg(any(test, feature = "fuzzing"))]
+pub const MAX_ARBITRARY_IDENTIFIER_LENGTH: usize = 32;
+
 #[cfg(any(test, feature = "fuzzing"))]
 impl Arbitrary for Identifier {
     type Parameters = ();
     type Strategy = BoxedStrategy<Self>;
 
     fn arbitrary_with((): ()) -> Self::Strategy {
-        ALLOWED_NO_SELF_IDENTIFIERS
+        // ALLOWED_NO_SELF_IDENTIFIERS, with bounded repetitions
+        let max_tail = MAX_ARBITRARY_IDENTIFIER_LENGTH - 1;
+        let regex = format!(
+            r"(?:[a-zA-Z][a-zA-Z0-9_]{{0,{0}}})|(?:_[a-zA-Z0-9_]{{1,{0}}})",
+            max_tail
+        );
+        proptest::string::string_regex(&regex)
+            .expect("valid regex")
             .prop_map(|s| {
                 // Identifier::new will verify that generated identifiers are correct.
                 Identifier::new(s).unwrap()
@@ -282,35 +610,19 @@ const _: fn() = || {
 /// use move_core_types::{ident_str, identifier::IdentStr};
 /// const INVALID_IDENT: &'static IdentStr = ident_str!("123Foo"); // Fails to compile!
 /// ```
-// TODO(philiphayes): this should really be an associated const fn like `IdentStr::new`;
-// unfortunately, both unsafe-reborrow and unsafe-transmute don't currently work
-// inside const fn's. Only unsafe-transmute works inside static const-blocks
-// (but not const-fn's).
+///
+/// even outside of const contexts:
+///
+/// ```rust,compile_fail
+/// use move_core_types::ident_str;
+/// let invalid_ident = ident_str!("Foo::Bar"); // Fails to compile!
+/// ```
 #[macro_export]
 macro_rules! ident_str {
     ($ident:expr) => {{
-        // Only static strings allowed.
-        let s: &'static str = $ident;
-
-        // Only valid identifier strings are allowed.
-        // Note: Work-around hack to print an error message in a const block.
-        let is_valid = $crate::identifier::is_valid(s);
-        ["String is not a valid Move identifier"][!is_valid as usize];
-
-        // SAFETY: the following transmute is safe because
-        // (1) it's equivalent to the unsafe-reborrow inside IdentStr::ref_cast()
-        //     (which we can't use b/c it's not const).
-        // (2) we've just asserted that IdentStr impls RefCast<From = str>, which
-        //     already guarantees the transmute is safe (RefCast checks that
-        //     IdentStr(str) is #[repr(transparent)]).
-        // (3) both in and out lifetimes are 'static, so we're not widening the lifetime.
-        // (4) we've just asserted that the IdentStr passes the is_valid check.
-        //
-        // Note: this lint is unjustified and no longer checked. See issue:
-        // https://github.com/rust-lang/rust-clippy/issues/6372
-        #[allow(clippy::transmute_ptr_to_ptr)]
-        unsafe {
-            ::std::mem::transmute::<&'static str, &'static $crate::identifier::IdentStr>(s)
-        }
+        // Evaluating in a const forces the validity check to happen at compile time.
+        const IDENT: &'static $crate::identifier::IdentStr =
+            $crate::identifier::IdentStr::const_new($ident);
+        IDENT
     }};
 }
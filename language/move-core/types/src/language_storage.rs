@@ -3,12 +3,30 @@
 
 use crate::{
     account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, TYPE_TAG_IDENTIFIER_BYTE_SIZE, TYPE_TAG_NODE_SIZE},
     identifier::{IdentStr, Identifier},
+    parser::{self, ParseError},
 };
+use anyhow::{bail, format_err, Result as AResult};
 #[cfg(any(test, feature = "fuzzing"))]
 use proptest_derive::Arbitrary;
-use serde::{Deserialize, Serialize};
-use std::fmt::{Display, Formatter};
+use serde::{
+    de::{self, value::MapAccessDeserializer},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use sha3::{Digest, Sha3_256};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering as AtomicOrdering},
+        Arc, Mutex,
+    },
+};
 
 pub const CODE_TAG: u8 = 0;
 pub const RESOURCE_TAG: u8 = 1;
@@ -16,33 +34,43 @@ pub const RESOURCE_TAG: u8 = 1;
 /// Hex address: 0x1
 pub const CORE_CODE_ADDRESS: AccountAddress = AccountAddress::ONE;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Hash, Eq, Clone, PartialOrd, Ord)]
+/// The maximum depth of the type tags produced by `TypeTagTemplate::substitute` and, by default,
+/// of those parsed by `TypeTag::from_str`
+pub const TYPE_TAG_DEPTH_MAX: usize = 256;
+
+/// The maximum number of nodes of the type tags parsed by `TypeTag::from_str` by default
+pub const TYPE_TAG_NODES_MAX: usize = 4096;
+
+/// The type of a Move value, as named in storage keys and transaction arguments.
+///
+/// Its `Display`, `Hash`, comparison and `Drop` implementations recurse into type arguments, so
+/// a tag from untrusted input should be checked with `TypeTag::check_limits` before use. Tags
+/// within `TypeTagLimits::default()`, which `TypeTag::from_str` enforces, are safe.
+///
+/// Tags are ordered independently of the order of the variants here, see the `Ord` impl.
+///
+/// Human-readable formats such as JSON represent a tag as its canonical string (see
+/// `to_canonical_string`), while other formats such as BCS represent it as the enum it is.
+#[derive(Debug, PartialEq, Hash, Eq, Clone)]
 pub enum TypeTag {
-    #[serde(rename = "bool")]
     Bool,
-    #[serde(rename = "u8")]
     U8,
-    #[serde(rename = "u64")]
     U64,
-    #[serde(rename = "u128")]
     U128,
-    #[serde(rename = "address")]
     Address,
-    #[serde(rename = "signer")]
     Signer,
-    #[serde(rename = "vector")]
     Vector(Box<TypeTag>),
-    #[serde(rename = "struct")]
     Struct(StructTag),
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Hash, Eq, Clone, PartialOrd, Ord)]
+/// Represented in serde formats like `TypeTag`: as its canonical string in human-readable ones,
+/// and as the struct it is in others
+#[derive(Debug, PartialEq, Hash, Eq, Clone)]
 pub struct StructTag {
     pub address: AccountAddress,
     pub module: Identifier,
     pub name: Identifier,
     // TODO: rename to "type_args" (or better "ty_args"?)
-    #[serde(rename = "type_args")]
     pub type_params: Vec<TypeTag>,
 }
 
@@ -56,6 +84,699 @@ impl StructTag {
     pub fn module_id(&self) -> ModuleId {
         ModuleId::new(self.address, self.module.to_owned())
     }
+
+    /// This tag with addresses in their shortest form, e.g. `0x1::Diem::Diem<0x1::XUS::XUS>`, as
+    /// displayed. Nothing is left out, so the result parses back into this tag.
+    pub fn short_str_lossless(&self) -> String {
+        self.to_string()
+    }
+
+    /// `short_str_lossless`, unless that is longer than `max_len` bytes and this tag has type
+    /// arguments, in which case they are elided as in `0x1::Diem::Diem<..>`. Identifiers are
+    /// never cut, so the result may still be longer than `max_len`.
+    pub fn short_str(&self, max_len: usize) -> String {
+        let s = self.short_str_lossless();
+        if s.len() <= max_len || self.type_params.is_empty() {
+            return s;
+        }
+        format!(
+            "0x{}::{}::{}<..>",
+            self.address.short_str_lossless(),
+            self.module,
+            self.name
+        )
+    }
+
+    /// The type arguments of this tag
+    pub fn type_args(&self) -> &[TypeTag] {
+        &self.type_params
+    }
+
+    /// This tag with `f` applied to each of its type arguments, e.g. to replace the coin type in
+    /// `CoinStore<OldCoin>`
+    pub fn map_type_args(self, f: impl FnMut(TypeTag) -> TypeTag) -> StructTag {
+        StructTag {
+            type_params: self.type_params.into_iter().map(f).collect(),
+            ..self
+        }
+    }
+
+    /// The key of the resource of this type stored at `address`
+    pub fn resource_key(&self, address: AccountAddress) -> ResourceKey {
+        ResourceKey::new(address, self.clone())
+    }
+
+    /// This tag as displayed, but with its addresses (and those of its type arguments) in full,
+    /// as all `2 * AccountAddress::LENGTH` hex digits, so that equal tags have equal strings.
+    /// `Display` shortens addresses instead, e.g. to `0x1::XUS::XUS`.
+    pub fn to_canonical_string(&self) -> String {
+        let mut s = format!(
+            "0x{}::{}::{}",
+            self.address.to_hex(),
+            self.module,
+            self.name
+        );
+        if !self.type_params.is_empty() {
+            let ty_args: Vec<_> = self
+                .type_params
+                .iter()
+                .map(TypeTag::to_canonical_string)
+                .collect();
+            s.push('<');
+            s.push_str(&ty_args.join(", "));
+            s.push('>');
+        }
+        s
+    }
+
+    /// Parse a tag in the form of `to_canonical_string`, failing on any other form of it
+    pub fn from_canonical_string(s: &str) -> Result<Self, ParseError> {
+        parser::struct_tag_from_canonical_str(s)
+    }
+
+    /// Display this tag with the addresses in `names` replaced by their names, see
+    /// `TypeTag::display_with`
+    pub fn display_with<'a>(
+        &'a self,
+        names: &'a BTreeMap<AccountAddress, String>,
+    ) -> WithAddressNames<'a, Self> {
+        WithAddressNames { value: self, names }
+    }
+
+    /// Parse a struct tag like `from_str`, but also accepting the names in `names` in place of the
+    /// addresses they name. Unlike `from_str`, the address must not be written without `0x`.
+    pub fn from_str_with_names(
+        s: &str,
+        names: &BTreeMap<AccountAddress, String>,
+    ) -> Result<Self, ParseError> {
+        parser::struct_tag_from_str_with_names(s, names)
+    }
+
+    /// The abstract size of this tag, see `TypeTag::abstract_size`
+    pub fn abstract_size(&self) -> u64 {
+        self.type_params
+            .iter()
+            .fold(self.node_size(), |size, ty| size + ty.abstract_size())
+    }
+
+    /// The abstract size of this tag without its type arguments
+    fn node_size(&self) -> u64 {
+        let identifier_bytes = (self.module.len() + self.name.len()) as u64;
+        TYPE_TAG_NODE_SIZE.get() + identifier_bytes * TYPE_TAG_IDENTIFIER_BYTE_SIZE.get()
+    }
+
+    /// Whether this tag names the same struct as `other`, at the same address and in the same
+    /// module, and, unless `ignore_type_args` is set, with the same type arguments
+    pub fn matches(&self, other: &StructTag, ignore_type_args: bool) -> bool {
+        self.address == other.address
+            && self.module == other.module
+            && self.name == other.name
+            && (ignore_type_args || self.type_params == other.type_params)
+    }
+}
+
+impl TypeTag {
+    /// Whether this is `bool`, an integer type, or `address`: the types whose values have no
+    /// structure, and that have the `copy`, `drop` and `store` abilities. `signer` is not
+    /// included, as it cannot be copied or stored.
+    pub fn is_primitive(&self) -> bool {
+        use TypeTag::*;
+        match self {
+            Bool | U8 | U64 | U128 | Address => true,
+            Signer | Vector(_) | Struct(_) => false,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        use TypeTag::*;
+        match self {
+            U8 | U64 | U128 => true,
+            Bool | Address | Signer | Vector(_) | Struct(_) => false,
+        }
+    }
+
+    /// Whether a transaction may pass a value of this type as an argument: a primitive, a vector
+    /// of such types, or, if `allow_structs`, a struct. Signers are never arguments; a function
+    /// may take them as leading parameters, but they are supplied by the VM, not the
+    /// transaction. Only the struct tag is checked for structs, whose fields are the caller's to
+    /// validate (see `MoveTypeLayout::is_valid_txn_arg_type`).
+    pub fn is_valid_txn_arg_type(&self, allow_structs: bool) -> bool {
+        use TypeTag::*;
+        match self {
+            Bool | U8 | U64 | U128 | Address => true,
+            Vector(elem) => elem.is_valid_txn_arg_type(allow_structs),
+            Struct(_) => allow_structs,
+            Signer => false,
+        }
+    }
+
+    /// This tag as displayed, but with the addresses in its struct tags in full, see
+    /// `StructTag::to_canonical_string`
+    pub fn to_canonical_string(&self) -> String {
+        match self {
+            TypeTag::Struct(s) => s.to_canonical_string(),
+            TypeTag::Vector(ty) => format!("vector<{}>", ty.to_canonical_string()),
+            ty => ty.to_string(),
+        }
+    }
+
+    /// Parse a tag in the form of `to_canonical_string`, failing on any other form of it
+    pub fn from_canonical_string(s: &str) -> Result<Self, ParseError> {
+        parser::type_tag_from_canonical_str(s)
+    }
+
+    /// Display this tag with the addresses in `names` replaced by their names, e.g. as
+    /// `vector<Std::Option::Option<0x42::M::S>>` rather than
+    /// `vector<0x1::Option::Option<0x42::M::S>>` if `0x1` is named `Std`. Other addresses are
+    /// displayed in hex, as by `Display`.
+    pub fn display_with<'a>(
+        &'a self,
+        names: &'a BTreeMap<AccountAddress, String>,
+    ) -> WithAddressNames<'a, Self> {
+        WithAddressNames { value: self, names }
+    }
+
+    /// Parse a type tag like `from_str`, but also accepting the names in `names` in place of the
+    /// addresses they name, so that the output of `display_with` parses back
+    pub fn from_str_with_names(
+        s: &str,
+        names: &BTreeMap<AccountAddress, String>,
+    ) -> Result<Self, ParseError> {
+        parser::type_tag_from_str_with_names(s, names)
+    }
+
+    /// This tag with `f` applied to every struct tag in it, at any nesting level. The type
+    /// arguments of a struct tag are rewritten before `f` is applied to it, so `f` sees them
+    /// rewritten already.
+    pub fn map_structs(self, mut f: impl FnMut(StructTag) -> StructTag) -> TypeTag {
+        self.map_structs_with(&mut f)
+    }
+
+    fn map_structs_with<F: FnMut(StructTag) -> StructTag>(self, f: &mut F) -> TypeTag {
+        match self {
+            TypeTag::Vector(elem) => TypeTag::Vector(Box::new(elem.map_structs_with(f))),
+            TypeTag::Struct(tag) => {
+                let tag = tag.map_type_args(|ty| ty.map_structs_with(f));
+                TypeTag::Struct(f(tag))
+            }
+            ty => ty,
+        }
+    }
+
+    /// Parse a type tag like `from_str`, but failing if it exceeds `limits` rather than the
+    /// default limits
+    pub fn from_str_with_limits(s: &str, limits: &TypeTagLimits) -> Result<Self, ParseError> {
+        parser::type_tag_from_str_with_limits(s, limits)
+    }
+
+    /// The depth of this tag: 1 for primitive types, plus one level for every enclosing vector or
+    /// struct
+    pub fn depth(&self) -> usize {
+        let mut max_depth = 0;
+        let mut pending = vec![(self, 1)];
+        while let Some((ty, depth)) = pending.pop() {
+            max_depth = std::cmp::max(max_depth, depth);
+            pending.extend(ty.type_args().iter().map(|ty| (ty, depth + 1)));
+        }
+        max_depth
+    }
+
+    /// The number of nodes in this tag, where a vector has its element type as child and a struct
+    /// its type arguments
+    pub fn num_nodes(&self) -> usize {
+        self.preorder_iter().count()
+    }
+
+    /// Iterate over this tag and the tags nested in it, each before the tags nested in it and
+    /// type arguments in order, e.g. `vector<0x1::M::S<u8, bool>>`, `0x1::M::S<u8, bool>`, `u8`,
+    /// `bool`. The iteration keeps its own stack, so it is safe on tags of any depth.
+    pub fn preorder_iter(&self) -> impl Iterator<Item = &TypeTag> {
+        TypeTagPreorderIter {
+            pending: vec![self],
+        }
+    }
+
+    /// Whether `signer` occurs anywhere in this tag
+    pub fn contains_signer(&self) -> bool {
+        self.preorder_iter().any(|ty| ty == &TypeTag::Signer)
+    }
+
+    /// Whether this tag is, or has nested in it, a struct declared in `module` at `address`
+    pub fn contains_struct_from(&self, address: &AccountAddress, module: &IdentStr) -> bool {
+        self.preorder_iter().any(|ty| match ty {
+            TypeTag::Struct(tag) => &tag.address == address && tag.module.as_ident_str() == module,
+            _ => false,
+        })
+    }
+
+    /// The abstract size of this tag, for charging gas for type arguments and bounding the tags
+    /// accepted from clients: `TYPE_TAG_NODE_SIZE` for every node (see `num_nodes`), plus
+    /// `TYPE_TAG_IDENTIFIER_BYTE_SIZE` for every byte of the module and struct names of every
+    /// struct tag in it. Adding type arguments to a tag never makes it smaller.
+    pub fn abstract_size(&self) -> u64 {
+        self.preorder_iter()
+            .map(|ty| match ty {
+                TypeTag::Struct(tag) => tag.node_size(),
+                _ => TYPE_TAG_NODE_SIZE.get(),
+            })
+            .sum()
+    }
+
+    /// Fail if this tag is nested deeper than `max_depth` (see `depth`) or has more than
+    /// `max_nodes` nodes (see `num_nodes`). Unlike the recursive implementations on tags, this
+    /// stops at the first violation and is safe on tags of any size.
+    pub fn check_limits(&self, max_depth: usize, max_nodes: usize) -> AResult<()> {
+        let mut nodes = 0;
+        let mut pending = vec![(self, 1)];
+        while let Some((ty, depth)) = pending.pop() {
+            if depth > max_depth {
+                bail!("type tag is nested more than {} levels", max_depth)
+            }
+            nodes += 1;
+            if nodes > max_nodes {
+                bail!("type tag has more than {} nodes", max_nodes)
+            }
+            pending.extend(ty.type_args().iter().map(|ty| (ty, depth + 1)));
+        }
+        Ok(())
+    }
+
+    /// The tags directly nested in this one: the element type of a vector, or the type arguments
+    /// of a struct
+    fn type_args(&self) -> &[TypeTag] {
+        match self {
+            TypeTag::Vector(elem) => std::slice::from_ref(elem.as_ref()),
+            TypeTag::Struct(tag) => &tag.type_params,
+            _ => &[],
+        }
+    }
+}
+
+/// A type tag that may contain type parameters, e.g. a field type as declared in a generic struct,
+/// such as `vector<0x1::Option::Option<T0>>`. `TypeTag`s name concrete types only, and
+/// `substitute` turns a template into one.
+#[derive(Debug, PartialEq, Hash, Eq, Clone, Serialize, Deserialize)]
+pub enum TypeTagTemplate {
+    Bool,
+    U8,
+    U64,
+    U128,
+    Address,
+    Signer,
+    Vector(Box<TypeTagTemplate>),
+    Struct(StructTagTemplate),
+    /// The type parameter with this index, written `T<index>`
+    TypeParameter(u16),
+}
+
+/// A struct tag whose type arguments may contain type parameters, e.g. `0x1::M::S<T0, u8>`, see
+/// `TypeTagTemplate`
+#[derive(Debug, PartialEq, Hash, Eq, Clone, Serialize, Deserialize)]
+pub struct StructTagTemplate {
+    pub address: AccountAddress,
+    pub module: Identifier,
+    pub name: Identifier,
+    pub type_params: Vec<TypeTagTemplate>,
+}
+
+impl TypeTagTemplate {
+    /// Substitute `ty_args[n]` for every `TypeParameter(n)` in this template, e.g. to instantiate
+    /// a field type from a signature. Fails if a parameter is out of range or if the result is
+    /// nested deeper than `TYPE_TAG_DEPTH_MAX`.
+    pub fn substitute(&self, ty_args: &[TypeTag]) -> AResult<TypeTag> {
+        self.substitute_with_max_depth(ty_args, TYPE_TAG_DEPTH_MAX)
+    }
+
+    /// `substitute`, with a limit of `max_depth` on the depth of the result
+    pub fn substitute_with_max_depth(
+        &self,
+        ty_args: &[TypeTag],
+        max_depth: usize,
+    ) -> AResult<TypeTag> {
+        self.substitute_at(ty_args, 1, max_depth)
+    }
+
+    /// Whether this template contains no type parameters, i.e. names a single type
+    pub fn is_concrete(&self) -> bool {
+        match self {
+            TypeTagTemplate::Vector(elem) => elem.is_concrete(),
+            TypeTagTemplate::Struct(tag) => tag.is_concrete(),
+            TypeTagTemplate::TypeParameter(_) => false,
+            _ => true,
+        }
+    }
+
+    /// `substitute` for this template nested at `depth` in the result
+    fn substitute_at(
+        &self,
+        ty_args: &[TypeTag],
+        depth: usize,
+        max_depth: usize,
+    ) -> AResult<TypeTag> {
+        if depth > max_depth {
+            bail!("type tag is nested more than {} levels", max_depth)
+        }
+        Ok(match self {
+            TypeTagTemplate::Bool => TypeTag::Bool,
+            TypeTagTemplate::U8 => TypeTag::U8,
+            TypeTagTemplate::U64 => TypeTag::U64,
+            TypeTagTemplate::U128 => TypeTag::U128,
+            TypeTagTemplate::Address => TypeTag::Address,
+            TypeTagTemplate::Signer => TypeTag::Signer,
+            TypeTagTemplate::Vector(elem) => TypeTag::Vector(Box::new(elem.substitute_at(
+                ty_args,
+                depth + 1,
+                max_depth,
+            )?)),
+            TypeTagTemplate::Struct(tag) => {
+                TypeTag::Struct(tag.substitute_at(ty_args, depth, max_depth)?)
+            }
+            TypeTagTemplate::TypeParameter(idx) => {
+                let ty_arg = ty_args.get(*idx as usize).ok_or_else(|| {
+                    format_err!(
+                        "type parameter {} is out of range for {} type arguments",
+                        idx,
+                        ty_args.len()
+                    )
+                })?;
+                if depth - 1 + ty_arg.depth() > max_depth {
+                    bail!("type tag is nested more than {} levels", max_depth)
+                }
+                ty_arg.clone()
+            }
+        })
+    }
+}
+
+impl StructTagTemplate {
+    /// Substitute `ty_args[n]` for every type parameter `n` in the type arguments of this
+    /// template, see `TypeTagTemplate::substitute`
+    pub fn substitute(&self, ty_args: &[TypeTag]) -> AResult<StructTag> {
+        self.substitute_at(ty_args, 1, TYPE_TAG_DEPTH_MAX)
+    }
+
+    /// Whether the type arguments of this template contain no type parameters
+    pub fn is_concrete(&self) -> bool {
+        self.type_params.iter().all(TypeTagTemplate::is_concrete)
+    }
+
+    /// `substitute` for a struct tag nested at `depth`, whose type arguments are one level deeper
+    fn substitute_at(
+        &self,
+        ty_args: &[TypeTag],
+        depth: usize,
+        max_depth: usize,
+    ) -> AResult<StructTag> {
+        Ok(StructTag {
+            address: self.address,
+            module: self.module.clone(),
+            name: self.name.clone(),
+            type_params: self
+                .type_params
+                .iter()
+                .map(|ty| ty.substitute_at(ty_args, depth + 1, max_depth))
+                .collect::<AResult<_>>()?,
+        })
+    }
+}
+
+impl From<TypeTag> for TypeTagTemplate {
+    fn from(ty: TypeTag) -> Self {
+        match ty {
+            TypeTag::Bool => TypeTagTemplate::Bool,
+            TypeTag::U8 => TypeTagTemplate::U8,
+            TypeTag::U64 => TypeTagTemplate::U64,
+            TypeTag::U128 => TypeTagTemplate::U128,
+            TypeTag::Address => TypeTagTemplate::Address,
+            TypeTag::Signer => TypeTagTemplate::Signer,
+            TypeTag::Vector(elem) => TypeTagTemplate::Vector(Box::new((*elem).into())),
+            TypeTag::Struct(tag) => TypeTagTemplate::Struct(tag.into()),
+        }
+    }
+}
+
+impl From<StructTag> for StructTagTemplate {
+    fn from(tag: StructTag) -> Self {
+        StructTagTemplate {
+            address: tag.address,
+            module: tag.module,
+            name: tag.name,
+            type_params: tag.type_params.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// The stable order of type tags, e.g. for the keys of persisted or shared `BTreeMap`s: tags are
+/// ordered as their canonical strings (see `to_canonical_string`) are, byte by byte, so that the
+/// order can be reproduced from those strings alone. Struct tags, whose strings start with their
+/// `0x` address, thus come before primitives and vectors.
+impl Ord for TypeTag {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_canonical_string().cmp(&other.to_canonical_string())
+    }
+}
+
+impl PartialOrd for TypeTag {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Struct tags are ordered as their canonical strings are, like `TypeTag`s
+impl Ord for StructTag {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_canonical_string().cmp(&other.to_canonical_string())
+    }
+}
+
+impl PartialOrd for StructTag {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The iterator of `TypeTag::preorder_iter`
+struct TypeTagPreorderIter<'a> {
+    /// The tags still to visit, the next one last
+    pending: Vec<&'a TypeTag>,
+}
+
+impl<'a> Iterator for TypeTagPreorderIter<'a> {
+    type Item = &'a TypeTag;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ty = self.pending.pop()?;
+        self.pending.extend(ty.type_args().iter().rev());
+        Some(ty)
+    }
+}
+
+/// A filter on struct tags, e.g. to pick the events or resources of a type. It starts out
+/// matching every tag, and each constraint added narrows it down:
+///
+/// ```
+/// # use move_core_types::{account_address::AccountAddress, language_storage::*};
+/// let coins = StructTagMatcher::new()
+///     .address(AccountAddress::ONE)
+///     .module("Diem")
+///     .name("Diem");
+/// assert!(coins.matches(&"0x1::Diem::Diem<0x1::XUS::XUS>".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StructTagMatcher {
+    address: Option<AccountAddress>,
+    module: Option<Identifier>,
+    name: Option<Identifier>,
+    type_args: Option<Vec<TypeTag>>,
+}
+
+impl StructTagMatcher {
+    /// A matcher for every struct tag
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A matcher for `tag` only, type arguments included
+    pub fn exact(tag: &StructTag) -> Self {
+        Self {
+            type_args: Some(tag.type_params.clone()),
+            ..Self::ignoring_type_args(tag)
+        }
+    }
+
+    /// A matcher for the struct that `tag` names, with any type arguments
+    pub fn ignoring_type_args(tag: &StructTag) -> Self {
+        Self::new()
+            .address(tag.address)
+            .module(tag.module.as_str())
+            .name(tag.name.as_str())
+    }
+
+    /// Only match tags at `address`
+    pub fn address(self, address: AccountAddress) -> Self {
+        Self {
+            address: Some(address),
+            ..self
+        }
+    }
+
+    /// Only match tags in a module named `module`. Panics if `module` is not a valid identifier.
+    pub fn module(self, module: &str) -> Self {
+        Self {
+            module: Some(Identifier::new(module).expect("invalid module name")),
+            ..self
+        }
+    }
+
+    /// Only match tags of structs named `name`. Panics if `name` is not a valid identifier.
+    pub fn name(self, name: &str) -> Self {
+        Self {
+            name: Some(Identifier::new(name).expect("invalid struct name")),
+            ..self
+        }
+    }
+
+    /// Only match tags with exactly the type arguments `type_args`
+    pub fn type_args(self, type_args: Vec<TypeTag>) -> Self {
+        Self {
+            type_args: Some(type_args),
+            ..self
+        }
+    }
+
+    /// Whether `tag` meets every constraint of this matcher
+    pub fn matches(&self, tag: &StructTag) -> bool {
+        self.address.map_or(true, |address| tag.address == address)
+            && self
+                .module
+                .as_ref()
+                .map_or(true, |module| &tag.module == module)
+            && self.name.as_ref().map_or(true, |name| &tag.name == name)
+            && self
+                .type_args
+                .as_ref()
+                .map_or(true, |type_args| &tag.type_params == type_args)
+    }
+}
+
+/// Bounds on the size of the type tags parsed from strings, see `TypeTag::from_str_with_limits`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeTagLimits {
+    /// Maximum depth of a tag, see `TypeTag::depth`
+    pub max_depth: usize,
+    /// Maximum number of nodes of a tag, see `TypeTag::num_nodes`
+    pub max_nodes: usize,
+}
+
+impl Default for TypeTagLimits {
+    /// The limits that `TypeTag::from_str` applies
+    fn default() -> Self {
+        Self {
+            max_depth: TYPE_TAG_DEPTH_MAX,
+            max_nodes: TYPE_TAG_NODES_MAX,
+        }
+    }
+}
+
+/// A bounded, thread-safe pool of shared `StructTag`s. Interning equal tags gives the same `Arc`,
+/// so that a node handling the same few tags over and over keeps a single copy of each, and
+/// decorated values can share it (see `MoveValue::decorate_cached`).
+///
+/// The pool holds at most `capacity` tags, and interning a new tag into a full pool evicts the
+/// least recently used one. A tag interned again after its eviction gets a new `Arc`: equal to
+/// the old one, but not the same allocation.
+///
+/// `StructTagRegistry` is the unbounded, single-threaded counterpart, for decorating one value.
+#[derive(Debug)]
+pub struct TypeTagCache {
+    capacity: usize,
+    lru: Mutex<TagLru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct TagLru {
+    /// The shared tags, with the tick of their last use
+    tags: HashMap<Arc<StructTag>, u64>,
+    /// The shared tags by the tick of their last use, least recent first
+    by_use: BTreeMap<u64, Arc<StructTag>>,
+    tick: u64,
+}
+
+impl TypeTagCache {
+    /// An empty cache holding at most `capacity` tags. With a capacity of 0 nothing is shared.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Mutex::new(TagLru::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The shared copy of `tag`, adding one if there is none
+    pub fn intern(&self, tag: StructTag) -> Arc<StructTag> {
+        self.intern_impl(Cow::Owned(tag))
+    }
+
+    /// Like `intern`, but `tag` is only cloned if it has no shared copy yet
+    pub fn intern_borrowed(&self, tag: &StructTag) -> Arc<StructTag> {
+        self.intern_impl(Cow::Borrowed(tag))
+    }
+
+    fn intern_impl(&self, tag: Cow<StructTag>) -> Arc<StructTag> {
+        let mut guard = self.lru.lock().unwrap();
+        let lru = &mut *guard;
+        lru.tick += 1;
+        if let Some(last_use) = lru.tags.get_mut(&*tag) {
+            let shared = lru
+                .by_use
+                .remove(&*last_use)
+                .expect("every cached tag has a last use");
+            *last_use = lru.tick;
+            lru.by_use.insert(lru.tick, shared.clone());
+            self.hits.fetch_add(1, AtomicOrdering::Relaxed);
+            return shared;
+        }
+
+        self.misses.fetch_add(1, AtomicOrdering::Relaxed);
+        let shared = Arc::new(tag.into_owned());
+        if self.capacity == 0 {
+            return shared;
+        }
+        if lru.tags.len() == self.capacity {
+            let oldest = *lru.by_use.keys().next().expect("a full cache is not empty");
+            let evicted = lru.by_use.remove(&oldest).expect("key was just found");
+            lru.tags.remove(&evicted);
+        }
+        lru.tags.insert(shared.clone(), lru.tick);
+        lru.by_use.insert(lru.tick, shared.clone());
+        shared
+    }
+
+    /// The number of calls to `intern` that found a shared copy
+    pub fn hits(&self) -> u64 {
+        self.hits.load(AtomicOrdering::Relaxed)
+    }
+
+    /// The number of calls to `intern` that had to add a shared copy
+    pub fn misses(&self) -> u64 {
+        self.misses.load(AtomicOrdering::Relaxed)
+    }
+
+    /// The number of tags currently cached
+    pub fn len(&self) -> usize {
+        self.lru.lock().unwrap().tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
 }
 
 /// Represents the intitial key into global storage where we first index by the address, and then
@@ -80,11 +801,33 @@ impl ResourceKey {
     pub fn new(address: AccountAddress, type_: StructTag) -> Self {
         ResourceKey { address, type_ }
     }
+
+    /// The canonical BCS encoding of this key: its address, then its struct tag
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bcs::to_bytes(self).expect("resource keys always serialize")
+    }
+
+    /// The SHA3-256 digest of `to_bytes`, which identifies the resource independently of how it
+    /// is stored
+    pub fn sha3_256(&self) -> [u8; 32] {
+        Sha3_256::digest(&self.to_bytes()).into()
+    }
+
+    /// The key of the resource in global storage: the BCS encoding of the address paired with the
+    /// path `StructTag::access_vector` under that address
+    pub fn access_key(&self) -> Vec<u8> {
+        access_key(self.address, self.type_.access_vector())
+    }
+}
+
+/// The BCS encoding of the storage key for `path` under `address`
+fn access_key(address: AccountAddress, path: Vec<u8>) -> Vec<u8> {
+    bcs::to_bytes(&(address, path)).expect("access keys always serialize")
 }
 
 /// Represents the initial key into global storage where we first index by the address, and then
 /// the struct tag
-#[derive(Serialize, Deserialize, Debug, PartialEq, Hash, Eq, Clone, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Hash, Eq, Clone)]
 #[cfg_attr(any(test, feature = "fuzzing"), derive(Arbitrary))]
 #[cfg_attr(any(test, feature = "fuzzing"), proptest(no_params))]
 pub struct ModuleId {
@@ -111,19 +854,78 @@ impl ModuleId {
         &self.address
     }
 
+    /// Whether `tag` names a struct declared in this module
+    pub fn contains(&self, tag: &StructTag) -> bool {
+        tag.address == self.address && tag.module == self.name
+    }
+
+    /// This module id with its address in its shortest form, e.g. `0x1::Account`, like the address
+    /// of `StructTag`'s display. It parses back into this module id.
+    pub fn short_str_lossless(&self) -> String {
+        format!("0x{}::{}", self.address.short_str_lossless(), self.name)
+    }
+
+    /// This module id with its address in full, as all `2 * AccountAddress::LENGTH` hex digits, so
+    /// that equal module ids have equal strings, see `StructTag::to_canonical_string`
+    pub fn to_canonical_string(&self) -> String {
+        format!("0x{}::{}", self.address.to_hex(), self.name)
+    }
+
+    /// Display this module id with its address replaced by its name if it is in `names`, see
+    /// `TypeTag::display_with`
+    pub fn display_with<'a>(
+        &'a self,
+        names: &'a BTreeMap<AccountAddress, String>,
+    ) -> WithAddressNames<'a, Self> {
+        WithAddressNames { value: self, names }
+    }
+
+    /// Parse a module id like `StructTag::from_str_with_names`
+    pub fn from_str_with_names(
+        s: &str,
+        names: &BTreeMap<AccountAddress, String>,
+    ) -> Result<Self, ParseError> {
+        parser::module_id_from_str_with_names(s, names)
+    }
+
     pub fn access_vector(&self) -> Vec<u8> {
         let mut key = vec![CODE_TAG];
         key.append(&mut bcs::to_bytes(self).unwrap());
         key
     }
+
+    /// The key of the module in global storage, see `ResourceKey::access_key`
+    pub fn access_key(&self) -> Vec<u8> {
+        access_key(self.address, self.access_vector())
+    }
 }
 
+/// Displays the address in full and without `0x`, see `ModuleId::short_str_lossless` for the
+/// form used in struct tags
 impl Display for ModuleId {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "{}::{}", self.address, self.name)
     }
 }
 
+/// Module ids are ordered by address, then by name, which compare as in their canonical strings
+/// (see `ModuleId::to_canonical_string`), e.g. for allow-lists kept as a `BTreeSet<ModuleId>`.
+impl Ord for ModuleId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.address
+            .cmp(&other.address)
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+impl PartialOrd for ModuleId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Displays addresses in their short form, see `StructTag::to_canonical_string` for a form that
+/// keeps them in full
 impl Display for StructTag {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(
@@ -160,14 +962,325 @@ impl Display for TypeTag {
     }
 }
 
+impl Display for TypeTagTemplate {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            TypeTagTemplate::Struct(s) => write!(f, "{}", s),
+            TypeTagTemplate::Vector(ty) => write!(f, "vector<{}>", ty),
+            TypeTagTemplate::TypeParameter(idx) => write!(f, "T{}", idx),
+            TypeTagTemplate::U8 => write!(f, "u8"),
+            TypeTagTemplate::U64 => write!(f, "u64"),
+            TypeTagTemplate::U128 => write!(f, "u128"),
+            TypeTagTemplate::Address => write!(f, "address"),
+            TypeTagTemplate::Signer => write!(f, "signer"),
+            TypeTagTemplate::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+impl Display for StructTagTemplate {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "0x{}::{}::{}",
+            self.address.short_str_lossless(),
+            self.module,
+            self.name
+        )?;
+        if let Some(first_ty) = self.type_params.first() {
+            write!(f, "<{}", first_ty)?;
+            for ty in self.type_params.iter().skip(1) {
+                write!(f, ", {}", ty)?;
+            }
+            write!(f, ">")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TypeTag {
+    type Err = ParseError;
+
+    /// Parse a type tag as it is displayed, e.g. `vector<0x1::Diem::Diem<0x1::XUS::XUS>>`.
+    /// Whitespace between the parts of a tag is ignored. Tags exceeding
+    /// `TypeTagLimits::default()` are rejected.
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        parser::type_tag_from_str(s)
+    }
+}
+
+impl FromStr for StructTag {
+    type Err = ParseError;
+
+    /// Parse a struct tag like `TypeTag::from_str`, e.g. `0x1::Diem::Diem<0x1::XUS::XUS>`. Its
+    /// own address may be written without `0x`, but those of its type arguments may not.
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        parser::struct_tag_from_str(s)
+    }
+}
+
+impl FromStr for ModuleId {
+    type Err = ParseError;
+
+    /// Parse a module id like `0x1::Account`, with its address in short form or in full, with or
+    /// without `0x`, so that the `Display` form parses back
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        parser::module_id_from_str(s)
+    }
+}
+
+/// A tag or module id displayed with the names of its addresses, see `TypeTag::display_with`
+pub struct WithAddressNames<'a, T: ?Sized> {
+    value: &'a T,
+    names: &'a BTreeMap<AccountAddress, String>,
+}
+
+impl<'a, T: ?Sized> WithAddressNames<'a, T> {
+    /// `address` as its name, if it has one
+    fn name_of(&self, address: &AccountAddress) -> Option<&'a str> {
+        self.names.get(address).map(String::as_str)
+    }
+}
+
+impl<'a> Display for WithAddressNames<'a, ModuleId> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self.name_of(&self.value.address) {
+            Some(name) => write!(f, "{}::{}", name, self.value.name),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+impl<'a> Display for WithAddressNames<'a, StructTag> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let tag = self.value;
+        match self.name_of(&tag.address) {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "0x{}", tag.address.short_str_lossless())?,
+        }
+        write!(f, "::{}::{}", tag.module, tag.name)?;
+        if let Some(first_ty) = tag.type_params.first() {
+            write!(f, "<{}", first_ty.display_with(self.names))?;
+            for ty in tag.type_params.iter().skip(1) {
+                write!(f, ", {}", ty.display_with(self.names))?;
+            }
+            write!(f, ">")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Display for WithAddressNames<'a, TypeTag> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self.value {
+            TypeTag::Struct(tag) => write!(f, "{}", tag.display_with(self.names)),
+            TypeTag::Vector(ty) => write!(f, "vector<{}>", ty.display_with(self.names)),
+            ty => write!(f, "{}", ty),
+        }
+    }
+}
+
 impl Display for ResourceKey {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "0x{}/{}", self.address.short_str_lossless(), self.type_)
     }
 }
 
+impl FromStr for ResourceKey {
+    type Err = ParseError;
+
+    /// Parse a resource key as it is displayed, e.g. `0x1/0x1::Diem::Diem<0x1::XUS::XUS>`: an
+    /// address with or without `0x`, a `/`, and a struct tag
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        parser::resource_key_from_str(s)
+    }
+}
+
 impl From<StructTag> for TypeTag {
     fn from(t: StructTag) -> TypeTag {
         TypeTag::Struct(t)
     }
 }
+
+impl Serialize for TypeTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_canonical_string())
+        } else {
+            Structured(self).serialize(serializer)
+        }
+    }
+}
+
+impl Serialize for StructTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_canonical_string())
+        } else {
+            Structured(self).serialize(serializer)
+        }
+    }
+}
+
+/// A tag that serializes in the form that non-human-readable formats use, with the same names and
+/// variant indices as the serde data model of the tag types, even in human-readable formats
+pub(crate) struct Structured<'a, T: ?Sized>(pub &'a T);
+
+impl<'a> Serialize for Structured<'a, TypeTag> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            TypeTag::Bool => serializer.serialize_unit_variant("TypeTag", 0, "bool"),
+            TypeTag::U8 => serializer.serialize_unit_variant("TypeTag", 1, "u8"),
+            TypeTag::U64 => serializer.serialize_unit_variant("TypeTag", 2, "u64"),
+            TypeTag::U128 => serializer.serialize_unit_variant("TypeTag", 3, "u128"),
+            TypeTag::Address => serializer.serialize_unit_variant("TypeTag", 4, "address"),
+            TypeTag::Signer => serializer.serialize_unit_variant("TypeTag", 5, "signer"),
+            TypeTag::Vector(elem) => {
+                serializer.serialize_newtype_variant("TypeTag", 6, "vector", &Structured(&**elem))
+            }
+            TypeTag::Struct(tag) => {
+                serializer.serialize_newtype_variant("TypeTag", 7, "struct", &Structured(tag))
+            }
+        }
+    }
+}
+
+impl<'a> Serialize for Structured<'a, StructTag> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut t = serializer.serialize_struct("StructTag", 4)?;
+        t.serialize_field("address", &self.0.address)?;
+        t.serialize_field("module", &self.0.module)?;
+        t.serialize_field("name", &self.0.name)?;
+        t.serialize_field("type_args", &Structured(self.0.type_params.as_slice()))?;
+        t.end()
+    }
+}
+
+impl<'a> Serialize for Structured<'a, [TypeTag]> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(Structured))
+    }
+}
+
+/// The structured form of a `TypeTag` as it is deserialized. Type tags nested in it are
+/// deserialized as `TypeTag`s, in whichever form the format uses.
+#[derive(Deserialize)]
+#[serde(rename = "TypeTag")]
+enum StructuredTypeTag {
+    #[serde(rename = "bool")]
+    Bool,
+    #[serde(rename = "u8")]
+    U8,
+    #[serde(rename = "u64")]
+    U64,
+    #[serde(rename = "u128")]
+    U128,
+    #[serde(rename = "address")]
+    Address,
+    #[serde(rename = "signer")]
+    Signer,
+    #[serde(rename = "vector")]
+    Vector(Box<TypeTag>),
+    #[serde(rename = "struct")]
+    Struct(StructTag),
+}
+
+impl From<StructuredTypeTag> for TypeTag {
+    fn from(tag: StructuredTypeTag) -> Self {
+        match tag {
+            StructuredTypeTag::Bool => TypeTag::Bool,
+            StructuredTypeTag::U8 => TypeTag::U8,
+            StructuredTypeTag::U64 => TypeTag::U64,
+            StructuredTypeTag::U128 => TypeTag::U128,
+            StructuredTypeTag::Address => TypeTag::Address,
+            StructuredTypeTag::Signer => TypeTag::Signer,
+            StructuredTypeTag::Vector(elem) => TypeTag::Vector(elem),
+            StructuredTypeTag::Struct(tag) => TypeTag::Struct(tag),
+        }
+    }
+}
+
+/// The structured form of a `StructTag` as it is deserialized, see `StructuredTypeTag`
+#[derive(Deserialize)]
+#[serde(rename = "StructTag")]
+struct StructuredStructTag {
+    address: AccountAddress,
+    module: Identifier,
+    name: Identifier,
+    type_args: Vec<TypeTag>,
+}
+
+impl From<StructuredStructTag> for StructTag {
+    fn from(tag: StructuredStructTag) -> Self {
+        StructTag {
+            address: tag.address,
+            module: tag.module,
+            name: tag.name,
+            type_params: tag.type_args,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TypeTag {
+    /// In human-readable formats, a tag may be given either as a string that `from_str` parses
+    /// or in its structured form
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer
+                .deserialize_any(TagVisitor::<StructuredTypeTag, TypeTag>::new("a type tag"))
+        } else {
+            Ok(StructuredTypeTag::deserialize(deserializer)?.into())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StructTag {
+    /// Like `TypeTag::deserialize`, in human-readable formats a tag may be given either as a
+    /// string or in its structured form
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(TagVisitor::<StructuredStructTag, StructTag>::new(
+                "a struct tag",
+            ))
+        } else {
+            Ok(StructuredStructTag::deserialize(deserializer)?.into())
+        }
+    }
+}
+
+/// Visits a tag of type `T` in a human-readable format, given either as a string or as a map
+/// holding the structured form `R` of the tag
+struct TagVisitor<R, T> {
+    expecting: &'static str,
+    tag: std::marker::PhantomData<fn() -> (R, T)>,
+}
+
+impl<R, T> TagVisitor<R, T> {
+    fn new(expecting: &'static str) -> Self {
+        Self {
+            expecting,
+            tag: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'de, R, T> de::Visitor<'de> for TagVisitor<R, T>
+where
+    R: Deserialize<'de> + Into<T>,
+    T: FromStr<Err = ParseError>,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{} as a string or in structured form", self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<T, E> {
+        s.parse()
+            .map_err(|e| E::custom(format!("`{}` is not {}: {}", s, self.expecting, e)))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        Ok(R::deserialize(MapAccessDeserializer::new(map))?.into())
+    }
+}
@@ -4,10 +4,11 @@
 use crate::{
     account_address::AccountAddress,
     identifier::{IdentStr, Identifier},
-    language_storage::{StructTag, TypeTag},
+    language_storage::{ModuleId, ResourceKey, StructTag, TypeTag},
 };
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 
+/// A Rust type that mirrors a Move struct, i.e. whose BCS encoding is that of the struct's values
 pub trait MoveStructType {
     const ADDRESS: AccountAddress = crate::language_storage::CORE_CODE_ADDRESS;
     const MODULE_NAME: &'static IdentStr;
@@ -21,6 +22,10 @@ pub trait MoveStructType {
         Self::STRUCT_NAME.to_owned()
     }
 
+    fn module_id() -> ModuleId {
+        ModuleId::new(Self::ADDRESS, Self::module_identifier())
+    }
+
     fn type_params() -> Vec<TypeTag> {
         vec![]
     }
@@ -35,8 +40,15 @@ pub trait MoveStructType {
     }
 }
 
-pub trait MoveResource: MoveStructType + DeserializeOwned {
+/// A `MoveStructType` for a struct with the `key` ability, which can be read from and written to
+/// global storage
+pub trait MoveResource: MoveStructType + DeserializeOwned + Serialize {
     fn resource_path() -> Vec<u8> {
         Self::struct_tag().access_vector()
     }
+
+    /// The key of the resource of this type published under `address`
+    fn resource_key(address: AccountAddress) -> ResourceKey {
+        ResourceKey::new(address, Self::struct_tag())
+    }
 }
@@ -4,11 +4,58 @@
 use crate::{
     account_address::AccountAddress,
     identifier::{self, Identifier},
-    language_storage::{StructTag, TypeTag},
+    language_storage::{ModuleId, ResourceKey, StructTag, TypeTag, TypeTagLimits},
     transaction_argument::TransactionArgument,
+    value::MoveValue,
 };
 use anyhow::{bail, format_err, Result};
-use std::iter::Peekable;
+use std::{collections::BTreeMap, fmt, iter::Peekable};
+
+/// A failure to parse a type tag or transaction argument: what was expected at a byte offset of
+/// the input, and what was found there instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    offset: usize,
+    expected: String,
+    found: String,
+}
+
+impl ParseError {
+    fn new(offset: usize, expected: impl Into<String>, found: impl Into<String>) -> Self {
+        Self {
+            offset,
+            expected: expected.into(),
+            found: found.into(),
+        }
+    }
+
+    /// The byte offset in the input at which parsing failed
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// What was expected at `offset`, e.g. "a type" or "`>`"
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// What was found at `offset` instead
+    pub fn found(&self) -> &str {
+        &self.found
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expected {} at byte {}, found {}",
+            self.expected, self.offset, self.found
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
 
 #[derive(Eq, PartialEq, Debug)]
 enum Token {
@@ -31,6 +78,9 @@ enum Token {
     ColonColon,
     Lt,
     Gt,
+    LBracket,
+    RBracket,
+    At,
     Comma,
     EOF,
 }
@@ -39,6 +89,57 @@ impl Token {
     fn is_whitespace(&self) -> bool {
         matches!(self, Self::Whitespace(_))
     }
+
+    /// The name this token stands for where a name is expected. The keywords for types and
+    /// literals are valid identifiers too, e.g. for a module named `vector`.
+    fn into_name(self) -> Result<String, Token> {
+        Ok(match self {
+            Token::Name(s) => s,
+            Token::U8Type => "u8".to_string(),
+            Token::U64Type => "u64".to_string(),
+            Token::U128Type => "u128".to_string(),
+            Token::BoolType => "bool".to_string(),
+            Token::AddressType => "address".to_string(),
+            Token::VectorType => "vector".to_string(),
+            Token::SignerType => "signer".to_string(),
+            Token::True => "true".to_string(),
+            Token::False => "false".to_string(),
+            tok => return Err(tok),
+        })
+    }
+
+    /// This token as it appears in error messages
+    fn describe(&self) -> String {
+        match self {
+            Token::Whitespace(_) => "whitespace".to_string(),
+            Token::Name(s) | Token::Address(s) | Token::U64(s) => format!("`{}`", s),
+            Token::U8(s) => format!("`{}u8`", s),
+            Token::U128(s) => format!("`{}u128`", s),
+            Token::Bytes(_) => "a byte string".to_string(),
+            Token::ColonColon => "`::`".to_string(),
+            Token::Lt => "`<`".to_string(),
+            Token::Gt => "`>`".to_string(),
+            Token::LBracket => "`[`".to_string(),
+            Token::RBracket => "`]`".to_string(),
+            Token::At => "`@`".to_string(),
+            Token::Comma => "`,`".to_string(),
+            Token::EOF => "end of input".to_string(),
+            tok => format!(
+                "`{}`",
+                match tok {
+                    Token::U8Type => "u8",
+                    Token::U64Type => "u64",
+                    Token::U128Type => "u128",
+                    Token::BoolType => "bool",
+                    Token::AddressType => "address",
+                    Token::VectorType => "vector",
+                    Token::SignerType => "signer",
+                    Token::True => "true",
+                    _ => "false",
+                }
+            ),
+        }
+    }
 }
 
 fn name_token(s: String) -> Token {
@@ -97,6 +198,9 @@ fn next_token(s: &str) -> Result<Option<(Token, usize)>> {
         Some(c) => Ok(Some(match c {
             '<' => (Token::Lt, 1),
             '>' => (Token::Gt, 1),
+            '[' => (Token::LBracket, 1),
+            ']' => (Token::RBracket, 1),
+            '@' => (Token::At, 1),
             ',' => (Token::Comma, 1),
             ':' => match it.next() {
                 Some(':') => (Token::ColonColon, 2),
@@ -163,7 +267,7 @@ fn next_token(s: &str) -> Result<Option<(Token, usize)>> {
                 let len = r.len();
                 (Token::Whitespace(r), len)
             }
-            c if c.is_ascii_alphabetic() => {
+            c if c.is_ascii_alphabetic() || c == '_' => {
                 let mut r = String::new();
                 r.push(c);
                 for c in it {
@@ -181,41 +285,116 @@ fn next_token(s: &str) -> Result<Option<(Token, usize)>> {
     }
 }
 
-fn tokenize(mut s: &str) -> Result<Vec<Token>> {
+/// The tokens of `s`, each with its byte offset
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, ParseError> {
     let mut v = vec![];
-    while let Some((tok, n)) = next_token(s)? {
-        v.push(tok);
-        s = &s[n..];
+    let mut offset = 0;
+    loop {
+        let rest = &s[offset..];
+        match next_token(rest) {
+            Ok(Some((tok, n))) => {
+                v.push((tok, offset));
+                offset += n;
+            }
+            Ok(None) => return Ok(v),
+            Err(_) => {
+                let word = rest
+                    .split(|c: char| c.is_whitespace() || "<>[],:".contains(c))
+                    .next()
+                    .filter(|word| !word.is_empty())
+                    .unwrap_or(rest);
+                let word: String = word.chars().take(32).collect();
+                return Err(ParseError::new(offset, "a token", format!("`{}`", word)));
+            }
+        }
     }
-    Ok(v)
 }
 
-struct Parser<I: Iterator<Item = Token>> {
+struct Parser<I: Iterator<Item = (Token, usize)>> {
     it: Peekable<I>,
+    /// The length of the input, where it ends
+    end: usize,
+    /// The limits on the type tags parsed
+    limits: TypeTagLimits,
+    /// The depth of the type tag being parsed
+    depth: usize,
+    /// The number of type tag nodes parsed so far
+    nodes: usize,
+    /// The names that may be written in place of addresses
+    named_addresses: BTreeMap<String, AccountAddress>,
 }
 
-impl<I: Iterator<Item = Token>> Parser<I> {
-    fn new<T: IntoIterator<Item = Token, IntoIter = I>>(v: T) -> Self {
+impl<I: Iterator<Item = (Token, usize)>> Parser<I> {
+    fn new<T: IntoIterator<Item = (Token, usize), IntoIter = I>>(v: T, end: usize) -> Self {
         Self {
             it: v.into_iter().peekable(),
+            end,
+            limits: TypeTagLimits::default(),
+            depth: 0,
+            nodes: 0,
+            named_addresses: BTreeMap::new(),
         }
     }
 
-    fn next(&mut self) -> Result<Token> {
+    /// The next token and its offset. The tokens end with `Token::EOF`, which parsers do not
+    /// read past.
+    fn next(&mut self) -> Result<(Token, usize), ParseError> {
         match self.it.next() {
             Some(tok) => Ok(tok),
-            None => bail!("out of tokens, this should not happen"),
+            None => Err(ParseError::new(self.end, "more input", "end of input")),
         }
     }
 
     fn peek(&mut self) -> Option<&Token> {
-        self.it.peek()
+        self.it.peek().map(|(tok, _)| tok)
+    }
+
+    /// Whether the next token is an address, or a name standing for one
+    fn peek_address(&mut self) -> bool {
+        match self.it.peek() {
+            Some((Token::Address(_), _)) => true,
+            Some((Token::Name(name), _)) => self.named_addresses.contains_key(name),
+            _ => false,
+        }
     }
 
-    fn consume(&mut self, tok: Token) -> Result<()> {
-        let t = self.next()?;
+    /// The offset of the next token
+    fn peek_offset(&mut self) -> usize {
+        let end = self.end;
+        self.it.peek().map_or(end, |(_, offset)| *offset)
+    }
+
+    /// Start parsing a type tag node one level deeper, failing if that exceeds the limits. Every
+    /// call is paired with a `leave_type_tag` once the node is parsed.
+    fn enter_type_tag(&mut self) -> Result<(), ParseError> {
+        let offset = self.peek_offset();
+        self.depth += 1;
+        self.nodes += 1;
+        if self.depth > self.limits.max_depth {
+            return Err(ParseError::new(
+                offset,
+                format!("types nested at most {} levels", self.limits.max_depth),
+                "a deeper type",
+            ));
+        }
+        if self.nodes > self.limits.max_nodes {
+            return Err(ParseError::new(
+                offset,
+                format!("at most {} types in total", self.limits.max_nodes),
+                "another type",
+            ));
+        }
+        Ok(())
+    }
+
+    fn leave_type_tag(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn consume(&mut self, tok: Token) -> Result<(), ParseError> {
+        let (t, offset) = self.next()?;
         if t != tok {
-            bail!("expected token {:?}, got {:?}", tok, t)
+            return Err(ParseError::new(offset, tok.describe(), t.describe()));
         }
         Ok(())
     }
@@ -225,9 +404,9 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         parse_list_item: F,
         end_token: Token,
         allow_trailing_comma: bool,
-    ) -> Result<Vec<R>>
+    ) -> Result<Vec<R>, ParseError>
     where
-        F: Fn(&mut Self) -> Result<R>,
+        F: Fn(&mut Self) -> Result<R, ParseError>,
         R: std::fmt::Debug,
     {
         let mut v = vec![];
@@ -237,7 +416,11 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 if self.peek() == Some(&end_token) {
                     break;
                 }
-                self.consume(Token::Comma)?;
+                let (tok, offset) = self.next()?;
+                if tok != Token::Comma {
+                    let expected = format!("`,` or {}", end_token.describe());
+                    return Err(ParseError::new(offset, expected, tok.describe()));
+                }
                 if self.peek() == Some(&end_token) && allow_trailing_comma {
                     break;
                 }
@@ -246,15 +429,30 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         Ok(v)
     }
 
-    fn parse_string(&mut self) -> Result<String> {
-        Ok(match self.next()? {
-            Token::Name(s) => s,
-            tok => bail!("unexpected token {:?}, expected string", tok),
-        })
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        match self.next()? {
+            (Token::Name(s), _) => Ok(s),
+            (tok, offset) => Err(ParseError::new(offset, "a name", tok.describe())),
+        }
     }
 
-    fn parse_type_tag(&mut self) -> Result<TypeTag> {
-        Ok(match self.next()? {
+    /// An identifier, described as `expected` in errors
+    fn parse_identifier(&mut self, expected: &str) -> Result<Identifier, ParseError> {
+        let (tok, offset) = self.next()?;
+        match tok.into_name() {
+            Ok(name) => Identifier::new(name.as_str())
+                .map_err(|_| ParseError::new(offset, expected, format!("`{}`", name))),
+            Err(tok) => Err(ParseError::new(offset, expected, tok.describe())),
+        }
+    }
+
+    fn parse_type_tag(&mut self) -> Result<TypeTag, ParseError> {
+        if self.peek_address() {
+            return Ok(TypeTag::Struct(self.parse_struct_tag()?));
+        }
+        self.enter_type_tag()?;
+        let (tok, offset) = self.next()?;
+        let ty = match tok {
             Token::U8Type => TypeTag::U8,
             Token::U64Type => TypeTag::U64,
             Token::U128Type => TypeTag::U128,
@@ -267,119 +465,384 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 self.consume(Token::Gt)?;
                 TypeTag::Vector(Box::new(ty))
             }
-            Token::Address(addr) => {
-                self.consume(Token::ColonColon)?;
-                match self.next()? {
-                    Token::Name(module) => {
-                        self.consume(Token::ColonColon)?;
-                        match self.next()? {
-                            Token::Name(name) => {
-                                let ty_args = if self.peek() == Some(&Token::Lt) {
-                                    self.next()?;
-                                    let ty_args = self.parse_comma_list(
-                                        |parser| parser.parse_type_tag(),
-                                        Token::Gt,
-                                        true,
-                                    )?;
-                                    self.consume(Token::Gt)?;
-                                    ty_args
-                                } else {
-                                    vec![]
-                                };
-                                TypeTag::Struct(StructTag {
-                                    address: AccountAddress::from_hex_literal(&addr)?,
-                                    module: Identifier::new(module)?,
-                                    name: Identifier::new(name)?,
-                                    type_params: ty_args,
-                                })
-                            }
-                            t => bail!("expected name, got {:?}", t),
-                        }
-                    }
-                    t => bail!("expected name, got {:?}", t),
-                }
+            tok => return Err(ParseError::new(offset, "a type", tok.describe())),
+        };
+        self.leave_type_tag();
+        Ok(ty)
+    }
+
+    fn parse_module_id(&mut self) -> Result<ModuleId, ParseError> {
+        let (tok, offset) = self.next()?;
+        let address = match tok {
+            Token::Address(addr) => parse_address(&addr, offset)?,
+            Token::Name(name) if self.named_addresses.contains_key(&name) => {
+                self.named_addresses[&name]
+            }
+            tok => return Err(ParseError::new(offset, "an address", tok.describe())),
+        };
+        self.consume(Token::ColonColon)?;
+        let name = self.parse_identifier("a module name")?;
+        Ok(ModuleId::new(address, name))
+    }
+
+    fn parse_struct_tag(&mut self) -> Result<StructTag, ParseError> {
+        if !self.peek_address() {
+            let (tok, offset) = self.next()?;
+            return Err(ParseError::new(
+                offset,
+                "a struct tag `0xADDR::Module::Name`",
+                tok.describe(),
+            ));
+        }
+        self.enter_type_tag()?;
+        let (address, module) = self.parse_module_id()?.into();
+        self.consume(Token::ColonColon)?;
+        let name = self.parse_identifier("a struct name")?;
+        let type_params = if self.peek() == Some(&Token::Lt) {
+            self.next()?;
+            if self.peek() == Some(&Token::Gt) {
+                let (tok, offset) = self.next()?;
+                return Err(ParseError::new(offset, "a type argument", tok.describe()));
             }
-            tok => bail!("unexpected token {:?}, expected type tag", tok),
+            let ty_args =
+                self.parse_comma_list(|parser| parser.parse_type_tag(), Token::Gt, true)?;
+            self.consume(Token::Gt)?;
+            ty_args
+        } else {
+            vec![]
+        };
+        self.leave_type_tag();
+        Ok(StructTag {
+            address,
+            module,
+            name,
+            type_params,
         })
     }
 
-    fn parse_transaction_argument(&mut self) -> Result<TransactionArgument> {
-        Ok(match self.next()? {
-            Token::U8(s) => TransactionArgument::U8(s.parse()?),
-            Token::U64(s) => TransactionArgument::U64(s.parse()?),
-            Token::U128(s) => TransactionArgument::U128(s.parse()?),
+    fn parse_transaction_argument(&mut self) -> Result<TransactionArgument, ParseError> {
+        let (tok, offset) = self.next()?;
+        let out_of_range = |tok: &Token, ty: &str| {
+            ParseError::new(offset, format!("a {} literal", ty), tok.describe())
+        };
+        Ok(match tok {
+            Token::U8(ref s) => {
+                TransactionArgument::U8(s.parse().map_err(|_| out_of_range(&tok, "u8"))?)
+            }
+            Token::U64(ref s) => {
+                TransactionArgument::U64(s.parse().map_err(|_| out_of_range(&tok, "u64"))?)
+            }
+            Token::U128(ref s) => {
+                TransactionArgument::U128(s.parse().map_err(|_| out_of_range(&tok, "u128"))?)
+            }
             Token::True => TransactionArgument::Bool(true),
             Token::False => TransactionArgument::Bool(false),
-            Token::Address(addr) => {
-                TransactionArgument::Address(AccountAddress::from_hex_literal(&addr)?)
+            Token::Address(addr) => TransactionArgument::Address(parse_address(&addr, offset)?),
+            Token::Bytes(s) => TransactionArgument::U8Vector(hex::decode(&s).map_err(|_| {
+                ParseError::new(offset, "an even number of hex digits", format!("`{}`", s))
+            })?),
+            tok => {
+                return Err(ParseError::new(
+                    offset,
+                    "a transaction argument",
+                    tok.describe(),
+                ))
             }
-            Token::Bytes(s) => TransactionArgument::U8Vector(hex::decode(s)?),
-            tok => bail!("unexpected token {:?}, expected transaction argument", tok),
         })
     }
+
+    /// A value literal, see `parse_move_value`. Vectors are nested at most as deep as type
+    /// tags may be.
+    fn parse_move_value(&mut self) -> Result<MoveValue, ParseError> {
+        let offset = self.peek_offset();
+        Ok(match self.peek() {
+            Some(Token::At) => {
+                self.next()?;
+                match self.next()? {
+                    (Token::Address(addr), offset) => {
+                        MoveValue::Address(parse_address(&addr, offset)?)
+                    }
+                    (tok, offset) => {
+                        return Err(ParseError::new(offset, "an address", tok.describe()))
+                    }
+                }
+            }
+            Some(Token::VectorType) => {
+                self.next()?;
+                self.consume(Token::LBracket)?;
+                self.depth += 1;
+                if self.depth > self.limits.max_depth {
+                    return Err(ParseError::new(
+                        offset,
+                        format!("values nested at most {} levels", self.limits.max_depth),
+                        "a deeper value",
+                    ));
+                }
+                let elems = self.parse_comma_list(
+                    |parser| parser.parse_move_value(),
+                    Token::RBracket,
+                    true,
+                )?;
+                self.consume(Token::RBracket)?;
+                self.depth -= 1;
+                MoveValue::Vector(elems)
+            }
+            _ => match self.parse_transaction_argument() {
+                Ok(arg) => arg.into(),
+                Err(e) if e.offset() == offset => {
+                    return Err(ParseError::new(offset, "a value", e.found()))
+                }
+                Err(e) => return Err(e),
+            },
+        })
+    }
+}
+
+/// The address literal `addr` found at `offset`
+fn parse_address(addr: &str, offset: usize) -> Result<AccountAddress, ParseError> {
+    AccountAddress::from_hex_literal(addr).map_err(|_| {
+        ParseError::new(
+            offset,
+            format!("an address of at most {} bytes", AccountAddress::LENGTH),
+            format!("`{}`", addr),
+        )
+    })
+}
+
+fn parse<F, T>(s: &str, f: F) -> Result<T, ParseError>
+where
+    F: Fn(&mut Parser<std::vec::IntoIter<(Token, usize)>>) -> Result<T, ParseError>,
+{
+    parse_with_limits(s, &TypeTagLimits::default(), f)
 }
 
-fn parse<F, T>(s: &str, f: F) -> Result<T>
+/// Parse `s` like `parse`, failing on type tags that exceed `limits`
+fn parse_with_limits<F, T>(s: &str, limits: &TypeTagLimits, f: F) -> Result<T, ParseError>
 where
-    F: Fn(&mut Parser<std::vec::IntoIter<Token>>) -> Result<T>,
+    F: Fn(&mut Parser<std::vec::IntoIter<(Token, usize)>>) -> Result<T, ParseError>,
 {
     let mut tokens: Vec<_> = tokenize(s)?
         .into_iter()
-        .filter(|tok| !tok.is_whitespace())
+        .filter(|(tok, _)| !tok.is_whitespace())
         .collect();
-    tokens.push(Token::EOF);
-    let mut parser = Parser::new(tokens);
+    tokens.push((Token::EOF, s.len()));
+    let mut parser = Parser::new(tokens, s.len());
+    parser.limits = *limits;
     let res = f(&mut parser)?;
     parser.consume(Token::EOF)?;
     Ok(res)
 }
 
+/// Parse `s` like `parse`, accepting the names in `names` in place of the addresses they name
+fn parse_with_names<F, T>(
+    s: &str,
+    names: &BTreeMap<AccountAddress, String>,
+    f: F,
+) -> Result<T, ParseError>
+where
+    F: Fn(&mut Parser<std::vec::IntoIter<(Token, usize)>>) -> Result<T, ParseError>,
+{
+    let named_addresses: BTreeMap<_, _> = names
+        .iter()
+        .map(|(address, name)| (name.clone(), *address))
+        .collect();
+    parse_with_limits(s, &TypeTagLimits::default(), |parser| {
+        parser.named_addresses = named_addresses.clone();
+        f(parser)
+    })
+}
+
 pub fn parse_string_list(s: &str) -> Result<Vec<String>> {
-    parse(s, |parser| {
+    Ok(parse(s, |parser| {
         parser.parse_comma_list(|parser| parser.parse_string(), Token::EOF, true)
-    })
+    })?)
 }
 
 pub fn parse_type_tags(s: &str) -> Result<Vec<TypeTag>> {
-    parse(s, |parser| {
+    Ok(parse(s, |parser| {
         parser.parse_comma_list(|parser| parser.parse_type_tag(), Token::EOF, true)
-    })
+    })?)
 }
 
 pub fn parse_type_tag(s: &str) -> Result<TypeTag> {
+    Ok(type_tag_from_str(s)?)
+}
+
+/// `TypeTag::from_str`
+pub(crate) fn type_tag_from_str(s: &str) -> Result<TypeTag, ParseError> {
     parse(s, |parser| parser.parse_type_tag())
 }
 
+/// `TypeTag::from_str_with_limits`
+pub(crate) fn type_tag_from_str_with_limits(
+    s: &str,
+    limits: &TypeTagLimits,
+) -> Result<TypeTag, ParseError> {
+    parse_with_limits(s, limits, |parser| parser.parse_type_tag())
+}
+
+/// `TypeTag::from_str_with_names`
+pub(crate) fn type_tag_from_str_with_names(
+    s: &str,
+    names: &BTreeMap<AccountAddress, String>,
+) -> Result<TypeTag, ParseError> {
+    parse_with_names(s, names, |parser| parser.parse_type_tag())
+}
+
+/// `StructTag::from_str_with_names`
+pub(crate) fn struct_tag_from_str_with_names(
+    s: &str,
+    names: &BTreeMap<AccountAddress, String>,
+) -> Result<StructTag, ParseError> {
+    parse_with_names(s, names, |parser| parser.parse_struct_tag())
+}
+
+/// `ModuleId::from_str_with_names`
+pub(crate) fn module_id_from_str_with_names(
+    s: &str,
+    names: &BTreeMap<AccountAddress, String>,
+) -> Result<ModuleId, ParseError> {
+    parse_with_names(s, names, |parser| parser.parse_module_id())
+}
+
+/// `TypeTag::from_canonical_string`
+pub(crate) fn type_tag_from_canonical_str(s: &str) -> Result<TypeTag, ParseError> {
+    let tag = type_tag_from_str(s)?;
+    check_canonical(s, &tag.to_canonical_string())?;
+    Ok(tag)
+}
+
+/// `StructTag::from_canonical_string`
+pub(crate) fn struct_tag_from_canonical_str(s: &str) -> Result<StructTag, ParseError> {
+    let tag = parse(s, |parser| parser.parse_struct_tag())?;
+    check_canonical(s, &tag.to_canonical_string())?;
+    Ok(tag)
+}
+
+/// Fail at the first byte where `s` differs from `canonical`, the canonical form of what it
+/// parsed into
+fn check_canonical(s: &str, canonical: &str) -> Result<(), ParseError> {
+    let offset = match s.bytes().zip(canonical.bytes()).position(|(a, b)| a != b) {
+        Some(offset) => offset,
+        None if s.len() == canonical.len() => return Ok(()),
+        None => std::cmp::min(s.len(), canonical.len()),
+    };
+    let found = match s[offset..].chars().next() {
+        Some(c) => format!("`{}`", c),
+        None => "end of input".to_string(),
+    };
+    Err(ParseError::new(
+        offset,
+        format!("the canonical form `{}`", canonical),
+        found,
+    ))
+}
+
+/// `StructTag::from_str`
+pub(crate) fn struct_tag_from_str(s: &str) -> Result<StructTag, ParseError> {
+    parse_with_bare_address(s, |parser| parser.parse_struct_tag())
+}
+
+/// `ResourceKey::from_str`
+pub(crate) fn resource_key_from_str(s: &str) -> Result<ResourceKey, ParseError> {
+    let slash = s
+        .find('/')
+        .ok_or_else(|| ParseError::new(s.len(), "`/`", "end of input"))?;
+    let trimmed = s[..slash].trim();
+    let start = s.len() - s.trim_start().len();
+    // the address may be written without `0x`, however short
+    let literal = if trimmed.starts_with("0x") {
+        trimmed.to_string()
+    } else {
+        format!("0x{}", trimmed)
+    };
+    let address = AccountAddress::from_hex_literal(&literal).map_err(|_| {
+        ParseError::new(
+            start,
+            format!("an address of at most {} bytes", AccountAddress::LENGTH),
+            format!("`{}`", trimmed),
+        )
+    })?;
+    let type_ = parse(&s[slash + 1..], |parser| parser.parse_struct_tag()).map_err(|mut e| {
+        e.offset += slash + 1;
+        e
+    })?;
+    Ok(ResourceKey::new(address, type_))
+}
+
+/// `ModuleId::from_str`
+pub(crate) fn module_id_from_str(s: &str) -> Result<ModuleId, ParseError> {
+    parse_with_bare_address(s, |parser| parser.parse_module_id())
+}
+
+/// Parse `s` like `parse`, but allowing its leading address to be written without `0x`, as
+/// `ModuleId`s display it. Offsets in errors are those in `s`.
+fn parse_with_bare_address<F, T>(s: &str, f: F) -> Result<T, ParseError>
+where
+    F: Fn(&mut Parser<std::vec::IntoIter<(Token, usize)>>) -> Result<T, ParseError>,
+{
+    let start = s.len() - s.trim_start().len();
+    let head = s[start..].split("::").next().unwrap_or("").trim_end();
+    let bare = s[start..].contains("::")
+        && !head.is_empty()
+        && !head.starts_with("0x")
+        && head.chars().all(|c| c.is_ascii_hexdigit());
+    if !bare {
+        return parse(s, f);
+    }
+    let prefixed = format!("{}0x{}", &s[..start], &s[start..]);
+    parse(&prefixed, f).map_err(|mut e| {
+        if e.offset > start {
+            e.offset = std::cmp::max(e.offset - 2, start);
+        }
+        e
+    })
+}
+
 pub fn parse_transaction_arguments(s: &str) -> Result<Vec<TransactionArgument>> {
-    parse(s, |parser| {
+    Ok(parse(s, |parser| {
         parser.parse_comma_list(
             |parser| parser.parse_transaction_argument(),
             Token::EOF,
             true,
         )
-    })
+    })?)
 }
 
 pub fn parse_transaction_argument(s: &str) -> Result<TransactionArgument> {
-    parse(s, |parser| parser.parse_transaction_argument())
+    Ok(parse(s, |parser| parser.parse_transaction_argument())?)
+}
+
+/// Parse comma-separated value literals, see `parse_move_value`
+pub fn parse_move_values(s: &str) -> Result<Vec<MoveValue>> {
+    Ok(parse(s, |parser| {
+        parser.parse_comma_list(|parser| parser.parse_move_value(), Token::EOF, true)
+    })?)
+}
+
+/// Parse the literal of a primitive value or of a vector, as written by
+/// `MoveValue::to_move_literal`, e.g. `vector[@0x1, @0x2]`. The forms of transaction arguments
+/// are accepted too: numbers without a suffix are `u64`s, addresses may omit the `@`, and byte
+/// strings such as `x"beef"` and `b"text"` are `vector<u8>`s. Structs, whose literals need their
+/// declarations to be read, are not supported.
+pub fn parse_move_value(s: &str) -> Result<MoveValue> {
+    Ok(parse(s, |parser| parser.parse_move_value())?)
 }
 
 pub fn parse_struct_tag(s: &str) -> Result<StructTag> {
-    let type_tag = parse(s, |parser| parser.parse_type_tag())
-        .map_err(|e| format_err!("invalid struct tag: {}, {}", s, e))?;
-    if let TypeTag::Struct(struct_tag) = type_tag {
-        Ok(struct_tag)
-    } else {
-        bail!("invalid struct tag: {}", s)
-    }
+    struct_tag_from_str(s).map_err(|e| format_err!("invalid struct tag: {}, {}", s, e))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         account_address::AccountAddress,
-        parser::{parse_struct_tag, parse_transaction_argument, parse_type_tag},
+        parser::{
+            parse_move_value, parse_move_values, parse_struct_tag, parse_transaction_argument,
+            parse_type_tag,
+        },
         transaction_argument::TransactionArgument,
+        value::MoveValue,
     };
 
     #[allow(clippy::unreadable_literal)]
@@ -457,6 +920,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tests_parse_move_value() {
+        use MoveValue as V;
+
+        let addr = |s| AccountAddress::from_hex_literal(s).unwrap();
+        for (s, expected) in vec![
+            ("7u8", V::U8(7)),
+            ("7", V::U64(7)),
+            ("7u128", V::U128(7)),
+            ("true", V::Bool(true)),
+            ("@0x1", V::Address(addr("0x1"))),
+            ("0x1", V::Address(addr("0x1"))),
+            ("x\"beef\"", V::vector_u8(vec![0xbe, 0xef])),
+            ("b\"hi\"", V::vector_u8(b"hi".to_vec())),
+            ("vector[]", V::Vector(vec![])),
+            ("vector[1u8, 2u8,]", V::vector_u8(vec![1, 2])),
+            (
+                " vector [ vector[@0x1], vector[] ] ",
+                V::Vector(vec![
+                    V::Vector(vec![V::Address(addr("0x1"))]),
+                    V::Vector(vec![]),
+                ]),
+            ),
+        ] {
+            assert_eq!(parse_move_value(s).unwrap(), expected, "{}", s);
+            // literals of primitives and vectors read back
+            if let Ok(literal) = expected.to_move_literal() {
+                assert_eq!(parse_move_value(&literal).unwrap(), expected, "{}", literal);
+            }
+        }
+        assert_eq!(
+            parse_move_values("1u8, vector[true], @0x2").unwrap(),
+            vec![
+                V::U8(1),
+                V::Vector(vec![V::Bool(true)]),
+                V::Address(addr("0x2"))
+            ]
+        );
+        assert_eq!(parse_move_values("").unwrap(), vec![]);
+
+        for s in &[
+            "",
+            "@",
+            "@true",
+            "vector",
+            "vector[1u8",
+            "vector[1u8 2u8]",
+            "vector<u8>[]",
+            "256u8",
+            "0x1::M::S { f: 1u64 }",
+        ] {
+            assert!(parse_move_value(s).is_err(), "{}", s);
+        }
+        let deep = format!("{}{}", "vector[".repeat(300), "]".repeat(300));
+        assert!(parse_move_value(&deep).is_err());
+    }
+
     #[test]
     fn test_type_tag() {
         for s in &[
@@ -8,11 +8,30 @@ use crate::{
     transaction_argument::TransactionArgument,
 };
 use proptest::{collection::vec, prelude::*};
+
+/// The shape of the type tags generated by `any_with::<TypeTag>` and `any_with::<StructTag>`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypeTagParams {
+    /// Maximum number of vectors and structs nested above the primitive types of a tag
+    pub max_depth: u32,
+    /// Maximum number of type arguments of a struct tag
+    pub max_type_args: usize,
+}
+
+impl Default for TypeTagParams {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_type_args: 4,
+        }
+    }
+}
+
 impl Arbitrary for TypeTag {
-    type Parameters = ();
+    type Parameters = TypeTagParams;
     type Strategy = BoxedStrategy<Self>;
 
-    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    fn arbitrary_with(params: Self::Parameters) -> Self::Strategy {
         use TypeTag::*;
         let leaf = prop_oneof![
             Just(Bool),
@@ -20,33 +39,59 @@ impl Arbitrary for TypeTag {
             Just(U64),
             Just(U128),
             Just(Address),
-            Just(Vector(Box::new(Bool))),
+            Just(Signer),
         ];
+        let max_type_args = params.max_type_args;
         leaf.prop_recursive(
-            8,  // levels deep
-            16, // max size
-            4,  // max number of items per collection
-            |inner| {
-                (
-                    any::<AccountAddress>(),
-                    any::<Identifier>(),
-                    any::<Identifier>(),
-                    vec(inner, 0..4),
-                )
-                    .prop_map(|(address, module, name, type_params)| {
-                        Struct(StructTag {
-                            address,
-                            module,
-                            name,
-                            type_params,
-                        })
-                    })
+            params.max_depth,
+            // the desired number of nodes, and the expected number of children of a node
+            params.max_depth * max_type_args as u32,
+            max_type_args as u32,
+            move |inner| {
+                prop_oneof![
+                    inner.clone().prop_map(|ty| Vector(Box::new(ty))),
+                    struct_tag_strategy(vec(inner, 0..=max_type_args)).prop_map(Struct),
+                ]
             },
         )
         .boxed()
     }
 }
 
+impl Arbitrary for StructTag {
+    type Parameters = TypeTagParams;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(params: Self::Parameters) -> Self::Strategy {
+        if params.max_depth == 0 {
+            return struct_tag_strategy(Just(vec![])).boxed();
+        }
+        let ty_args = any_with::<TypeTag>(TypeTagParams {
+            max_depth: params.max_depth - 1,
+            ..params
+        });
+        struct_tag_strategy(vec(ty_args, 0..=params.max_type_args)).boxed()
+    }
+}
+
+/// Struct tags with arbitrary names and the type arguments of `type_params`
+fn struct_tag_strategy(
+    type_params: impl Strategy<Value = Vec<TypeTag>>,
+) -> impl Strategy<Value = StructTag> {
+    (
+        any::<AccountAddress>(),
+        any::<Identifier>(),
+        any::<Identifier>(),
+        type_params,
+    )
+        .prop_map(|(address, module, name, type_params)| StructTag {
+            address,
+            module,
+            name,
+            type_params,
+        })
+}
+
 impl Arbitrary for TransactionArgument {
     type Parameters = ();
     fn arbitrary_with(_args: ()) -> Self::Strategy {
@@ -64,7 +64,7 @@ impl TryFrom<MoveValue> for TransactionArgument {
                     })
                     .collect::<Result<Vec<u8>>>()?,
             ),
-            MoveValue::Signer(_) | MoveValue::Struct(_) => {
+            MoveValue::Signer(_) | MoveValue::Struct(_) | MoveValue::Variant { .. } => {
                 return Err(anyhow!("invalid transaction argument: {:?}", val))
             }
         })
@@ -0,0 +1,122 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The behaviors that depend on the length of addresses, checked for whichever length the
+//! `address20` and `address32` features select. Run with each of them to cover every length.
+
+use crate::{
+    account_address::AccountAddress,
+    ident_str,
+    language_storage::{ModuleId, StructTag, TypeTag},
+    value::{MoveStructLayout, MoveTypeLayout, MoveValue},
+};
+
+fn xus() -> StructTag {
+    StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("XUS").to_owned(),
+        name: ident_str!("XUS").to_owned(),
+        type_params: vec![],
+    }
+}
+
+#[test]
+fn length_follows_features() {
+    let expected = if cfg!(feature = "address20") {
+        20
+    } else if cfg!(feature = "address32") {
+        32
+    } else {
+        16
+    };
+    assert_eq!(AccountAddress::LENGTH, expected);
+    assert_eq!(AccountAddress::ZERO.to_vec().len(), expected);
+    assert_eq!(std::mem::size_of::<AccountAddress>(), expected);
+}
+
+#[test]
+fn hex_forms_have_full_width() {
+    let width = AccountAddress::LENGTH * 2;
+    let one = AccountAddress::ONE;
+    let full = format!("{}1", "0".repeat(width - 1));
+    assert_eq!(one.to_canonical_string(), full);
+    assert_eq!(one.to_hex(), full);
+    assert_eq!(one.to_string(), full);
+    assert_eq!(format!("{:#x}", one), format!("0x{}", full));
+    assert_eq!(one.to_checksum_string(), format!("0x{}", full));
+    assert_eq!(one.to_hex_literal(), "0x1");
+    assert_eq!(
+        serde_json::to_value(one).unwrap(),
+        serde_json::json!(full.clone())
+    );
+    assert_eq!(AccountAddress::MAX.to_hex(), "f".repeat(width));
+}
+
+#[test]
+fn short_forms_are_padded() {
+    let width = AccountAddress::LENGTH * 2;
+    assert_eq!(
+        AccountAddress::from_hex_literal("0x1"),
+        Ok(AccountAddress::ONE)
+    );
+    let widest = format!("0x{}", "f".repeat(width));
+    assert_eq!(
+        AccountAddress::from_hex_literal(&widest),
+        Ok(AccountAddress::MAX)
+    );
+    assert!(AccountAddress::from_hex_literal(&format!("{}f", widest)).is_err());
+    assert!(AccountAddress::from_hex(&"1".repeat(width - 1)).is_err());
+    assert!(AccountAddress::from_str_strict(&"1".repeat(width)).is_ok());
+    assert_eq!(
+        AccountAddress::from_bytes_left_padded([1u8]),
+        Ok(AccountAddress::ONE)
+    );
+    assert!(AccountAddress::from_bytes(vec![0u8; AccountAddress::LENGTH + 1]).is_err());
+}
+
+#[test]
+fn encodings_have_address_length() {
+    let one = AccountAddress::ONE;
+    assert_eq!(bcs::to_bytes(&one).unwrap().len(), AccountAddress::LENGTH);
+    let value = MoveValue::vector_address(vec![one, one]);
+    assert_eq!(
+        value.simple_serialize().unwrap().len(),
+        1 + 2 * AccountAddress::LENGTH
+    );
+    assert_eq!(
+        MoveValue::simple_deserialize(
+            &value.simple_serialize().unwrap(),
+            &MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address))
+        )
+        .unwrap(),
+        value
+    );
+    // layout encodings prefix the addresses of struct tags with their length
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_types(xus(), vec![]));
+    let bytes = layout.to_bytes();
+    assert!(bytes
+        .windows(AccountAddress::LENGTH + 1)
+        .any(|w| w[0] as usize == AccountAddress::LENGTH && w[1..] == AccountAddress::ONE[..]));
+    assert_eq!(MoveTypeLayout::from_bytes(&bytes).unwrap(), layout);
+}
+
+#[test]
+fn tags_use_full_width_addresses() {
+    let width = AccountAddress::LENGTH * 2;
+    let tag = xus();
+    let canonical = format!("0x{}1::XUS::XUS", "0".repeat(width - 1));
+    assert_eq!(tag.to_canonical_string(), canonical);
+    assert_eq!(
+        StructTag::from_canonical_string(&canonical),
+        Ok(tag.clone())
+    );
+    assert_eq!(tag.to_string(), "0x1::XUS::XUS");
+    assert_eq!(TypeTag::Struct(tag).to_canonical_string(), canonical);
+
+    let module = ModuleId::new(AccountAddress::ONE, ident_str!("XUS").to_owned());
+    assert_eq!(
+        module.to_canonical_string(),
+        format!("0x{}1::XUS", "0".repeat(width - 1))
+    );
+    assert_eq!(module.to_string().parse::<ModuleId>(), Ok(module));
+}
@@ -0,0 +1,39 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Generated by `value::codegen::generate_rust` from `value_test::codegen_account_layout`; kept
+// in sync by `value_test::generate_rust_definitions`.
+
+use crate::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Account {
+    pub authentication_key: Vec<u8>,
+    pub balance: CoinXUS,
+    pub history: Vec<CoinXUS>,
+    pub limits: AccountLimits,
+    pub status: AccountStatus,
+    pub r#type: bool,
+    #[serde(rename = "self")]
+    pub self_: AccountAddress,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CoinXUS {
+    pub value: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AccountLimits {
+    pub daily: u128,
+    pub delegates: Vec<AccountAddress>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum AccountStatus {
+    Active {
+        since: u64,
+    },
+    Frozen,
+}
@@ -1,7 +1,14 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::identifier::{IdentStr, Identifier, ALLOWED_IDENTIFIERS, ALLOWED_NO_SELF_IDENTIFIERS};
+use crate::{
+    ident_str,
+    identifier::{
+        is_valid, validity_error, IdentStr, Identifier, IdentifierPool, InvalidIdentifierKind,
+        ALLOWED_IDENTIFIERS, ALLOWED_NO_SELF_IDENTIFIERS, MAX_ARBITRARY_IDENTIFIER_LENGTH,
+        MAX_IDENTIFIER_LENGTH,
+    },
+};
 use bcs::test_helpers::assert_canonical_encode_decode;
 use once_cell::sync::Lazy;
 use proptest::prelude::*;
@@ -59,6 +66,75 @@ fn invalid_identifiers() {
     }
 }
 
+#[test]
+fn identifier_validity_errors() {
+    use InvalidIdentifierKind::*;
+
+    let longest = "a".repeat(MAX_IDENTIFIER_LENGTH);
+    let too_long = "a".repeat(MAX_IDENTIFIER_LENGTH + 1);
+    let cases: &[(&str, Option<InvalidIdentifierKind>)] = &[
+        ("foo", None),
+        ("Foo_0", None),
+        ("_foo", None),
+        ("__", None),
+        ("_0", None),
+        ("<SELF>", None),
+        (longest.as_str(), None),
+        ("", Some(Empty)),
+        ("0foo", Some(StartsWithDigit)),
+        ("9", Some(StartsWithDigit)),
+        ("_", Some(Reserved)),
+        (":foo", Some(InvalidChar { index: 0 })),
+        ("foo-bar", Some(InvalidChar { index: 3 })),
+        ("foo.", Some(InvalidChar { index: 3 })),
+        ("<SELF", Some(InvalidChar { index: 0 })),
+        // byte indices, not character indices
+        ("f\u{e9}e", Some(InvalidChar { index: 1 })),
+        ("\u{1f389}foo", Some(InvalidChar { index: 0 })),
+        ("foo\u{1f389}", Some(InvalidChar { index: 3 })),
+        (
+            too_long.as_str(),
+            Some(TooLong {
+                len: MAX_IDENTIFIER_LENGTH + 1,
+                max: MAX_IDENTIFIER_LENGTH,
+            }),
+        ),
+    ];
+    for (s, expected) in cases {
+        let shown = &s[..s.len().min(16)];
+        assert_eq!(validity_error(s), *expected, "{:?}", shown);
+        assert_eq!(is_valid(s), expected.is_none(), "{:?}", shown);
+        assert_eq!(
+            Identifier::new(*s).is_ok(),
+            expected.is_none(),
+            "{:?}",
+            shown
+        );
+        assert_eq!(IdentStr::new(s).is_ok(), expected.is_none(), "{:?}", shown);
+        if let Some(kind) = expected {
+            let message = Identifier::new(*s).unwrap_err().to_string();
+            assert!(message.ends_with(&kind.to_string()), "{}", message);
+        }
+    }
+}
+
+#[test]
+fn const_identifiers() {
+    const ACCOUNT: &IdentStr = IdentStr::const_new("Account");
+    static BALANCE: &IdentStr = ident_str!("Balance");
+    assert_eq!(ACCOUNT.as_str(), "Account");
+    assert_eq!(BALANCE, IdentStr::new("Balance").unwrap());
+    assert_eq!(ident_str!("_0").as_str(), "_0");
+}
+
+#[test]
+#[should_panic]
+fn const_new_invalid_at_runtime() {
+    // only known at runtime, so const_new panics instead of failing to compile
+    let invalid: &'static str = Box::leak(String::from("0foo").into_boxed_str());
+    IdentStr::const_new(invalid);
+}
+
 proptest! {
     #[test]
     fn invalid_identifiers_proptest(identifier in invalid_identifier_strategy()) {
@@ -73,6 +149,12 @@ proptest! {
         prop_assert!(Identifier::is_valid(&identifier));
     }
 
+    #[test]
+    fn arbitrary_identifiers_are_valid(identifier in any::<Identifier>()) {
+        prop_assert!(Identifier::is_valid(identifier.as_str()));
+        prop_assert!(identifier.len() <= MAX_ARBITRARY_IDENTIFIER_LENGTH);
+    }
+
     #[test]
     fn identifier_string_roundtrip(identifier in any::<Identifier>()) {
         let s = identifier.clone().into_string();
@@ -122,3 +204,221 @@ fn serde_serialize_no_wrapper() {
     let s = serde_json::to_string(&foobar).expect("Identifier should serialize correctly");
     assert_eq!(s, "\"foobar\"");
 }
+
+/// Clones share their string, but are otherwise indistinguishable from the original.
+#[test]
+fn clones_share_string() {
+    let foobar = Identifier::new("foobar").unwrap();
+    let clone = foobar.clone();
+    assert_eq!(foobar, clone);
+    assert!(std::ptr::eq(foobar.as_str(), clone.as_str()));
+    assert_eq!(
+        bcs::to_bytes(&clone).unwrap(),
+        bcs::to_bytes("foobar").unwrap()
+    );
+}
+
+/// `&IdentStr` deserializes from the same formats as `Identifier`, borrowing from the input.
+#[test]
+fn deserialize_borrowed() {
+    let foobar = Identifier::new("foobar").unwrap();
+
+    let blob = bcs::to_bytes(&foobar).unwrap();
+    let borrowed: &IdentStr = bcs::from_bytes(&blob).unwrap();
+    assert_eq!(borrowed, foobar.as_ident_str());
+    assert!(blob.as_ptr_range().contains(&borrowed.as_bytes().as_ptr()));
+
+    let json = serde_json::to_string(&foobar).unwrap();
+    let borrowed: &IdentStr = serde_json::from_str(&json).unwrap();
+    assert_eq!(borrowed, foobar.as_ident_str());
+
+    let invalid = bcs::to_bytes("0foo").unwrap();
+    bcs::from_bytes::<&IdentStr>(&invalid).unwrap_err();
+    // JSON strings with escapes cannot be borrowed
+    serde_json::from_str::<&IdentStr>(r#""\u0066oobar""#).unwrap_err();
+    assert_eq!(
+        serde_json::from_str::<Identifier>(r#""\u0066oobar""#).unwrap(),
+        foobar
+    );
+}
+
+#[test]
+fn identifier_pool() {
+    let pool = IdentifierPool::new(2);
+    assert!(pool.is_empty());
+    let value = pool.get_or_intern("value").unwrap();
+    let again = pool.intern(ident_str!("value"));
+    assert!(std::ptr::eq(value.as_str(), again.as_str()));
+    let owned = pool.intern_owned(Identifier::new("value").unwrap());
+    assert!(std::ptr::eq(value.as_str(), owned.as_str()));
+
+    // interned identifiers are indistinguishable from others
+    let fresh = Identifier::new("value").unwrap();
+    assert_eq!(value, fresh);
+    assert_eq!(
+        bcs::to_bytes(&value).unwrap(),
+        bcs::to_bytes(&fresh).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        serde_json::to_string(&fresh).unwrap()
+    );
+
+    pool.get_or_intern("0value").unwrap_err();
+    assert_eq!(pool.len(), 1);
+
+    // once full, new identifiers are not shared
+    let coin = pool.get_or_intern("coin").unwrap();
+    let balance = pool.get_or_intern("balance").unwrap();
+    let balance_again = pool.get_or_intern("balance").unwrap();
+    assert_eq!(pool.len(), pool.capacity());
+    assert_eq!(balance, balance_again);
+    assert!(!std::ptr::eq(balance.as_str(), balance_again.as_str()));
+    assert!(std::ptr::eq(
+        coin.as_str(),
+        pool.get_or_intern("coin").unwrap().as_str()
+    ));
+}
+
+#[test]
+fn identifier_pool_concurrent() {
+    let pool = std::sync::Arc::new(IdentifierPool::new(16));
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let pool = pool.clone();
+            std::thread::spawn(move || {
+                (0..100)
+                    .map(|i| pool.get_or_intern(["value", "coin"][i % 2]).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let shared = pool.get_or_intern("value").unwrap();
+    for thread in threads {
+        for ident in thread.join().unwrap() {
+            if ident.as_str() == "value" {
+                assert!(std::ptr::eq(ident.as_str(), shared.as_str()));
+            }
+        }
+    }
+    assert_eq!(pool.len(), 2);
+}
+
+#[test]
+fn identifier_length_limit() {
+    let longest = "a".repeat(MAX_IDENTIFIER_LENGTH);
+    let too_long = "a".repeat(MAX_IDENTIFIER_LENGTH + 1);
+    assert_eq!(MAX_IDENTIFIER_LENGTH, 255);
+
+    // construction
+    assert_eq!(Identifier::new(longest.as_str()).unwrap().len(), 255);
+    Identifier::new(too_long.as_str()).unwrap_err();
+    IdentStr::new(&too_long).unwrap_err();
+    Identifier::from_utf8(too_long.clone().into_bytes()).unwrap_err();
+
+    // deserialization, which must not accept what construction rejects
+    let longest_bytes = bcs::to_bytes(&longest).unwrap();
+    let too_long_bytes = bcs::to_bytes(&too_long).unwrap();
+    assert_eq!(
+        bcs::from_bytes::<Identifier>(&longest_bytes)
+            .unwrap()
+            .as_str(),
+        longest
+    );
+    assert_eq!(
+        bcs::from_bytes::<&IdentStr>(&longest_bytes)
+            .unwrap()
+            .as_str(),
+        longest
+    );
+    bcs::from_bytes::<Identifier>(&too_long_bytes).unwrap_err();
+    bcs::from_bytes::<&IdentStr>(&too_long_bytes).unwrap_err();
+    serde_json::from_str::<Identifier>(&format!("{:?}", too_long)).unwrap_err();
+    // as must the character rules
+    bcs::from_bytes::<Identifier>(&bcs::to_bytes("foo-bar").unwrap()).unwrap_err();
+
+    // stricter limits
+    assert!(Identifier::new_with_max_length("abc", 3).is_ok());
+    let err = Identifier::new_with_max_length("abcd", 3).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("identifier of 4 bytes is longer than the maximum of 3"));
+}
+
+#[test]
+fn case_conversions() {
+    let cases = [
+        // (name, snake case, upper camel case)
+        ("coin_store", "coin_store", "CoinStore"),
+        ("CoinStore", "coin_store", "CoinStore"),
+        ("coinStore", "coin_store", "CoinStore"),
+        ("BCSBytes", "bcs_bytes", "BcsBytes"),
+        ("bcs_bytes", "bcs_bytes", "BcsBytes"),
+        ("parseBCS", "parse_bcs", "ParseBcs"),
+        ("XUS", "xus", "Xus"),
+        ("u64_value", "u64_value", "U64Value"),
+        ("U64Value", "u64_value", "U64Value"),
+        ("value2", "value2", "Value2"),
+        ("_private", "_private", "_Private"),
+        ("__Private", "__private", "__Private"),
+        ("_0", "_0", "_0"),
+        ("__", "__", "__"),
+        ("a__b", "a_b", "AB"),
+        ("trailing_", "trailing", "Trailing"),
+        ("<SELF>", "self", "Self"),
+    ];
+    for (name, snake, camel) in &cases {
+        let ident = IdentStr::new(name).unwrap();
+        assert_eq!(ident.to_snake_case().unwrap().as_str(), *snake, "{}", name);
+        assert_eq!(
+            ident.to_upper_camel_case().unwrap().as_str(),
+            *camel,
+            "{}",
+            name
+        );
+        assert!(IdentStr::new(snake).unwrap().is_snake(), "{}", snake);
+        if camel
+            .trim_start_matches('_')
+            .starts_with(char::is_alphabetic)
+        {
+            assert!(IdentStr::new(camel).unwrap().is_upper_camel(), "{}", camel);
+        }
+    }
+
+    // unicode and other characters only separate words
+    assert_eq!(crate::identifier::to_snake_case("caf\u{e9}Bar"), "caf_bar");
+    assert_eq!(
+        crate::identifier::to_upper_camel_case("foo-bar.baz"),
+        "FooBarBaz"
+    );
+
+    assert!(crate::identifier::is_upper_camel("BCSBytes"));
+    assert!(crate::identifier::is_upper_camel("_Private"));
+    assert!(!crate::identifier::is_upper_camel("Coin_Store"));
+    assert!(!crate::identifier::is_upper_camel("coinStore"));
+    assert!(!crate::identifier::is_upper_camel("__"));
+    assert!(crate::identifier::is_snake("coin_store"));
+    assert!(crate::identifier::is_snake("_0"));
+    assert!(!crate::identifier::is_snake("coinStore"));
+    assert!(!crate::identifier::is_snake("0coin"));
+
+    // snake case can outgrow the length limit
+    let long = "aB".repeat(MAX_IDENTIFIER_LENGTH / 2);
+    IdentStr::new(&long).unwrap().to_snake_case().unwrap_err();
+    IdentStr::new(&long).unwrap().to_upper_camel_case().unwrap();
+}
+
+proptest! {
+    #[test]
+    fn case_conversions_are_valid(identifier in any::<Identifier>()) {
+        // identifiers longer than half the limit might outgrow it in snake case
+        prop_assume!(identifier.len() <= MAX_IDENTIFIER_LENGTH / 2);
+        let snake = identifier.to_snake_case().unwrap();
+        let camel = identifier.to_upper_camel_case().unwrap();
+        prop_assert!(snake.is_snake());
+        prop_assert_eq!(snake.to_snake_case().unwrap(), snake.clone());
+        if identifier.as_str().trim_start_matches('_').starts_with(char::is_alphabetic) {
+            prop_assert!(camel.is_upper_camel());
+        }
+    }
+}
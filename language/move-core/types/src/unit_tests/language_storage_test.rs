@@ -1,13 +1,989 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::language_storage::ModuleId;
+use crate::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, TYPE_TAG_IDENTIFIER_BYTE_SIZE, TYPE_TAG_NODE_SIZE},
+    ident_str,
+    identifier::{Identifier, MAX_IDENTIFIER_LENGTH},
+    language_storage::{
+        ModuleId, ResourceKey, StructTag, StructTagMatcher, StructTagTemplate, TypeTag,
+        TypeTagCache, TypeTagLimits, TypeTagTemplate, TYPE_TAG_DEPTH_MAX, TYPE_TAG_NODES_MAX,
+    },
+    proptest_types::TypeTagParams,
+};
 use bcs::test_helpers::assert_canonical_encode_decode;
-use proptest::prelude::*;
+use proptest::{collection::vec, prelude::*};
+use serde::Serialize;
+use serde_json::json;
+use sha3::{Digest, Sha3_256};
+use std::{collections::BTreeMap, sync::Arc, thread};
 
 proptest! {
     #[test]
     fn test_module_id_canonical_roundtrip(module_id in any::<ModuleId>()) {
         assert_canonical_encode_decode(module_id);
     }
+
+    #[test]
+    fn test_type_tag_display_roundtrip(tag in any::<TypeTag>()) {
+        prop_assert_eq!(tag.to_string().parse::<TypeTag>(), Ok(tag));
+    }
+
+    #[test]
+    fn test_type_tag_canonical_roundtrip(tag in any::<TypeTag>()) {
+        let canonical = tag.to_canonical_string();
+        prop_assert_eq!(TypeTag::from_canonical_string(&canonical), Ok(tag.clone()));
+        prop_assert_eq!(canonical.parse::<TypeTag>(), Ok(tag));
+    }
+
+    #[test]
+    fn test_type_tag_bcs_unchanged(tag in any::<TypeTag>()) {
+        prop_assert_eq!(
+            bcs::to_bytes(&tag).unwrap(),
+            bcs::to_bytes(&DerivedTypeTag::from(&tag)).unwrap()
+        );
+        assert_canonical_encode_decode(tag);
+    }
+
+    #[test]
+    fn test_type_tag_json_roundtrip(tag in any::<TypeTag>()) {
+        let json = serde_json::to_value(&tag).unwrap();
+        prop_assert_eq!(&json, &json!(tag.to_canonical_string()));
+        prop_assert_eq!(serde_json::from_value::<TypeTag>(json).unwrap(), tag);
+    }
+
+    #[test]
+    fn test_abstract_size_grows_with_type_args(
+        type_params in vec(any::<TypeTag>(), 0..4),
+        extra in any::<TypeTag>(),
+    ) {
+        let mut tag = StructTag {
+            address: AccountAddress::ONE,
+            module: Identifier::new("M").unwrap(),
+            name: Identifier::new("S").unwrap(),
+            type_params,
+        };
+        let size = tag.abstract_size();
+        prop_assert_eq!(TypeTag::Struct(tag.clone()).abstract_size(), size);
+        tag.type_params.push(extra.clone());
+        prop_assert_eq!(tag.abstract_size(), size + extra.abstract_size());
+        prop_assert!(tag.abstract_size() > size);
+    }
+
+    #[test]
+    fn test_type_tag_order_agrees_with_eq(tag1 in any::<TypeTag>(), tag2 in any::<TypeTag>()) {
+        prop_assert_eq!(tag1.cmp(&tag2) == std::cmp::Ordering::Equal, tag1 == tag2);
+        prop_assert_eq!(tag1.cmp(&tag2), tag2.cmp(&tag1).reverse());
+    }
+
+    #[test]
+    fn test_type_tag_order_agrees_with_canonical_string(
+        tag1 in any::<TypeTag>(),
+        tag2 in any::<TypeTag>(),
+    ) {
+        prop_assert_eq!(
+            tag1.cmp(&tag2),
+            tag1.to_canonical_string().cmp(&tag2.to_canonical_string())
+        );
+    }
+
+    #[test]
+    fn test_struct_tag_order_agrees_with_canonical_string(
+        tag1 in any::<StructTag>(),
+        tag2 in any::<StructTag>(),
+    ) {
+        prop_assert_eq!(
+            tag1.cmp(&tag2),
+            tag1.to_canonical_string().cmp(&tag2.to_canonical_string())
+        );
+        prop_assert_eq!(
+            tag1.cmp(&tag2),
+            TypeTag::Struct(tag1.clone()).cmp(&TypeTag::Struct(tag2.clone()))
+        );
+    }
+
+    #[test]
+    fn test_struct_tag_display_roundtrip(tag in any::<StructTag>()) {
+        prop_assert_eq!(tag.to_string().parse::<StructTag>(), Ok(tag.clone()));
+        prop_assert_eq!(StructTag::from_canonical_string(&tag.to_canonical_string()), Ok(tag));
+    }
+
+    #[test]
+    fn test_type_tag_params_bound_shape(
+        tag in any_with::<TypeTag>(TypeTagParams { max_depth: 2, max_type_args: 6 }),
+        struct_tag in any_with::<StructTag>(TypeTagParams { max_depth: 2, max_type_args: 6 }),
+    ) {
+        prop_assert!(tag.depth() <= 3);
+        prop_assert!(struct_tag.type_params.len() <= 6);
+        prop_assert!(TypeTag::Struct(struct_tag.clone()).depth() <= 3);
+        prop_assert!(tag
+            .preorder_iter()
+            .all(|ty| !matches!(ty, TypeTag::Struct(s) if s.type_params.len() > 6)));
+        prop_assert_eq!(tag.to_string().parse::<TypeTag>(), Ok(tag));
+    }
+
+    #[test]
+    fn test_module_id_display_roundtrip(module_id in any::<ModuleId>()) {
+        prop_assert_eq!(module_id.to_string().parse::<ModuleId>(), Ok(module_id));
+    }
+}
+
+#[test]
+fn test_parse_type_tag_with_whitespace() {
+    let tag: TypeTag = " vector < 0x1 :: Diem :: Diem < 0x1::XUS::XUS , signer > > "
+        .parse()
+        .unwrap();
+    assert_eq!(
+        tag.to_string(),
+        "vector<0x1::Diem::Diem<0x1::XUS::XUS, signer>>"
+    );
+    // Keywords are names in name position
+    let tag: TypeTag = "0x1::vector::u8<vector<u8>>".parse().unwrap();
+    assert_eq!(tag.to_string(), "0x1::vector::u8<vector<u8>>");
+}
+
+#[test]
+fn test_parse_malformed_type_tags() {
+    let cases = [
+        ("vector<u8", 9, "`>`"),
+        ("0x1::M::S<u8, vector<u8>", 24, "`,` or `>`"),
+        ("vector<u8>>", 10, "end of input"),
+        ("0x1::M::S<>", 10, "a type argument"),
+        ("0x1::S", 6, "`::`"),
+        ("0x1::M::", 8, "a struct name"),
+        ("0x1::<u8>", 5, "a module name"),
+        ("u16", 0, "a type"),
+        ("vector<u8, u8>", 9, "`>`"),
+        ("0x1::M::S<u8, $>", 14, "a token"),
+    ];
+    for (input, offset, expected) in cases.iter() {
+        let err = input.parse::<TypeTag>().unwrap_err();
+        assert_eq!(
+            (err.offset(), err.expected()),
+            (*offset, *expected),
+            "{}: {}",
+            input,
+            err
+        );
+    }
+}
+
+#[test]
+fn test_parse_generic_struct_tag() {
+    let tag: StructTag = "0x1::Pool::Pool<vector<vector<0x1::XUS::XUS>>, u64>"
+        .parse()
+        .unwrap();
+    let xus = StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new("XUS").unwrap(),
+        name: Identifier::new("XUS").unwrap(),
+        type_params: vec![],
+    };
+    assert_eq!(
+        tag,
+        StructTag {
+            address: AccountAddress::ONE,
+            module: Identifier::new("Pool").unwrap(),
+            name: Identifier::new("Pool").unwrap(),
+            type_params: vec![
+                TypeTag::Vector(Box::new(TypeTag::Vector(Box::new(TypeTag::Struct(xus))))),
+                TypeTag::U64,
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_parse_addresses_without_prefix() {
+    let account = ModuleId::new(AccountAddress::ONE, Identifier::new("Account").unwrap());
+    let full = "0".repeat(2 * AccountAddress::LENGTH - 1) + "1";
+    for input in [
+        "0x1::Account".to_string(),
+        "1::Account".to_string(),
+        format!("0x{}::Account", full),
+        format!(" {} :: Account", full),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            input.parse::<ModuleId>().as_ref(),
+            Ok(&account),
+            "{}",
+            input
+        );
+    }
+
+    let tag: StructTag = "1::Account::Balance<0x1::XUS::XUS>".parse().unwrap();
+    assert_eq!(tag.address, AccountAddress::ONE);
+    assert_eq!(tag.to_string(), "0x1::Account::Balance<0x1::XUS::XUS>");
+    let tag: StructTag = "a1::M::S".parse().unwrap();
+    assert_eq!(
+        tag.address,
+        AccountAddress::from_hex_literal("0xa1").unwrap()
+    );
+}
+
+#[test]
+fn test_parse_malformed_struct_tags_and_module_ids() {
+    let err = "u8".parse::<StructTag>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a struct tag `0xADDR::Module::Name` at byte 0, found `u8`"
+    );
+    let err = "vector<0x1::M::S>".parse::<StructTag>().unwrap_err();
+    assert_eq!((err.offset(), err.found()), (0, "`vector`"));
+    // Offsets are those of the input as written, without the `0x` of a bare address
+    let err = "1::M::S<u8".parse::<StructTag>().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (10, "`,` or `>`"));
+    let err = "1::M::S<1::N::T>".parse::<StructTag>().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (8, "a type"));
+
+    let err = "0x1::Account::S".parse::<ModuleId>().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (12, "end of input"));
+    let err = "0x1".parse::<ModuleId>().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (3, "`::`"));
+    let err = "Account".parse::<ModuleId>().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (0, "an address"));
+}
+
+#[test]
+fn test_canonical_strings_ignore_address_padding() {
+    let full = "0".repeat(2 * AccountAddress::LENGTH - 1) + "1";
+    let short: TypeTag = "vector<0x1::Pool::Pool<0x1::XUS::XUS, u8>>"
+        .parse()
+        .unwrap();
+    let padded: TypeTag = format!("vector<0x{}::Pool::Pool<0x01::XUS::XUS, u8>>", full)
+        .parse()
+        .unwrap();
+    assert_eq!(short.to_canonical_string(), padded.to_canonical_string());
+    assert_eq!(
+        short.to_canonical_string(),
+        format!("vector<0x{0}::Pool::Pool<0x{0}::XUS::XUS, u8>>", full)
+    );
+    // Display is unchanged
+    assert_eq!(
+        short.to_string(),
+        "vector<0x1::Pool::Pool<0x1::XUS::XUS, u8>>"
+    );
+
+    let tag: StructTag = "0x1::XUS::XUS".parse().unwrap();
+    assert_eq!(
+        StructTag::from_canonical_string(&tag.to_canonical_string()),
+        Ok(tag)
+    );
+    let err = StructTag::from_canonical_string("0x1::XUS::XUS").unwrap_err();
+    assert_eq!((err.offset(), err.found()), (2, "`1`"));
+    let err =
+        TypeTag::from_canonical_string(&format!("vector< 0x{}::XUS::XUS>", full)).unwrap_err();
+    assert_eq!((err.offset(), err.found()), (7, "` `"));
+    assert!(TypeTag::from_canonical_string("u8 ").is_err());
+}
+
+#[test]
+fn test_substitute_type_parameters() {
+    let param = TypeTagTemplate::TypeParameter;
+    let s = |ty_args| {
+        TypeTagTemplate::Struct(StructTagTemplate {
+            address: AccountAddress::ONE,
+            module: Identifier::new("M").unwrap(),
+            name: Identifier::new("S").unwrap(),
+            type_params: ty_args,
+        })
+    };
+    let template = TypeTagTemplate::Vector(Box::new(s(vec![
+        param(0),
+        TypeTagTemplate::Vector(Box::new(param(1))),
+    ])));
+    assert_eq!(template.to_string(), "vector<0x1::M::S<T0, vector<T1>>>");
+    let coin: TypeTag = "0x1::Diem::Diem<0x1::XUS::XUS>".parse().unwrap();
+    let ty = template
+        .substitute(&[coin.clone(), TypeTag::U8, TypeTag::Bool])
+        .unwrap();
+    assert_eq!(
+        ty.to_string(),
+        "vector<0x1::M::S<0x1::Diem::Diem<0x1::XUS::XUS>, vector<u8>>>"
+    );
+    assert_eq!(ty.depth(), 4);
+
+    // templates without parameters are the tags they were made from
+    assert_eq!(
+        TypeTagTemplate::from(coin.clone()).substitute(&[]).unwrap(),
+        coin
+    );
+    assert!(TypeTagTemplate::from(coin.clone()).is_concrete());
+    assert!(!template.is_concrete());
+
+    let err = template.substitute(&[TypeTag::U8]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "type parameter 1 is out of range for 1 type arguments"
+    );
+
+    // the result is limited, not the template
+    assert!(template
+        .substitute_with_max_depth(&[TypeTag::U8, TypeTag::U8], 4)
+        .is_ok());
+    assert!(template
+        .substitute_with_max_depth(&[TypeTag::Vector(Box::new(coin)), TypeTag::U8], 4)
+        .is_err());
+    assert!(template
+        .substitute_with_max_depth(&[TypeTag::U8, TypeTag::U8], 3)
+        .is_err());
+}
+
+/// `vector<...<u8>...>` with `depth - 1` vectors
+fn nested_vector(depth: usize) -> TypeTag {
+    (1..depth).fold(TypeTag::U8, |ty, _| TypeTag::Vector(Box::new(ty)))
+}
+
+#[test]
+fn test_check_limits_on_huge_tag() {
+    let tag = nested_vector(100_000);
+    assert_eq!(tag.depth(), 100_000);
+    assert_eq!(tag.num_nodes(), 100_000);
+    let err = tag
+        .check_limits(TYPE_TAG_DEPTH_MAX, TYPE_TAG_NODES_MAX)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "type tag is nested more than 256 levels");
+    let err = tag.check_limits(usize::MAX, 1000).unwrap_err();
+    assert_eq!(err.to_string(), "type tag has more than 1000 nodes");
+    assert!(tag.check_limits(100_000, 100_000).is_ok());
+
+    // dropping the tag recursively would overflow the stack, so take it apart level by level
+    let mut ty = tag;
+    while let TypeTag::Vector(elem) = ty {
+        ty = *elem;
+    }
+}
+
+#[test]
+fn test_parse_enforces_limits() {
+    let source = |depth| format!("{}u8{}", "vector<".repeat(depth - 1), ">".repeat(depth - 1));
+
+    // recursing through a tag at the limit is safe
+    let tag: TypeTag = source(TYPE_TAG_DEPTH_MAX).parse().unwrap();
+    assert_eq!(tag, nested_vector(TYPE_TAG_DEPTH_MAX));
+    assert_eq!(tag.to_string(), source(TYPE_TAG_DEPTH_MAX));
+    let mut set = std::collections::HashSet::new();
+    set.insert(tag.clone());
+    assert!(set.contains(&tag));
+    drop(set);
+
+    let err = source(TYPE_TAG_DEPTH_MAX + 1)
+        .parse::<TypeTag>()
+        .unwrap_err();
+    assert_eq!(
+        (err.offset(), err.expected()),
+        (7 * TYPE_TAG_DEPTH_MAX, "types nested at most 256 levels")
+    );
+    // far too deep input fails without overflowing the parser's stack either
+    assert!(source(100_000).parse::<TypeTag>().is_err());
+
+    let limits = TypeTagLimits {
+        max_depth: 3,
+        max_nodes: 4,
+    };
+    let tag = "0x1::M::S<vector<u8>, u64>";
+    assert!(TypeTag::from_str_with_limits(tag, &limits).is_ok());
+    let err = TypeTag::from_str_with_limits("0x1::M::S<vector<u8>, u64, u8>", &limits).unwrap_err();
+    assert_eq!(
+        (err.offset(), err.expected()),
+        (27, "at most 4 types in total")
+    );
+    let err = TypeTag::from_str_with_limits("0x1::M::S<vector<vector<u8>>>", &limits).unwrap_err();
+    assert_eq!(err.offset(), 24);
+
+    let many_args = format!("0x1::M::S<{}>", vec!["u8"; TYPE_TAG_NODES_MAX].join(", "));
+    assert!(many_args.parse::<TypeTag>().is_err());
+}
+
+#[test]
+fn test_struct_tag_matching() {
+    let generic: StructTag = "0x1::Diem::Diem<0x1::XUS::XUS>".parse().unwrap();
+    let other_generic: StructTag = "0x1::Diem::Diem<0x1::XDX::XDX>".parse().unwrap();
+    let plain: StructTag = "0x1::Diem::Diem".parse().unwrap();
+    let elsewhere: StructTag = "0x2::Diem::Diem".parse().unwrap();
+
+    assert!(generic.matches(&generic, false));
+    assert!(!generic.matches(&other_generic, false));
+    assert!(!generic.matches(&plain, false));
+    assert!(generic.matches(&other_generic, true));
+    assert!(generic.matches(&plain, true));
+    assert!(!generic.matches(&elsewhere, true));
+    assert_eq!(generic.module_id(), "0x1::Diem".parse().unwrap());
+
+    let exact = StructTagMatcher::exact(&generic);
+    assert!(exact.matches(&generic));
+    assert!(!exact.matches(&other_generic));
+    assert!(!exact.matches(&plain));
+    let exact = StructTagMatcher::exact(&plain);
+    assert!(exact.matches(&plain));
+    assert!(!exact.matches(&generic));
+
+    let any_instance = StructTagMatcher::ignoring_type_args(&plain);
+    assert!(any_instance.matches(&generic));
+    assert!(any_instance.matches(&other_generic));
+    assert!(any_instance.matches(&plain));
+    assert!(!any_instance.matches(&elsewhere));
+
+    let at_one = StructTagMatcher::new().address(AccountAddress::ONE);
+    assert!(at_one.matches(&generic) && at_one.matches(&plain));
+    assert!(!at_one.matches(&elsewhere));
+    assert!(StructTagMatcher::new().matches(&elsewhere));
+}
+
+#[test]
+fn test_preorder_iter() {
+    let tag: TypeTag = "vector<0x1::M::S<u8, vector<bool>>>".parse().unwrap();
+    let visited: Vec<_> = tag.preorder_iter().map(|ty| ty.to_string()).collect();
+    assert_eq!(
+        visited,
+        vec![
+            "vector<0x1::M::S<u8, vector<bool>>>",
+            "0x1::M::S<u8, vector<bool>>",
+            "u8",
+            "vector<bool>",
+            "bool",
+        ]
+    );
+
+    // the forbidden types only occur as the third type argument of a nested generic
+    let tag: TypeTag = "0x1::M::Outer<u8, vector<0x1::M::Triple<u64, bool, signer>>>"
+        .parse()
+        .unwrap();
+    assert!(tag.contains_signer());
+    let tag: TypeTag = "0x1::M::Outer<u8, vector<0x1::M::Triple<u64, bool, 0x2::Banned::Coin>>>"
+        .parse()
+        .unwrap();
+    assert!(!tag.contains_signer());
+    let banned = AccountAddress::from_hex_literal("0x2").unwrap();
+    assert!(tag.contains_struct_from(&banned, ident_str!("Banned")));
+    assert!(tag.contains_struct_from(&AccountAddress::ONE, ident_str!("M")));
+    assert!(!tag.contains_struct_from(&AccountAddress::ONE, ident_str!("Banned")));
+    assert!(!tag.contains_struct_from(&banned, ident_str!("M")));
+    assert!(!TypeTag::Address.contains_signer());
+
+    let deep = nested_vector(100_000);
+    assert_eq!(deep.preorder_iter().count(), 100_000);
+    assert_eq!(deep.preorder_iter().last(), Some(&TypeTag::U8));
+    let mut ty = deep;
+    while let TypeTag::Vector(elem) = ty {
+        ty = *elem;
+    }
+}
+
+#[test]
+fn test_type_tag_order() {
+    // in ascending order
+    let sorted = [
+        "0x1::A::Z",
+        "0x1::M::S",
+        "0x1::M::S<0x1::A::Z>",
+        "0x1::M::S<bool, u8>",
+        "0x1::M::S<bool>",
+        "0x1::M::S<u8>",
+        "0x1::M::T",
+        "0x1::N::A",
+        "0x2::A::A",
+        "0x10::A::A",
+        "address",
+        "bool",
+        "signer",
+        "u128",
+        "u64",
+        "u8",
+        "vector<0x1::M::S>",
+        "vector<address>",
+        "vector<u8>",
+        "vector<vector<bool>>",
+    ];
+    let tags: Vec<TypeTag> = sorted.iter().map(|s| s.parse().unwrap()).collect();
+    for (i, tag1) in tags.iter().enumerate() {
+        for (j, tag2) in tags.iter().enumerate() {
+            assert_eq!(tag1.cmp(tag2), i.cmp(&j), "{} vs {}", tag1, tag2);
+        }
+    }
+
+    // which is the order of their canonical strings
+    let canonical: Vec<_> = tags.iter().map(TypeTag::to_canonical_string).collect();
+    let mut resorted = canonical.clone();
+    resorted.sort();
+    assert_eq!(resorted, canonical);
+
+    let mut tags = tags;
+    tags.reverse();
+    tags.sort();
+    let resorted: Vec<_> = tags.iter().map(|tag| tag.to_string()).collect();
+    assert_eq!(resorted, sorted);
+}
+
+/// `TypeTag` with the `Serialize` impl it used to derive, to compare encodings against
+#[derive(Serialize)]
+enum DerivedTypeTag {
+    #[serde(rename = "bool")]
+    Bool,
+    #[serde(rename = "u8")]
+    U8,
+    #[serde(rename = "u64")]
+    U64,
+    #[serde(rename = "u128")]
+    U128,
+    #[serde(rename = "address")]
+    Address,
+    #[serde(rename = "signer")]
+    Signer,
+    #[serde(rename = "vector")]
+    Vector(Box<DerivedTypeTag>),
+    #[serde(rename = "struct")]
+    Struct(DerivedStructTag),
+}
+
+#[derive(Serialize)]
+struct DerivedStructTag {
+    address: AccountAddress,
+    module: Identifier,
+    name: Identifier,
+    #[serde(rename = "type_args")]
+    type_params: Vec<DerivedTypeTag>,
+}
+
+impl From<&TypeTag> for DerivedTypeTag {
+    fn from(tag: &TypeTag) -> Self {
+        match tag {
+            TypeTag::Bool => DerivedTypeTag::Bool,
+            TypeTag::U8 => DerivedTypeTag::U8,
+            TypeTag::U64 => DerivedTypeTag::U64,
+            TypeTag::U128 => DerivedTypeTag::U128,
+            TypeTag::Address => DerivedTypeTag::Address,
+            TypeTag::Signer => DerivedTypeTag::Signer,
+            TypeTag::Vector(elem) => DerivedTypeTag::Vector(Box::new(elem.as_ref().into())),
+            TypeTag::Struct(tag) => DerivedTypeTag::Struct(DerivedStructTag {
+                address: tag.address,
+                module: tag.module.clone(),
+                name: tag.name.clone(),
+                type_params: tag.type_params.iter().map(DerivedTypeTag::from).collect(),
+            }),
+        }
+    }
+}
+
+// The golden encoding assumes 16-byte addresses
+#[cfg(not(any(feature = "address20", feature = "address32")))]
+#[test]
+fn test_type_tag_bcs_golden() {
+    let tag: TypeTag = "vector<0x1::M::S<u8, address>>".parse().unwrap();
+    let bytes = bcs::to_bytes(&tag).unwrap();
+    assert_eq!(
+        hex::encode(&bytes),
+        "060700000000000000000000000000000001014d0153020104"
+    );
+    assert_eq!(
+        hex::encode(Sha3_256::digest(&bytes)),
+        "0cecca85f237038850d2fb5c3d99bfd78c59fa34f9f5bb1ec67659cf2c44c3b7"
+    );
+    assert_eq!(bcs::from_bytes::<TypeTag>(&bytes).unwrap(), tag);
+}
+
+#[test]
+fn test_type_tag_json() {
+    let one = format!("0x{}", AccountAddress::ONE.to_hex());
+    let tag: TypeTag = "vector<0x1::M::S<u8, address>>".parse().unwrap();
+    let json = serde_json::to_value(&tag).unwrap();
+    assert_eq!(json, json!(format!("vector<{}::M::S<u8, address>>", one)));
+    assert_eq!(serde_json::from_value::<TypeTag>(json).unwrap(), tag);
+
+    // the display form and the structured form are accepted too
+    let display = json!("vector<0x1::M::S<u8, address>>");
+    assert_eq!(serde_json::from_value::<TypeTag>(display).unwrap(), tag);
+    let structured = json!({
+        "vector": {
+            "struct": {
+                "address": AccountAddress::ONE.to_hex(),
+                "module": "M",
+                "name": "S",
+                "type_args": ["u8", "address"],
+            }
+        }
+    });
+    assert_eq!(serde_json::from_value::<TypeTag>(structured).unwrap(), tag);
+    assert!(serde_json::from_value::<TypeTag>(json!("vector<u8")).is_err());
+    assert!(serde_json::from_value::<TypeTag>(json!(8)).is_err());
+
+    let struct_tag: StructTag = "0x1::Diem::Diem<0x1::XUS::XUS>".parse().unwrap();
+    let json = serde_json::to_value(&struct_tag).unwrap();
+    assert_eq!(json, json!(struct_tag.to_canonical_string()));
+    assert_eq!(
+        serde_json::from_value::<StructTag>(json).unwrap(),
+        struct_tag
+    );
+    let structured = json!({
+        "address": AccountAddress::ONE.to_hex(),
+        "module": "Diem",
+        "name": "Diem",
+        "type_args": ["0x1::XUS::XUS"],
+    });
+    assert_eq!(
+        serde_json::from_value::<StructTag>(structured).unwrap(),
+        struct_tag
+    );
+}
+
+#[test]
+fn test_abstract_size() {
+    let node = TYPE_TAG_NODE_SIZE.get();
+    let byte = TYPE_TAG_IDENTIFIER_BYTE_SIZE.get();
+    assert_eq!(TypeTag::U8.abstract_size(), node);
+    assert_eq!(nested_vector(3).abstract_size(), 3 * node);
+
+    let tag: StructTag = "0x1::Diem::Diem<0x1::XUS::XUS>".parse().unwrap();
+    assert_eq!(tag.abstract_size(), 2 * node + 14 * byte);
+    assert_eq!(TypeTag::Struct(tag).abstract_size(), 2 * node + 14 * byte);
+
+    // long identifiers cost by the byte
+    let long_name = "A".repeat(MAX_IDENTIFIER_LENGTH);
+    let tag: StructTag = format!("0x1::{0}::{0}<vector<u8>>", long_name)
+        .parse()
+        .unwrap();
+    assert_eq!(
+        tag.abstract_size(),
+        3 * node + 2 * MAX_IDENTIFIER_LENGTH as u64 * byte
+    );
+
+    // as do many type arguments
+    let args = vec!["0x1::XUS::XUS"; 100].join(", ");
+    let tag: StructTag = format!("0x1::M::S<{}>", args).parse().unwrap();
+    assert_eq!(tag.abstract_size(), 101 * node + (2 + 100 * 6) * byte);
+}
+
+#[test]
+fn test_display_with_address_names() {
+    let other = AccountAddress::from_hex_literal("0x42").unwrap();
+    let mut names = BTreeMap::new();
+    names.insert(AccountAddress::ONE, "Std".to_string());
+    names.insert(
+        AccountAddress::from_hex_literal("0xA550C18").unwrap(),
+        "DiemRoot".to_string(),
+    );
+
+    let tag: TypeTag = "vector<0x1::Option::Option<0x42::M::S<0x1::XUS::XUS, u8>>>"
+        .parse()
+        .unwrap();
+    let named = tag.display_with(&names).to_string();
+    assert_eq!(
+        named,
+        "vector<Std::Option::Option<0x42::M::S<Std::XUS::XUS, u8>>>"
+    );
+    assert_eq!(
+        TypeTag::from_str_with_names(&named, &names),
+        Ok(tag.clone())
+    );
+    // hex addresses are accepted as well
+    assert_eq!(
+        TypeTag::from_str_with_names(&tag.to_string(), &names),
+        Ok(tag)
+    );
+    // but unknown names are not
+    let err = "vector<Core::M::S>".parse::<TypeTag>().unwrap_err();
+    assert_eq!((err.offset(), err.found()), (7, "`Core`"));
+    assert!(TypeTag::from_str_with_names("vector<Core::M::S>", &names).is_err());
+
+    let struct_tag: StructTag = "0x42::M::S<0x1::XUS::XUS>".parse().unwrap();
+    let named = struct_tag.display_with(&names).to_string();
+    assert_eq!(named, "0x42::M::S<Std::XUS::XUS>");
+    assert_eq!(
+        StructTag::from_str_with_names(&named, &names),
+        Ok(struct_tag)
+    );
+
+    let module_id = ModuleId::new(AccountAddress::ONE, Identifier::new("Vector").unwrap());
+    assert_eq!(module_id.display_with(&names).to_string(), "Std::Vector");
+    assert_eq!(
+        ModuleId::from_str_with_names("Std::Vector", &names),
+        Ok(module_id)
+    );
+    let module_id = ModuleId::new(other, Identifier::new("M").unwrap());
+    assert_eq!(
+        module_id.display_with(&names).to_string(),
+        module_id.to_string()
+    );
+}
+
+// The golden encodings assume 16-byte addresses
+#[cfg(not(any(feature = "address20", feature = "address32")))]
+#[test]
+fn test_storage_key_bytes() {
+    let tag: StructTag = "0x1::M::S<u8>".parse().unwrap();
+    let key = tag.resource_key(AccountAddress::ONE);
+    assert_eq!(key, ResourceKey::new(AccountAddress::ONE, tag));
+    assert_eq!(
+        hex::encode(key.to_bytes()),
+        "0000000000000000000000000000000100000000000000000000000000000001014d01530101"
+    );
+    assert_eq!(
+        hex::encode(key.sha3_256()),
+        "a73db3ce701df25a8d4a7880810d9386b7264b6eeb7818077e3120b1aae06fd0"
+    );
+    assert_eq!(
+        hex::encode(key.access_key()),
+        "00000000000000000000000000000001170100000000000000000000000000000001014d01530101"
+    );
+    assert_eq!(
+        bcs::from_bytes::<ResourceKey>(&key.to_bytes()).unwrap(),
+        key
+    );
+
+    let module_id = ModuleId::new(AccountAddress::ONE, Identifier::new("M").unwrap());
+    assert_eq!(
+        hex::encode(module_id.access_key()),
+        "00000000000000000000000000000001130000000000000000000000000000000001014d"
+    );
+}
+
+#[test]
+fn test_resource_key_display_roundtrip() {
+    let tag: StructTag = "0x1::Diem::Diem<0x1::XUS::XUS>".parse().unwrap();
+    let key = tag.resource_key(AccountAddress::from_hex_literal("0xA550C18").unwrap());
+    assert_eq!(key.to_string(), "0xa550c18/0x1::Diem::Diem<0x1::XUS::XUS>");
+    assert_eq!(key.to_string().parse::<ResourceKey>(), Ok(key.clone()));
+    assert_eq!(
+        "a550c18 / 0x1::Diem::Diem<0x1::XUS::XUS>".parse::<ResourceKey>(),
+        Ok(key)
+    );
+
+    let err = "0x1::Diem::Diem".parse::<ResourceKey>().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (15, "`/`"));
+    let err = "0xZZ/0x1::M::S".parse::<ResourceKey>().unwrap_err();
+    assert_eq!(err.offset(), 0);
+    assert!("/0x1::M::S".parse::<ResourceKey>().is_err());
+    let err = "0x1/0x1::M::".parse::<ResourceKey>().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (12, "a struct name"));
+}
+
+#[test]
+fn test_rewrite_type_args() {
+    let old_coin: StructTag = "0x1::OldCoin::OldCoin".parse().unwrap();
+    let new_coin: StructTag = "0x2::NewCoin::NewCoin".parse().unwrap();
+
+    let store: StructTag = "0x1::CoinStore::CoinStore<0x1::OldCoin::OldCoin>"
+        .parse()
+        .unwrap();
+    assert_eq!(store.type_args(), &[TypeTag::Struct(old_coin.clone())]);
+    let new_store = store.map_type_args(|_| TypeTag::Struct(new_coin.clone()));
+    assert_eq!(
+        new_store.to_string(),
+        "0x1::CoinStore::CoinStore<0x2::NewCoin::NewCoin>"
+    );
+
+    let tag: TypeTag =
+        "vector<0x1::Option::Option<0x1::CoinStore::CoinStore<0x1::OldCoin::OldCoin>>>"
+            .parse()
+            .unwrap();
+    let mut visited = vec![];
+    let rewritten = tag.map_structs(|tag| {
+        visited.push(tag.to_string());
+        if tag == old_coin {
+            new_coin.clone()
+        } else {
+            tag
+        }
+    });
+    assert_eq!(
+        rewritten.to_string(),
+        "vector<0x1::Option::Option<0x1::CoinStore::CoinStore<0x2::NewCoin::NewCoin>>>"
+    );
+    // innermost first, with their type arguments already rewritten
+    assert_eq!(
+        visited,
+        vec![
+            "0x1::OldCoin::OldCoin",
+            "0x1::CoinStore::CoinStore<0x2::NewCoin::NewCoin>",
+            "0x1::Option::Option<0x1::CoinStore::CoinStore<0x2::NewCoin::NewCoin>>",
+        ]
+    );
+
+    // everything else is left alone
+    let tag: TypeTag = "0x1::Pair::Pair<u8, vector<signer>, 0x1::XUS::XUS>"
+        .parse()
+        .unwrap();
+    assert_eq!(tag.clone().map_structs(|tag| tag), tag);
+    assert_eq!(TypeTag::U64.map_structs(|_| new_coin.clone()), TypeTag::U64);
+}
+
+#[test]
+fn test_struct_tag_short_str() {
+    let none: StructTag = "0x00001::Coin::Coin".parse().unwrap();
+    assert_eq!(none.short_str_lossless(), "0x1::Coin::Coin");
+    assert_eq!(none.short_str(0), "0x1::Coin::Coin");
+
+    let one: StructTag = "0x1::Coin::Coin<0x0001::XUS::XUS>".parse().unwrap();
+    let lossless = one.short_str_lossless();
+    assert_eq!(lossless, "0x1::Coin::Coin<0x1::XUS::XUS>");
+    assert_eq!(lossless.parse::<StructTag>(), Ok(one.clone()));
+    // elided only once the full form is too long
+    assert_eq!(one.short_str(lossless.len()), lossless);
+    assert_eq!(one.short_str(lossless.len() - 1), "0x1::Coin::Coin<..>");
+    assert_eq!(one.short_str(0), "0x1::Coin::Coin<..>");
+
+    let five: StructTag =
+        "0xA550C18::Pool::Pool<u8, u64, vector<bool>, 0x1::XUS::XUS, 0x2::XDX::XDX>"
+            .parse()
+            .unwrap();
+    let lossless = five.short_str_lossless();
+    assert_eq!(
+        lossless,
+        "0xa550c18::Pool::Pool<u8, u64, vector<bool>, 0x1::XUS::XUS, 0x2::XDX::XDX>"
+    );
+    assert_eq!(lossless.parse::<StructTag>(), Ok(five.clone()));
+    assert_eq!(five.short_str(100), lossless);
+    assert_eq!(five.short_str(40), "0xa550c18::Pool::Pool<..>");
+}
+
+#[test]
+fn test_module_id_strings_and_order() {
+    let account: ModuleId = "0x1::Account".parse().unwrap();
+    assert_eq!(account.address(), &AccountAddress::ONE);
+    assert_eq!(account.name().as_str(), "Account");
+    assert_eq!(account.short_str_lossless(), "0x1::Account");
+    assert_eq!(
+        account.to_canonical_string(),
+        format!("0x{}::Account", AccountAddress::ONE.to_hex())
+    );
+    for s in [
+        account.short_str_lossless(),
+        account.to_canonical_string(),
+        account.to_string(),
+    ] {
+        assert_eq!(s.parse::<ModuleId>(), Ok(account.clone()));
+    }
+
+    // in ascending order
+    let sorted = [
+        "0x1::Account",
+        "0x1::Diem",
+        "0x1::DiemAccount",
+        "0x1::Vector",
+        "0x1::account",
+        "0x2::Account",
+        "0x10::Account",
+    ];
+    let ids: Vec<ModuleId> = sorted.iter().map(|s| s.parse().unwrap()).collect();
+    for (i, id1) in ids.iter().enumerate() {
+        for (j, id2) in ids.iter().enumerate() {
+            assert_eq!(id1.cmp(id2), i.cmp(&j), "{} vs {}", id1, id2);
+        }
+    }
+    let mut canonical: Vec<_> = ids.iter().map(ModuleId::to_canonical_string).collect();
+    canonical.sort();
+    let expected: Vec<_> = ids.iter().map(ModuleId::to_canonical_string).collect();
+    assert_eq!(canonical, expected);
+
+    let allowed: std::collections::BTreeSet<ModuleId> = ["0x1::Diem", "0x1::Account", "0x1::Diem"]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+    assert_eq!(allowed.len(), 2);
+    assert!(allowed.contains(&account));
+
+    let diem: ModuleId = "0x1::Diem".parse().unwrap();
+    let coin: StructTag = "0x1::Diem::Diem<0x1::XUS::XUS>".parse().unwrap();
+    assert!(diem.contains(&coin));
+    assert!(diem.contains(&"0x1::Diem::Preburn".parse().unwrap()));
+    // the type arguments' modules do not count
+    assert!(!"0x1::XUS".parse::<ModuleId>().unwrap().contains(&coin));
+    // nor do modules of the same name at other addresses, or other modules at the same address
+    assert!(!"0x2::Diem".parse::<ModuleId>().unwrap().contains(&coin));
+    assert!(!account.contains(&coin));
+}
+
+fn numbered_tag(i: usize) -> StructTag {
+    StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("Coin").to_owned(),
+        name: Identifier::new(format!("C{}", i)).unwrap(),
+        type_params: vec![],
+    }
+}
+
+#[test]
+fn test_type_tag_cache_lru() {
+    let cache = TypeTagCache::new(2);
+    let first = cache.intern(numbered_tag(0));
+    assert!(Arc::ptr_eq(&first, &cache.intern(numbered_tag(0))));
+    cache.intern(numbered_tag(1));
+    // 0 is more recently used than 1, so 1 is evicted to make room for 2
+    cache.intern_borrowed(&numbered_tag(0));
+    cache.intern(numbered_tag(2));
+    assert_eq!(cache.len(), 2);
+    assert!(Arc::ptr_eq(&first, &cache.intern(numbered_tag(0))));
+    assert_eq!((cache.hits(), cache.misses()), (3, 3));
+    cache.intern(numbered_tag(1));
+    assert_eq!((cache.hits(), cache.misses()), (3, 4));
+
+    // an evicted tag comes back as a new, equal copy
+    cache.intern(numbered_tag(2));
+    let again = cache.intern(numbered_tag(0));
+    assert_eq!(again, first);
+    assert!(!Arc::ptr_eq(&again, &first));
+
+    let uncached = TypeTagCache::new(0);
+    assert!(!Arc::ptr_eq(
+        &uncached.intern(numbered_tag(0)),
+        &uncached.intern(numbered_tag(0))
+    ));
+    assert!(uncached.is_empty());
+}
+
+#[test]
+fn test_type_tag_cache_concurrent() {
+    const THREADS: usize = 8;
+    const TAGS: usize = 64;
+    let cache = Arc::new(TypeTagCache::new(TAGS));
+    // every thread interns every tag many times, starting at a different one
+    let handles: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let cache = cache.clone();
+            thread::spawn(move || -> Vec<Arc<StructTag>> {
+                (0..TAGS * 100)
+                    .map(|i| cache.intern(numbered_tag((t * 7 + i) % TAGS)))
+                    .collect()
+            })
+        })
+        .collect();
+    let interned = handles.into_iter().map(|handle| handle.join().unwrap());
+
+    let mut shared: BTreeMap<StructTag, Arc<StructTag>> = BTreeMap::new();
+    for tag in interned.into_iter().flatten() {
+        let first = shared.entry((*tag).clone()).or_insert_with(|| tag.clone());
+        assert!(Arc::ptr_eq(first, &tag), "two copies of {}", tag);
+    }
+    assert_eq!(shared.len(), TAGS);
+    assert_eq!(cache.misses(), TAGS as u64);
+    assert_eq!(cache.hits(), (THREADS * TAGS * 100 - TAGS) as u64);
+}
+
+#[test]
+fn test_type_tag_classification() {
+    let coin = TypeTag::Struct(numbered_tag(0));
+    let vec_of = |t: &TypeTag| TypeTag::Vector(Box::new(t.clone()));
+    // tag, is_primitive, is_integer, valid argument without structs, valid argument with structs
+    let table = vec![
+        (TypeTag::Bool, true, false, true, true),
+        (TypeTag::U8, true, true, true, true),
+        (TypeTag::U64, true, true, true, true),
+        (TypeTag::U128, true, true, true, true),
+        (TypeTag::Address, true, false, true, true),
+        (TypeTag::Signer, false, false, false, false),
+        (vec_of(&TypeTag::U8), false, false, true, true),
+        (vec_of(&vec_of(&TypeTag::Address)), false, false, true, true),
+        (vec_of(&TypeTag::Signer), false, false, false, false),
+        (coin.clone(), false, false, false, true),
+        (vec_of(&coin), false, false, false, true),
+    ];
+    for (tag, primitive, integer, arg, struct_arg) in table {
+        assert_eq!(tag.is_primitive(), primitive, "{}", tag);
+        assert_eq!(tag.is_integer(), integer, "{}", tag);
+        assert_eq!(tag.is_valid_txn_arg_type(false), arg, "{}", tag);
+        assert_eq!(tag.is_valid_txn_arg_type(true), struct_arg, "{}", tag);
+    }
 }
@@ -1,6 +1,14 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+mod address_length_test;
+// Generated code, compared verbatim against the generator's output
+#[rustfmt::skip]
+mod codegen_fixture;
 mod identifier_test;
 mod language_storage_test;
+mod move_resource_test;
+// The golden encodings assume 16-byte addresses
+#[cfg(not(any(feature = "address20", feature = "address32")))]
+mod value_compat_tests;
 mod value_test;
@@ -0,0 +1,78 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account_address::AccountAddress,
+    ident_str,
+    identifier::IdentStr,
+    language_storage::{ModuleId, ResourceKey, StructTag, TypeTag, CORE_CODE_ADDRESS},
+    move_resource::{MoveResource, MoveStructType},
+    parser::parse_struct_tag,
+    value::{MoveStructLayout, MoveTypeLayout, MoveValue},
+};
+use serde::{Deserialize, Serialize};
+
+/// Mirrors `0x1::Coin::CoinStore<0x1::XUS::XUS>`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CoinStore {
+    value: u64,
+    frozen: bool,
+}
+
+struct Xus;
+
+impl MoveStructType for Xus {
+    const MODULE_NAME: &'static IdentStr = ident_str!("XUS");
+    const STRUCT_NAME: &'static IdentStr = ident_str!("XUS");
+}
+
+impl MoveStructType for CoinStore {
+    const MODULE_NAME: &'static IdentStr = ident_str!("Coin");
+    const STRUCT_NAME: &'static IdentStr = ident_str!("CoinStore");
+
+    fn type_params() -> Vec<TypeTag> {
+        vec![TypeTag::Struct(Xus::struct_tag())]
+    }
+}
+
+impl MoveResource for CoinStore {}
+
+#[test]
+fn struct_type_tags() {
+    assert_eq!(
+        CoinStore::struct_tag(),
+        parse_struct_tag("0x1::Coin::CoinStore<0x1::XUS::XUS>").unwrap()
+    );
+    assert_eq!(
+        CoinStore::module_id(),
+        ModuleId::new(CORE_CODE_ADDRESS, ident_str!("Coin").to_owned())
+    );
+    assert_eq!(CoinStore::module_id(), CoinStore::struct_tag().module_id());
+}
+
+#[test]
+fn resource_paths() {
+    let tag: StructTag = CoinStore::struct_tag();
+    assert_eq!(CoinStore::resource_path(), tag.access_vector());
+    let address = AccountAddress::random();
+    assert_eq!(
+        CoinStore::resource_key(address),
+        ResourceKey::new(address, tag)
+    );
+}
+
+#[test]
+fn resource_encoding_matches_move_values() {
+    let store = CoinStore {
+        value: 7,
+        frozen: true,
+    };
+    let blob = bcs::to_bytes(&store).unwrap();
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::new(vec![
+        MoveTypeLayout::U64,
+        MoveTypeLayout::Bool,
+    ]));
+    let value = MoveValue::simple_deserialize(&blob, &layout).unwrap();
+    assert_eq!(value.simple_serialize().unwrap(), blob);
+    assert_eq!(bcs::from_bytes::<CoinStore>(&blob).unwrap(), store);
+}
@@ -0,0 +1,248 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Golden vectors for the BCS encoding of `MoveValue`s, which is consensus-critical. The expected
+//! encodings live in `value_compat_tests.exp`; a change to them must be deliberate. Run with
+//! `UPDATE_BASELINE=1` to regenerate them.
+
+use crate::{
+    account_address::AccountAddress,
+    ident_str,
+    language_storage::StructTag,
+    value::{
+        MoveEnumLayout, MoveFieldLayout, MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue,
+    },
+};
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+/// A value, and the layout to decode its expected encoding with. Values are undecorated, as only
+/// the encoding of runtime values is fixed; a decorated layout checks that decorating does not
+/// change how the bytes are read.
+struct Case {
+    name: &'static str,
+    layout: MoveTypeLayout,
+    value: MoveValue,
+}
+
+fn case(name: &'static str, layout: MoveTypeLayout, value: MoveValue) -> Case {
+    Case {
+        name,
+        layout,
+        value,
+    }
+}
+
+fn vector(elem: MoveTypeLayout) -> MoveTypeLayout {
+    MoveTypeLayout::Vector(Box::new(elem))
+}
+
+fn runtime_struct(fields: Vec<MoveTypeLayout>) -> MoveTypeLayout {
+    MoveTypeLayout::Struct(MoveStructLayout::new(fields))
+}
+
+fn struct_value(fields: Vec<MoveValue>) -> MoveValue {
+    MoveValue::Struct(MoveStruct::new(fields))
+}
+
+fn cases() -> Vec<Case> {
+    use MoveTypeLayout as L;
+    use MoveValue as V;
+
+    let address = AccountAddress::from_hex_literal("0xcafe").unwrap();
+    let coin = MoveStructLayout::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Coin").to_owned(),
+            name: ident_str!("Coin").to_owned(),
+            type_params: vec![],
+        },
+        vec![MoveFieldLayout::new(ident_str!("value").to_owned(), L::U64)],
+    );
+    let status = MoveEnumLayout::new(vec![
+        (ident_str!("Active").to_owned(), vec![]),
+        (
+            ident_str!("Frozen").to_owned(),
+            vec![
+                MoveFieldLayout::new(ident_str!("since").to_owned(), L::U64),
+                MoveFieldLayout::new(ident_str!("by").to_owned(), L::Address),
+            ],
+        ),
+    ]);
+
+    vec![
+        case("bool_false", L::Bool, V::Bool(false)),
+        case("bool_true", L::Bool, V::Bool(true)),
+        case("u8_zero", L::U8, V::U8(0)),
+        case("u8_max", L::U8, V::U8(u8::MAX)),
+        case("u64_zero", L::U64, V::U64(0)),
+        case("u64_little_endian", L::U64, V::U64(0x0102_0304_0506_0708)),
+        case("u64_max", L::U64, V::U64(u64::MAX)),
+        case("u128_zero", L::U128, V::U128(0)),
+        case("u128_max", L::U128, V::U128(u128::MAX)),
+        case("address_zero", L::Address, V::Address(AccountAddress::ZERO)),
+        case("address", L::Address, V::Address(address)),
+        case("signer", L::Signer, V::Signer(address)),
+        case("vector_u8_empty", vector(L::U8), V::vector_u8(vec![])),
+        case("vector_u8", vector(L::U8), V::vector_u8(b"move".to_vec())),
+        // 128 elements need a two-byte length prefix
+        case(
+            "vector_u8_long",
+            vector(L::U8),
+            V::vector_u8(vec![0xab; 128]),
+        ),
+        case(
+            "vector_u64",
+            vector(L::U64),
+            V::Vector(vec![V::U64(1), V::U64(u64::MAX)]),
+        ),
+        case(
+            "vector_bool",
+            vector(L::Bool),
+            V::Vector(vec![V::Bool(true), V::Bool(false)]),
+        ),
+        case(
+            "vector_address",
+            vector(L::Address),
+            V::vector_address(vec![AccountAddress::ONE, address]),
+        ),
+        case(
+            "vector_vector_u8",
+            vector(vector(L::U8)),
+            V::Vector(vec![V::vector_u8(vec![]), V::vector_u8(vec![1, 2])]),
+        ),
+        case(
+            "vector_struct_empty",
+            vector(L::Struct(coin.clone())),
+            V::Vector(vec![]),
+        ),
+        case(
+            "struct",
+            runtime_struct(vec![L::U64, L::Bool, L::Address]),
+            struct_value(vec![V::U64(7), V::Bool(true), V::Address(address)]),
+        ),
+        case(
+            "struct_decorated_layout",
+            L::Struct(coin.clone()),
+            struct_value(vec![V::U64(100)]),
+        ),
+        case(
+            "struct_nested",
+            runtime_struct(vec![
+                L::Struct(coin.clone()),
+                vector(L::Struct(coin)),
+                runtime_struct(vec![vector(L::U8), L::U128]),
+            ]),
+            struct_value(vec![
+                struct_value(vec![V::U64(1)]),
+                V::Vector(vec![
+                    struct_value(vec![V::U64(2)]),
+                    struct_value(vec![V::U64(3)]),
+                ]),
+                struct_value(vec![V::vector_u8(vec![0xff]), V::U128(u128::MAX)]),
+            ]),
+        ),
+        case(
+            "enum_unit_variant",
+            L::Enum(status.clone()),
+            V::Variant {
+                tag: 0,
+                name: None,
+                fields: vec![],
+            },
+        ),
+        case(
+            "enum_variant_with_fields",
+            L::Enum(status),
+            V::Variant {
+                tag: 1,
+                name: None,
+                fields: vec![V::U64(42), V::Address(address)],
+            },
+        ),
+    ]
+}
+
+fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/unit_tests/value_compat_tests.exp")
+}
+
+/// Mirrors `move_command_line_common::testing::read_env_update_baseline`
+fn update_baseline() -> bool {
+    ["UPDATE_BASELINE", "UPBL", "UB"].iter().any(|var| {
+        let val = env::var(var).unwrap_or_default().to_lowercase();
+        val.parse::<bool>() == Ok(true) || val.parse::<usize>() == Ok(1)
+    })
+}
+
+fn render(encodings: &BTreeMap<String, String>) -> String {
+    encodings
+        .iter()
+        .map(|(name, hex)| format!("{}: {}\n", name, hex))
+        .collect()
+}
+
+fn parse(golden: &str) -> BTreeMap<String, String> {
+    golden
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, hex) = line.split_once(": ").expect("`name: hex` line");
+            (name.to_string(), hex.to_string())
+        })
+        .collect()
+}
+
+/// One entry per case whose encoding differs from the golden one, or that only one side has
+fn diff(expected: &BTreeMap<String, String>, actual: &BTreeMap<String, String>) -> String {
+    let missing = "<none>".to_string();
+    let mut out = String::new();
+    for name in expected
+        .keys()
+        .chain(actual.keys().filter(|n| !expected.contains_key(*n)))
+    {
+        let (expected, actual) = (
+            expected.get(name).unwrap_or(&missing),
+            actual.get(name).unwrap_or(&missing),
+        );
+        if expected != actual {
+            out.push_str(&format!(
+                "{}:\n  expected: {}\n  actual:   {}\n",
+                name, expected, actual
+            ));
+        }
+    }
+    out
+}
+
+#[test]
+fn golden_value_encodings() {
+    let cases = cases();
+    let actual: BTreeMap<_, _> = cases
+        .iter()
+        .map(|case| {
+            let blob = case.value.simple_serialize().unwrap();
+            (case.name.to_string(), hex::encode(blob))
+        })
+        .collect();
+    assert_eq!(actual.len(), cases.len(), "case names must be unique");
+
+    let path = golden_path();
+    if update_baseline() {
+        fs::write(&path, render(&actual)).unwrap();
+        return;
+    }
+    let expected = parse(&fs::read_to_string(&path).unwrap());
+    let diff = diff(&expected, &actual);
+    assert!(
+        diff.is_empty(),
+        "the encoding of values changed; if that is deliberate, rerun with UPDATE_BASELINE=1\n{}",
+        diff
+    );
+
+    // the golden encodings also decode back into the values
+    for case in &cases {
+        let blob = hex::decode(&expected[case.name]).unwrap();
+        let value = MoveValue::simple_deserialize(&blob, &case.layout).unwrap();
+        assert_eq!(value.canonicalize(), case.value, "{}", case.name);
+    }
+}
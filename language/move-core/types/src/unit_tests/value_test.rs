@@ -4,11 +4,20 @@
 use crate::{
     account_address::AccountAddress,
     ident_str,
-    identifier::Identifier,
-    language_storage::{StructTag, TypeTag},
-    value::{MoveStruct, MoveValue},
+    identifier::{Identifier, IdentifierPool},
+    language_storage::{
+        StructTag, StructTagMatcher, StructTagTemplate, TypeTag, TypeTagCache, TypeTagTemplate,
+    },
+    value::{
+        deserialize_values_packed, diff, diff_modulo_decoration, serialize_values,
+        serialize_values_into, serialize_values_packed, try_serialize_values, DeserializationError,
+        DeserializationLimits, LazyMoveValue, MoveEnumLayout, MoveFieldLayout, MoveLiteralOptions,
+        MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue, PrettyOptions, SerdeOptions,
+        StructTagRegistry, ValueError,
+    },
 };
 use serde_json::json;
+use std::{collections::BTreeMap, convert::TryInto, sync::Arc};
 
 #[test]
 fn struct_deserialization() {
@@ -101,3 +110,3955 @@ fn nested_typed_struct_deserialization() {
         })
     );
 }
+
+#[test]
+fn structured_type_serialization() {
+    let inner_type = StructTag {
+        address: AccountAddress::from_hex_literal("0x2").unwrap(),
+        module: ident_str!("N").to_owned(),
+        name: ident_str!("T").to_owned(),
+        type_params: vec![],
+    };
+    let struct_type = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("M").to_owned(),
+        name: ident_str!("S").to_owned(),
+        type_params: vec![TypeTag::U8, TypeTag::Struct(inner_type)],
+    };
+    let value = MoveValue::Struct(MoveStruct::with_types(
+        struct_type,
+        vec![(ident_str!("f").to_owned(), MoveValue::U64(7))],
+    ));
+
+    // the string form stays the default
+    assert_eq!(
+        serde_json::to_value(value.with_serde_options(SerdeOptions::default())).unwrap(),
+        serde_json::to_value(&value).unwrap(),
+    );
+    assert_eq!(
+        serde_json::to_value(value.with_serde_options(SerdeOptions {
+            structured_type: true,
+            ..SerdeOptions::default()
+        }))
+        .unwrap(),
+        json!({
+            "fields": { "f": 7 },
+            "type": {
+                "address": "00000000000000000000000000000001",
+                "module": "M",
+                "name": "S",
+                "type_args": [
+                    "u8",
+                    {
+                        "struct": {
+                            "address": "00000000000000000000000000000002",
+                            "module": "N",
+                            "name": "T",
+                            "type_args": [],
+                        }
+                    }
+                ],
+            }
+        })
+    );
+}
+
+#[test]
+fn runtime_struct_named_fields() {
+    let values = vec![MoveValue::U64(7), MoveValue::Bool(true), MoveValue::U8(1)];
+    let runtime_value = MoveStruct::Runtime(values.clone());
+    let expected: Vec<(Identifier, MoveValue)> = vec![
+        ident_str!("field_0").to_owned(),
+        ident_str!("field_1").to_owned(),
+        ident_str!("field_2").to_owned(),
+    ]
+    .into_iter()
+    .zip(values)
+    .collect();
+
+    let borrowed: Vec<(Identifier, MoveValue)> = runtime_value
+        .named_fields_iter()
+        .map(|(name, v)| (name.into_owned(), v.clone()))
+        .collect();
+    assert_eq!(borrowed, expected);
+    assert_eq!(runtime_value.into_named_fields(), expected);
+
+    // decorated structs keep their own names
+    let fielded_value = MoveStruct::with_fields(expected.clone());
+    assert_eq!(
+        fielded_value
+            .named_fields_iter()
+            .map(|(name, _)| name.into_owned())
+            .collect::<Vec<_>>(),
+        expected
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(fielded_value.into_named_fields(), expected);
+}
+
+#[test]
+fn equality_modulo_decoration() {
+    let struct_type = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("M").to_owned(),
+        name: ident_str!("S").to_owned(),
+        type_params: vec![],
+    };
+    let inner = |decorated: bool| {
+        let fields = vec![MoveValue::U64(7), MoveValue::Address(AccountAddress::ONE)];
+        MoveValue::Struct(if decorated {
+            MoveStruct::with_fields(
+                vec![ident_str!("a").to_owned(), ident_str!("b").to_owned()]
+                    .into_iter()
+                    .zip(fields)
+                    .collect(),
+            )
+        } else {
+            MoveStruct::Runtime(fields)
+        })
+    };
+    let runtime = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::Bool(true),
+        MoveValue::Vector(vec![inner(false), inner(false)]),
+    ]));
+    // decorate the outer struct and only one of the nested ones
+    let mixed = MoveValue::Struct(MoveStruct::with_types(
+        struct_type,
+        vec![
+            (ident_str!("flag").to_owned(), MoveValue::Bool(true)),
+            (
+                ident_str!("inner").to_owned(),
+                MoveValue::Vector(vec![inner(true), inner(false)]),
+            ),
+        ],
+    ));
+
+    assert_ne!(runtime, mixed);
+    assert!(runtime.equals_modulo_decoration(&mixed));
+    assert!(mixed.equals_modulo_decoration(&runtime));
+    assert_eq!(mixed.clone().canonicalize(), runtime);
+
+    // a missing field or element is a difference
+    let shorter = MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::Bool(true)]));
+    assert!(!runtime.equals_modulo_decoration(&shorter));
+    assert!(!shorter.equals_modulo_decoration(&runtime));
+    let fewer_elements = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::Bool(true),
+        MoveValue::Vector(vec![inner(true)]),
+    ]));
+    assert!(!mixed.equals_modulo_decoration(&fewer_elements));
+
+    // signers and addresses stay distinct
+    assert!(!MoveValue::Signer(AccountAddress::ONE)
+        .equals_modulo_decoration(&MoveValue::Address(AccountAddress::ONE)));
+}
+
+fn nested_holders() -> MoveValue {
+    let holder = |addr: AccountAddress, amount: u64| {
+        MoveValue::Struct(MoveStruct::with_fields(vec![
+            (ident_str!("addr").to_owned(), MoveValue::Address(addr)),
+            (
+                ident_str!("coin").to_owned(),
+                MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(amount)])),
+            ),
+        ]))
+    };
+    MoveValue::Struct(MoveStruct::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Registry").to_owned(),
+            name: ident_str!("Holders").to_owned(),
+            type_params: vec![],
+        },
+        vec![
+            (ident_str!("count").to_owned(), MoveValue::U64(2)),
+            (
+                ident_str!("holders").to_owned(),
+                MoveValue::Vector(vec![
+                    holder(AccountAddress::ONE, 10),
+                    holder(AccountAddress::ZERO, 20),
+                ]),
+            ),
+        ],
+    ))
+}
+
+#[test]
+fn get_value_by_path() {
+    let value = nested_holders();
+    assert_eq!(value.get_path(""), Some(&value));
+    assert_eq!(value.get_path("count"), Some(&MoveValue::U64(2)));
+    assert_eq!(
+        value.get_path("holders.1.addr"),
+        Some(&MoveValue::Address(AccountAddress::ZERO))
+    );
+    // numeric indices work for named fields as well as for the runtime struct `coin`
+    assert_eq!(value.get_path("1.0.1.0"), Some(&MoveValue::U64(10)));
+    assert_eq!(
+        value.get_path("holders.0.coin.0"),
+        Some(&MoveValue::U64(10))
+    );
+
+    for missing in &[
+        "balance",
+        "holders.2",
+        "holders.first",
+        "holders.0.coin.value",
+        "count.0",
+        "holders..addr",
+    ] {
+        assert_eq!(value.get_path(missing), None, "{}", missing);
+    }
+}
+
+#[test]
+fn set_value_by_path() {
+    let mut value = nested_holders();
+    value
+        .set_path("holders.1.coin.0", MoveValue::U64(25), false)
+        .unwrap();
+    assert_eq!(
+        value.get_path("holders.1.coin.0"),
+        Some(&MoveValue::U64(25))
+    );
+
+    // replacing a struct with a differently decorated struct is allowed
+    value
+        .set_path(
+            "holders.0.coin",
+            MoveValue::Struct(MoveStruct::with_fields(vec![(
+                ident_str!("value").to_owned(),
+                MoveValue::U64(5),
+            )])),
+            false,
+        )
+        .unwrap();
+    assert_eq!(
+        value.get_path("holders.0.coin.value"),
+        Some(&MoveValue::U64(5))
+    );
+
+    let err = value
+        .set_path("count", MoveValue::U8(2), false)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("cannot replace u64 value at path `count` with u8 value"),
+        "{}",
+        err
+    );
+    value.set_path("count", MoveValue::U8(2), true).unwrap();
+    assert_eq!(value.get_path("count"), Some(&MoveValue::U8(2)));
+
+    let err = value
+        .set_path(
+            "holders.7.addr",
+            MoveValue::Address(AccountAddress::ONE),
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("segment `7` (#1)"), "{}", err);
+    assert!(
+        err.contains("out of bounds for a vector of length 2"),
+        "{}",
+        err
+    );
+
+    let err = value
+        .set_path(
+            "holders.0.owner",
+            MoveValue::Address(AccountAddress::ONE),
+            true,
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("segment `owner` (#2)"), "{}", err);
+    assert!(err.contains("no field named `owner`"), "{}", err);
+
+    let err = value
+        .set_path("count.0", MoveValue::U8(0), true)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("cannot descend into u8 value"), "{}", err);
+}
+
+#[test]
+fn typed_getters_by_path() {
+    let mut value = nested_holders();
+    value
+        .set_path(
+            "holders.1.addr",
+            MoveValue::vector_u8(b"key".to_vec()),
+            true,
+        )
+        .unwrap();
+    assert_eq!(value.get_u64_at("count").unwrap(), 2);
+    assert_eq!(
+        value.get_address_at("holders.0.addr").unwrap(),
+        AccountAddress::ONE
+    );
+    assert_eq!(value.get_u64_at("holders.0.coin.0").unwrap(), 10);
+    assert_eq!(value.get_bytes_at("holders.1.addr").unwrap(), b"key");
+    assert_eq!(
+        value.get_struct_at("holders.1.coin").unwrap(),
+        &MoveStruct::Runtime(vec![MoveValue::U64(20)])
+    );
+
+    // the same paths work from the struct, with the empty path denoting the struct itself
+    let s = value.get_struct_at("").unwrap();
+    assert_eq!(s.get_u64_at("holders.1.coin.0").unwrap(), 20);
+    assert_eq!(s.get_struct_at("").unwrap(), s);
+    let err = s.get_u64_at("").unwrap_err().to_string();
+    assert_eq!(err, "expected u64 at path ``, found struct");
+
+    // wrong types name the path and what was found there
+    let err = value.get_u128_at("count").unwrap_err().to_string();
+    assert_eq!(err, "expected u128 at path `count`, found u64");
+    let err = value.get_bool_at("holders.0").unwrap_err().to_string();
+    assert_eq!(err, "expected bool at path `holders.0`, found struct");
+    let err = value.get_bytes_at("holders").unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "expected vector<u8> at path `holders`, found vector<struct>"
+    );
+    let err = s.get_address_at("holders.1.addr").unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "expected address at path `holders.1.addr`, found vector<u8>"
+    );
+
+    // missing paths name the segment that could not be followed
+    let err = value
+        .get_u64_at("holders.2.coin.0")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("segment `2` (#1)"), "{}", err);
+    assert!(
+        err.contains("out of bounds for a vector of length 2"),
+        "{}",
+        err
+    );
+    let err = s.get_u8_at("balance").unwrap_err().to_string();
+    assert!(err.contains("segment `balance` (#0)"), "{}", err);
+    assert!(err.contains("no field named `balance`"), "{}", err);
+    let err = value.get_u64_at("count.0").unwrap_err().to_string();
+    assert!(err.contains("cannot descend into u64 value"), "{}", err);
+}
+
+#[test]
+fn deserialization_limits() {
+    // a vector announcing 2^31 - 1 fieldless structs: five bytes of input, but far too many
+    // values to materialize
+    let blob = [0xff, 0xff, 0xff, 0xff, 0x07];
+    let layout = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(MoveStructLayout::new(
+        vec![],
+    ))));
+    let err = MoveValue::simple_deserialize(&blob, &layout).unwrap_err();
+    assert!(err.to_string().contains("vector length"), "{}", err);
+
+    // nested vectors stay under the length limit, but not under the node limit
+    let layout = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Vector(Box::new(
+        MoveTypeLayout::U64,
+    ))));
+    let value = MoveValue::Vector(vec![
+        MoveValue::Vector(vec![MoveValue::U64(1), MoveValue::U64(2)]),
+        MoveValue::Vector(vec![MoveValue::U64(3)]),
+    ]);
+    let blob = value.simple_serialize().unwrap();
+    let limits = |max_nodes, max_vector_length| DeserializationLimits {
+        max_nodes,
+        max_vector_length,
+        ..DeserializationLimits::default()
+    };
+
+    // 1 outer vector, 2 inner vectors, 3 integers
+    assert_eq!(
+        MoveValue::simple_deserialize_with_limits(&blob, &layout, &limits(6, 2)).unwrap(),
+        value
+    );
+    let err = MoveValue::simple_deserialize_with_limits(&blob, &layout, &limits(5, 2)).unwrap_err();
+    assert!(err.to_string().contains("limit of 5 nodes"), "{}", err);
+    let err = MoveValue::simple_deserialize_with_limits(&blob, &layout, &limits(6, 1)).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("vector length 2 exceeds the limit of 1"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn struct_layout_fields() {
+    let field_types = [
+        MoveTypeLayout::U64,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+    ];
+    let decorated_fields = || {
+        vec![
+            MoveFieldLayout::new(ident_str!("f").to_owned(), field_types[0].clone()),
+            MoveFieldLayout::new(ident_str!("g").to_owned(), field_types[1].clone()),
+        ]
+    };
+    let struct_type = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("M").to_owned(),
+        name: ident_str!("S").to_owned(),
+        type_params: vec![],
+    };
+
+    for layout in &[
+        MoveStructLayout::new(field_types.to_vec()),
+        MoveStructLayout::with_fields(decorated_fields()),
+        MoveStructLayout::with_types(struct_type.clone(), decorated_fields()),
+    ] {
+        assert_eq!(layout.field_count(), 2);
+        assert_eq!(
+            layout.field_layouts(),
+            field_types.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(layout.clone().into_fields(), field_types);
+    }
+
+    for empty in &[
+        MoveStructLayout::new(vec![]),
+        MoveStructLayout::with_fields(vec![]),
+        MoveStructLayout::with_types(struct_type.clone(), vec![]),
+    ] {
+        assert_eq!(empty.field_count(), 0);
+        assert!(empty.field_layouts().is_empty());
+        assert!(empty.clone().into_fields().is_empty());
+    }
+}
+
+#[test]
+fn serialize_into_buffer() {
+    let values = vec![
+        MoveValue::U8(1),
+        nested_holders(),
+        MoveValue::vector_u8(vec![]),
+        MoveValue::Signer(AccountAddress::ONE),
+    ];
+
+    // existing contents of the buffer are kept
+    let mut buf = vec![0xff];
+    let ranges = serialize_values_into(&values, &mut buf).unwrap();
+    assert_eq!(buf[0], 0xff);
+    assert_eq!(ranges.first().unwrap().start, 1);
+    assert_eq!(ranges.last().unwrap().end, buf.len());
+    let blobs: Vec<_> = ranges.into_iter().map(|r| buf[r].to_vec()).collect();
+    assert_eq!(blobs, serialize_values(&values));
+
+    let inner = match &values[1] {
+        MoveValue::Struct(s) => s,
+        _ => unreachable!(),
+    };
+    let mut buf = vec![];
+    inner.serialize_into(&mut buf).unwrap();
+    values[1].serialize_into(&mut buf).unwrap();
+    let blob = values[1].simple_serialize().unwrap();
+    assert_eq!(buf, [blob.clone(), blob].concat());
+}
+
+#[test]
+fn fallible_value_serialization() {
+    // BCS rejects named containers nested deeper than `MAX_CONTAINER_DEPTH`. (The other failure
+    // mode, a vector longer than `MAX_SEQUENCE_LENGTH`, would need tens of GB to construct.)
+    let struct_type = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("M").to_owned(),
+        name: ident_str!("S").to_owned(),
+        type_params: vec![],
+    };
+    let nest = |depth| {
+        (0..depth).fold(MoveValue::Bool(true), |inner, _| {
+            MoveValue::Struct(MoveStruct::with_types(
+                struct_type.clone(),
+                vec![(ident_str!("f").to_owned(), inner)],
+            ))
+        })
+    };
+
+    let values = vec![MoveValue::U8(0), nest(10)];
+    assert_eq!(
+        try_serialize_values(&values).unwrap(),
+        serialize_values(&values)
+    );
+
+    let values = vec![
+        MoveValue::U8(0),
+        nest(bcs::MAX_CONTAINER_DEPTH + 1),
+        MoveValue::U8(1),
+    ];
+    let (idx, err) = try_serialize_values(&values).unwrap_err();
+    assert_eq!(idx, 1);
+    assert!(matches!(err, bcs::Error::ExceededContainerDepthLimit(_)));
+}
+
+/// `enum Option { None, Some { value: u64 } }`
+fn option_layout() -> MoveTypeLayout {
+    MoveTypeLayout::Enum(MoveEnumLayout::new(vec![
+        (ident_str!("None").to_owned(), vec![]),
+        (
+            ident_str!("Some").to_owned(),
+            vec![MoveFieldLayout::new(
+                ident_str!("value").to_owned(),
+                MoveTypeLayout::U64,
+            )],
+        ),
+    ]))
+}
+
+#[test]
+fn enum_variant_bcs() {
+    let layout = option_layout();
+    let none = MoveValue::Variant {
+        tag: 0,
+        name: None,
+        fields: vec![],
+    };
+    let some = MoveValue::Variant {
+        tag: 1,
+        name: None,
+        fields: vec![MoveValue::U64(7)],
+    };
+
+    // the ULEB128 variant index followed by the fields
+    assert_eq!(none.simple_serialize().unwrap(), vec![0]);
+    let blob = some.simple_serialize().unwrap();
+    assert_eq!(blob, vec![1, 7, 0, 0, 0, 0, 0, 0, 0]);
+
+    // deserializing fills in the variant names from the layout
+    let decoded = MoveValue::simple_deserialize(&blob, &layout).unwrap();
+    assert_eq!(decoded, some.clone().decorate(&layout));
+    assert_eq!(
+        decoded,
+        MoveValue::Variant {
+            tag: 1,
+            name: Some(ident_str!("Some").to_owned()),
+            fields: vec![MoveValue::U64(7)],
+        }
+    );
+    assert!(decoded.equals_modulo_decoration(&some));
+    assert_eq!(decoded.canonicalize(), some);
+    assert_eq!(
+        MoveValue::simple_deserialize(&[0], &layout).unwrap(),
+        none.clone().decorate(&layout)
+    );
+
+    // variants that do not match the layout are left undecorated
+    let extra = MoveValue::Variant {
+        tag: 1,
+        name: None,
+        fields: vec![MoveValue::U64(7), MoveValue::U64(8)],
+    };
+    assert_eq!(extra.clone().decorate(&layout), extra);
+    let empty_some = MoveValue::Variant {
+        tag: 1,
+        name: None,
+        fields: vec![],
+    };
+    assert_eq!(empty_some.clone().decorate(&layout), empty_some);
+
+    let err = MoveValue::simple_deserialize(&[2], &layout).unwrap_err();
+    assert!(
+        err.to_string().contains("variant tag 2 is out of range"),
+        "{}",
+        err
+    );
+    assert!(MoveValue::simple_deserialize(&[1, 7], &layout).is_err());
+
+    // nested in a vector
+    let vector_layout = MoveTypeLayout::Vector(Box::new(layout.clone()));
+    let blob = MoveValue::Vector(vec![some.clone(), none.clone(), some.clone()])
+        .simple_serialize()
+        .unwrap();
+    assert_eq!(
+        MoveValue::simple_deserialize(&blob, &vector_layout)
+            .unwrap()
+            .canonicalize(),
+        MoveValue::Vector(vec![some.clone(), none, some])
+    );
+}
+
+#[test]
+fn enum_variant_json_and_display() {
+    let layout = option_layout();
+    let some = MoveValue::Variant {
+        tag: 1,
+        name: None,
+        fields: vec![MoveValue::U64(7)],
+    };
+    assert_eq!(
+        serde_json::to_value(&some).unwrap(),
+        json!({ "variant": 1, "fields": [7] })
+    );
+    assert_eq!(
+        serde_json::to_value(some.decorate(&layout)).unwrap(),
+        json!({ "variant": "Some", "fields": [7] })
+    );
+    assert_eq!(
+        serde_json::to_value(&MoveValue::Variant {
+            tag: 0,
+            name: Some(ident_str!("None").to_owned()),
+            fields: vec![],
+        })
+        .unwrap(),
+        json!({ "variant": "None", "fields": [] })
+    );
+
+    assert_eq!(
+        layout.to_string(),
+        "enum { None { }, Some { value: u64, }, }"
+    );
+}
+
+#[test]
+fn field_layout_lookup() {
+    let coin = MoveTypeLayout::Struct(MoveStructLayout::new(vec![MoveTypeLayout::U64]));
+    let layout = MoveStructLayout::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Account").to_owned(),
+            name: ident_str!("Balance").to_owned(),
+            type_params: vec![],
+        },
+        vec![
+            MoveFieldLayout::new(ident_str!("owner").to_owned(), MoveTypeLayout::Address),
+            MoveFieldLayout::new(ident_str!("coin").to_owned(), coin.clone()),
+        ],
+    );
+
+    let field = layout.field_by_name(ident_str!("coin")).unwrap();
+    assert_eq!(field.name(), ident_str!("coin"));
+    assert_eq!(field.layout(), &coin);
+    assert_eq!(
+        field.clone().into_inner(),
+        (ident_str!("coin").to_owned(), coin)
+    );
+    assert_eq!(
+        layout.field_by_name(ident_str!("owner")).unwrap().layout(),
+        &MoveTypeLayout::Address
+    );
+    assert!(layout.field_by_name(ident_str!("value")).is_none());
+
+    // runtime layouts have no field names to look up
+    let runtime = MoveStructLayout::new(layout.clone().into_fields());
+    assert!(runtime.field_by_name(ident_str!("coin")).is_none());
+}
+
+#[test]
+fn struct_field_iteration() {
+    let names = ["z", "a", "m"];
+    let values = vec![MoveValue::U8(0), MoveValue::U8(1), MoveValue::U8(2)];
+    let named: Vec<(Identifier, MoveValue)> = names
+        .iter()
+        .map(|n| Identifier::new(*n).unwrap())
+        .zip(values.clone())
+        .collect();
+    let decorated = MoveStruct::with_fields(named.clone());
+
+    // declaration order is kept even though it isn't alphabetical
+    let mut iter = decorated.iter();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(
+        iter.next(),
+        Some((Some(ident_str!("z")), &MoveValue::U8(0)))
+    );
+    assert_eq!(iter.size_hint(), (2, Some(2)));
+    let borrowed: Vec<_> = (&decorated).into_iter().map(|(n, _)| n.unwrap()).collect();
+    assert_eq!(
+        borrowed,
+        vec![ident_str!("z"), ident_str!("a"), ident_str!("m")]
+    );
+
+    let map: BTreeMap<_, _> = decorated
+        .clone()
+        .into_iter()
+        .map(|(n, v)| (n.unwrap(), v))
+        .collect();
+    assert_eq!(map, named.iter().cloned().collect());
+    let mut fields = Vec::new();
+    for (name, value) in decorated {
+        fields.push((name.unwrap(), value));
+    }
+    assert_eq!(fields, named);
+
+    let runtime = MoveStruct::Runtime(values.clone());
+    let mut iter = runtime.clone().into_iter();
+    assert_eq!(iter.next(), Some((None, MoveValue::U8(0))));
+    assert_eq!(iter.len(), 2);
+    assert_eq!(
+        runtime.iter().map(|(_, v)| v.clone()).collect::<Vec<_>>(),
+        values
+    );
+    assert_eq!(MoveStruct::Runtime(vec![]).into_iter().len(), 0);
+
+    let vector = MoveValue::Vector(values.clone());
+    let elements = vector.elements().unwrap();
+    assert_eq!(elements.len(), 3);
+    assert_eq!(elements.cloned().collect::<Vec<_>>(), values);
+    assert!(MoveValue::U8(0).elements().is_none());
+}
+
+fn pretty_fixture() -> MoveValue {
+    let xus = TypeTag::Struct(StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("XUS").to_owned(),
+        name: ident_str!("XUS").to_owned(),
+        type_params: vec![],
+    });
+    let coin = |value| {
+        MoveValue::Struct(MoveStruct::with_types(
+            StructTag {
+                address: AccountAddress::ONE,
+                module: ident_str!("Diem").to_owned(),
+                name: ident_str!("Diem").to_owned(),
+                type_params: vec![xus.clone()],
+            },
+            vec![(ident_str!("value").to_owned(), MoveValue::U64(value))],
+        ))
+    };
+    MoveValue::Struct(MoveStruct::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Ledger").to_owned(),
+            name: ident_str!("Ledger").to_owned(),
+            type_params: vec![xus.clone()],
+        },
+        vec![
+            (
+                ident_str!("owner").to_owned(),
+                MoveValue::Address(AccountAddress::ONE),
+            ),
+            (ident_str!("balance").to_owned(), coin(100)),
+            (
+                ident_str!("auth_key").to_owned(),
+                MoveValue::vector_u8(vec![0xbe, 0xef]),
+            ),
+            (
+                ident_str!("history").to_owned(),
+                MoveValue::Vector((0..1000).map(coin).collect()),
+            ),
+            (
+                ident_str!("flags").to_owned(),
+                MoveValue::Struct(MoveStruct::Runtime(vec![
+                    MoveValue::Bool(true),
+                    MoveValue::Vector(vec![]),
+                ])),
+            ),
+        ],
+    ))
+}
+
+#[test]
+fn move_literal_golden() {
+    let literal = |v: MoveValue| v.to_move_literal().unwrap();
+    assert_eq!(literal(MoveValue::U8(7)), "7u8");
+    assert_eq!(literal(MoveValue::U64(u64::MAX)), "18446744073709551615u64");
+    assert_eq!(literal(MoveValue::U128(0)), "0u128");
+    assert_eq!(literal(MoveValue::Bool(false)), "false");
+    assert_eq!(literal(MoveValue::Address(AccountAddress::ONE)), "@0x1");
+    assert_eq!(
+        literal(MoveValue::vector_u8(vec![1, 2])),
+        "vector[1u8, 2u8]"
+    );
+    assert_eq!(literal(MoveValue::Vector(vec![])), "vector[]");
+    let mut fixture = pretty_fixture();
+    if let MoveValue::Struct(MoveStruct::WithTypes { fields, .. }) = &mut fixture {
+        fields[3].1 = MoveValue::Vector(vec![]);
+        fields.pop();
+    }
+    assert_eq!(
+        literal(fixture.clone()),
+        "0x1::Ledger::Ledger<0x1::XUS::XUS> { owner: @0x1, \
+         balance: 0x1::Diem::Diem<0x1::XUS::XUS> { value: 100u64 }, \
+         auth_key: vector[190u8, 239u8], history: vector[] }"
+    );
+    let empty = MoveValue::Struct(MoveStruct::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("M").to_owned(),
+            name: ident_str!("Empty").to_owned(),
+            type_params: vec![],
+        },
+        vec![],
+    ));
+    assert_eq!(literal(empty), "0x1::M::Empty {}");
+
+    // structs without a type, signers, and enums have no literal form
+    let untyped = MoveValue::Struct(MoveStruct::new(vec![
+        MoveValue::Bool(true),
+        MoveValue::Vector(vec![]),
+    ]));
+    assert!(untyped.to_move_literal().is_err());
+    let signer = MoveValue::Signer(AccountAddress::ONE);
+    assert_eq!(
+        signer.to_move_literal().unwrap_err().to_string(),
+        "signer 0x1 has no literal form in Move"
+    );
+    let variant = MoveValue::Variant {
+        tag: 1,
+        name: Some(ident_str!("Frozen").to_owned()),
+        fields: vec![],
+    };
+    assert_eq!(
+        variant.to_move_literal().unwrap_err().to_string(),
+        "enum variant Frozen has no literal form in Move"
+    );
+
+    let opts = MoveLiteralOptions {
+        constructor_calls: true,
+    };
+    assert_eq!(
+        fixture.to_move_literal_with(&opts).unwrap(),
+        "0x1::Ledger::Ledger<0x1::XUS::XUS>(@0x1, 0x1::Diem::Diem<0x1::XUS::XUS>(100u64), \
+         vector[190u8, 239u8], vector[])"
+    );
+    assert_eq!(
+        untyped.to_move_literal_with(&opts).unwrap(),
+        "S(true, vector[])"
+    );
+    assert!(signer.to_move_literal_with(&opts).is_err());
+}
+
+#[test]
+fn pretty_print_golden() {
+    let opts = PrettyOptions {
+        max_vector_elements: 3,
+        ..PrettyOptions::default()
+    };
+    assert_eq!(
+        pretty_fixture().pretty_print(&opts),
+        r#"Ledger<XUS> {
+    owner: 0x1,
+    balance: Diem<XUS> { value: 100 },
+    auth_key: x"beef",
+    history: [
+        Diem<XUS> { value: 0 },
+        Diem<XUS> { value: 1 },
+        Diem<XUS> { value: 2 },
+        ... (997 more)
+    ],
+    flags: { true, [] },
+}"#
+    );
+
+    let opts = PrettyOptions {
+        indent: 2,
+        max_inline_width: 40,
+        hex_bytes: false,
+        qualified_types: true,
+        ..opts
+    };
+    assert_eq!(
+        pretty_fixture().pretty_print(&opts),
+        r#"0x1::Ledger::Ledger<0x1::XUS::XUS> {
+  owner: 0x1,
+  balance: 0x1::Diem::Diem<0x1::XUS::XUS> {
+    value: 100,
+  },
+  auth_key: [190, 239],
+  history: [
+    0x1::Diem::Diem<0x1::XUS::XUS> {
+      value: 0,
+    },
+    0x1::Diem::Diem<0x1::XUS::XUS> {
+      value: 1,
+    },
+    0x1::Diem::Diem<0x1::XUS::XUS> {
+      value: 2,
+    },
+    ... (997 more)
+  ],
+  flags: { true, [] },
+}"#
+    );
+
+    // elision also applies to values printed on a single line
+    let mut out = String::new();
+    MoveValue::Vector(vec![
+        MoveValue::vector_u8((0..=255).collect()),
+        MoveValue::Variant {
+            tag: 1,
+            name: None,
+            fields: vec![MoveValue::Signer(AccountAddress::ONE)],
+        },
+    ])
+    .fmt_pretty(
+        &mut out,
+        &PrettyOptions {
+            max_vector_elements: 2,
+            max_inline_width: usize::MAX,
+            ..PrettyOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(out, r#"[x"0001" ... (254 more), #1 { signer(0x1) }]"#);
+}
+
+#[test]
+fn value_diffs() {
+    let old = nested_holders();
+    let mut new = old.clone();
+    new.set_path("holders.0.coin.0", MoveValue::U64(11), false)
+        .unwrap();
+    new.set_path(
+        "holders.1.addr",
+        MoveValue::Address(AccountAddress::ONE),
+        false,
+    )
+    .unwrap();
+    assert!(diff(&old, &old).is_empty());
+
+    let diffs = diff(&old, &new);
+    assert_eq!(
+        diffs
+            .iter()
+            .map(|d| (d.path.as_str(), d.old.clone(), d.new.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "holders.0.coin.0",
+                Some(MoveValue::U64(10)),
+                Some(MoveValue::U64(11))
+            ),
+            (
+                "holders.1.addr",
+                Some(MoveValue::Address(AccountAddress::ZERO)),
+                Some(MoveValue::Address(AccountAddress::ONE))
+            ),
+        ]
+    );
+    // diff paths can be fed back into `get_path`
+    for d in &diffs {
+        assert_eq!(new.get_path(&d.path), d.new.as_ref());
+    }
+    assert_eq!(diffs[0].to_string(), "holders.0.coin.0: 10 -> 11");
+    assert_eq!(diffs[1].to_string(), "holders.1.addr: 0x0 -> 0x1");
+
+    // vectors are compared positionally
+    let shorter = MoveValue::Vector(vec![MoveValue::U8(1)]);
+    let longer = MoveValue::Vector(vec![MoveValue::U8(2), MoveValue::U8(3)]);
+    let diffs = diff(&shorter, &longer);
+    assert_eq!(diffs.len(), 2);
+    assert_eq!(diffs[0].to_string(), "0: 1 -> 2");
+    assert_eq!(diffs[1].to_string(), "1: <none> -> 3");
+
+    // decoration differences are reported unless they are ignored
+    let canonical = old.clone().canonicalize();
+    assert_eq!(diff(&old, &canonical).len(), 1);
+    assert!(diff_modulo_decoration(&old, &canonical).is_empty());
+    // field names are taken from whichever side is decorated
+    let diffs = diff_modulo_decoration(&canonical, &new);
+    assert_eq!(diffs[0].path, "holders.0.coin.0");
+    assert_eq!(diffs[1].path, "holders.1.addr");
+    let diffs = diff_modulo_decoration(&new, &canonical);
+    assert_eq!(diffs[0].path, "holders.0.coin.0");
+    assert_eq!(
+        diff(&MoveValue::U8(0), &MoveValue::Bool(false))[0].to_string(),
+        "<root>: 0 -> false"
+    );
+}
+
+#[test]
+fn layout_metrics() {
+    assert_eq!(MoveTypeLayout::U64.depth(), 1);
+    assert_eq!(MoveTypeLayout::U64.count_nodes(), 1);
+
+    // struct { u64, vector<address>, struct {} }
+    let layout = MoveStructLayout::new(vec![
+        MoveTypeLayout::U64,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+        MoveTypeLayout::Struct(MoveStructLayout::new(vec![])),
+    ]);
+    assert_eq!(layout.depth(), 3);
+    assert_eq!(layout.count_nodes(), 5);
+    let layout = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(layout)));
+    assert_eq!(layout.depth(), 4);
+    assert_eq!(layout.count_nodes(), 6);
+    assert_eq!(option_layout().depth(), 2);
+    assert_eq!(option_layout().count_nodes(), 2);
+    assert_eq!(MoveStructLayout::new(vec![]).depth(), 1);
+
+    let mut deep = MoveTypeLayout::U8;
+    for i in 0..10_000 {
+        deep = if i % 2 == 0 {
+            MoveTypeLayout::Vector(Box::new(deep))
+        } else {
+            MoveTypeLayout::Struct(MoveStructLayout::new(vec![deep, MoveTypeLayout::Bool]))
+        };
+    }
+    assert_eq!(deep.depth(), 10_001);
+    assert_eq!(deep.count_nodes(), 15_001);
+}
+
+#[test]
+fn deserialization_error_paths() {
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+    let account_tag = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("Account").to_owned(),
+        name: ident_str!("Account").to_owned(),
+        type_params: vec![],
+    };
+    let coin = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![field(
+        "value",
+        MoveTypeLayout::U64,
+    )]));
+    let account = MoveStructLayout::with_types(
+        account_tag,
+        vec![
+            field(
+                "authentication_key",
+                MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+            ),
+            field(
+                "balance",
+                MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![field("coin", coin)])),
+            ),
+        ],
+    );
+    let layout = MoveTypeLayout::Struct(account.clone());
+    let blob = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::vector_u8(vec![1, 2, 3]),
+        MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::Struct(
+            MoveStruct::Runtime(vec![MoveValue::U64(100)]),
+        )])),
+    ]))
+    .simple_serialize()
+    .unwrap();
+    let truncated = &blob[..blob.len() - 3];
+
+    let err = MoveValue::simple_deserialize(truncated, &layout).unwrap_err();
+    assert!(
+        err.to_string()
+            .ends_with("while decoding 0x1::Account::Account.balance.coin.value (u64)"),
+        "{}",
+        err
+    );
+    let err = err.downcast::<DeserializationError>().unwrap();
+    assert_eq!(err.path(), "0x1::Account::Account.balance.coin.value (u64)");
+    assert!(matches!(err.bcs_error(), bcs::Error::Eof));
+    assert!(std::error::Error::source(&err).is_some());
+
+    // the struct entry point reports the same path
+    let err = MoveStruct::simple_deserialize(truncated, &account).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DeserializationError>().unwrap().path(),
+        "0x1::Account::Account.balance.coin.value (u64)"
+    );
+
+    // vector elements and runtime struct fields are reported by index
+    let err = MoveValue::simple_deserialize(&blob[..2], &layout).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DeserializationError>().unwrap().path(),
+        "0x1::Account::Account.authentication_key.1 (u8)"
+    );
+    let runtime = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(MoveStructLayout::new(
+        vec![MoveTypeLayout::Bool, MoveTypeLayout::U64],
+    ))));
+    let err =
+        MoveValue::simple_deserialize(&[2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1], &runtime).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DeserializationError>().unwrap().path(),
+        "vector<struct>.1.1 (u64)"
+    );
+}
+
+#[test]
+fn decorated_struct_from_json_map() {
+    use serde::de::DeserializeSeed;
+
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+    let layout = MoveStructLayout::with_fields(vec![
+        field("addr", MoveTypeLayout::Address),
+        field("amount", MoveTypeLayout::U64),
+        field("frozen", MoveTypeLayout::Bool),
+    ]);
+    let value = MoveStruct::with_fields(vec![
+        (
+            ident_str!("addr").to_owned(),
+            MoveValue::Address(AccountAddress::ONE),
+        ),
+        (ident_str!("amount").to_owned(), MoveValue::U64(7)),
+        (ident_str!("frozen").to_owned(), MoveValue::Bool(false)),
+    ]);
+    let addr = serde_json::to_value(AccountAddress::ONE).unwrap();
+
+    // serialized output round trips, as do human-authored objects with keys in any order
+    let json = serde_json::to_value(&value).unwrap();
+    assert_eq!(layout.deserialize(json).unwrap(), value);
+    let json = json!({ "frozen": false, "amount": 7, "addr": addr });
+    assert_eq!(layout.deserialize(json).unwrap(), value);
+    // BCS still decodes the fields positionally
+    let blob = MoveValue::Struct(value.clone().canonicalize())
+        .simple_serialize()
+        .unwrap();
+    assert_eq!(
+        MoveStruct::simple_deserialize(&blob, &layout).unwrap(),
+        value
+    );
+
+    let err = layout
+        .deserialize(json!({ "frozen": false, "addr": addr }))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "missing field `amount`");
+    let err = layout
+        .deserialize(json!({ "frozen": false, "amount": 7, "addr": addr, "owner": addr }))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown field `owner`, expected one of `addr`, `amount`, `frozen`"
+    );
+    // `serde_json::Value` keeps only the last of two equal keys, so go through the text form
+    let mut de = serde_json::Deserializer::from_str(
+        r#"{ "amount": 7, "frozen": false, "amount": 8, "addr": "0x1" }"#,
+    );
+    let err = layout.deserialize(&mut de).unwrap_err();
+    assert!(
+        err.to_string().starts_with("duplicate field `amount`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn constant_round_trip() {
+    let addrs = MoveValue::Vector(vec![
+        MoveValue::Address(AccountAddress::ONE),
+        MoveValue::Address(AccountAddress::ZERO),
+        MoveValue::Address(AccountAddress::random()),
+    ]);
+    let (layout, data) = addrs.serialize_constant().unwrap();
+    assert_eq!(
+        layout,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address))
+    );
+    assert_eq!(data, addrs.simple_serialize().unwrap());
+    assert_eq!(
+        MoveValue::deserialize_constant(&layout, &data).unwrap(),
+        addrs
+    );
+
+    let nested = MoveValue::Vector(vec![addrs.clone(), addrs]);
+    let (layout, data) = nested.serialize_constant().unwrap();
+    assert_eq!(layout.to_string(), "vector<vector<address>>");
+    assert_eq!(
+        MoveValue::deserialize_constant(&layout, &data).unwrap(),
+        nested
+    );
+
+    // an empty vector decodes given its type, but its type cannot be inferred
+    let empty = MoveValue::Vector(vec![]);
+    assert_eq!(empty.serialize_constant(), None);
+    assert_eq!(MoveValue::deserialize_constant(&layout, &[0]), Some(empty));
+    // truncated data and trailing bytes are both rejected
+    assert_eq!(
+        MoveValue::deserialize_constant(&layout, &data[..data.len() - 1]),
+        None
+    );
+    assert_eq!(
+        MoveValue::deserialize_constant(&MoveTypeLayout::U8, &[1, 2]),
+        None
+    );
+
+    // structs, signers, and mixed vectors cannot be constants
+    let s = MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U8(1)]));
+    assert_eq!(s.serialize_constant(), None);
+    let signer = MoveValue::Signer(AccountAddress::ONE);
+    assert_eq!(signer.serialize_constant(), None);
+    assert_eq!(
+        MoveValue::Vector(vec![MoveValue::U8(1), MoveValue::U64(1)]).serialize_constant(),
+        None
+    );
+    let struct_layout = MoveTypeLayout::Struct(MoveStructLayout::new(vec![MoveTypeLayout::U8]));
+    assert_eq!(
+        MoveValue::deserialize_constant(&struct_layout, &s.simple_serialize().unwrap()),
+        None
+    );
+    assert_eq!(
+        MoveValue::deserialize_constant(
+            &MoveTypeLayout::Signer,
+            &signer.simple_serialize().unwrap()
+        ),
+        None
+    );
+}
+
+#[test]
+fn layout_classification() {
+    use MoveTypeLayout as L;
+    let vec_of = |l: L| L::Vector(Box::new(l));
+    let struct_of = |fields: Vec<L>| L::Struct(MoveStructLayout::new(fields));
+    let named = |l: L| {
+        L::Struct(MoveStructLayout::with_fields(vec![MoveFieldLayout::new(
+            ident_str!("f").to_owned(),
+            l,
+        )]))
+    };
+    // layout, is_primitive, is_integer, valid argument without structs, with structs
+    let table = vec![
+        (L::Bool, true, false, true, true),
+        (L::U8, true, true, true, true),
+        (L::U64, true, true, true, true),
+        (L::U128, true, true, true, true),
+        (L::Address, true, false, true, true),
+        (L::Signer, false, false, false, false),
+        (L::TypeParameter(0), false, false, false, false),
+        (vec_of(L::U64), false, false, true, true),
+        (vec_of(vec_of(L::Bool)), false, false, true, true),
+        (vec_of(L::Signer), false, false, false, false),
+        (
+            struct_of(vec![L::U8, L::Address]),
+            false,
+            false,
+            false,
+            true,
+        ),
+        (vec_of(struct_of(vec![])), false, false, false, true),
+        (
+            struct_of(vec![L::U8, L::Signer]),
+            false,
+            false,
+            false,
+            false,
+        ),
+        (named(L::U64), false, false, false, true),
+        (named(vec_of(L::Signer)), false, false, false, false),
+        (named(L::TypeParameter(0)), false, false, false, false),
+        (option_layout(), false, false, false, true),
+        (
+            L::Enum(MoveEnumLayout::new(vec![(
+                ident_str!("Owner").to_owned(),
+                vec![MoveFieldLayout::new(
+                    ident_str!("signer").to_owned(),
+                    L::Signer,
+                )],
+            )])),
+            false,
+            false,
+            false,
+            false,
+        ),
+    ];
+    for (layout, primitive, integer, arg, struct_arg) in table {
+        assert_eq!(layout.is_primitive(), primitive, "{}", layout);
+        assert_eq!(layout.is_integer(), integer, "{}", layout);
+        assert_eq!(layout.is_valid_txn_arg_type(false), arg, "{}", layout);
+        assert_eq!(layout.is_valid_txn_arg_type(true), struct_arg, "{}", layout);
+    }
+
+    // type parameters are not valid arguments, but hold no signer
+    let without_signers = vec![
+        L::TypeParameter(0),
+        named(L::TypeParameter(0)),
+        vec_of(vec_of(L::Bool)),
+        struct_of(vec![L::U8, L::Address]),
+        option_layout(),
+    ];
+    for layout in &without_signers {
+        assert!(!layout.contains_signer(), "{}", layout);
+    }
+    let with_signers = vec![
+        L::Signer,
+        vec_of(L::Signer),
+        struct_of(vec![L::U8, L::Signer]),
+        named(vec_of(L::Signer)),
+        L::Enum(MoveEnumLayout::new(vec![
+            (ident_str!("None").to_owned(), vec![]),
+            (
+                ident_str!("Some").to_owned(),
+                vec![MoveFieldLayout::new(
+                    ident_str!("value").to_owned(),
+                    L::Signer,
+                )],
+            ),
+        ])),
+    ];
+    for layout in &with_signers {
+        assert!(layout.contains_signer(), "{}", layout);
+    }
+}
+
+#[test]
+fn canonical_hash() {
+    let decorated = nested_holders();
+    let runtime = decorated.clone().canonicalize();
+    assert_ne!(
+        decorated.simple_serialize().unwrap(),
+        runtime.simple_serialize().unwrap()
+    );
+    assert_eq!(decorated.sha3_256().unwrap(), runtime.sha3_256().unwrap());
+    let mut changed = decorated.clone();
+    changed
+        .set_path("holders.1.coin.0", MoveValue::U64(21), false)
+        .unwrap();
+    assert_ne!(changed.sha3_256().unwrap(), decorated.sha3_256().unwrap());
+
+    // the digest of a primitive is that of its BCS bytes: SHA3-256 of [0x01]
+    assert_eq!(
+        hex::encode(MoveValue::Bool(true).sha3_256().unwrap()),
+        "2767f15c8af2f2c7225d5273fdd683edc714110a987d1054697c348aed4e6cc7"
+    );
+}
+
+#[test]
+#[cfg(not(any(feature = "address20", feature = "address32")))]
+fn addresses_bcs_golden_and_json() {
+    use serde::de::DeserializeSeed;
+
+    let max = AccountAddress::new([0xff; AccountAddress::LENGTH]);
+    let cafe = AccountAddress::from_hex_literal("0xcafe").unwrap();
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("owner").to_owned(), MoveTypeLayout::Address),
+        MoveFieldLayout::new(
+            ident_str!("delegates").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+        ),
+    ]));
+    let value = MoveValue::Struct(MoveStruct::with_fields(vec![
+        (
+            ident_str!("owner").to_owned(),
+            MoveValue::Address(AccountAddress::ONE),
+        ),
+        (
+            ident_str!("delegates").to_owned(),
+            MoveValue::vector_address(vec![cafe, max]),
+        ),
+    ]));
+
+    // addresses are encoded as their raw bytes, however they are represented in JSON
+    assert_eq!(
+        hex::encode(value.simple_serialize().unwrap()),
+        "00000000000000000000000000000001\
+         02\
+         0000000000000000000000000000cafe\
+         ffffffffffffffffffffffffffffffff"
+    );
+    assert_eq!(
+        hex::encode(value.sha3_256().unwrap()),
+        "289a0da1fdcd592e9e16d8d704acc55e656ec6b8b3b935ca660de14e73473bed"
+    );
+
+    let json = serde_json::to_value(&value).unwrap();
+    assert_eq!(
+        json,
+        json!({
+            "owner": "00000000000000000000000000000001",
+            "delegates": [
+                "0000000000000000000000000000cafe",
+                "ffffffffffffffffffffffffffffffff",
+            ],
+        })
+    );
+    let short =
+        json!({ "owner": "0x1", "delegates": ["0xcafe", "0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"] });
+    for json in vec![json, short] {
+        assert_eq!((&layout).deserialize(json).unwrap(), value);
+    }
+}
+
+#[test]
+fn layout_encoding_golden() {
+    let runtime = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(MoveStructLayout::new(
+        vec![MoveTypeLayout::U64, MoveTypeLayout::Bool],
+    ))));
+    let enum_ = option_layout();
+    let mut golden = vec![
+        (runtime, "01000607000202 00"),
+        (
+            enum_,
+            "0103044e6f6e6504536f6d650576616c7565 0802 0000 0101 0202",
+        ),
+    ];
+    if AccountAddress::LENGTH == 16 {
+        let field =
+            |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+        let coin = MoveTypeLayout::Struct(MoveStructLayout::with_types(
+            StructTag {
+                address: AccountAddress::ONE,
+                module: ident_str!("Coin").to_owned(),
+                name: ident_str!("Coin").to_owned(),
+                type_params: vec![TypeTag::U8],
+            },
+            vec![
+                field("value", MoveTypeLayout::U64),
+                field(
+                    "history",
+                    MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U64)),
+                ),
+            ],
+        ));
+        golden.push((
+            coin,
+            "0103 04436f696e 0576616c7565 07686973746f7279 \
+             0702 10 00000000000000000000000000000001 00 00 01 01 \
+             02 01 02 02 0602",
+        ));
+    }
+
+    for (layout, hex) in golden {
+        let bytes = hex::decode(hex.replace(' ', "")).unwrap();
+        assert_eq!(
+            hex::encode(layout.to_bytes()),
+            hex::encode(&bytes),
+            "{}",
+            layout
+        );
+        assert_eq!(MoveTypeLayout::from_bytes(&bytes).unwrap(), layout);
+    }
+}
+
+#[test]
+fn layout_encoding_errors() {
+    let layout = option_layout();
+    let mut bytes = layout.to_bytes();
+
+    let mut unknown_version = bytes.clone();
+    unknown_version[0] = 2;
+    let err = MoveTypeLayout::from_bytes(&unknown_version).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unsupported layout encoding version 2 (expected 1)"
+    );
+    assert!(MoveTypeLayout::from_bytes(&[]).is_err());
+    for len in 1..bytes.len() {
+        assert!(
+            MoveTypeLayout::from_bytes(&bytes[..len]).is_err(),
+            "{}",
+            len
+        );
+    }
+    bytes.push(0);
+    let err = MoveTypeLayout::from_bytes(&bytes).unwrap_err();
+    assert_eq!(err.to_string(), "1 trailing bytes after layout");
+    // an unknown tag and a reference to a missing identifier
+    let err = MoveTypeLayout::from_bytes(&[1, 0, 10]).unwrap_err();
+    assert_eq!(err.to_string(), "unknown layout tag 10 at byte 2");
+    let err = MoveTypeLayout::from_bytes(&[1, 0, 7, 1, 1, 0, 2]).unwrap_err();
+    assert_eq!(err.to_string(), "unknown identifier #0 at byte 5");
+
+    let nest = |depth| {
+        let mut layout = MoveTypeLayout::U8;
+        for _ in 0..depth {
+            layout = MoveTypeLayout::Vector(Box::new(layout));
+        }
+        layout
+    };
+    let layout = nest(1024);
+    assert_eq!(
+        MoveTypeLayout::from_bytes(&layout.to_bytes()).unwrap(),
+        layout
+    );
+    let err = MoveTypeLayout::from_bytes(&nest(1025).to_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "layout is nested more than 1024 levels");
+}
+
+#[test]
+fn instantiate_generic_layout() {
+    use MoveTypeLayout as L;
+
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+    let with_fields = |fields| L::Struct(MoveStructLayout::with_fields(fields));
+    // struct Option<T> { vec: vector<T> }
+    let option_fields = |t| vec![field("vec", L::Vector(Box::new(t)))];
+    let option = |t: L| {
+        let t_tag: TypeTag = (&t).try_into().unwrap();
+        let tag = StructTag {
+            address: AccountAddress::ONE,
+            module: Identifier::new("Option").unwrap(),
+            name: Identifier::new("Option").unwrap(),
+            type_params: vec![t_tag],
+        };
+        L::Struct(MoveStructLayout::with_types(tag, option_fields(t)))
+    };
+    // generic structs may be left untagged, see `instantiate_generic_struct_tags` otherwise
+    let generic_option = |t| with_fields(option_fields(t));
+    // struct Pool<T, U> { entries: vector<Option<T>>, total: U }
+    let pool = |option, u| {
+        with_fields(vec![
+            field("entries", L::Vector(Box::new(option))),
+            field("total", u),
+        ])
+    };
+
+    let generic = pool(generic_option(L::TypeParameter(0)), L::TypeParameter(1));
+    assert_eq!(
+        generic.instantiate(&[L::Address, L::U64]).unwrap(),
+        pool(generic_option(L::Address), L::U64)
+    );
+    // decorated layouts of concrete types are kept as they are
+    assert_eq!(
+        pool(option(L::U8), L::TypeParameter(0))
+            .instantiate(&[L::Bool])
+            .unwrap(),
+        pool(option(L::U8), L::Bool)
+    );
+    // type arguments may themselves be generic, and be instantiated further
+    let nested = generic
+        .instantiate(&[generic_option(L::TypeParameter(0)), L::U8])
+        .unwrap()
+        .instantiate(&[option(L::Bool)])
+        .unwrap();
+    assert_eq!(
+        nested,
+        pool(generic_option(generic_option(option(L::Bool))), L::U8)
+    );
+
+    let err = generic.instantiate(&[L::U8]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "type parameter 1 is out of range for 1 type arguments"
+    );
+    let vector_of_t = L::Vector(Box::new(L::TypeParameter(0)));
+    assert_eq!(vector_of_t.to_string(), "vector<T0>");
+    let runtime = L::Struct(MoveStructLayout::new(vec![L::U8]));
+    let expected = L::Vector(Box::new(runtime.clone()));
+    assert_eq!(vector_of_t.instantiate(&[runtime]).unwrap(), expected);
+
+    // type parameters name no type, but have a place in type tag templates
+    let err = TryInto::<TypeTag>::try_into(&vector_of_t).unwrap_err();
+    assert!(
+        err.to_string().contains("type parameter T0 names no type"),
+        "{}",
+        err
+    );
+    let template = vector_of_t.type_tag_template().unwrap();
+    assert_eq!(
+        template,
+        TypeTagTemplate::Vector(Box::new(TypeTagTemplate::TypeParameter(0)))
+    );
+    assert_eq!(
+        template.substitute(&[TypeTag::U8]).unwrap(),
+        TypeTag::Vector(Box::new(TypeTag::U8))
+    );
+    assert!(generic.type_tag_template().is_err());
+
+    // uninstantiated layouts encode, but cannot be used to decode values
+    assert_eq!(
+        MoveTypeLayout::from_bytes(&generic.to_bytes()).unwrap(),
+        generic
+    );
+    let err = MoveValue::simple_deserialize(&[1, 0], &vector_of_t).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("uninstantiated type parameter T0 while decoding vector<T0>.0 (T0)"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn instantiate_generic_struct_tags() {
+    use MoveTypeLayout as L;
+    use TypeTagTemplate as T;
+
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+    // struct Option<T> { vec: vector<T> }, with `t_tag` as the tag of `t`
+    let option = |t_tag, t| {
+        L::Struct(MoveStructLayout::WithTypeTemplate {
+            type_: StructTagTemplate {
+                address: AccountAddress::ONE,
+                module: Identifier::new("Option").unwrap(),
+                name: Identifier::new("Option").unwrap(),
+                type_params: vec![t_tag],
+            },
+            fields: vec![field("vec", L::Vector(Box::new(t)))],
+        })
+    };
+    let generic = L::Vector(Box::new(option(T::TypeParameter(0), L::TypeParameter(0))));
+    assert_eq!(
+        generic.type_tag_template().unwrap().to_string(),
+        "vector<0x1::Option::Option<T0>>"
+    );
+
+    // the tag of the instantiated struct names its type argument
+    let instantiated = generic.instantiate(&[L::U64]).unwrap();
+    let tag = StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new("Option").unwrap(),
+        name: Identifier::new("Option").unwrap(),
+        type_params: vec![TypeTag::U64],
+    };
+    let fields = vec![field("vec", L::Vector(Box::new(L::U64)))];
+    assert_eq!(
+        instantiated,
+        L::Vector(Box::new(L::Struct(MoveStructLayout::with_types(
+            tag, fields
+        ))))
+    );
+    let ty: TypeTag = (&instantiated).try_into().unwrap();
+    assert_eq!(ty.to_string(), "vector<0x1::Option::Option<u64>>");
+
+    // generic type arguments leave a template, to be instantiated further
+    let nested = generic
+        .instantiate(&[option(T::TypeParameter(1), L::TypeParameter(1))])
+        .unwrap();
+    assert_eq!(
+        nested.type_tag_template().unwrap().to_string(),
+        "vector<0x1::Option::Option<0x1::Option::Option<T1>>>"
+    );
+    let ty: TypeTag = (&nested.instantiate(&[L::Bool, L::Address]).unwrap())
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        ty.to_string(),
+        "vector<0x1::Option::Option<0x1::Option::Option<address>>>"
+    );
+
+    let err = generic.instantiate(&[]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "type parameter 0 is out of range for 0 type arguments"
+    );
+    // an untagged type argument cannot be substituted in a tag
+    let runtime = L::Struct(MoveStructLayout::new(vec![L::U8]));
+    assert!(generic.instantiate(&[runtime]).is_err());
+
+    // templates encode, but cannot be used to decode values
+    assert_eq!(
+        MoveTypeLayout::from_bytes(&generic.to_bytes()).unwrap(),
+        generic
+    );
+    let err = MoveValue::simple_deserialize(&[1, 0], &generic).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("uninstantiated generic struct 0x1::Option::Option<T0>"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn signer_json_marker() {
+    use serde::de::DeserializeSeed;
+
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+    let layout = MoveStructLayout::with_fields(vec![
+        field("owner", MoveTypeLayout::Address),
+        field("sender", MoveTypeLayout::Signer),
+    ]);
+    let value = MoveStruct::with_fields(vec![
+        (
+            ident_str!("owner").to_owned(),
+            MoveValue::Address(AccountAddress::ONE),
+        ),
+        (
+            ident_str!("sender").to_owned(),
+            MoveValue::Signer(AccountAddress::ONE),
+        ),
+    ]);
+    let addr = serde_json::to_value(AccountAddress::ONE).unwrap();
+
+    let json = serde_json::to_value(&value).unwrap();
+    assert_eq!(json, json!({ "owner": addr, "sender": { "signer": addr } }));
+    assert_eq!(layout.deserialize(json).unwrap(), value);
+    // bare addresses are still accepted for signers
+    assert_eq!(
+        layout
+            .deserialize(json!({ "owner": addr, "sender": addr }))
+            .unwrap(),
+        value
+    );
+    // in the same forms as addresses
+    let short = json!({ "owner": "0x1", "sender": "0x1" });
+    let from_short = layout.deserialize(short).unwrap();
+    assert_eq!(from_short, value);
+    assert_eq!(
+        layout
+            .deserialize(serde_json::to_value(&from_short).unwrap())
+            .unwrap(),
+        value
+    );
+    assert!(layout
+        .deserialize(json!({ "owner": "0x1", "sender": "1" }))
+        .is_err());
+    let err = layout
+        .deserialize(json!({ "owner": addr, "sender": { "address": addr } }))
+        .unwrap_err();
+    assert!(
+        err.to_string().starts_with("unknown field `address`"),
+        "{}",
+        err
+    );
+
+    // BCS encodes signers as plain addresses
+    let blob = MoveValue::Struct(value.clone().canonicalize())
+        .simple_serialize()
+        .unwrap();
+    assert_eq!(
+        blob,
+        MoveValue::vector_address(vec![AccountAddress::ONE, AccountAddress::ONE])
+            .simple_serialize()
+            .unwrap()[1..]
+    );
+    assert_eq!(
+        MoveStruct::simple_deserialize(&blob, &layout).unwrap(),
+        value
+    );
+}
+
+#[test]
+fn random_value_round_trip() {
+    use crate::value::testing::{random_layout, random_value, GenConfig};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = GenConfig::default();
+    for _ in 0..200 {
+        let layout = random_layout(&mut rng, 4, &config);
+        let value = random_value(&layout, &mut rng, &config);
+        let blob = value.clone().canonicalize().simple_serialize().unwrap();
+        assert_eq!(
+            MoveValue::simple_deserialize(&blob, &layout).unwrap(),
+            value,
+            "{}",
+            layout
+        );
+    }
+
+    let config = GenConfig {
+        vector_len: 5..6,
+        addresses: vec![AccountAddress::ONE],
+        allow_signers: false,
+    };
+    let layout = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address));
+    assert_eq!(
+        random_value(&layout, &mut rng, &config),
+        MoveValue::vector_address(vec![AccountAddress::ONE; 5])
+    );
+    // layouts generated without signers have values under the same config
+    for _ in 0..200 {
+        let layout = random_layout(&mut rng, 4, &config);
+        random_value(&layout, &mut rng, &config);
+    }
+}
+
+#[test]
+fn owned_layout_conversions() {
+    use std::convert::TryFrom;
+
+    let coin_tag = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("Coin").to_owned(),
+        name: ident_str!("Coin").to_owned(),
+        type_params: vec![],
+    };
+    let coin = MoveStructLayout::with_types(
+        coin_tag.clone(),
+        vec![MoveFieldLayout::new(
+            ident_str!("value").to_owned(),
+            MoveTypeLayout::U64,
+        )],
+    );
+    let coins = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(coin.clone())));
+    assert_eq!(
+        TypeTag::try_from(coins).unwrap(),
+        TypeTag::Vector(Box::new(TypeTag::Struct(coin_tag.clone())))
+    );
+    assert_eq!(StructTag::try_from(coin).unwrap(), coin_tag);
+
+    // only `WithTypes` structs know their type, also when nested in vectors
+    let runtime = MoveStructLayout::new(vec![MoveTypeLayout::U64]);
+    assert!(StructTag::try_from(runtime.clone()).is_err());
+    let err = TypeTag::try_from(MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(
+        runtime,
+    ))))
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("needed MoveLayoutType::WithTypes"),
+        "{}",
+        err
+    );
+
+    let bytes = TypeTag::Vector(Box::new(TypeTag::Vector(Box::new(TypeTag::U8))));
+    assert_eq!(
+        MoveTypeLayout::try_from(&bytes).unwrap().to_string(),
+        "vector<vector<u8>>"
+    );
+    assert_eq!(
+        MoveTypeLayout::try_from(&TypeTag::Signer).unwrap(),
+        MoveTypeLayout::Signer
+    );
+    let err = MoveTypeLayout::try_from(&TypeTag::Vector(Box::new(TypeTag::Struct(coin_tag))))
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("the layout of struct 0x1::Coin::Coin must be resolved"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn borrowed_deserialization() {
+    use crate::value::{BorrowedMoveStruct, BorrowedMoveValue};
+
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("id").to_owned(), MoveTypeLayout::U64),
+        MoveFieldLayout::new(
+            ident_str!("data").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+        ),
+        MoveFieldLayout::new(
+            ident_str!("chunks").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Vector(Box::new(
+                MoveTypeLayout::U8,
+            )))),
+        ),
+    ]));
+    let value = MoveValue::Struct(MoveStruct::with_fields(vec![
+        (ident_str!("id").to_owned(), MoveValue::U64(7)),
+        (
+            ident_str!("data").to_owned(),
+            MoveValue::vector_u8(vec![1, 2, 3, 4]),
+        ),
+        (
+            ident_str!("chunks").to_owned(),
+            MoveValue::Vector(vec![
+                MoveValue::vector_u8(vec![5]),
+                MoveValue::vector_u8(vec![]),
+            ]),
+        ),
+    ]));
+    let blob = value.clone().canonicalize().simple_serialize().unwrap();
+
+    let borrowed = MoveValue::simple_deserialize_borrowed(&blob, &layout).unwrap();
+    let fields = match &borrowed {
+        BorrowedMoveValue::Struct(BorrowedMoveStruct::WithFields(fields)) => fields,
+        _ => panic!("expected a decorated struct, got {:?}", borrowed),
+    };
+    // the bytes point into the blob instead of a copy
+    let data = match fields[1].1 {
+        BorrowedMoveValue::Bytes(data) => data,
+        _ => panic!("expected bytes, got {:?}", fields[1].1),
+    };
+    assert_eq!(data, &[1, 2, 3, 4]);
+    let blob_range = blob.as_ptr_range();
+    assert!(blob_range.contains(&data.as_ptr()));
+
+    assert_eq!(borrowed.to_owned(), value);
+    assert_eq!(
+        borrowed.to_owned(),
+        MoveValue::simple_deserialize(&blob, &layout).unwrap()
+    );
+
+    // failures are reported the same way as by the owned path
+    let err = MoveValue::simple_deserialize_borrowed(&blob[..blob.len() - 1], &layout)
+        .unwrap_err()
+        .to_string();
+    let owned_err = MoveValue::simple_deserialize(&blob[..blob.len() - 1], &layout)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, owned_err);
+}
+
+#[test]
+fn abstract_memory_sizes() {
+    assert_eq!(MoveValue::U128(1).abstract_memory_size(), 16);
+    assert_eq!(
+        MoveValue::Address(AccountAddress::ONE).abstract_memory_size(),
+        16
+    );
+    assert_eq!(
+        MoveValue::Signer(AccountAddress::ONE).abstract_memory_size(),
+        2 + 16
+    );
+    // packed vectors
+    assert_eq!(MoveValue::vector_u8(vec![0; 10]).abstract_memory_size(), 10);
+    assert_eq!(
+        MoveValue::Vector(vec![MoveValue::U64(0), MoveValue::U64(1)]).abstract_memory_size(),
+        2 * 8
+    );
+    assert_eq!(MoveValue::Vector(vec![]).abstract_memory_size(), 0);
+    // struct { u64, vector<u8> of 3, vector<struct { bool }> of 2 }
+    let nested = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::U64(0),
+        MoveValue::vector_u8(vec![1, 2, 3]),
+        MoveValue::Vector(vec![
+            MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::Bool(true)])),
+            MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::Bool(false)])),
+        ]),
+    ]));
+    assert_eq!(
+        nested.abstract_memory_size(),
+        2 + 16 + 3 + (2 + 2 * (2 + 16))
+    );
+    // decoration does not change the size
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("a").to_owned(), MoveTypeLayout::U64),
+        MoveFieldLayout::new(
+            ident_str!("b").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+        ),
+        MoveFieldLayout::new(
+            ident_str!("c").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(MoveStructLayout::new(
+                vec![MoveTypeLayout::Bool],
+            )))),
+        ),
+    ]));
+    assert_eq!(
+        nested.clone().decorate(&layout).abstract_memory_size(),
+        nested.abstract_memory_size()
+    );
+
+    // with a layout, empty vectors are sized by their element type
+    assert_eq!(
+        nested.abstract_memory_size_with_layout(&layout),
+        2 + 16 + 3 + (2 + 2 * (2 + 16))
+    );
+    let vector_of = |l| MoveTypeLayout::Vector(Box::new(l));
+    let empty = MoveValue::Vector(vec![]);
+    assert_eq!(
+        empty.abstract_memory_size_with_layout(&vector_of(MoveTypeLayout::U8)),
+        0
+    );
+    assert_eq!(
+        empty.abstract_memory_size_with_layout(&vector_of(MoveTypeLayout::Address)),
+        0
+    );
+    assert_eq!(
+        empty.abstract_memory_size_with_layout(&vector_of(MoveTypeLayout::Signer)),
+        2
+    );
+    assert_eq!(
+        empty.abstract_memory_size_with_layout(&vector_of(vector_of(MoveTypeLayout::U8))),
+        2
+    );
+    let no_coins = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::U64(0),
+        MoveValue::vector_u8(vec![]),
+        MoveValue::Vector(vec![]),
+    ]));
+    assert_eq!(no_coins.abstract_memory_size(), 2 + 16);
+    assert_eq!(
+        no_coins.abstract_memory_size_with_layout(&layout),
+        2 + 16 + 2
+    );
+    // values that do not match the layout are sized without it
+    assert_eq!(
+        nested.abstract_memory_size_with_layout(&MoveTypeLayout::U8),
+        nested.abstract_memory_size()
+    );
+}
+
+proptest::proptest! {
+    #[test]
+    fn abstract_memory_size_monotone_in_vector_length(len in 0usize..64, extra in 1usize..8) {
+        let elem = || MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(0)]));
+        let shorter = MoveValue::Vector((0..len).map(|_| elem()).collect());
+        let longer = MoveValue::Vector((0..len + extra).map(|_| elem()).collect());
+        proptest::prop_assert!(shorter.abstract_memory_size() < longer.abstract_memory_size());
+        let shorter = MoveValue::vector_u8(vec![0; len]);
+        let longer = MoveValue::vector_u8(vec![0; len + extra]);
+        proptest::prop_assert!(shorter.abstract_memory_size() < longer.abstract_memory_size());
+    }
+}
+
+#[test]
+fn flatten_values() {
+    use crate::value::{FlatValue, FlattenOptions, RuntimeFieldNames};
+
+    let holder = |addr: AccountAddress| {
+        MoveValue::Struct(MoveStruct::with_fields(vec![(
+            ident_str!("addr").to_owned(),
+            MoveValue::Address(addr),
+        )]))
+    };
+    let value = MoveValue::Struct(MoveStruct::with_fields(vec![
+        (
+            ident_str!("balance").to_owned(),
+            MoveValue::Struct(MoveStruct::with_fields(vec![(
+                ident_str!("coin").to_owned(),
+                MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(100)])),
+            )])),
+        ),
+        (
+            ident_str!("holders").to_owned(),
+            MoveValue::Vector(vec![
+                holder(AccountAddress::ZERO),
+                holder(AccountAddress::ONE),
+            ]),
+        ),
+        (
+            ident_str!("nonces").to_owned(),
+            MoveValue::Vector(vec![MoveValue::U64(1), MoveValue::U64(2)]),
+        ),
+        (
+            ident_str!("key").to_owned(),
+            MoveValue::vector_u8(vec![0xbe, 0xef]),
+        ),
+        (
+            ident_str!("empty").to_owned(),
+            MoveValue::Struct(MoveStruct::with_fields(vec![])),
+        ),
+    ]));
+
+    let flat = value.flatten();
+    let expected = vec![
+        ("balance.coin.0", FlatValue::U64(100)),
+        ("holders[0].addr", FlatValue::Address(AccountAddress::ZERO)),
+        ("holders[1].addr", FlatValue::Address(AccountAddress::ONE)),
+        ("nonces[0]", FlatValue::U64(1)),
+        ("nonces[1]", FlatValue::U64(2)),
+        ("key", FlatValue::Bytes(vec![0xbe, 0xef])),
+    ];
+    let expected: Vec<_> = expected
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+    assert_eq!(flat, expected);
+
+    let opts = FlattenOptions {
+        separator: "/".to_string(),
+        runtime_fields: RuntimeFieldNames::Synthesized,
+        keep_primitive_vectors: true,
+    };
+    let flat = value.flatten_with_options(&opts);
+    assert_eq!(flat[0].0, "balance/coin/field_0");
+    assert_eq!(flat[1].0, "holders[0]/addr");
+    assert_eq!(
+        flat[3],
+        (
+            "nonces".to_string(),
+            FlatValue::Vector(vec![FlatValue::U64(1), FlatValue::U64(2)])
+        )
+    );
+    assert_eq!(flat.len(), 5);
+
+    // empty structs have no leaves, and a root primitive has the empty path
+    assert!(MoveValue::Struct(MoveStruct::Runtime(vec![]))
+        .flatten()
+        .is_empty());
+    assert_eq!(
+        MoveValue::Bool(true).flatten(),
+        vec![(String::new(), FlatValue::Bool(true))]
+    );
+    // variants are keyed by their name, or their tag if undecorated
+    let variant = MoveValue::Variant {
+        tag: 1,
+        name: Some(ident_str!("Active").to_owned()),
+        fields: vec![MoveValue::U8(3)],
+    };
+    assert_eq!(variant.flatten()[0].0, "Active.0");
+    assert_eq!(variant.canonicalize().flatten()[0].0, "1.0");
+}
+
+#[test]
+fn deserialize_many_blobs() {
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::new(vec![
+        MoveTypeLayout::U64,
+        MoveTypeLayout::Address,
+    ]));
+    let values: Vec<_> = (0..5u64)
+        .map(|i| {
+            MoveValue::Struct(MoveStruct::Runtime(vec![
+                MoveValue::U64(i),
+                MoveValue::Address(AccountAddress::ONE),
+            ]))
+        })
+        .collect();
+    let mut blobs: Vec<_> = values
+        .iter()
+        .map(|v| v.simple_serialize().unwrap())
+        .collect();
+    assert_eq!(
+        MoveValue::simple_deserialize_many(blobs.iter().map(Vec::as_slice), &layout).unwrap(),
+        values
+    );
+    assert!(MoveValue::simple_deserialize_many(vec![], &layout)
+        .unwrap()
+        .is_empty());
+
+    // the failing blob is named, and its error is the one `simple_deserialize` reports
+    blobs[3].pop();
+    let err =
+        MoveValue::simple_deserialize_many(blobs.iter().map(Vec::as_slice), &layout).unwrap_err();
+    assert_eq!(err.to_string(), "failed to deserialize blob #3");
+    let err = err.downcast::<DeserializationError>().unwrap();
+    assert!(matches!(err.bcs_error(), bcs::Error::Eof), "{}", err);
+    assert!(MoveValue::simple_deserialize(&blobs[3], &layout).is_err());
+
+    // blobs of variable-size layouts are decoded without a length check
+    let layout = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8));
+    let blobs = [vec![0], vec![2, 7, 8], vec![1]];
+    let err =
+        MoveValue::simple_deserialize_many(blobs.iter().map(Vec::as_slice), &layout).unwrap_err();
+    assert_eq!(err.to_string(), "failed to deserialize blob #2");
+    assert_eq!(
+        err.chain().nth(1).unwrap().to_string(),
+        MoveValue::simple_deserialize(&blobs[2], &layout)
+            .unwrap_err()
+            .to_string()
+    );
+}
+
+#[test]
+fn strip_layout_decoration() {
+    let inner = MoveStructLayout::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Coin").to_owned(),
+            name: ident_str!("Coin").to_owned(),
+            type_params: vec![],
+        },
+        vec![MoveFieldLayout::new(
+            ident_str!("value").to_owned(),
+            MoveTypeLayout::U64,
+        )],
+    );
+    let decorated = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("id").to_owned(), MoveTypeLayout::U8),
+        MoveFieldLayout::new(
+            ident_str!("coins").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Vector(Box::new(
+                MoveTypeLayout::Struct(inner.clone()),
+            )))),
+        ),
+    ]));
+    assert!(!decorated.is_runtime_only());
+
+    let stripped = decorated.clone().strip_decoration();
+    assert!(stripped.is_runtime_only());
+    assert_eq!(
+        stripped,
+        MoveTypeLayout::Struct(MoveStructLayout::new(vec![
+            MoveTypeLayout::U8,
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Vector(Box::new(
+                MoveTypeLayout::Struct(MoveStructLayout::new(vec![MoveTypeLayout::U64]))
+            )))),
+        ]))
+    );
+    // `fields` no longer panics on the stripped layout
+    match &stripped {
+        MoveTypeLayout::Struct(s) => assert_eq!(s.fields().len(), 2),
+        _ => unreachable!(),
+    }
+    assert_eq!(
+        MoveStructLayout::new(vec![]).strip_decoration(),
+        MoveStructLayout::new(vec![])
+    );
+    assert!(
+        !MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(inner.clone()))).is_runtime_only()
+    );
+
+    // deserializing with the stripped layout yields runtime structs only
+    let value = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::U8(1),
+        MoveValue::Vector(vec![MoveValue::Vector(vec![MoveValue::Struct(
+            MoveStruct::Runtime(vec![MoveValue::U64(5)]),
+        )])]),
+    ]));
+    let blob = value.simple_serialize().unwrap();
+    assert_eq!(
+        MoveValue::simple_deserialize(&blob, &stripped).unwrap(),
+        value
+    );
+    assert_eq!(
+        MoveValue::simple_deserialize(&blob, &decorated)
+            .unwrap()
+            .canonicalize(),
+        value
+    );
+
+    // enums keep their names, but their fields are stripped
+    let enum_layout = MoveTypeLayout::Enum(MoveEnumLayout::new(vec![(
+        ident_str!("Some").to_owned(),
+        vec![MoveFieldLayout::new(
+            ident_str!("coin").to_owned(),
+            MoveTypeLayout::Struct(inner),
+        )],
+    )]));
+    assert!(!enum_layout.is_runtime_only());
+    let stripped = enum_layout.strip_decoration();
+    assert!(stripped.is_runtime_only());
+    assert_eq!(
+        stripped.to_string(),
+        "enum { Some { coin: { 0: u64, }, }, }"
+    );
+}
+
+#[test]
+fn validate_struct_layouts() {
+    use crate::value::{LayoutValidationError, LayoutViolation, ValidationOptions};
+
+    let tag = |module: &str, name: &str| StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new_unchecked(module),
+        name: Identifier::new_unchecked(name),
+        type_params: vec![],
+    };
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new_unchecked(name), layout);
+    let violation = |layout: &MoveStructLayout| {
+        layout
+            .validate()
+            .unwrap_err()
+            .downcast::<LayoutValidationError>()
+            .unwrap()
+    };
+
+    let coin = MoveStructLayout::with_types(
+        tag("Coin", "Coin"),
+        vec![field("value", MoveTypeLayout::U64)],
+    );
+    let account = MoveStructLayout::with_types(
+        tag("Account", "Account"),
+        vec![
+            field("balance", MoveTypeLayout::Struct(coin.clone())),
+            field(
+                "history",
+                MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(coin.clone()))),
+            ),
+        ],
+    );
+    account.validate().unwrap();
+
+    let duplicate = MoveStructLayout::WithFields(vec![
+        field("a", MoveTypeLayout::U8),
+        field("a", MoveTypeLayout::U64),
+    ]);
+    let err = violation(&duplicate);
+    assert_eq!(
+        err.violation(),
+        &LayoutViolation::DuplicateField(ident_str!("a").to_owned())
+    );
+    assert_eq!(err.to_string(), "duplicate field `a` in struct");
+
+    let invalid_name = MoveStructLayout::WithFields(vec![field("1a", MoveTypeLayout::U8)]);
+    assert_eq!(
+        violation(&invalid_name).to_string(),
+        "invalid field name `1a` in struct"
+    );
+
+    // violations in nested layouts name the path to the offending struct
+    let bad_tag = MoveStructLayout::WithTypes {
+        type_: tag("", "Coin"),
+        fields: vec![],
+    };
+    let nested = MoveStructLayout::WithTypes {
+        type_: tag("Account", "Account"),
+        fields: vec![field(
+            "coins",
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(bad_tag))),
+        )],
+    };
+    let err = violation(&nested);
+    assert_eq!(err.path(), "0x1::Account::Account.coins");
+    assert!(matches!(
+        err.violation(),
+        LayoutViolation::InvalidStructTag(_)
+    ));
+
+    // signers are rejected unless allowed
+    let signer_field = MoveStructLayout::new(vec![MoveTypeLayout::U8, MoveTypeLayout::Signer]);
+    let err = violation(&signer_field);
+    assert_eq!(err.violation(), &LayoutViolation::SignerField);
+    assert_eq!(err.to_string(), "field struct.1 is a signer");
+    let signers = MoveStructLayout::WithFields(vec![field(
+        "owners",
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Signer)),
+    )]);
+    let err = violation(&signers);
+    assert_eq!(err.violation(), &LayoutViolation::SignerInVector);
+    assert_eq!(err.path(), "struct.owners");
+    let lenient = ValidationOptions {
+        allow_signers: true,
+    };
+    signer_field.validate_with(&lenient).unwrap();
+    signers.validate_with(&lenient).unwrap();
+
+    // enum variants are checked like decorated structs
+    let with_enum = MoveStructLayout::new(vec![MoveTypeLayout::Enum(MoveEnumLayout::new(vec![(
+        ident_str!("V").to_owned(),
+        vec![
+            field("x", MoveTypeLayout::U8),
+            field("x", MoveTypeLayout::U8),
+        ],
+    )]))]);
+    assert_eq!(violation(&with_enum).path(), "struct.0.V");
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "inconsistent struct layout: duplicate field `a`")]
+fn with_types_validates_in_debug_builds() {
+    MoveStructLayout::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("M").to_owned(),
+            name: ident_str!("S").to_owned(),
+            type_params: vec![],
+        },
+        vec![
+            MoveFieldLayout::new(ident_str!("a").to_owned(), MoveTypeLayout::U8),
+            MoveFieldLayout::new(ident_str!("a").to_owned(), MoveTypeLayout::U8),
+        ],
+    );
+}
+
+#[test]
+fn deserialize_from_reader() {
+    use crate::value::ReadValueError;
+    use std::io::{self, Read};
+
+    /// Produces one byte per read, and fails with `error` once the bytes run out if set
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        error: Option<io::ErrorKind>,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.bytes.split_first(), self.error) {
+                (Some((b, rest)), _) if !buf.is_empty() => {
+                    buf[0] = *b;
+                    self.bytes = rest;
+                    Ok(1)
+                }
+                (None, Some(kind)) => Err(io::Error::new(kind, "storage went away")),
+                _ => Ok(0),
+            }
+        }
+    }
+
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("id").to_owned(), MoveTypeLayout::U128),
+        MoveFieldLayout::new(
+            ident_str!("items").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Vector(Box::new(
+                MoveTypeLayout::U8,
+            )))),
+        ),
+        MoveFieldLayout::new(ident_str!("owner").to_owned(), MoveTypeLayout::Address),
+    ]));
+    let value = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::U128(u128::MAX),
+        MoveValue::Vector(vec![
+            MoveValue::vector_u8(vec![1; 200]),
+            MoveValue::vector_u8(vec![]),
+        ]),
+        MoveValue::Address(AccountAddress::ONE),
+    ]));
+    let blob = value.simple_serialize().unwrap();
+    let limits = DeserializationLimits::default();
+    let read =
+        |bytes, error| MoveValue::deserialize_from(Trickle { bytes, error }, &layout, &limits);
+
+    let decoded = read(&blob, None).unwrap();
+    assert_eq!(
+        decoded,
+        MoveValue::simple_deserialize(&blob, &layout).unwrap()
+    );
+    assert_eq!(decoded.canonicalize(), value);
+
+    // an early end is malformed data, and reported like `simple_deserialize` does
+    let truncated = &blob[..blob.len() - 3];
+    let err = read(truncated, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        MoveValue::simple_deserialize(truncated, &layout)
+            .unwrap_err()
+            .to_string()
+    );
+    assert!(matches!(
+        err.downcast_ref::<ReadValueError>(),
+        Some(ReadValueError::Malformed(_))
+    ));
+    let mut trailing = blob.clone();
+    trailing.push(0);
+    assert!(matches!(
+        read(&trailing, None).unwrap_err().downcast_ref(),
+        Some(ReadValueError::Malformed(_))
+    ));
+
+    // failures of the source keep their original error
+    let err = read(truncated, Some(io::ErrorKind::ConnectionReset)).unwrap_err();
+    match err.downcast_ref::<ReadValueError>() {
+        Some(ReadValueError::Io(e)) => {
+            assert_eq!(e.kind(), io::ErrorKind::ConnectionReset);
+            assert_eq!(e.to_string(), "storage went away");
+        }
+        _ => panic!("expected an IO error, got {}", err),
+    }
+
+    // limits apply as for `simple_deserialize`
+    let tight = DeserializationLimits {
+        max_nodes: 1 << 24,
+        max_vector_length: 100,
+        ..DeserializationLimits::default()
+    };
+    let err = MoveValue::deserialize_from(blob.as_slice(), &layout, &tight).unwrap_err();
+    assert!(
+        err.to_string().contains("exceeds the limit of 100"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn packed_values_round_trip() {
+    let values = vec![
+        MoveValue::U64(0),
+        MoveValue::vector_u8(vec![]),
+        MoveValue::Bool(false),
+        MoveValue::Address(AccountAddress::ZERO),
+        MoveValue::Vector(vec![MoveValue::U128(0), MoveValue::U128(u128::MAX)]),
+        MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U8(0)])),
+    ];
+    let layouts = vec![
+        MoveTypeLayout::U64,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+        MoveTypeLayout::Bool,
+        MoveTypeLayout::Address,
+        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U128)),
+        MoveTypeLayout::Struct(MoveStructLayout::new(vec![MoveTypeLayout::U8])),
+    ];
+    let blob = serialize_values_packed(&values).unwrap();
+    // a count, then each value with its length
+    assert_eq!(&blob[..11], &[6, 8, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(blob, bcs::to_bytes(&serialize_values(&values)).unwrap());
+    assert_eq!(deserialize_values_packed(&blob, &layouts).unwrap(), values);
+
+    let empty = serialize_values_packed(&[]).unwrap();
+    assert_eq!(empty, vec![0]);
+    assert!(deserialize_values_packed(&empty, &[]).unwrap().is_empty());
+
+    let err = deserialize_values_packed(&blob, &layouts[1..]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "packed blob holds 6 values, but 5 layouts were given"
+    );
+    let mut swapped = layouts.clone();
+    swapped.swap(0, 2);
+    let err = deserialize_values_packed(&blob, &swapped).unwrap_err();
+    assert_eq!(err.to_string(), "failed to deserialize value #0");
+    assert!(err.downcast_ref::<DeserializationError>().is_some());
+    let err = deserialize_values_packed(&blob[..blob.len() - 1], &layouts).unwrap_err();
+    assert_eq!(err.to_string(), "malformed packed values");
+}
+
+#[test]
+fn shared_values_copy_on_write() {
+    use crate::value::SharedMoveValue;
+
+    let bytes = || MoveValue::vector_u8(vec![7; 1000]);
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("id").to_owned(), MoveTypeLayout::U64),
+        MoveFieldLayout::new(
+            ident_str!("data").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+        ),
+    ]));
+    let value = MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(1), bytes()]));
+    let original = SharedMoveValue::new(value.clone());
+    let mut snapshot = original.clone();
+    assert!(snapshot.ptr_eq(&original));
+
+    // mutating the clone leaves the original untouched
+    match snapshot.make_mut() {
+        MoveValue::Struct(MoveStruct::Runtime(fields)) => fields[0] = MoveValue::U64(2),
+        _ => unreachable!(),
+    }
+    assert!(!snapshot.ptr_eq(&original));
+    assert_eq!(*original, value);
+    assert_ne!(snapshot, original);
+    assert_eq!(
+        snapshot.clone().into_owned(),
+        MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(2), bytes()]))
+    );
+    // an unshared value is mutated in place
+    let before = &*snapshot as *const MoveValue;
+    snapshot.make_mut();
+    assert_eq!(before, &*snapshot as *const MoveValue);
+
+    // serialization, equality, and decoration are those of the underlying value
+    assert_eq!(
+        bcs::to_bytes(&original).unwrap(),
+        value.simple_serialize().unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&original).unwrap(),
+        serde_json::to_value(&value).unwrap()
+    );
+    assert_eq!(original, SharedMoveValue::from(value.clone()));
+    assert_eq!(
+        original.clone().decorate(&layout).into_owned(),
+        value.clone().decorate(&layout)
+    );
+    assert_eq!(MoveValue::from(original), value);
+}
+
+#[test]
+fn json_safe_integers() {
+    use serde::de::DeserializeSeed;
+
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("small").to_owned(), MoveTypeLayout::U8),
+        MoveFieldLayout::new(ident_str!("balance").to_owned(), MoveTypeLayout::U64),
+        MoveFieldLayout::new(ident_str!("supply").to_owned(), MoveTypeLayout::U128),
+    ]));
+    let value = MoveValue::Struct(MoveStruct::with_fields(vec![
+        (ident_str!("small").to_owned(), MoveValue::U8(u8::MAX)),
+        (ident_str!("balance").to_owned(), MoveValue::U64(u64::MAX)),
+        (ident_str!("supply").to_owned(), MoveValue::U128(u128::MAX)),
+    ]));
+    let options = SerdeOptions {
+        integers_as_strings: true,
+        ..SerdeOptions::default()
+    };
+
+    let json = serde_json::to_string(&value.with_serde_options(options)).unwrap();
+    assert_eq!(
+        json,
+        format!(
+            r#"{{"small":255,"balance":"{}","supply":"{}"}}"#,
+            u64::MAX,
+            u128::MAX
+        )
+    );
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    assert_eq!(layout.deserialize(&mut deserializer).unwrap(), value);
+    let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(layout.deserialize(parsed).unwrap(), value);
+
+    // numbers are still accepted, and are what the default options produce
+    let json = json!({ "small": 1, "balance": u64::MAX, "supply": 5 });
+    assert_eq!(
+        serde_json::to_value(layout.deserialize(json.clone()).unwrap()).unwrap(),
+        json
+    );
+    for bad in &[json!("-1"), json!("+1"), json!("1.5"), json!(""), json!(-1)] {
+        let json = json!({ "small": 1, "balance": bad, "supply": 5 });
+        assert!(layout.deserialize(json).is_err(), "{}", bad);
+    }
+    let too_big = json!({ "small": 1, "balance": u128::MAX.to_string(), "supply": 5 });
+    assert!(layout.deserialize(too_big).is_err());
+
+    // BCS is unaffected
+    let canonical = value.clone().canonicalize();
+    assert_eq!(
+        bcs::to_bytes(&canonical.with_serde_options(options)).unwrap(),
+        canonical.simple_serialize().unwrap()
+    );
+}
+
+fn codegen_account_layout() -> MoveStructLayout {
+    let tag = |module: &str, name: &str, type_params| StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new_unchecked(module),
+        name: Identifier::new_unchecked(name),
+        type_params,
+    };
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new_unchecked(name), layout);
+
+    let xus = TypeTag::Struct(tag("XUS", "XUS", vec![]));
+    let coin = MoveTypeLayout::Struct(MoveStructLayout::with_types(
+        tag("Coin", "Coin", vec![xus]),
+        vec![field("value", MoveTypeLayout::U64)],
+    ));
+    let status = MoveEnumLayout::new(vec![
+        (
+            Identifier::new_unchecked("Active"),
+            vec![field("since", MoveTypeLayout::U64)],
+        ),
+        (Identifier::new_unchecked("Frozen"), vec![]),
+    ]);
+    MoveStructLayout::with_types(
+        tag("Account", "Account", vec![]),
+        vec![
+            field(
+                "authentication_key",
+                MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+            ),
+            field("balance", coin.clone()),
+            field("history", MoveTypeLayout::Vector(Box::new(coin))),
+            field(
+                "limits",
+                MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+                    field("daily", MoveTypeLayout::U128),
+                    field(
+                        "delegates",
+                        MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+                    ),
+                ])),
+            ),
+            field("status", MoveTypeLayout::Enum(status)),
+            field("type", MoveTypeLayout::Bool),
+            field("self", MoveTypeLayout::Signer),
+        ],
+    )
+}
+
+#[test]
+fn generate_rust_definitions() {
+    use super::codegen_fixture as fixture;
+    use crate::value::codegen::{generate_rust, CodegenOptions};
+
+    let opts = CodegenOptions {
+        extra_derives: vec!["Debug".to_string(), "PartialEq".to_string()],
+        crate_path: "crate".to_string(),
+        ..CodegenOptions::default()
+    };
+    let layout = codegen_account_layout();
+    let generated = generate_rust(&layout, &opts).unwrap();
+    let fixture = include_str!("codegen_fixture.rs");
+    let body = &fixture[fixture.find("use crate::").unwrap()..];
+    assert_eq!(generated, body);
+
+    // The generated types have the BCS encoding of the corresponding values
+    let account = fixture::Account {
+        authentication_key: vec![1, 2, 3],
+        balance: fixture::CoinXUS { value: 10 },
+        history: vec![fixture::CoinXUS { value: 3 }, fixture::CoinXUS { value: 7 }],
+        limits: fixture::AccountLimits {
+            daily: u128::MAX,
+            delegates: vec![AccountAddress::ONE, AccountAddress::random()],
+        },
+        status: fixture::AccountStatus::Active { since: 42 },
+        r#type: true,
+        self_: AccountAddress::random(),
+    };
+    let blob = bcs::to_bytes(&account).unwrap();
+    let value =
+        MoveValue::simple_deserialize(&blob, &MoveTypeLayout::Struct(layout.clone())).unwrap();
+    assert_eq!(value.canonicalize().simple_serialize().unwrap(), blob);
+    let frozen = fixture::AccountStatus::Frozen;
+    assert_eq!(
+        bcs::to_bytes(&frozen).unwrap(),
+        MoveValue::Variant {
+            tag: 1,
+            name: None,
+            fields: vec![]
+        }
+        .simple_serialize()
+        .unwrap()
+    );
+    assert_eq!(bcs::from_bytes::<fixture::Account>(&blob).unwrap(), account);
+
+    // Layouts without field names or with type parameters cannot be generated
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new_unchecked(name), layout);
+    let runtime = MoveStructLayout::with_fields(vec![field(
+        "inner",
+        MoveTypeLayout::Struct(MoveStructLayout::new(vec![MoveTypeLayout::U8])),
+    )]);
+    let named = CodegenOptions {
+        root_name: Some("Outer".to_string()),
+        ..CodegenOptions::default()
+    };
+    assert!(generate_rust(&runtime, &named)
+        .unwrap_err()
+        .to_string()
+        .contains("struct.inner"));
+    assert!(generate_rust(&runtime, &CodegenOptions::default()).is_err());
+    let generic =
+        MoveStructLayout::with_fields(vec![field("value", MoveTypeLayout::TypeParameter(0))]);
+    assert!(generate_rust(&generic, &named).is_err());
+}
+
+#[test]
+fn checked_struct_constructors() {
+    let tag = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("Coin").to_owned(),
+        name: ident_str!("Coin").to_owned(),
+        type_params: vec![],
+    };
+    let values = |names: &[&str]| -> Vec<(Identifier, MoveValue)> {
+        names
+            .iter()
+            .map(|n| (Identifier::new(*n).unwrap(), MoveValue::U64(0)))
+            .collect()
+    };
+    let layouts = |names: &[&str]| -> Vec<MoveFieldLayout> {
+        names
+            .iter()
+            .map(|n| MoveFieldLayout::new(Identifier::new(*n).unwrap(), MoveTypeLayout::U64))
+            .collect()
+    };
+
+    let duplicate = ["value", "frozen", "value"];
+    let err = MoveStruct::try_with_fields(values(&duplicate)).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate field `value` in struct");
+    let err = MoveStruct::try_with_types(tag.clone(), values(&duplicate)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate field `value` in 0x1::Coin::Coin"
+    );
+    let err = MoveStructLayout::try_with_fields(layouts(&duplicate)).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate field `value` in struct");
+    let err = MoveStructLayout::try_with_types(tag.clone(), layouts(&duplicate)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate field `value` in 0x1::Coin::Coin"
+    );
+
+    // names are case-sensitive, so near-duplicates are distinct fields
+    let distinct = ["value", "Value", "VALUE"];
+    assert_eq!(
+        MoveStruct::try_with_fields(values(&distinct)).unwrap(),
+        MoveStruct::with_fields(values(&distinct))
+    );
+    assert_eq!(
+        MoveStruct::try_with_types(tag.clone(), values(&distinct)).unwrap(),
+        MoveStruct::with_types(tag.clone(), values(&distinct))
+    );
+    assert_eq!(
+        MoveStructLayout::try_with_fields(layouts(&distinct)).unwrap(),
+        MoveStructLayout::with_fields(layouts(&distinct))
+    );
+    assert_eq!(
+        MoveStructLayout::try_with_types(tag.clone(), layouts(&distinct)).unwrap(),
+        MoveStructLayout::with_types(tag, layouts(&distinct))
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "serializing a struct with duplicate field names")]
+fn serializing_duplicate_fields_panics_in_debug_builds() {
+    let value = MoveValue::Struct(MoveStruct::with_fields(vec![
+        (ident_str!("a").to_owned(), MoveValue::U8(0)),
+        (ident_str!("a").to_owned(), MoveValue::U8(1)),
+    ]));
+    serde_json::to_value(&value).unwrap();
+}
+
+#[test]
+fn struct_layout_compatibility() {
+    use crate::value::Incompatibility;
+
+    let tag = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("Coin").to_owned(),
+        name: ident_str!("CoinStore").to_owned(),
+        type_params: vec![],
+    };
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+    let store = |fields| MoveStructLayout::with_types(tag.clone(), fields);
+    let coin =
+        |value| MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![field("value", value)]));
+    let old = store(vec![
+        field("coin", coin(MoveTypeLayout::U64)),
+        field("frozen", MoveTypeLayout::Bool),
+        field(
+            "events",
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+        ),
+    ]);
+
+    let same = old.compatible_with(&old);
+    assert!(same.is_compatible() && !same.has_renames());
+    assert_eq!(same.to_string(), "compatible");
+
+    // renamed fields keep the layout compatible, but are reported
+    let renamed = store(vec![
+        field("balance", coin(MoveTypeLayout::U64)),
+        field("frozen", MoveTypeLayout::Bool),
+        field(
+            "events",
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+        ),
+    ]);
+    let result = renamed.compatible_with(&old);
+    assert!(result.is_compatible() && result.has_renames());
+    assert_eq!(
+        result.to_string(),
+        "compatible\n  0x1::Coin::CoinStore.balance was renamed from `coin` to `balance`"
+    );
+
+    // ... as do undecorated layouts of the same structure
+    assert!(old
+        .clone()
+        .strip_decoration()
+        .compatible_with(&old)
+        .is_compatible());
+
+    let added = store(vec![
+        field("coin", coin(MoveTypeLayout::U64)),
+        field("frozen", MoveTypeLayout::Bool),
+        field(
+            "events",
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+        ),
+        field("nonce", MoveTypeLayout::U64),
+    ]);
+    assert_eq!(
+        added.compatible_with(&old).incompatibilities(),
+        &[Incompatibility::FieldAdded {
+            path: "0x1::Coin::CoinStore.nonce".to_string()
+        }]
+    );
+    assert_eq!(
+        old.compatible_with(&added).incompatibilities(),
+        &[Incompatibility::FieldRemoved {
+            path: "0x1::Coin::CoinStore.nonce".to_string()
+        }]
+    );
+
+    let reordered = store(vec![
+        field("frozen", MoveTypeLayout::Bool),
+        field("coin", coin(MoveTypeLayout::U64)),
+        field(
+            "events",
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+        ),
+    ]);
+    let result = reordered.compatible_with(&old);
+    assert!(!result.is_compatible());
+    assert_eq!(
+        result.to_string(),
+        "incompatible\n  \
+         field 0x1::Coin::CoinStore.frozen moved from position 1 to position 0\n  \
+         0x1::Coin::CoinStore.frozen changed from struct to bool\n  \
+         field 0x1::Coin::CoinStore.coin moved from position 0 to position 1\n  \
+         0x1::Coin::CoinStore.coin changed from bool to struct"
+    );
+
+    let retyped = store(vec![
+        field("coin", coin(MoveTypeLayout::U128)),
+        field("frozen", MoveTypeLayout::Bool),
+        field(
+            "events",
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Signer)),
+        ),
+    ]);
+    assert_eq!(
+        retyped.compatible_with(&old).incompatibilities(),
+        &[
+            Incompatibility::TypeChanged {
+                path: "0x1::Coin::CoinStore.coin.value".to_string(),
+                old: "u64".to_string(),
+                new: "u128".to_string(),
+            },
+            Incompatibility::TypeChanged {
+                path: "0x1::Coin::CoinStore.events[]".to_string(),
+                old: "address".to_string(),
+                new: "signer".to_string(),
+            },
+        ]
+    );
+
+    // enums may gain variants, but not lose them
+    let status = |names: &[&str]| {
+        MoveStructLayout::with_fields(vec![field(
+            "status",
+            MoveTypeLayout::Enum(MoveEnumLayout::new(
+                names
+                    .iter()
+                    .map(|n| (Identifier::new(*n).unwrap(), vec![]))
+                    .collect(),
+            )),
+        )])
+    };
+    let fewer = status(&["Active"]);
+    let more = status(&["Active", "Frozen"]);
+    assert!(more.compatible_with(&fewer).is_compatible());
+    assert_eq!(
+        fewer.compatible_with(&more).incompatibilities(),
+        &[Incompatibility::VariantRemoved {
+            path: "struct.status.Frozen".to_string()
+        }]
+    );
+}
+
+#[test]
+fn blob_size_limits() {
+    let bytes = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8));
+    let words = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U64));
+    let unbounded = DeserializationLimits {
+        max_vector_length: usize::MAX,
+        ..DeserializationLimits::default()
+    };
+    let error_message = |err: anyhow::Error| {
+        err.downcast::<DeserializationError>()
+            .unwrap()
+            .bcs_error()
+            .to_string()
+    };
+
+    // a 16-byte blob whose length prefix claims 2^30 elements only gets as many elements
+    // reserved as it could hold, and fails once it runs out
+    let mut blob = vec![0x80, 0x80, 0x80, 0x80, 0x04];
+    blob.resize(16, 0);
+    let err = MoveValue::simple_deserialize(&blob, &bytes).unwrap_err();
+    assert!(error_message(err).contains("vector length 1073741824 exceeds the limit"));
+    for err in [
+        MoveValue::simple_deserialize_with_limits(&blob, &bytes, &unbounded).unwrap_err(),
+        MoveValue::simple_deserialize_with_limits(&blob, &words, &unbounded).unwrap_err(),
+    ] {
+        let err = err.downcast::<DeserializationError>().unwrap();
+        assert!(matches!(err.bcs_error(), bcs::Error::Eof));
+    }
+
+    let mut blob = vec![2];
+    blob.extend_from_slice(&[7; 16]);
+    assert_eq!(
+        MoveValue::simple_deserialize(&blob, &words).unwrap(),
+        MoveValue::Vector(vec![MoveValue::U64(0x0707_0707_0707_0707); 2])
+    );
+
+    // blobs above `max_blob_size` are refused outright
+    let small = DeserializationLimits {
+        max_blob_size: 16,
+        ..DeserializationLimits::default()
+    };
+    assert!(MoveValue::simple_deserialize_with_limits(&blob[..16], &bytes, &small).is_err());
+    let err = MoveValue::simple_deserialize_with_limits(&blob, &words, &small).unwrap_err();
+    assert_eq!(
+        error_message(err),
+        "blob of 17 bytes exceeds the limit of 16 bytes"
+    );
+    let err = MoveValue::deserialize_from(blob.as_slice(), &words, &small).unwrap_err();
+    let err = match err.downcast::<crate::value::ReadValueError>().unwrap() {
+        crate::value::ReadValueError::Malformed(e) => e,
+        e => panic!("unexpected error {}", e),
+    };
+    assert_eq!(
+        err.bcs_error().to_string(),
+        "blob exceeds the limit of 16 bytes"
+    );
+    let fits = DeserializationLimits {
+        max_blob_size: 17,
+        ..DeserializationLimits::default()
+    };
+    assert_eq!(
+        MoveValue::deserialize_from(blob.as_slice(), &words, &fits).unwrap(),
+        MoveValue::simple_deserialize_with_limits(&blob, &words, &fits).unwrap()
+    );
+}
+
+#[test]
+fn lazy_field_access() {
+    let entry = MoveStructLayout::new(vec![MoveTypeLayout::Bool, MoveTypeLayout::U64]);
+    let status = MoveEnumLayout::new(vec![
+        (ident_str!("Active").to_owned(), vec![]),
+        (
+            ident_str!("Frozen").to_owned(),
+            vec![MoveFieldLayout::new(
+                ident_str!("since").to_owned(),
+                MoveTypeLayout::U64,
+            )],
+        ),
+    ]);
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(
+            ident_str!("data").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8)),
+        ),
+        MoveFieldLayout::new(
+            ident_str!("entries").to_owned(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(entry))),
+        ),
+        MoveFieldLayout::new(
+            ident_str!("status").to_owned(),
+            MoveTypeLayout::Enum(status),
+        ),
+        MoveFieldLayout::new(ident_str!("balance").to_owned(), MoveTypeLayout::U64),
+    ]));
+    let value = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::vector_u8(vec![0xab; 1000]),
+        MoveValue::Vector(vec![
+            MoveValue::Struct(MoveStruct::Runtime(vec![
+                MoveValue::Bool(true),
+                MoveValue::U64(1),
+            ])),
+            MoveValue::Struct(MoveStruct::Runtime(vec![
+                MoveValue::Bool(false),
+                MoveValue::U64(2),
+            ])),
+        ]),
+        MoveValue::Variant {
+            tag: 1,
+            name: None,
+            fields: vec![MoveValue::U64(9)],
+        },
+        MoveValue::U64(42),
+    ]));
+    let blob = value.simple_serialize().unwrap();
+    let decoded = MoveValue::simple_deserialize(&blob, &layout).unwrap();
+    let lazy = MoveValue::lazy(&blob, &layout).unwrap();
+
+    // parts decode into the same values as the parts of the whole value
+    assert_eq!(lazy.decode().unwrap(), decoded);
+    for path in [
+        "balance",
+        "3",
+        "entries.1",
+        "entries.1.1",
+        "status.0",
+        "data.999",
+    ] {
+        let mut part = lazy.clone();
+        for segment in path.split('.') {
+            part = part.get_field(segment).unwrap();
+        }
+        assert_eq!(
+            &part.decode().unwrap(),
+            decoded.get_path(path).unwrap(),
+            "{}",
+            path
+        );
+    }
+    // the byte vector is skipped over, not copied
+    let data = lazy.get_field("data").unwrap();
+    assert_eq!(data.bytes().len(), 1002);
+    assert_eq!(data.bytes().as_ptr(), blob.as_ptr());
+
+    assert!(lazy.get_field("missing").is_err());
+    assert!(lazy.get_field("4").is_err());
+    assert!(lazy.get_field("entries").unwrap().get_field("2").is_err());
+    assert!(lazy.get_field("balance").unwrap().get_field("0").is_err());
+    let runtime = MoveTypeLayout::Struct(MoveStructLayout::new(vec![MoveTypeLayout::U64]));
+    let lazy_runtime = LazyMoveValue::with_limits(&blob[1002..], &runtime, &Default::default());
+    assert!(lazy_runtime.unwrap().get_field("balance").is_err());
+
+    // skipped values count against the limits, like decoded ones
+    let bcs_error = |err: anyhow::Error| {
+        err.downcast::<DeserializationError>()
+            .unwrap()
+            .into_bcs_error()
+    };
+    for limits in [
+        DeserializationLimits {
+            max_nodes: 1000,
+            ..DeserializationLimits::default()
+        },
+        DeserializationLimits {
+            max_vector_length: 999,
+            ..DeserializationLimits::default()
+        },
+    ] {
+        assert!(MoveValue::simple_deserialize_with_limits(&blob, &layout, &limits).is_err());
+        let lazy = LazyMoveValue::with_limits(&blob, &layout, &limits).unwrap();
+        let err = bcs_error(lazy.get_field("balance").unwrap_err());
+        assert!(err.to_string().contains("exceeds the limit"), "{}", err);
+    }
+    // a part fits if decoding up to and including it does
+    let limits = DeserializationLimits {
+        max_nodes: 1002,
+        ..DeserializationLimits::default()
+    };
+    let lazy = LazyMoveValue::with_limits(&blob, &layout, &limits).unwrap();
+    assert!(lazy.get_field("data").unwrap().decode().is_ok());
+    assert!(lazy.get_field("entries").is_err());
+
+    // skipped values are checked as they are skipped
+    let mut bad_bool = blob.clone();
+    bad_bool[1003] = 2;
+    let lazy = MoveValue::lazy(&bad_bool, &layout).unwrap();
+    assert_eq!(
+        bcs_error(lazy.get_field("balance").unwrap_err()),
+        bcs::Error::ExpectedBoolean
+    );
+    assert!(lazy.get_field("data").is_ok());
+    let lazy = MoveValue::lazy(&blob[..blob.len() - 1], &layout).unwrap();
+    assert_eq!(
+        bcs_error(lazy.get_field("balance").unwrap_err()),
+        bcs::Error::Eof
+    );
+    assert!(lazy.get_field("status").is_ok());
+}
+
+#[test]
+fn decorate_with_interned_struct_tags() {
+    let coin_tag = |module: &str| StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new(module).unwrap(),
+        name: ident_str!("Coin").to_owned(),
+        type_params: vec![],
+    };
+    let coin = |module| {
+        MoveTypeLayout::Struct(MoveStructLayout::with_types(
+            coin_tag(module),
+            vec![MoveFieldLayout::new(
+                ident_str!("value").to_owned(),
+                MoveTypeLayout::U64,
+            )],
+        ))
+    };
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(
+            ident_str!("coins").to_owned(),
+            MoveTypeLayout::Vector(Box::new(coin("XUS"))),
+        ),
+        MoveFieldLayout::new(ident_str!("reserve").to_owned(), coin("XDX")),
+    ]));
+    let runtime = MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::Vector(
+            (0..3)
+                .map(|i| MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(i)])))
+                .collect(),
+        ),
+        MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(7)])),
+    ]));
+
+    let mut registry = StructTagRegistry::new();
+    let interned = runtime.clone().decorate_interned(&layout, &mut registry);
+    let decorated = runtime.decorate(&layout);
+    assert_eq!(interned, decorated);
+    assert_eq!(
+        interned.simple_serialize().unwrap(),
+        decorated.simple_serialize().unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&interned).unwrap(),
+        serde_json::to_value(&decorated).unwrap()
+    );
+    assert_eq!(registry.len(), 2);
+
+    let tag = |v: &MoveValue| match v {
+        MoveValue::Struct(MoveStruct::WithTypes { type_, .. }) => type_.clone(),
+        v => panic!("not a decorated struct: {:?}", v),
+    };
+    let (coins, xdx) = match &interned {
+        MoveValue::Struct(MoveStruct::WithFields(fields)) => {
+            (fields[0].1.elements().unwrap(), &fields[1].1)
+        }
+        v => panic!("unexpected value {:?}", v),
+    };
+    let coins: Vec<_> = coins.map(tag).collect();
+    assert!(coins.iter().all(|t| Arc::ptr_eq(t, &coins[0])));
+    assert!(Arc::ptr_eq(&coins[0], &registry.intern(&coin_tag("XUS"))));
+    assert_eq!(*tag(xdx), coin_tag("XDX"));
+
+    // the registry carries over to later values
+    let more = MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(8)]))
+        .decorate_interned(&coin("XDX"), &mut registry);
+    assert!(Arc::ptr_eq(&tag(&more), &tag(xdx)));
+    assert_eq!(registry.len(), 2);
+}
+
+#[test]
+fn decorate_with_cached_struct_tags() {
+    let tag = StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("XUS").to_owned(),
+        name: ident_str!("Coin").to_owned(),
+        type_params: vec![],
+    };
+    let layout = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(
+        MoveStructLayout::with_types(
+            tag.clone(),
+            vec![MoveFieldLayout::new(
+                ident_str!("value").to_owned(),
+                MoveTypeLayout::U64,
+            )],
+        ),
+    )));
+    let runtime = MoveValue::Vector(
+        (0..3)
+            .map(|i| MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(i)])))
+            .collect(),
+    );
+    let tags = |v: &MoveValue| -> Vec<Arc<StructTag>> {
+        v.elements()
+            .unwrap()
+            .map(|v| match v {
+                MoveValue::Struct(MoveStruct::WithTypes { type_, .. }) => type_.clone(),
+                v => panic!("not a decorated struct: {:?}", v),
+            })
+            .collect()
+    };
+
+    let cache = TypeTagCache::new(16);
+    let cached = runtime.clone().decorate_cached(&layout, Some(&cache));
+    assert_eq!(cached, runtime.clone().decorate(&layout));
+    let shared = cache.intern(tag);
+    assert!(tags(&cached).iter().all(|t| Arc::ptr_eq(t, &shared)));
+    assert_eq!((cache.hits(), cache.misses()), (3, 1));
+
+    // without a cache, every struct gets a tag of its own
+    let uncached = tags(&runtime.decorate_cached(&layout, None));
+    assert!(!Arc::ptr_eq(&uncached[0], &uncached[1]));
+    assert_eq!((cache.hits(), cache.misses()), (3, 1));
+}
+
+#[test]
+fn decorate_with_pooled_names() {
+    let layout = MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(
+        MoveStructLayout::with_types(
+            StructTag {
+                address: AccountAddress::ONE,
+                module: ident_str!("XUS").to_owned(),
+                name: ident_str!("Coin").to_owned(),
+                type_params: vec![],
+            },
+            vec![MoveFieldLayout::new(
+                ident_str!("value").to_owned(),
+                MoveTypeLayout::U64,
+            )],
+        ),
+    )));
+    // structs with names of their own, as built by hand
+    let with_fields = MoveValue::Vector(
+        (0..3)
+            .map(|i| {
+                MoveValue::Struct(MoveStruct::with_fields(vec![(
+                    Identifier::new("value").unwrap(),
+                    MoveValue::U64(i),
+                )]))
+            })
+            .collect(),
+    );
+    let names = |v: &MoveValue| -> Vec<Identifier> {
+        v.elements()
+            .unwrap()
+            .map(|v| match v {
+                MoveValue::Struct(MoveStruct::WithTypes { fields, .. }) => fields[0].0.clone(),
+                v => panic!("not a decorated struct: {:?}", v),
+            })
+            .collect()
+    };
+
+    let pool = IdentifierPool::new(16);
+    let pooled = with_fields
+        .clone()
+        .decorate_pooled(&layout, None, Some(&pool));
+    let unpooled = with_fields.decorate(&layout);
+    assert_eq!(pooled, unpooled);
+    assert_eq!(
+        pooled.simple_serialize().unwrap(),
+        unpooled.simple_serialize().unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&pooled).unwrap(),
+        serde_json::to_value(&unpooled).unwrap()
+    );
+
+    let shared = pool.get_or_intern("value").unwrap();
+    assert!(names(&pooled)
+        .iter()
+        .all(|n| std::ptr::eq(n.as_str(), shared.as_str())));
+    let unshared = names(&unpooled);
+    assert!(!std::ptr::eq(unshared[0].as_str(), unshared[1].as_str()));
+    assert_eq!(pool.len(), 1);
+}
+
+#[test]
+fn layout_from_bytes_pooled() {
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("coin").to_owned(), MoveTypeLayout::U64),
+        MoveFieldLayout::new(ident_str!("balance").to_owned(), MoveTypeLayout::U128),
+    ]));
+    let bytes = layout.to_bytes();
+    let pool = IdentifierPool::new(16);
+    let first = MoveTypeLayout::from_bytes_pooled(&bytes, Some(&pool)).unwrap();
+    let second = MoveTypeLayout::from_bytes_pooled(&bytes, Some(&pool)).unwrap();
+    assert_eq!(first, layout);
+    assert_eq!(second, layout);
+    assert_eq!(first.to_bytes(), bytes);
+    let field_name = |l: &MoveTypeLayout| match l {
+        MoveTypeLayout::Struct(MoveStructLayout::WithFields(fields)) => fields[0].name.clone(),
+        l => panic!("unexpected layout {}", l),
+    };
+    assert!(std::ptr::eq(
+        field_name(&first).as_str(),
+        field_name(&second).as_str()
+    ));
+    assert_eq!(pool.len(), 2);
+
+    MoveTypeLayout::from_bytes_pooled(&bytes[..bytes.len() - 1], Some(&pool)).unwrap_err();
+}
+
+#[test]
+fn json_schema_of_generic_struct() {
+    let xus = TypeTag::Struct(StructTag {
+        address: AccountAddress::ONE,
+        module: ident_str!("XUS").to_owned(),
+        name: ident_str!("XUS").to_owned(),
+        type_params: vec![],
+    });
+    let diem = MoveTypeLayout::Struct(MoveStructLayout::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Diem").to_owned(),
+            name: ident_str!("Diem").to_owned(),
+            type_params: vec![xus.clone()],
+        },
+        vec![MoveFieldLayout::new(
+            ident_str!("value").to_owned(),
+            MoveTypeLayout::U64,
+        )],
+    ));
+    let ledger = MoveTypeLayout::Struct(MoveStructLayout::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Ledger").to_owned(),
+            name: ident_str!("Ledger").to_owned(),
+            type_params: vec![xus],
+        },
+        vec![
+            MoveFieldLayout::new(ident_str!("owner").to_owned(), MoveTypeLayout::Address),
+            MoveFieldLayout::new(ident_str!("balance").to_owned(), diem.clone()),
+            MoveFieldLayout::new(
+                ident_str!("history").to_owned(),
+                MoveTypeLayout::Vector(Box::new(diem)),
+            ),
+            MoveFieldLayout::new(ident_str!("flags").to_owned(), MoveTypeLayout::U8),
+        ],
+    ));
+
+    let address = json!({ "type": "string", "pattern": format!("^[0-9a-f]{{{}}}$", AccountAddress::LENGTH * 2) });
+    let ledger_schema = |u64_schema: serde_json::Value| {
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$ref": "#/$defs/0x1::Ledger::Ledger%3C0x1::XUS::XUS%3E",
+            "$defs": {
+                "0x1::Ledger::Ledger<0x1::XUS::XUS>": {
+                    "type": "object",
+                    "properties": {
+                        "type": { "const": "0x1::Ledger::Ledger<0x1::XUS::XUS>" },
+                        "fields": {
+                            "type": "object",
+                            "properties": {
+                                "owner": address,
+                                "balance": { "$ref": "#/$defs/0x1::Diem::Diem%3C0x1::XUS::XUS%3E" },
+                                "history": {
+                                    "type": "array",
+                                    "items": { "$ref": "#/$defs/0x1::Diem::Diem%3C0x1::XUS::XUS%3E" },
+                                },
+                                "flags": { "type": "integer", "minimum": 0, "maximum": 255 },
+                            },
+                            "required": ["owner", "balance", "history", "flags"],
+                            "additionalProperties": false,
+                        },
+                    },
+                    "required": ["type", "fields"],
+                    "additionalProperties": false,
+                },
+                "0x1::Diem::Diem<0x1::XUS::XUS>": {
+                    "type": "object",
+                    "properties": {
+                        "type": { "const": "0x1::Diem::Diem<0x1::XUS::XUS>" },
+                        "fields": {
+                            "type": "object",
+                            "properties": { "value": u64_schema },
+                            "required": ["value"],
+                            "additionalProperties": false,
+                        },
+                    },
+                    "required": ["type", "fields"],
+                    "additionalProperties": false,
+                },
+            },
+        })
+    };
+    assert_eq!(
+        ledger.to_json_schema(),
+        ledger_schema(json!({ "type": "integer", "minimum": 0, "maximum": u64::MAX }))
+    );
+    let opts = SerdeOptions {
+        integers_as_strings: true,
+        ..SerdeOptions::default()
+    };
+    assert_eq!(
+        ledger.to_json_schema_with(opts),
+        ledger_schema(json!({ "type": "string", "pattern": "^(0|[1-9][0-9]*)$", "maxLength": 20 }))
+    );
+
+    // structs without a tag are written inline
+    let layout = MoveTypeLayout::Struct(MoveStructLayout::new(vec![
+        MoveTypeLayout::Bool,
+        MoveTypeLayout::Signer,
+        MoveTypeLayout::Enum(MoveEnumLayout::new(vec![
+            (ident_str!("None").to_owned(), vec![]),
+            (
+                ident_str!("Some").to_owned(),
+                vec![MoveFieldLayout::new(
+                    ident_str!("value").to_owned(),
+                    MoveTypeLayout::TypeParameter(0),
+                )],
+            ),
+        ])),
+    ]));
+    let variant = |name: &str, fields: Vec<serde_json::Value>| {
+        json!({
+            "type": "object",
+            "properties": {
+                "variant": { "const": name },
+                "fields": { "type": "array", "prefixItems": fields, "items": false, "minItems": fields.len() },
+            },
+            "required": ["variant", "fields"],
+            "additionalProperties": false,
+        })
+    };
+    assert_eq!(
+        layout.to_json_schema(),
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "array",
+            "prefixItems": [
+                { "type": "boolean" },
+                {
+                    "type": "object",
+                    "properties": { "signer": address },
+                    "required": ["signer"],
+                    "additionalProperties": false,
+                },
+                {
+                    "oneOf": [
+                        variant("None", vec![]),
+                        variant("Some", vec![json!({ "description": "uninstantiated type parameter T0" })]),
+                    ],
+                },
+            ],
+            "items": false,
+            "minItems": 3,
+        })
+    );
+}
+
+#[test]
+fn conformance_manifest() {
+    use crate::value::conformance::{edge_cases, manifest, verify_manifest};
+
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+    let vector = |elem| MoveTypeLayout::Vector(Box::new(elem));
+    let coin = MoveTypeLayout::Struct(MoveStructLayout::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Coin").to_owned(),
+            name: ident_str!("Coin").to_owned(),
+            type_params: vec![],
+        },
+        vec![field("value", MoveTypeLayout::U64)],
+    ));
+    let layouts = vec![
+        MoveTypeLayout::U64,
+        MoveTypeLayout::U128,
+        MoveTypeLayout::Signer,
+        vector(MoveTypeLayout::U8),
+        vector(vector(MoveTypeLayout::Bool)),
+        MoveTypeLayout::Struct(MoveStructLayout::new(vec![
+            MoveTypeLayout::Address,
+            vector(coin.clone()),
+        ])),
+        MoveTypeLayout::Struct(MoveStructLayout::with_fields(vec![
+            field("flag", MoveTypeLayout::Bool),
+            field("coin", coin),
+        ])),
+        MoveTypeLayout::Enum(MoveEnumLayout::new(vec![
+            (ident_str!("None").to_owned(), vec![]),
+            (
+                ident_str!("Some").to_owned(),
+                vec![field("value", MoveTypeLayout::U8)],
+            ),
+        ])),
+    ];
+
+    let generated = manifest(&layouts).unwrap();
+    verify_manifest(&generated).unwrap();
+    assert_eq!(generated, manifest(&layouts).unwrap());
+    let entries = generated["entries"].as_array().unwrap();
+    assert_eq!(
+        entries.len(),
+        layouts
+            .iter()
+            .map(|l| edge_cases(l).unwrap().len())
+            .sum::<usize>()
+    );
+    assert_eq!(
+        entries[2],
+        json!({
+            "layout": "u64",
+            "layout_encoding": "010002",
+            "value": "18446744073709551615",
+            "bcs": "ffffffffffffffff",
+        })
+    );
+    // empty, singleton, all edge cases, and a vector with a two-byte length prefix
+    let byte_vectors: Vec<_> = entries
+        .iter()
+        .filter(|e| e["layout"] == "vector<u8>")
+        .map(|e| e["bcs"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        byte_vectors,
+        vec![
+            "00",
+            "0100",
+            "030001ff",
+            &format!("8001{}", "00".repeat(128))
+        ]
+    );
+    let variants: Vec<_> = entries
+        .iter()
+        .filter(|e| e["layout"].as_str().unwrap().starts_with("enum"))
+        .map(|e| (e["value"].clone(), e["bcs"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        variants,
+        vec![
+            (json!({ "variant": "None", "fields": [] }), "00"),
+            (json!({ "variant": "Some", "fields": [0] }), "0100"),
+            (json!({ "variant": "Some", "fields": [1] }), "0101"),
+            (json!({ "variant": "Some", "fields": [255] }), "01ff"),
+        ]
+    );
+
+    // a mismatch in either direction is reported with the entry
+    let mut tampered = generated.clone();
+    tampered["entries"][2]["bcs"] = json!("feffffffffffffff");
+    let err = verify_manifest(&tampered).unwrap_err();
+    assert_eq!(err.to_string(), "entry 2 (\"u64\")");
+    let mut tampered = generated;
+    tampered["entries"][2]["value"] = json!("1");
+    assert!(verify_manifest(&tampered).is_err());
+
+    assert!(edge_cases(&MoveTypeLayout::TypeParameter(0)).is_err());
+}
+
+#[test]
+fn value_error_kinds() {
+    use MoveTypeLayout as L;
+
+    let pair = L::Struct(MoveStructLayout::new(vec![L::Bool, L::U64]));
+    let status = L::Enum(MoveEnumLayout::new(vec![
+        (ident_str!("Active").to_owned(), vec![]),
+        (ident_str!("Frozen").to_owned(), vec![]),
+    ]));
+    let bytes = L::Vector(Box::new(L::U8));
+    let pairs = L::Vector(Box::new(pair.clone()));
+    let small = DeserializationLimits {
+        max_nodes: 3,
+        max_vector_length: 2,
+        ..DeserializationLimits::default()
+    };
+    let pair_blob = [1, 7, 0, 0, 0, 0, 0, 0, 0];
+
+    let check = |blob: &[u8], layout: &MoveTypeLayout, expected: ValueError| {
+        let err = MoveValue::checked_deserialize_with_limits(blob, layout, &small).unwrap_err();
+        assert_eq!(err, expected);
+        // the legacy API reports the same kind
+        let err = MoveValue::simple_deserialize_with_limits(blob, layout, &small).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<DeserializationError>().unwrap().kind(),
+            &expected
+        );
+    };
+    check(&pair_blob[..7], &L::U64, ValueError::TruncatedInput);
+    check(&pair_blob[..5], &pair, ValueError::TruncatedInput);
+    check(&pair_blob, &L::U64, ValueError::TrailingBytes);
+    check(&[2], &L::Bool, ValueError::InvalidBoolByte);
+    check(&[2, 0], &pairs, ValueError::NodeLimitExceeded);
+    check(
+        &[2],
+        &status,
+        ValueError::LayoutMismatch {
+            path: "enum (enum)".to_string(),
+        },
+    );
+    check(
+        &[3, 1, 2, 3],
+        &bytes,
+        ValueError::Custom("vector length 3 exceeds the limit of 2".to_string()),
+    );
+    // decoding this deeply nested a value needs more than the default stack of test threads
+    let deep = std::thread::Builder::new().stack_size(64 << 20).spawn(|| {
+        let mut nested = L::Enum(MoveEnumLayout::new(vec![(
+            ident_str!("A").to_owned(),
+            vec![],
+        )]));
+        for _ in 0..bcs::MAX_CONTAINER_DEPTH {
+            nested = L::Enum(MoveEnumLayout::new(vec![(
+                ident_str!("A").to_owned(),
+                vec![MoveFieldLayout::new(ident_str!("a").to_owned(), nested)],
+            )]));
+        }
+        MoveValue::checked_deserialize(&[0; bcs::MAX_CONTAINER_DEPTH + 1], &nested).unwrap_err()
+    });
+    assert_eq!(
+        deep.unwrap().join().unwrap(),
+        ValueError::DepthLimitExceeded
+    );
+    assert!(MoveValue::checked_deserialize(&pair_blob, &pair).is_ok());
+
+    // failures of the checked APIs still convert into `anyhow::Error`s
+    let err: anyhow::Error = MoveValue::checked_deserialize(&[], &L::U8)
+        .unwrap_err()
+        .into();
+    assert_eq!(err.to_string(), "unexpected end of input");
+
+    // lazily read values report the kinds of the values skipped on the way
+    let lazy = LazyMoveValue::with_limits(&[2, 0], &pairs, &small).unwrap();
+    let err = lazy.get_field("0").unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DeserializationError>().unwrap().kind(),
+        &ValueError::NodeLimitExceeded
+    );
+    let lazy = MoveValue::lazy(&pair_blob[..5], &pair).unwrap();
+    let err = lazy.get_field("1").unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DeserializationError>().unwrap().kind(),
+        &ValueError::TruncatedInput
+    );
+}
+
+#[test]
+fn deserialize_into_rust_types() {
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Coin(u64);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Status {
+        Active,
+        Frozen { since: u64 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Account {
+        owner: AccountAddress,
+        #[serde(with = "serde_bytes")]
+        auth_key: Vec<u8>,
+        coins: Vec<Coin>,
+        status: Status,
+        limits: (u64, bool),
+    }
+
+    let coin = |value| {
+        let fields = vec![(ident_str!("value").to_owned(), MoveValue::U64(value))];
+        MoveValue::Struct(MoveStruct::with_types(
+            StructTag {
+                address: AccountAddress::ONE,
+                module: ident_str!("Coin").to_owned(),
+                name: ident_str!("Coin").to_owned(),
+                type_params: vec![],
+            },
+            fields,
+        ))
+    };
+    let account = MoveStruct::with_types(
+        StructTag {
+            address: AccountAddress::ONE,
+            module: ident_str!("Account").to_owned(),
+            name: ident_str!("Account").to_owned(),
+            type_params: vec![],
+        },
+        vec![
+            // fields without a counterpart in the Rust type are ignored
+            (ident_str!("extra").to_owned(), MoveValue::U8(1)),
+            (
+                ident_str!("owner").to_owned(),
+                MoveValue::Address(AccountAddress::ONE),
+            ),
+            (
+                ident_str!("auth_key").to_owned(),
+                MoveValue::vector_u8(vec![1, 2, 3]),
+            ),
+            (
+                ident_str!("coins").to_owned(),
+                MoveValue::Vector(vec![coin(10), coin(20)]),
+            ),
+            (
+                ident_str!("status").to_owned(),
+                MoveValue::Variant {
+                    tag: 1,
+                    name: Some(ident_str!("Frozen").to_owned()),
+                    fields: vec![MoveValue::U64(7)],
+                },
+            ),
+            (
+                ident_str!("limits").to_owned(),
+                MoveValue::Struct(MoveStruct::Runtime(vec![
+                    MoveValue::U64(100),
+                    MoveValue::Bool(true),
+                ])),
+            ),
+        ],
+    );
+    let expected = Account {
+        owner: AccountAddress::ONE,
+        auth_key: vec![1, 2, 3],
+        coins: vec![Coin(10), Coin(20)],
+        status: Status::Frozen { since: 7 },
+        limits: (100, true),
+    };
+    assert_eq!(account.deserialize_into::<Account>().unwrap(), expected);
+    let value = MoveValue::Struct(account.clone());
+    assert_eq!(value.deserialize_into::<Account>().unwrap(), expected);
+
+    // byte vectors also read into plain vectors, and variants without names by their tag
+    assert_eq!(
+        MoveValue::vector_u8(vec![4, 5])
+            .deserialize_into::<Vec<u8>>()
+            .unwrap(),
+        vec![4, 5]
+    );
+    let unnamed = MoveValue::Variant {
+        tag: 0,
+        name: None,
+        fields: vec![],
+    };
+    assert_eq!(
+        unnamed.deserialize_into::<Status>().unwrap(),
+        Status::Active
+    );
+
+    // fields are matched by name, so a missing one fails
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct WithNonce {
+        owner: AccountAddress,
+        nonce: u64,
+    }
+    assert_eq!(
+        account.deserialize_into::<WithNonce>().unwrap_err(),
+        ValueError::Custom("missing field `nonce`".to_string())
+    );
+    // and fields of the wrong type fail too
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct WrongType {
+        owner: u64,
+    }
+    assert!(account.deserialize_into::<WrongType>().is_err());
+    // runtime structs read by position
+    let runtime = MoveStruct::Runtime(vec![MoveValue::U64(1), MoveValue::Bool(false)]);
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Limits {
+        max: u64,
+        enabled: bool,
+    }
+    assert_eq!(
+        runtime.deserialize_into::<Limits>().unwrap(),
+        Limits {
+            max: 1,
+            enabled: false
+        }
+    );
+    assert!(MoveStruct::Runtime(vec![MoveValue::U64(1)])
+        .deserialize_into::<Limits>()
+        .is_err());
+}
+
+#[test]
+fn find_structs_by_type() {
+    let coin = |ty: &str, value| {
+        MoveValue::Struct(MoveStruct::with_types(
+            ty.parse::<StructTag>().unwrap(),
+            vec![(ident_str!("value").to_owned(), MoveValue::U64(value))],
+        ))
+    };
+    let xus = "0x1::Diem::Diem<0x1::XUS::XUS>";
+    let xdx = "0x1::Diem::Diem<0x1::XDX::XDX>";
+    let wallet = MoveValue::Struct(MoveStruct::with_types(
+        "0x2::Wallet::Wallet".parse::<StructTag>().unwrap(),
+        vec![
+            (ident_str!("main").to_owned(), coin(xus, 1)),
+            (
+                ident_str!("others").to_owned(),
+                MoveValue::Vector(vec![
+                    coin(xdx, 2),
+                    coin("0x1::Diem::Diem", 3),
+                    // untyped structs are searched but never match
+                    MoveValue::Struct(MoveStruct::Runtime(vec![coin(xus, 4)])),
+                ]),
+            ),
+        ],
+    ));
+    let values = |found: Vec<&MoveStruct>| -> Vec<u64> {
+        found
+            .into_iter()
+            .map(|s| s.get_u64_at("value").unwrap())
+            .collect()
+    };
+
+    let xus_tag: StructTag = xus.parse().unwrap();
+    assert_eq!(
+        values(wallet.find_structs(&StructTagMatcher::exact(&xus_tag))),
+        vec![1, 4]
+    );
+    // the generic instantiations and the non-generic struct of the same name
+    assert_eq!(
+        values(wallet.find_structs(&StructTagMatcher::ignoring_type_args(&xus_tag))),
+        vec![1, 2, 3, 4]
+    );
+    assert_eq!(
+        values(wallet.find_structs(&StructTagMatcher::new().type_args(vec![]).name("Diem"))),
+        vec![3]
+    );
+    let by_address =
+        StructTagMatcher::new().address(AccountAddress::from_hex_literal("0x2").unwrap());
+    assert_eq!(wallet.find_structs(&by_address).len(), 1);
+    assert_eq!(wallet.find_structs(&StructTagMatcher::new()).len(), 5);
+    assert!(MoveValue::U64(1)
+        .find_structs(&StructTagMatcher::new())
+        .is_empty());
+}
+
+#[test]
+fn build_nested_fixture() {
+    use crate::value::{BuildError, BuildErrorKind, StructBuilder, VectorBuilder};
+
+    let tag = |name: &str| StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params: vec![],
+    };
+    let field = |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+    let meta_layout = |tag_layout| {
+        MoveTypeLayout::Struct(MoveStructLayout::with_types(
+            tag("Meta"),
+            vec![
+                field("tag", tag_layout),
+                field(
+                    "flags",
+                    MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Bool)),
+                ),
+            ],
+        ))
+    };
+    let store_layout = |meta| {
+        MoveStructLayout::with_types(
+            tag("Store"),
+            vec![
+                field("value", MoveTypeLayout::U64),
+                field(
+                    "holders",
+                    MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Address)),
+                ),
+                field(
+                    "coin",
+                    MoveTypeLayout::Struct(MoveStructLayout::with_types(
+                        tag("Coin"),
+                        vec![field("value", MoveTypeLayout::U64), field("meta", meta)],
+                    )),
+                ),
+            ],
+        )
+    };
+    let layout = store_layout(meta_layout(MoveTypeLayout::U8));
+
+    // Fields may be given in any order, and nested structs take their tags from the layout
+    let builder = StructBuilder::for_tag(tag("Store"))
+        .nested("coin", |b| {
+            b.nested("meta", |b| {
+                b.field("tag", 3u8).field_vec("flags", vec![true, false])
+            })
+            .field("value", 5u64)
+        })
+        .field("value", 10u64)
+        .field_vec("holders", vec![AccountAddress::ONE, AccountAddress::TWO]);
+
+    let named = |name: &str, value| (Identifier::new(name).unwrap(), value);
+    let expected = MoveStruct::with_types(
+        tag("Store"),
+        vec![
+            named("value", MoveValue::U64(10)),
+            named(
+                "holders",
+                MoveValue::vector_address(vec![AccountAddress::ONE, AccountAddress::TWO]),
+            ),
+            named(
+                "coin",
+                MoveValue::Struct(MoveStruct::with_types(
+                    tag("Coin"),
+                    vec![
+                        named("value", MoveValue::U64(5)),
+                        named(
+                            "meta",
+                            MoveValue::Struct(MoveStruct::with_types(
+                                tag("Meta"),
+                                vec![
+                                    named("tag", MoveValue::U8(3)),
+                                    named(
+                                        "flags",
+                                        MoveValue::Vector(vec![
+                                            MoveValue::Bool(true),
+                                            MoveValue::Bool(false),
+                                        ]),
+                                    ),
+                                ],
+                            )),
+                        ),
+                    ],
+                )),
+            ),
+        ],
+    );
+    assert_eq!(builder.clone().build_with(&layout).unwrap(), expected);
+
+    // Unchecked, fields keep the order they were set in and nested structs have no tags
+    match builder.clone().build_unchecked() {
+        MoveStruct::WithTypes { type_, fields } => {
+            assert_eq!(*type_, tag("Store"));
+            assert_eq!(fields[0].0.as_str(), "coin");
+            assert!(matches!(
+                fields[0].1,
+                MoveValue::Struct(MoveStruct::WithFields(_))
+            ));
+        }
+        s => panic!("expected a struct with types, got {:?}", s),
+    }
+
+    let build_err = |builder: StructBuilder, layout: &MoveStructLayout| {
+        builder
+            .build_with(layout)
+            .unwrap_err()
+            .downcast::<BuildError>()
+            .unwrap()
+    };
+
+    let err = build_err(
+        builder.clone(),
+        &store_layout(meta_layout(MoveTypeLayout::U64)),
+    );
+    assert_eq!(err.path(), "0x1::M::Store.coin.meta.tag");
+    assert_eq!(err.kind(), &BuildErrorKind::Mismatch("u64".to_string()));
+    assert_eq!(
+        err.to_string(),
+        "0x1::M::Store.coin.meta.tag is not of type u64"
+    );
+
+    let err = build_err(
+        builder.clone().nested("coin", |b| b.field("value", 5u64)),
+        &layout,
+    );
+    assert_eq!(err.path(), "0x1::M::Store.coin");
+    assert_eq!(
+        err.kind(),
+        &BuildErrorKind::MissingField(Identifier::new("meta").unwrap())
+    );
+
+    let err = build_err(builder.clone().field("extra", true), &layout);
+    assert_eq!(err.to_string(), "unknown field `extra` in 0x1::M::Store");
+
+    let err = build_err(builder.field_vec("holders", vec![1u8]), &layout);
+    assert_eq!(
+        err.to_string(),
+        "0x1::M::Store.holders.0 is not of type address"
+    );
+
+    let err = build_err(
+        StructBuilder::for_tag(tag("Coin")).field("value", 1u64),
+        &layout,
+    );
+    assert_eq!(err.kind(), &BuildErrorKind::WrongTag(tag("Coin")));
+
+    // Vectors of structs
+    let coins = VectorBuilder::new()
+        .push_struct(|b| b.field("value", 1u64))
+        .push(StructBuilder::new().field("value", 2u64))
+        .build_with(&MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Struct(
+            MoveStructLayout::with_fields(vec![field("value", MoveTypeLayout::U64)]),
+        ))))
+        .unwrap();
+    assert_eq!(
+        coins,
+        MoveValue::Vector(
+            (1..=2)
+                .map(|v| MoveValue::Struct(MoveStruct::with_fields(vec![named(
+                    "value",
+                    MoveValue::U64(v)
+                )])))
+                .collect()
+        )
+    );
+}
@@ -3,20 +3,77 @@
 
 use crate::{
     account_address::AccountAddress,
-    identifier::Identifier,
-    language_storage::{StructTag, TypeTag},
+    gas_schedule::{
+        GasAlgebra, ADDRESS_SIZE, CONST_SIZE, STRUCT_SIZE, VEC_ADDRESS_ELEMENT_SIZE,
+        VEC_BOOL_ELEMENT_SIZE, VEC_U128_ELEMENT_SIZE, VEC_U64_ELEMENT_SIZE, VEC_U8_ELEMENT_SIZE,
+    },
+    identifier::{IdentStr, Identifier, IdentifierPool},
+    language_storage::{
+        StructTag, StructTagMatcher, StructTagTemplate, Structured, TypeTag, TypeTagCache,
+        TypeTagTemplate,
+    },
 };
-use anyhow::{bail, Result as AResult};
+use anyhow::{anyhow, bail, Context, Result as AResult};
 use serde::{
-    de::Error as DeError,
-    ser::{SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple},
+    de::{DeserializeSeed, Error as DeError, Unexpected},
+    ser::{SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple, SerializeTupleVariant},
     Deserialize, Serialize,
 };
+use sha3::{Digest, Sha3_256};
 use std::{
-    convert::TryInto,
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::BTreeSet,
+    convert::{TryFrom, TryInto},
     fmt::{self, Debug},
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    slice,
+    str::FromStr,
+    sync::Arc,
+    vec,
 };
 
+mod batch;
+mod borrowed;
+mod builder;
+pub mod codegen;
+mod compat;
+#[cfg(any(test, feature = "serde_json"))]
+pub mod conformance;
+mod deserializer;
+mod diff;
+mod encoding;
+mod flatten;
+mod intern;
+mod lazy;
+mod literal;
+mod pretty;
+#[cfg(any(test, feature = "serde_json"))]
+mod schema;
+mod shared;
+mod stream;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod validate;
+
+pub use borrowed::{BorrowedMoveStruct, BorrowedMoveValue};
+pub use builder::{BuildError, BuildErrorKind, StructBuilder, VectorBuilder};
+pub use compat::{CompatibilityResult, FieldRename, Incompatibility};
+pub use deserializer::{MoveStructDeserializer, MoveValueDeserializer};
+pub use diff::{diff, diff_modulo_decoration, ValueDiff};
+pub use flatten::{FlatValue, FlattenOptions, RuntimeFieldNames};
+pub use intern::StructTagRegistry;
+pub use lazy::LazyMoveValue;
+pub use literal::MoveLiteralOptions;
+pub use pretty::PrettyOptions;
+pub use shared::SharedMoveValue;
+pub use stream::ReadValueError;
+pub use validate::{LayoutValidationError, LayoutViolation, ValidationOptions};
+
+use intern::TagSource;
+
 /// In the `WithTypes` configuration, a Move struct gets serialized into a Serde struct with this name
 pub const MOVE_STRUCT_NAME: &str = "struct";
 
@@ -26,15 +83,30 @@ pub const MOVE_STRUCT_TYPE: &str = "type";
 /// In the `WithTypes` configuration, a Move struct gets serialized into a Serde struct with this as the second field
 pub const MOVE_STRUCT_FIELDS: &str = "fields";
 
+/// A Move enum variant gets serialized into a Serde enum (or, for human-readable formats, a Serde
+/// struct) with this name
+pub const MOVE_ENUM_NAME: &str = "enum";
+
+/// In human-readable formats, a Move enum variant gets serialized into a Serde struct with this as
+/// the first field, holding the variant name if known and its tag otherwise
+pub const MOVE_ENUM_VARIANT: &str = "variant";
+
+/// In human-readable formats, a signer gets serialized into a Serde struct with this name and a
+/// single field of this name holding its address, e.g. `{"signer": "0x1"}`, to tell it apart
+/// from an address
+pub const MOVE_SIGNER_NAME: &str = "signer";
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum MoveStruct {
     /// The representation used by the MoveVM
     Runtime(Vec<MoveValue>),
     /// A decorated representation with human-readable field names
     WithFields(Vec<(Identifier, MoveValue)>),
-    /// An even more decorated representation with both types and human-readable field names
+    /// An even more decorated representation with both types and human-readable field names.
+    /// The type is shared, so that structs of the same type can use a single copy of it (see
+    /// `StructTagRegistry` and `TypeTagCache`).
     WithTypes {
-        type_: StructTag,
+        type_: Arc<StructTag>,
         fields: Vec<(Identifier, MoveValue)>,
     },
 }
@@ -49,10 +121,17 @@ pub enum MoveValue {
     Vector(Vec<MoveValue>),
     Struct(MoveStruct),
     Signer(AccountAddress),
+    /// A value of a Move enum: the index of its variant, the variant name if known, and the
+    /// values of the variant's fields
+    Variant {
+        tag: u16,
+        name: Option<Identifier>,
+        fields: Vec<MoveValue>,
+    },
 }
 
 /// A layout associated with a named field
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MoveFieldLayout {
     name: Identifier,
     layout: MoveTypeLayout,
@@ -62,9 +141,21 @@ impl MoveFieldLayout {
     pub fn new(name: Identifier, layout: MoveTypeLayout) -> Self {
         Self { name, layout }
     }
+
+    pub fn name(&self) -> &IdentStr {
+        &self.name
+    }
+
+    pub fn layout(&self) -> &MoveTypeLayout {
+        &self.layout
+    }
+
+    pub fn into_inner(self) -> (Identifier, MoveTypeLayout) {
+        (self.name, self.layout)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MoveStructLayout {
     /// The representation used by the MoveVM
     Runtime(Vec<MoveTypeLayout>),
@@ -75,9 +166,32 @@ pub enum MoveStructLayout {
         type_: StructTag,
         fields: Vec<MoveFieldLayout>,
     },
+    /// The `WithTypes` representation of a generic struct, whose tag has type parameters in its
+    /// type arguments. `instantiate` turns it into `WithTypes` once the type arguments are known,
+    /// and values cannot be deserialized with it before.
+    WithTypeTemplate {
+        type_: StructTagTemplate,
+        fields: Vec<MoveFieldLayout>,
+    },
+}
+
+/// The layout of a Move enum: the name and field layouts of each of its variants, ordered by tag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveEnumLayout {
+    variants: Vec<(Identifier, Vec<MoveFieldLayout>)>,
+}
+
+impl MoveEnumLayout {
+    pub fn new(variants: Vec<(Identifier, Vec<MoveFieldLayout>)>) -> Self {
+        Self { variants }
+    }
+
+    pub fn variants(&self) -> &[(Identifier, Vec<MoveFieldLayout>)] {
+        &self.variants
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MoveTypeLayout {
     #[serde(rename = "bool")]
     Bool,
@@ -95,17 +209,117 @@ pub enum MoveTypeLayout {
     Struct(MoveStructLayout),
     #[serde(rename = "signer")]
     Signer,
+    #[serde(rename = "enum")]
+    Enum(MoveEnumLayout),
+    /// The `n`th type parameter of a generic layout, to be replaced by `instantiate`
+    #[serde(rename = "type_parameter")]
+    TypeParameter(u16),
 }
 
 impl MoveValue {
+    /// Deserialize `blob` according to `ty`, within the default `DeserializationLimits`.
+    /// Failures are reported as a `DeserializationError` saying where in the value they occurred.
     pub fn simple_deserialize(blob: &[u8], ty: &MoveTypeLayout) -> AResult<Self> {
-        Ok(bcs::from_bytes_seed(ty, blob)?)
+        Self::simple_deserialize_with_limits(blob, ty, &DeserializationLimits::default())
+    }
+
+    /// Deserialize `blob` according to `ty`, failing if the result would exceed `limits`
+    pub fn simple_deserialize_with_limits(
+        blob: &[u8],
+        ty: &MoveTypeLayout,
+        limits: &DeserializationLimits,
+    ) -> AResult<Self> {
+        Ok(Self::deserialize_annotated(blob, ty, limits)?)
+    }
+
+    /// Deserialize `blob` according to `ty`, within the default `DeserializationLimits`. Unlike
+    /// `simple_deserialize`, failures are reported as a `ValueError` to match on.
+    pub fn checked_deserialize(blob: &[u8], ty: &MoveTypeLayout) -> Result<Self, ValueError> {
+        Self::checked_deserialize_with_limits(blob, ty, &DeserializationLimits::default())
+    }
+
+    /// `checked_deserialize`, failing if the result would exceed `limits`
+    pub fn checked_deserialize_with_limits(
+        blob: &[u8],
+        ty: &MoveTypeLayout,
+        limits: &DeserializationLimits,
+    ) -> Result<Self, ValueError> {
+        Self::deserialize_annotated(blob, ty, limits).map_err(ValueError::from)
+    }
+
+    fn deserialize_annotated(
+        blob: &[u8],
+        ty: &MoveTypeLayout,
+        limits: &DeserializationLimits,
+    ) -> Result<Self, DeserializationError> {
+        let state = DeserializerState::new(*limits);
+        state
+            .start_blob(blob.len())
+            .map_err(|e| state.annotate(layout_name(ty), e))?;
+        let seed = TrackedSeed {
+            layout: ty,
+            state: &state,
+        };
+        bcs::from_bytes_seed(seed, blob).map_err(|e| state.annotate(layout_name(ty), e))
     }
 
     pub fn simple_serialize(&self) -> Option<Vec<u8>> {
         bcs::to_bytes(self).ok()
     }
 
+    /// Decode the data of a constant of type `layout`. Returns `None` if `layout` is not a legal
+    /// constant type (a primitive, an address, or vectors thereof) or `data` does not match it.
+    pub fn deserialize_constant(layout: &MoveTypeLayout, data: &[u8]) -> Option<Self> {
+        // constants have the types of non-struct transaction arguments
+        if !layout.is_valid_txn_arg_type(false) {
+            return None;
+        }
+        Self::simple_deserialize(data, layout).ok()
+    }
+
+    /// Encode this value as the data of a constant, along with the constant's type. Returns
+    /// `None` for values that cannot be constants (structs, enums and signers) and for values
+    /// whose type cannot be determined from the value alone, like empty vectors.
+    pub fn serialize_constant(&self) -> Option<(MoveTypeLayout, Vec<u8>)> {
+        let layout = self.constant_layout()?;
+        Some((layout, self.simple_serialize()?))
+    }
+
+    fn constant_layout(&self) -> Option<MoveTypeLayout> {
+        Some(match self {
+            MoveValue::U8(_) => MoveTypeLayout::U8,
+            MoveValue::U64(_) => MoveTypeLayout::U64,
+            MoveValue::U128(_) => MoveTypeLayout::U128,
+            MoveValue::Bool(_) => MoveTypeLayout::Bool,
+            MoveValue::Address(_) => MoveTypeLayout::Address,
+            MoveValue::Vector(vals) => {
+                let (first, rest) = vals.split_first()?;
+                let elem = first.constant_layout()?;
+                for val in rest {
+                    if val.constant_layout()? != elem {
+                        return None;
+                    }
+                }
+                MoveTypeLayout::Vector(Box::new(elem))
+            }
+            MoveValue::Struct(_) | MoveValue::Signer(_) | MoveValue::Variant { .. } => return None,
+        })
+    }
+
+    /// Append the BCS encoding of this value to `buf`, producing the same bytes as
+    /// `simple_serialize` without allocating a fresh buffer
+    pub fn serialize_into(&self, buf: &mut Vec<u8>) -> AResult<()> {
+        Ok(bcs::serialize_into(buf, self)?)
+    }
+
+    /// The elements of this value if it is a vector, `None` otherwise
+    pub fn elements(&self) -> Option<slice::Iter<'_, MoveValue>> {
+        match self {
+            MoveValue::Vector(vals) => Some(vals.iter()),
+            _ => None,
+        }
+    }
+
     pub fn vector_u8(v: Vec<u8>) -> Self {
         MoveValue::Vector(v.into_iter().map(MoveValue::U8).collect())
     }
@@ -115,28 +329,576 @@ impl MoveValue {
     }
 
     pub fn decorate(self, layout: &MoveTypeLayout) -> Self {
+        self.decorate_impl(layout, &mut TagSource::Fresh, None)
+    }
+
+    /// Like `decorate`, but the struct tags of decorated structs are taken from `registry`, so
+    /// that structs of the same type share one copy of it. Worthwhile for large values, such as
+    /// long vectors of structs.
+    pub fn decorate_interned(
+        self,
+        layout: &MoveTypeLayout,
+        registry: &mut StructTagRegistry,
+    ) -> Self {
+        self.decorate_impl(layout, &mut TagSource::Registry(registry), None)
+    }
+
+    /// Like `decorate`, but the struct tags of decorated structs are taken from `cache` if there
+    /// is one, so that they are shared with every other value decorated through it
+    pub fn decorate_cached(self, layout: &MoveTypeLayout, cache: Option<&TypeTagCache>) -> Self {
+        self.decorate_pooled(layout, cache, None)
+    }
+
+    /// Like `decorate_cached`, but the names that decorated structs and variants keep from this
+    /// value, rather than take from `layout`, are also interned into `pool` if there is one. Names
+    /// taken from `layout` are shared with it either way.
+    pub fn decorate_pooled(
+        self,
+        layout: &MoveTypeLayout,
+        cache: Option<&TypeTagCache>,
+        pool: Option<&IdentifierPool>,
+    ) -> Self {
+        match cache {
+            Some(cache) => self.decorate_impl(layout, &mut TagSource::Cache(cache), pool),
+            None => self.decorate_impl(layout, &mut TagSource::Fresh, pool),
+        }
+    }
+
+    fn decorate_impl(
+        self,
+        layout: &MoveTypeLayout,
+        tags: &mut TagSource,
+        names: Option<&IdentifierPool>,
+    ) -> Self {
         match (self, layout) {
-            (MoveValue::Struct(s), MoveTypeLayout::Struct(l)) => MoveValue::Struct(s.decorate(l)),
-            (MoveValue::Vector(vals), MoveTypeLayout::Vector(t)) => {
-                MoveValue::Vector(vals.into_iter().map(|v| v.decorate(t)).collect())
+            (MoveValue::Struct(s), MoveTypeLayout::Struct(l)) => {
+                MoveValue::Struct(s.decorate_impl(l, tags, names))
+            }
+            (MoveValue::Vector(vals), MoveTypeLayout::Vector(t)) => MoveValue::Vector(
+                vals.into_iter()
+                    .map(|v| v.decorate_impl(t, tags, names))
+                    .collect(),
+            ),
+            (MoveValue::Variant { tag, name, fields }, MoveTypeLayout::Enum(l)) => {
+                match l.variants.get(tag as usize) {
+                    // a variant whose fields do not match the layout is left undecorated
+                    Some((variant_name, layouts)) if layouts.len() == fields.len() => {
+                        MoveValue::Variant {
+                            tag,
+                            name: match name {
+                                Some(name) => Some(intern_name(name, names)),
+                                None => Some(variant_name.clone()),
+                            },
+                            fields: fields
+                                .into_iter()
+                                .zip(layouts)
+                                .map(|(v, l)| v.decorate_impl(&l.layout, tags, names))
+                                .collect(),
+                        }
+                    }
+                    _ => MoveValue::Variant { tag, name, fields },
+                }
             }
             (v, _) => v,
         }
     }
+
+    pub fn with_serde_options(&self, options: SerdeOptions) -> WithSerdeOptions<'_, Self> {
+        WithSerdeOptions {
+            value: self,
+            options,
+        }
+    }
+
+    /// Structural equality that ignores decoration: struct fields are compared in order, while
+    /// field names, struct tags, and the choice of `MoveStruct` representation are ignored.
+    /// `Signer(a)` and `Address(a)` are never equal.
+    pub fn equals_modulo_decoration(&self, other: &MoveValue) -> bool {
+        match (self, other) {
+            (MoveValue::Struct(s1), MoveValue::Struct(s2)) => s1.equals_modulo_decoration(s2),
+            (MoveValue::Vector(v1), MoveValue::Vector(v2)) => {
+                v1.len() == v2.len()
+                    && v1
+                        .iter()
+                        .zip(v2)
+                        .all(|(e1, e2)| e1.equals_modulo_decoration(e2))
+            }
+            (
+                MoveValue::Variant {
+                    tag: t1,
+                    fields: f1,
+                    ..
+                },
+                MoveValue::Variant {
+                    tag: t2,
+                    fields: f2,
+                    ..
+                },
+            ) => {
+                t1 == t2
+                    && f1.len() == f2.len()
+                    && f1
+                        .iter()
+                        .zip(f2)
+                        .all(|(e1, e2)| e1.equals_modulo_decoration(e2))
+            }
+            (v1, v2) => v1 == v2,
+        }
+    }
+
+    /// Strips all decoration from this value, mapping every struct to the `Runtime`
+    /// representation and dropping variant names. Two values are `equals_modulo_decoration` iff
+    /// their canonical forms are equal, which makes the canonical form suitable for hashing.
+    pub fn canonicalize(self) -> Self {
+        match self {
+            MoveValue::Struct(s) => MoveValue::Struct(s.canonicalize()),
+            MoveValue::Vector(vals) => {
+                MoveValue::Vector(vals.into_iter().map(MoveValue::canonicalize).collect())
+            }
+            MoveValue::Variant { tag, fields, .. } => MoveValue::Variant {
+                tag,
+                name: None,
+                fields: fields.into_iter().map(MoveValue::canonicalize).collect(),
+            },
+            v => v,
+        }
+    }
+
+    /// The SHA3-256 digest of the BCS encoding of this value's canonical form. Decoration does
+    /// not affect the digest, and since it is defined over the BCS bytes of the undecorated value,
+    /// it can be reproduced by any BCS implementation without knowledge of `MoveValue`.
+    pub fn sha3_256(&self) -> AResult<[u8; 32]> {
+        let bytes = bcs::to_bytes(&self.clone().canonicalize())?;
+        Ok(Sha3_256::digest(&bytes).into())
+    }
+
+    /// The abstract memory size of this value, as the VM computes it for the corresponding VM
+    /// value: `CONST_SIZE` for integers and booleans, `ADDRESS_SIZE` for addresses, and
+    /// `STRUCT_SIZE` plus the sizes of the fields for structs and signers (a struct holding an
+    /// address). Vectors of integers, booleans, or addresses are packed and cost their
+    /// `VEC_*_ELEMENT_SIZE` per element, while other vectors cost `STRUCT_SIZE` plus the sizes of
+    /// their elements. An empty vector reveals no element type and is sized as a packed one, i.e.
+    /// 0; `abstract_memory_size_with_layout` sizes it as the VM does. Enum variants have no VM
+    /// counterpart and are sized like structs of their fields.
+    pub fn abstract_memory_size(&self) -> u64 {
+        match self {
+            MoveValue::U8(_) | MoveValue::U64(_) | MoveValue::U128(_) | MoveValue::Bool(_) => {
+                CONST_SIZE.get()
+            }
+            MoveValue::Address(_) => ADDRESS_SIZE.get(),
+            MoveValue::Signer(_) => STRUCT_SIZE.get() + ADDRESS_SIZE.get(),
+            MoveValue::Vector(vals) => match packed_element_size(vals) {
+                Some(size) => vals.len() as u64 * size,
+                None => vals
+                    .iter()
+                    .fold(STRUCT_SIZE.get(), |acc, v| acc + v.abstract_memory_size()),
+            },
+            MoveValue::Struct(s) => s.abstract_memory_size(),
+            MoveValue::Variant { fields, .. } => fields
+                .iter()
+                .fold(STRUCT_SIZE.get(), |acc, v| acc + v.abstract_memory_size()),
+        }
+    }
+
+    /// `abstract_memory_size`, with vectors packed or not by their element type in `layout`
+    /// rather than by their first element, so that an empty vector of structs, vectors or
+    /// signers costs `STRUCT_SIZE` as in the VM. The parts of this value that do not match
+    /// `layout` are sized by `abstract_memory_size`.
+    pub fn abstract_memory_size_with_layout(&self, layout: &MoveTypeLayout) -> u64 {
+        match (self, layout) {
+            (MoveValue::Vector(vals), MoveTypeLayout::Vector(elem)) => {
+                match packed_layout_element_size(elem) {
+                    Some(size) => vals.len() as u64 * size,
+                    None => unpacked_size_with_layouts(vals.iter(), std::iter::repeat(&**elem)),
+                }
+            }
+            (MoveValue::Struct(s), MoveTypeLayout::Struct(l))
+                if s.field_values().count() == l.field_count() =>
+            {
+                unpacked_size_with_layouts(s.field_values(), l.field_layouts().into_iter())
+            }
+            (MoveValue::Variant { tag, fields, .. }, MoveTypeLayout::Enum(e)) => {
+                match e.variants().get(*tag as usize) {
+                    Some((_, layouts)) if layouts.len() == fields.len() => {
+                        unpacked_size_with_layouts(fields.iter(), layouts.iter().map(|f| &f.layout))
+                    }
+                    _ => self.abstract_memory_size(),
+                }
+            }
+            _ => self.abstract_memory_size(),
+        }
+    }
+
+    /// Look up the value at `path`, a `.`-separated list of segments that are either field names
+    /// (for decorated structs) or numeric indices (for struct fields in declaration order, enum
+    /// variant fields, and vector elements), e.g. `"balance.coin.value"` or `"holders.3.addr"`.
+    /// The empty path denotes `self`.
+    pub fn get_path(&self, path: &str) -> Option<&MoveValue> {
+        let mut current = self;
+        for segment in path_segments(path) {
+            current = current.child(segment).ok()?;
+        }
+        Some(current)
+    }
+
+    /// Replace the value at `path` (see `get_path`) with `new`. Unless `force` is set, `new` must
+    /// be the same kind of value (e.g. `U64`, `Vector`, `Struct`) as the value it replaces.
+    pub fn set_path(&mut self, path: &str, new: MoveValue, force: bool) -> AResult<()> {
+        let mut current = self;
+        for (i, segment) in path_segments(path).enumerate() {
+            current = current
+                .child_mut(segment)
+                .map_err(|reason| path_error(path, i, segment, reason))?;
+        }
+        if !force && mem::discriminant(current) != mem::discriminant(&new) {
+            bail!(
+                "cannot replace {} value at path `{}` with {} value",
+                current.kind(),
+                path,
+                new.kind()
+            )
+        }
+        *current = new;
+        Ok(())
+    }
+
+    /// The `u8` at `path` (see `get_path`). Fails, naming the path, if there is no value at
+    /// `path` or it is not a `u8`.
+    pub fn get_u8_at(&self, path: &str) -> AResult<u8> {
+        expect_u8(self.value_at(path)?, path)
+    }
+
+    /// The `u64` at `path`, see `get_u8_at`
+    pub fn get_u64_at(&self, path: &str) -> AResult<u64> {
+        expect_u64(self.value_at(path)?, path)
+    }
+
+    /// The `u128` at `path`, see `get_u8_at`
+    pub fn get_u128_at(&self, path: &str) -> AResult<u128> {
+        expect_u128(self.value_at(path)?, path)
+    }
+
+    /// The `bool` at `path`, see `get_u8_at`
+    pub fn get_bool_at(&self, path: &str) -> AResult<bool> {
+        expect_bool(self.value_at(path)?, path)
+    }
+
+    /// The address at `path`, see `get_u8_at`. Signers are not addresses.
+    pub fn get_address_at(&self, path: &str) -> AResult<AccountAddress> {
+        expect_address(self.value_at(path)?, path)
+    }
+
+    /// The contents of the `vector<u8>` at `path`, see `get_u8_at`
+    pub fn get_bytes_at(&self, path: &str) -> AResult<Vec<u8>> {
+        expect_bytes(self.value_at(path)?, path)
+    }
+
+    /// The struct at `path`, see `get_u8_at`
+    pub fn get_struct_at(&self, path: &str) -> AResult<&MoveStruct> {
+        expect_struct(self.value_at(path)?, path)
+    }
+
+    /// The structs in this value, itself included, whose types match `matcher`, in pre-order.
+    /// Only `WithTypes` structs have types, so other structs are never returned, though those
+    /// nested in them are.
+    pub fn find_structs(&self, matcher: &StructTagMatcher) -> Vec<&MoveStruct> {
+        let mut found = vec![];
+        collect_structs(self, matcher, &mut found);
+        found
+    }
+
+    /// The value at `path`, or an error naming the first segment that could not be followed
+    fn value_at(&self, path: &str) -> AResult<&MoveValue> {
+        let mut current = self;
+        for (i, segment) in path_segments(path).enumerate() {
+            current = current
+                .child(segment)
+                .map_err(|reason| path_error(path, i, segment, reason))?;
+        }
+        Ok(current)
+    }
+
+    fn child(&self, segment: &str) -> Result<&MoveValue, String> {
+        match self {
+            MoveValue::Struct(s) => s.field_at(segment),
+            MoveValue::Vector(vals) => Ok(&vals[vector_index(segment, vals.len())?]),
+            MoveValue::Variant { fields, .. } => Ok(&fields[variant_index(segment, fields.len())?]),
+            v => Err(format!("cannot descend into {} value", v.kind())),
+        }
+    }
+
+    fn child_mut(&mut self, segment: &str) -> Result<&mut MoveValue, String> {
+        match self {
+            MoveValue::Struct(s) => {
+                let pos = s.field_position(segment)?;
+                Ok(match s {
+                    MoveStruct::Runtime(vals) => &mut vals[pos],
+                    MoveStruct::WithFields(fields) | MoveStruct::WithTypes { fields, .. } => {
+                        &mut fields[pos].1
+                    }
+                })
+            }
+            MoveValue::Vector(vals) => {
+                let idx = vector_index(segment, vals.len())?;
+                Ok(&mut vals[idx])
+            }
+            MoveValue::Variant { fields, .. } => {
+                let idx = variant_index(segment, fields.len())?;
+                Ok(&mut fields[idx])
+            }
+            v => Err(format!("cannot descend into {} value", v.kind())),
+        }
+    }
+
+    /// Name of this value's variant, for error messages
+    fn kind(&self) -> &'static str {
+        match self {
+            MoveValue::U8(_) => "u8",
+            MoveValue::U64(_) => "u64",
+            MoveValue::U128(_) => "u128",
+            MoveValue::Bool(_) => "bool",
+            MoveValue::Address(_) => "address",
+            MoveValue::Vector(_) => "vector",
+            MoveValue::Struct(_) => "struct",
+            MoveValue::Signer(_) => "signer",
+            MoveValue::Variant { .. } => "enum",
+        }
+    }
+}
+
+/// Fail, naming the first duplicate, unless all `names` of the fields of `struct_` are distinct
+fn check_unique_fields<'a>(
+    names: impl IntoIterator<Item = &'a IdentStr>,
+    struct_: impl fmt::Display,
+) -> AResult<()> {
+    match first_duplicate(names) {
+        Some(name) => bail!("duplicate field `{}` in {}", name, struct_),
+        None => Ok(()),
+    }
+}
+
+fn first_duplicate<'a>(names: impl IntoIterator<Item = &'a IdentStr>) -> Option<&'a IdentStr> {
+    let mut seen = BTreeSet::new();
+    names.into_iter().find(|name| !seen.insert(*name))
+}
+
+fn path_error(path: &str, i: usize, segment: &str, reason: String) -> anyhow::Error {
+    anyhow!(
+        "invalid segment `{}` (#{}) of path `{}`: {}",
+        segment,
+        i,
+        path,
+        reason
+    )
+}
+
+fn type_mismatch(expected: &str, path: &str, found: &MoveValue) -> anyhow::Error {
+    let found = match found {
+        MoveValue::Vector(vals) => match vals.first() {
+            Some(elem) => format!("vector<{}>", elem.kind()),
+            None => "vector".to_string(),
+        },
+        v => v.kind().to_string(),
+    };
+    anyhow!("expected {} at path `{}`, found {}", expected, path, found)
+}
+
+fn expect_u8(value: &MoveValue, path: &str) -> AResult<u8> {
+    match value {
+        MoveValue::U8(v) => Ok(*v),
+        v => Err(type_mismatch("u8", path, v)),
+    }
+}
+
+fn expect_u64(value: &MoveValue, path: &str) -> AResult<u64> {
+    match value {
+        MoveValue::U64(v) => Ok(*v),
+        v => Err(type_mismatch("u64", path, v)),
+    }
+}
+
+fn expect_u128(value: &MoveValue, path: &str) -> AResult<u128> {
+    match value {
+        MoveValue::U128(v) => Ok(*v),
+        v => Err(type_mismatch("u128", path, v)),
+    }
+}
+
+fn expect_bool(value: &MoveValue, path: &str) -> AResult<bool> {
+    match value {
+        MoveValue::Bool(v) => Ok(*v),
+        v => Err(type_mismatch("bool", path, v)),
+    }
+}
+
+fn expect_address(value: &MoveValue, path: &str) -> AResult<AccountAddress> {
+    match value {
+        MoveValue::Address(a) => Ok(*a),
+        v => Err(type_mismatch("address", path, v)),
+    }
+}
+
+fn expect_bytes(value: &MoveValue, path: &str) -> AResult<Vec<u8>> {
+    let bytes = match value {
+        MoveValue::Vector(vals) => vals
+            .iter()
+            .map(|v| match v {
+                MoveValue::U8(b) => Some(*b),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    };
+    bytes.ok_or_else(|| type_mismatch("vector<u8>", path, value))
+}
+
+fn expect_struct<'a>(value: &'a MoveValue, path: &str) -> AResult<&'a MoveStruct> {
+    match value {
+        MoveValue::Struct(s) => Ok(s),
+        v => Err(type_mismatch("struct", path, v)),
+    }
+}
+
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    // `"".split('.')` yields a single empty segment, but the empty path denotes the root
+    path.split('.').filter(move |_| !path.is_empty())
+}
+
+fn variant_index(segment: &str, len: usize) -> Result<usize, String> {
+    let idx: usize = segment
+        .parse()
+        .map_err(|_| "expected a numeric index into the fields of an enum variant".to_string())?;
+    if idx >= len {
+        return Err(format!(
+            "index {} is out of bounds for a variant with {} fields",
+            idx, len
+        ));
+    }
+    Ok(idx)
+}
+
+fn vector_index(segment: &str, len: usize) -> Result<usize, String> {
+    let idx: usize = segment
+        .parse()
+        .map_err(|_| "expected a numeric index into a vector".to_string())?;
+    if idx >= len {
+        return Err(format!(
+            "index {} is out of bounds for a vector of length {}",
+            idx, len
+        ));
+    }
+    Ok(idx)
 }
 
 pub fn serialize_values<'a, I>(vals: I) -> Vec<Vec<u8>>
+where
+    I: IntoIterator<Item = &'a MoveValue>,
+{
+    try_serialize_values(vals).unwrap_or_else(|(idx, err)| {
+        panic!(
+            "serialization should succeed, but value #{} failed: {}",
+            idx, err
+        )
+    })
+}
+
+/// Fallible version of `serialize_values`. On failure, returns the index of the value that could
+/// not be serialized along with the BCS error.
+pub fn try_serialize_values<'a, I>(vals: I) -> Result<Vec<Vec<u8>>, (usize, bcs::Error)>
+where
+    I: IntoIterator<Item = &'a MoveValue>,
+{
+    vals.into_iter()
+        .enumerate()
+        .map(|(idx, val)| bcs::to_bytes(val).map_err(|err| (idx, err)))
+        .collect()
+}
+
+/// Serialize `vals` back to back into `buf`, returning the byte range of each value within it.
+/// Reusing `buf` across calls avoids the per-value allocations of `serialize_values`.
+pub fn serialize_values_into<'a, I>(vals: I, buf: &mut Vec<u8>) -> AResult<Vec<Range<usize>>>
 where
     I: IntoIterator<Item = &'a MoveValue>,
 {
     vals.into_iter()
         .map(|val| {
-            val.simple_serialize()
-                .expect("serialization should succeed")
+            let start = buf.len();
+            val.serialize_into(buf)?;
+            Ok(start..buf.len())
+        })
+        .collect()
+}
+
+/// Serialize `vals` into a single blob: the number of values followed by the BCS encoding of each,
+/// prefixed with its length, with counts and lengths as ULEB128. This is also the BCS encoding
+/// of the `Vec<Vec<u8>>` that `serialize_values` returns.
+pub fn serialize_values_packed<'a, I>(vals: I) -> AResult<Vec<u8>>
+where
+    I: IntoIterator<Item = &'a MoveValue>,
+{
+    let mut buf = vec![];
+    let ranges = serialize_values_into(vals, &mut buf)?;
+    let entries: Vec<&[u8]> = ranges.into_iter().map(|range| &buf[range]).collect();
+    Ok(bcs::to_bytes(&entries)?)
+}
+
+/// Inverse of `serialize_values_packed`, deserializing the `i`th value according to `layouts[i]`.
+/// Fails if `blob` does not hold exactly one value per layout.
+pub fn deserialize_values_packed(
+    blob: &[u8],
+    layouts: &[MoveTypeLayout],
+) -> AResult<Vec<MoveValue>> {
+    let entries: Vec<&[u8]> = bcs::from_bytes(blob).context("malformed packed values")?;
+    if entries.len() != layouts.len() {
+        bail!(
+            "packed blob holds {} values, but {} layouts were given",
+            entries.len(),
+            layouts.len()
+        )
+    }
+    entries
+        .into_iter()
+        .zip(layouts)
+        .enumerate()
+        .map(|(idx, (entry, layout))| {
+            MoveValue::simple_deserialize(entry, layout)
+                .with_context(|| format!("failed to deserialize value #{}", idx))
         })
         .collect()
 }
 
+/// Push the structs in `value` that match `matcher` onto `found`, see `MoveValue::find_structs`
+fn collect_structs<'a>(
+    value: &'a MoveValue,
+    matcher: &StructTagMatcher,
+    found: &mut Vec<&'a MoveStruct>,
+) {
+    match value {
+        MoveValue::Struct(s) => collect_structs_in_struct(s, matcher, found),
+        MoveValue::Vector(vals) | MoveValue::Variant { fields: vals, .. } => {
+            for v in vals {
+                collect_structs(v, matcher, found);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn collect_structs_in_struct<'a>(
+    s: &'a MoveStruct,
+    matcher: &StructTagMatcher,
+    found: &mut Vec<&'a MoveStruct>,
+) {
+    if let MoveStruct::WithTypes { type_, .. } = s {
+        if matcher.matches(type_) {
+            found.push(s);
+        }
+    }
+    for (_, field) in s.iter() {
+        collect_structs(field, matcher, found);
+    }
+}
+
 impl MoveStruct {
     pub fn new(value: Vec<MoveValue>) -> Self {
         Self::Runtime(value)
@@ -146,102 +908,1235 @@ impl MoveStruct {
         Self::WithFields(values)
     }
 
-    pub fn with_types(type_: StructTag, fields: Vec<(Identifier, MoveValue)>) -> Self {
-        Self::WithTypes { type_, fields }
+    pub fn with_types(
+        type_: impl Into<Arc<StructTag>>,
+        fields: Vec<(Identifier, MoveValue)>,
+    ) -> Self {
+        Self::WithTypes {
+            type_: type_.into(),
+            fields,
+        }
+    }
+
+    /// Like `with_fields`, but fails if two fields have the same name
+    pub fn try_with_fields(values: Vec<(Identifier, MoveValue)>) -> AResult<Self> {
+        check_unique_fields(values.iter().map(|(name, _)| name.as_ident_str()), "struct")?;
+        Ok(Self::WithFields(values))
+    }
+
+    /// Like `with_types`, but fails if two fields have the same name
+    pub fn try_with_types(
+        type_: impl Into<Arc<StructTag>>,
+        fields: Vec<(Identifier, MoveValue)>,
+    ) -> AResult<Self> {
+        let type_ = type_.into();
+        check_unique_fields(fields.iter().map(|(name, _)| name.as_ident_str()), &type_)?;
+        Ok(Self::WithTypes { type_, fields })
     }
 
     pub fn simple_deserialize(blob: &[u8], ty: &MoveStructLayout) -> AResult<Self> {
-        Ok(bcs::from_bytes_seed(ty, blob)?)
+        let state = DeserializerState::new(DeserializationLimits::default());
+        state
+            .start_blob(blob.len())
+            .map_err(|e| state.annotate(struct_layout_name(ty), e))?;
+        let seed = TrackedSeed {
+            layout: ty,
+            state: &state,
+        };
+        bcs::from_bytes_seed(seed, blob)
+            .map_err(|e| state.annotate(struct_layout_name(ty), e).into())
+    }
+
+    /// Append the BCS encoding of this struct to `buf`, see `MoveValue::serialize_into`
+    pub fn serialize_into(&self, buf: &mut Vec<u8>) -> AResult<()> {
+        Ok(bcs::serialize_into(buf, self)?)
     }
 
     pub fn decorate(self, layout: &MoveStructLayout) -> Self {
+        self.decorate_impl(layout, &mut TagSource::Fresh, None)
+    }
+
+    /// Like `decorate`, but the struct tags of decorated structs are taken from `registry`
+    pub fn decorate_interned(
+        self,
+        layout: &MoveStructLayout,
+        registry: &mut StructTagRegistry,
+    ) -> Self {
+        self.decorate_impl(layout, &mut TagSource::Registry(registry), None)
+    }
+
+    /// Like `decorate`, but the struct tags of decorated structs are taken from `cache` if there
+    /// is one
+    pub fn decorate_cached(self, layout: &MoveStructLayout, cache: Option<&TypeTagCache>) -> Self {
+        self.decorate_pooled(layout, cache, None)
+    }
+
+    /// Like `decorate_cached`, but the field names kept from this struct are also interned into
+    /// `pool` if there is one
+    pub fn decorate_pooled(
+        self,
+        layout: &MoveStructLayout,
+        cache: Option<&TypeTagCache>,
+        pool: Option<&IdentifierPool>,
+    ) -> Self {
+        match cache {
+            Some(cache) => self.decorate_impl(layout, &mut TagSource::Cache(cache), pool),
+            None => self.decorate_impl(layout, &mut TagSource::Fresh, pool),
+        }
+    }
+
+    fn decorate_impl(
+        self,
+        layout: &MoveStructLayout,
+        tags: &mut TagSource,
+        names: Option<&IdentifierPool>,
+    ) -> Self {
         match (self, layout) {
-            (MoveStruct::Runtime(vals), MoveStructLayout::WithFields(layouts)) => {
-                MoveStruct::WithFields(
-                    vals.into_iter()
-                        .zip(layouts)
-                        .map(|(v, l)| (l.name.clone(), v.decorate(&l.layout)))
+            (MoveStruct::Runtime(vals), MoveStructLayout::WithFields(layouts))
+            | (
+                MoveStruct::Runtime(vals),
+                MoveStructLayout::WithTypeTemplate {
+                    fields: layouts, ..
+                },
+            ) => MoveStruct::WithFields(
+                vals.into_iter()
+                    .zip(layouts)
+                    .map(|(v, l)| {
+                        let v = v.decorate_impl(&l.layout, tags, names);
+                        (l.name.clone(), v)
+                    })
+                    .collect(),
+            ),
+            (MoveStruct::Runtime(vals), MoveStructLayout::WithTypes { type_, fields }) => {
+                MoveStruct::WithTypes {
+                    type_: tags.intern(type_),
+                    fields: vals
+                        .into_iter()
+                        .zip(fields)
+                        .map(|(v, l)| {
+                            let v = v.decorate_impl(&l.layout, tags, names);
+                            (l.name.clone(), v)
+                        })
                         .collect(),
-                )
+                }
             }
-            (MoveStruct::Runtime(vals), MoveStructLayout::WithTypes { type_, fields }) => {
+            (MoveStruct::WithFields(vals), MoveStructLayout::WithTypes { type_, fields }) => {
                 MoveStruct::WithTypes {
-                    type_: type_.clone(),
+                    type_: tags.intern(type_),
                     fields: vals
                         .into_iter()
                         .zip(fields)
-                        .map(|(v, l)| (l.name.clone(), v.decorate(&l.layout)))
+                        .map(|((fld, v), l)| {
+                            let v = v.decorate_impl(&l.layout, tags, names);
+                            (intern_name(fld, names), v)
+                        })
                         .collect(),
                 }
             }
-            (MoveStruct::WithFields(vals), MoveStructLayout::WithTypes { type_, fields }) => {
-                MoveStruct::WithTypes {
-                    type_: type_.clone(),
-                    fields: vals
-                        .into_iter()
-                        .zip(fields)
-                        .map(|((fld, v), l)| (fld, v.decorate(&l.layout)))
-                        .collect(),
-                }
+            (v, _) => v, // already decorated
+        }
+    }
+
+    pub fn fields(&self) -> &[MoveValue] {
+        match self {
+            Self::Runtime(vals) => vals,
+            Self::WithFields(_) | Self::WithTypes { .. } => {
+                // It's not possible to implement this without changing the return type, and thus
+                // panicking is the best move
+                panic!("Getting fields for decorated representation")
+            }
+        }
+    }
+
+    pub fn into_fields(self) -> Vec<MoveValue> {
+        match self {
+            Self::Runtime(vals) => vals,
+            Self::WithFields(fields) | Self::WithTypes { fields, .. } => {
+                fields.into_iter().map(|(_, f)| f).collect()
+            }
+        }
+    }
+
+    pub fn with_serde_options(&self, options: SerdeOptions) -> WithSerdeOptions<'_, Self> {
+        WithSerdeOptions {
+            value: self,
+            options,
+        }
+    }
+
+    /// Returns the fields of this struct together with their names. `Runtime` structs have no
+    /// field names, so positional names (`field_0`, `field_1`, ...) are synthesized for them.
+    pub fn into_named_fields(self) -> Vec<(Identifier, MoveValue)> {
+        match self {
+            Self::Runtime(vals) => vals
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| (runtime_field_name(i), v))
+                .collect(),
+            Self::WithFields(fields) | Self::WithTypes { fields, .. } => fields,
+        }
+    }
+
+    /// Iterate over the fields of this struct in declaration order, along with their names for
+    /// decorated structs
+    pub fn iter(&self) -> Fields<'_> {
+        let (runtime, named) = self.split_fields();
+        Fields {
+            runtime: runtime.iter(),
+            named: named.iter(),
+        }
+    }
+
+    /// The structs in this struct, itself included, whose types match `matcher`, see
+    /// `MoveValue::find_structs`
+    pub fn find_structs(&self, matcher: &StructTagMatcher) -> Vec<&MoveStruct> {
+        let mut found = vec![];
+        collect_structs_in_struct(self, matcher, &mut found);
+        found
+    }
+
+    /// Borrowed counterpart of `into_named_fields`
+    pub fn named_fields_iter(&self) -> impl Iterator<Item = (Cow<'_, IdentStr>, &MoveValue)> {
+        let (runtime, named) = self.split_fields();
+        runtime
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (Cow::Owned(runtime_field_name(i)), v))
+            .chain(
+                named
+                    .iter()
+                    .map(|(name, v)| (Cow::Borrowed(name.as_ident_str()), v)),
+            )
+    }
+
+    /// Structural equality ignoring decoration, see `MoveValue::equals_modulo_decoration`
+    pub fn equals_modulo_decoration(&self, other: &MoveStruct) -> bool {
+        let (mut lhs, mut rhs) = (self.field_values(), other.field_values());
+        loop {
+            match (lhs.next(), rhs.next()) {
+                (Some(l), Some(r)) if l.equals_modulo_decoration(r) => (),
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+
+    /// `STRUCT_SIZE` plus the abstract memory sizes of the fields, see
+    /// `MoveValue::abstract_memory_size`. Field names do not count.
+    pub fn abstract_memory_size(&self) -> u64 {
+        self.iter().fold(STRUCT_SIZE.get(), |acc, (_, v)| {
+            acc + v.abstract_memory_size()
+        })
+    }
+
+    /// Maps this struct and every struct nested in it to the `Runtime` representation
+    pub fn canonicalize(self) -> Self {
+        MoveStruct::Runtime(
+            self.into_fields()
+                .into_iter()
+                .map(MoveValue::canonicalize)
+                .collect(),
+        )
+    }
+
+    /// The `u8` at `path`, which starts with a field of this struct, see `MoveValue::get_path`.
+    /// Fails, naming the path, if there is no value at `path` or it is not a `u8`.
+    pub fn get_u8_at(&self, path: &str) -> AResult<u8> {
+        expect_u8(self.value_at(path, "u8")?, path)
+    }
+
+    /// The `u64` at `path`, see `get_u8_at`
+    pub fn get_u64_at(&self, path: &str) -> AResult<u64> {
+        expect_u64(self.value_at(path, "u64")?, path)
+    }
+
+    /// The `u128` at `path`, see `get_u8_at`
+    pub fn get_u128_at(&self, path: &str) -> AResult<u128> {
+        expect_u128(self.value_at(path, "u128")?, path)
+    }
+
+    /// The `bool` at `path`, see `get_u8_at`
+    pub fn get_bool_at(&self, path: &str) -> AResult<bool> {
+        expect_bool(self.value_at(path, "bool")?, path)
+    }
+
+    /// The address at `path`, see `get_u8_at`. Signers are not addresses.
+    pub fn get_address_at(&self, path: &str) -> AResult<AccountAddress> {
+        expect_address(self.value_at(path, "address")?, path)
+    }
+
+    /// The contents of the `vector<u8>` at `path`, see `get_u8_at`
+    pub fn get_bytes_at(&self, path: &str) -> AResult<Vec<u8>> {
+        expect_bytes(self.value_at(path, "vector<u8>")?, path)
+    }
+
+    /// The struct at `path`, see `get_u8_at`. The empty path denotes `self`.
+    pub fn get_struct_at(&self, path: &str) -> AResult<&MoveStruct> {
+        if path.is_empty() {
+            return Ok(self);
+        }
+        expect_struct(self.value_at(path, "struct")?, path)
+    }
+
+    /// The value at `path`, which must not be empty as `self` is not a `MoveValue`. `expected`
+    /// names what the caller is looking for, for the error on the empty path.
+    fn value_at(&self, path: &str, expected: &str) -> AResult<&MoveValue> {
+        let mut segments = path_segments(path).enumerate();
+        let (i, segment) = segments
+            .next()
+            .ok_or_else(|| anyhow!("expected {} at path ``, found struct", expected))?;
+        let mut current = self
+            .field_at(segment)
+            .map_err(|reason| path_error(path, i, segment, reason))?;
+        for (i, segment) in segments {
+            current = current
+                .child(segment)
+                .map_err(|reason| path_error(path, i, segment, reason))?;
+        }
+        Ok(current)
+    }
+
+    fn field_at(&self, segment: &str) -> Result<&MoveValue, String> {
+        let pos = self.field_position(segment)?;
+        Ok(match self.split_fields() {
+            (vals, []) => &vals[pos],
+            (_, fields) => &fields[pos].1,
+        })
+    }
+
+    /// Resolve a path segment, either a field name or a numeric index, to a field position
+    fn field_position(&self, segment: &str) -> Result<usize, String> {
+        let (runtime, named) = self.split_fields();
+        let count = runtime.len() + named.len();
+        if let Ok(pos) = segment.parse::<usize>() {
+            return if pos < count {
+                Ok(pos)
+            } else {
+                Err(format!(
+                    "index {} is out of bounds for a struct with {} fields",
+                    pos, count
+                ))
+            };
+        }
+        if !runtime.is_empty() {
+            return Err(
+                "fields of an undecorated struct can only be accessed by index".to_string(),
+            );
+        }
+        named
+            .iter()
+            .position(|(name, _)| name.as_str() == segment)
+            .ok_or_else(|| format!("no field named `{}`", segment))
+    }
+
+    fn field_values(&self) -> impl Iterator<Item = &MoveValue> {
+        let (runtime, named) = self.split_fields();
+        runtime.iter().chain(named.iter().map(|(_, v)| v))
+    }
+
+    /// Exactly one of the two returned slices is non-empty, depending on the representation
+    fn split_fields(&self) -> (&[MoveValue], &[(Identifier, MoveValue)]) {
+        match self {
+            Self::Runtime(vals) => (vals, &[]),
+            Self::WithFields(fields) | Self::WithTypes { fields, .. } => (&[], fields),
+        }
+    }
+}
+
+impl IntoIterator for MoveStruct {
+    type Item = (Option<Identifier>, MoveValue);
+    type IntoIter = IntoFields;
+
+    /// Iterate over the fields of this struct in declaration order, along with their names for
+    /// decorated structs
+    fn into_iter(self) -> Self::IntoIter {
+        let (runtime, named) = match self {
+            Self::Runtime(vals) => (vals, vec![]),
+            Self::WithFields(fields) | Self::WithTypes { fields, .. } => (vec![], fields),
+        };
+        IntoFields {
+            runtime: runtime.into_iter(),
+            named: named.into_iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveStruct {
+    type Item = (Option<&'a IdentStr>, &'a MoveValue);
+    type IntoIter = Fields<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Owning iterator over the fields of a `MoveStruct`. At most one of the two inner iterators is
+/// non-empty, depending on the representation of the struct.
+pub struct IntoFields {
+    runtime: vec::IntoIter<MoveValue>,
+    named: vec::IntoIter<(Identifier, MoveValue)>,
+}
+
+impl Iterator for IntoFields {
+    type Item = (Option<Identifier>, MoveValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.runtime.next() {
+            Some(v) => Some((None, v)),
+            None => self.named.next().map(|(name, v)| (Some(name), v)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.runtime.len() + self.named.len();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for IntoFields {}
+
+/// Borrowing iterator over the fields of a `MoveStruct`, see `MoveStruct::iter`
+pub struct Fields<'a> {
+    runtime: slice::Iter<'a, MoveValue>,
+    named: slice::Iter<'a, (Identifier, MoveValue)>,
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = (Option<&'a IdentStr>, &'a MoveValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.runtime.next() {
+            Some(v) => Some((None, v)),
+            None => self
+                .named
+                .next()
+                .map(|(name, v)| (Some(name.as_ident_str()), v)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.runtime.len() + self.named.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for Fields<'a> {}
+
+/// The per-element size of `vals` if the VM stores them as a packed vector of primitives, which it
+/// does for all integer, boolean, and address vectors
+fn packed_element_size(vals: &[MoveValue]) -> Option<u64> {
+    let size = match vals.first() {
+        None => return Some(0),
+        Some(MoveValue::U8(_)) => VEC_U8_ELEMENT_SIZE,
+        Some(MoveValue::U64(_)) => VEC_U64_ELEMENT_SIZE,
+        Some(MoveValue::U128(_)) => VEC_U128_ELEMENT_SIZE,
+        Some(MoveValue::Bool(_)) => VEC_BOOL_ELEMENT_SIZE,
+        Some(MoveValue::Address(_)) => VEC_ADDRESS_ELEMENT_SIZE,
+        Some(_) => return None,
+    };
+    Some(size.get())
+}
+
+/// The size of the elements of a packed vector of `layout`s, or `None` if the VM keeps such a
+/// vector unpacked
+fn packed_layout_element_size(layout: &MoveTypeLayout) -> Option<u64> {
+    let size = match layout {
+        MoveTypeLayout::U8 => VEC_U8_ELEMENT_SIZE,
+        MoveTypeLayout::U64 => VEC_U64_ELEMENT_SIZE,
+        MoveTypeLayout::U128 => VEC_U128_ELEMENT_SIZE,
+        MoveTypeLayout::Bool => VEC_BOOL_ELEMENT_SIZE,
+        MoveTypeLayout::Address => VEC_ADDRESS_ELEMENT_SIZE,
+        _ => return None,
+    };
+    Some(size.get())
+}
+
+/// `STRUCT_SIZE` plus the sizes of `vals` with their `layouts`, see
+/// `MoveValue::abstract_memory_size_with_layout`
+fn unpacked_size_with_layouts<'a, 'b>(
+    vals: impl Iterator<Item = &'a MoveValue>,
+    layouts: impl Iterator<Item = &'b MoveTypeLayout>,
+) -> u64 {
+    vals.zip(layouts).fold(STRUCT_SIZE.get(), |acc, (v, l)| {
+        acc + v.abstract_memory_size_with_layout(l)
+    })
+}
+
+/// `name` from `pool` if there is one, see `decorate_pooled`
+fn intern_name(name: Identifier, pool: Option<&IdentifierPool>) -> Identifier {
+    match pool {
+        Some(pool) => pool.intern_owned(name),
+        None => name,
+    }
+}
+
+/// The name synthesized for the `i`th field of a `Runtime` struct
+fn runtime_field_name(i: usize) -> Identifier {
+    // `field_<i>` always satisfies the identifier rules
+    Identifier::new_unchecked(format!("field_{}", i))
+}
+
+impl MoveStructLayout {
+    pub fn new(types: Vec<MoveTypeLayout>) -> Self {
+        Self::Runtime(types)
+    }
+
+    pub fn with_fields(types: Vec<MoveFieldLayout>) -> Self {
+        Self::WithFields(types)
+    }
+
+    /// Like `with_fields`, but fails if two fields have the same name
+    pub fn try_with_fields(fields: Vec<MoveFieldLayout>) -> AResult<Self> {
+        check_unique_fields(fields.iter().map(|f| f.name.as_ident_str()), "struct")?;
+        Ok(Self::WithFields(fields))
+    }
+
+    /// Like `with_types`, but fails if two fields have the same name
+    pub fn try_with_types(type_: StructTag, fields: Vec<MoveFieldLayout>) -> AResult<Self> {
+        check_unique_fields(fields.iter().map(|f| f.name.as_ident_str()), &type_)?;
+        Ok(Self::with_types(type_, fields))
+    }
+
+    /// In debug builds, panics if the layout fails `validate_with` (allowing signers)
+    pub fn with_types(type_: StructTag, fields: Vec<MoveFieldLayout>) -> Self {
+        let layout = Self::WithTypes { type_, fields };
+        if cfg!(debug_assertions) {
+            if let Err(e) = layout.validate_with(&ValidationOptions {
+                allow_signers: true,
+            }) {
+                panic!("inconsistent struct layout: {}", e)
+            }
+        }
+        layout
+    }
+
+    pub fn fields(&self) -> &[MoveTypeLayout] {
+        match self {
+            Self::Runtime(vals) => vals,
+            Self::WithFields(_) | Self::WithTypes { .. } | Self::WithTypeTemplate { .. } => {
+                // It's not possible to implement this without changing the return type, and some
+                // performance-critical VM serialization code uses the Runtime case of this.
+                // panicking is the best move
+                panic!("Getting fields for decorated representation")
+            }
+        }
+    }
+
+    /// Owned counterpart of `field_layouts`
+    pub fn into_fields(self) -> Vec<MoveTypeLayout> {
+        let (runtime, decorated) = self.into_split_fields();
+        runtime
+            .into_iter()
+            .chain(decorated.into_iter().map(|f| f.layout))
+            .collect()
+    }
+
+    /// The layouts of the fields of this struct, in declaration order. Unlike `fields`, this
+    /// works for all representations.
+    pub fn field_layouts(&self) -> Vec<&MoveTypeLayout> {
+        let (runtime, decorated) = self.split_fields();
+        runtime
+            .iter()
+            .chain(decorated.iter().map(|f| &f.layout))
+            .collect()
+    }
+
+    /// The layout of the field called `name`, or `None` if there is no such field or this is a
+    /// `Runtime` layout without field names
+    pub fn field_by_name(&self, name: &IdentStr) -> Option<&MoveFieldLayout> {
+        let (_, decorated) = self.split_fields();
+        decorated.iter().find(|f| f.name.as_ident_str() == name)
+    }
+
+    /// The number of fields of this struct, for all representations
+    pub fn field_count(&self) -> usize {
+        let (runtime, decorated) = self.split_fields();
+        runtime.len() + decorated.len()
+    }
+
+    /// The depth of this layout tree, counting the struct itself as one level
+    pub fn depth(&self) -> usize {
+        self.measure().0
+    }
+
+    /// The number of nodes in this layout tree, including the struct itself
+    pub fn count_nodes(&self) -> usize {
+        self.measure().1
+    }
+
+    fn measure(&self) -> (usize, usize) {
+        let fields = self.field_layouts().into_iter().map(|l| (l, 2)).collect();
+        measure_layouts(fields, 1, 1)
+    }
+
+    /// Exactly one of the two returned slices is non-empty, depending on the representation
+    fn split_fields(&self) -> (&[MoveTypeLayout], &[MoveFieldLayout]) {
+        match self {
+            Self::Runtime(vals) => (vals, &[]),
+            Self::WithFields(fields)
+            | Self::WithTypes { fields, .. }
+            | Self::WithTypeTemplate { fields, .. } => (&[], fields),
+        }
+    }
+
+    /// Owned counterpart of `split_fields`
+    fn into_split_fields(self) -> (Vec<MoveTypeLayout>, Vec<MoveFieldLayout>) {
+        match self {
+            Self::Runtime(vals) => (vals, vec![]),
+            Self::WithFields(fields)
+            | Self::WithTypes { fields, .. }
+            | Self::WithTypeTemplate { fields, .. } => (vec![], fields),
+        }
+    }
+}
+
+/// Bounds on the size of the `MoveValue` produced when deserializing a blob. Since a single
+/// length prefix can announce a huge number of (possibly empty) elements, these limits protect
+/// against small untrusted blobs expanding into an excessive number of values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializationLimits {
+    /// Maximum number of value nodes in the result, counting every nested value
+    pub max_nodes: usize,
+    /// Maximum number of elements of any single vector
+    pub max_vector_length: usize,
+    /// Maximum length of the blob in bytes. Longer blobs are rejected before decoding starts.
+    pub max_blob_size: usize,
+}
+
+impl Default for DeserializationLimits {
+    /// Generous limits that are applied when no limits are given explicitly
+    fn default() -> Self {
+        Self {
+            max_nodes: 1 << 24,
+            max_vector_length: 1 << 24,
+            max_blob_size: 1 << 30,
+        }
+    }
+}
+
+/// The number of elements reserved up front for a vector whose length is announced when the size
+/// of the input is unknown
+const UNBOUNDED_PREALLOCATION: usize = 4096;
+
+/// A failure to deserialize a value, along with the position in the value at which it occurred
+#[derive(Debug)]
+pub struct DeserializationError {
+    path: String,
+    source: bcs::Error,
+    kind: ValueError,
+}
+
+impl DeserializationError {
+    /// The error for `source` at `path`, of the kind recorded as `failure` if a check of ours
+    /// failed, and otherwise of the kind of `source`
+    fn new(path: String, source: bcs::Error, failure: Option<Failure>) -> Self {
+        let kind = match (failure, &source) {
+            (Some(Failure::NodeLimit), _) => ValueError::NodeLimitExceeded,
+            (Some(Failure::LayoutMismatch), _) => ValueError::LayoutMismatch { path: path.clone() },
+            (None, bcs::Error::Eof) => ValueError::TruncatedInput,
+            (None, bcs::Error::RemainingInput) => ValueError::TrailingBytes,
+            (None, bcs::Error::ExpectedBoolean) => ValueError::InvalidBoolByte,
+            (None, bcs::Error::ExceededContainerDepthLimit(_)) => ValueError::DepthLimitExceeded,
+            (None, source) => ValueError::Custom(source.to_string()),
+        };
+        Self { path, source, kind }
+    }
+
+    /// What went wrong, to match on
+    pub fn kind(&self) -> &ValueError {
+        &self.kind
+    }
+
+    /// Where deserialization failed, e.g. `0x1::Account::Account.balance.coin.value (u64)`: the
+    /// root type, the field names and element indices leading to the value that was being
+    /// decoded, and that value's type
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The underlying BCS error
+    pub fn bcs_error(&self) -> &bcs::Error {
+        &self.source
+    }
+
+    pub fn into_bcs_error(self) -> bcs::Error {
+        self.source
+    }
+}
+
+impl fmt::Display for DeserializationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} while decoding {}", self.source, self.path)
+    }
+}
+
+impl std::error::Error for DeserializationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// The ways in which deserializing a value can fail, returned by `MoveValue::checked_deserialize`
+/// and available from a `DeserializationError` as its `kind`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    /// The blob ends before the value does
+    TruncatedInput,
+    /// The blob goes on after the value
+    TrailingBytes,
+    /// A `bool` is encoded as a byte other than 0 or 1
+    InvalidBoolByte,
+    /// Enums are nested deeper than BCS allows
+    DepthLimitExceeded,
+    /// The value has more nodes than `DeserializationLimits::max_nodes`
+    NodeLimitExceeded,
+    /// The blob does not encode a value of the layout at `path`, e.g. it has an enum tag that
+    /// names no variant
+    LayoutMismatch { path: String },
+    /// Any other failure, such as a vector longer than the limits allow
+    Custom(String),
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValueError::TruncatedInput => write!(f, "unexpected end of input"),
+            ValueError::TrailingBytes => write!(f, "input continues after the value"),
+            ValueError::InvalidBoolByte => write!(f, "expected a bool byte of 0 or 1"),
+            ValueError::DepthLimitExceeded => write!(f, "value exceeds the container depth limit"),
+            ValueError::NodeLimitExceeded => write!(f, "value exceeds the node limit"),
+            ValueError::LayoutMismatch { path } => {
+                write!(f, "input does not match the layout at {}", path)
+            }
+            ValueError::Custom(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl From<DeserializationError> for ValueError {
+    fn from(e: DeserializationError) -> Self {
+        e.kind
+    }
+}
+
+/// A failed check of ours during deserialization, which BCS only reports as a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    NodeLimit,
+    LayoutMismatch,
+}
+
+/// A short name for `layout` to use in error messages
+fn layout_name(layout: &MoveTypeLayout) -> String {
+    match layout {
+        MoveTypeLayout::Struct(s) => struct_layout_name(s),
+        MoveTypeLayout::Vector(elem) => format!("vector<{}>", layout_name(elem)),
+        MoveTypeLayout::Enum(_) => "enum".to_string(),
+        layout => layout.to_string(),
+    }
+}
+
+fn struct_layout_name(layout: &MoveStructLayout) -> String {
+    match layout {
+        MoveStructLayout::WithTypes { type_, .. } => type_.to_string(),
+        MoveStructLayout::WithTypeTemplate { type_, .. } => type_.to_string(),
+        MoveStructLayout::Runtime(_) | MoveStructLayout::WithFields(_) => "struct".to_string(),
+    }
+}
+
+enum PathSegment<'a> {
+    Field(&'a IdentStr),
+    Index(usize),
+}
+
+/// The state shared between all (nested) seeds and visitors of a single deserialization: the
+/// remaining node budget, and the path to the value currently being decoded. Path entries are
+/// only removed once a value was decoded successfully, so after a failure they point at the
+/// value that failed.
+struct DeserializerState<'a> {
+    limits: DeserializationLimits,
+    remaining: Cell<usize>,
+    /// The length of the blob being decoded, if it is known up front
+    blob_size: Cell<Option<usize>>,
+    path: RefCell<Vec<PathSegment<'a>>>,
+    layouts: RefCell<Vec<&'a MoveTypeLayout>>,
+    /// The check that failed, if deserialization failed in one of ours
+    failure: Cell<Option<Failure>>,
+}
+
+impl<'a> DeserializerState<'a> {
+    fn new(limits: DeserializationLimits) -> Self {
+        Self {
+            limits,
+            remaining: Cell::new(limits.max_nodes),
+            blob_size: Cell::new(None),
+            path: RefCell::new(vec![]),
+            layouts: RefCell::new(vec![]),
+            failure: Cell::new(None),
+        }
+    }
+
+    /// Prepare for deserializing another value within fresh limits, reusing the allocations
+    fn reset(&self) {
+        self.remaining.set(self.limits.max_nodes);
+        self.blob_size.set(None);
+        self.path.borrow_mut().clear();
+        self.layouts.borrow_mut().clear();
+        self.failure.set(None);
+    }
+
+    /// Check the length of the blob about to be decoded against the limits, and bound the
+    /// vectors that can be decoded from it
+    fn start_blob(&self, len: usize) -> Result<(), bcs::Error> {
+        if len > self.limits.max_blob_size {
+            return Err(bcs::Error::Custom(format!(
+                "blob of {} bytes exceeds the limit of {} bytes",
+                len, self.limits.max_blob_size
+            )));
+        }
+        self.blob_size.set(Some(len));
+        Ok(())
+    }
+
+    fn enter(&self, segment: PathSegment<'a>) {
+        self.path.borrow_mut().push(segment)
+    }
+
+    fn leave(&self) {
+        self.path.borrow_mut().pop();
+    }
+
+    /// Attach the current path to `error`, with the root of the path called `root`
+    fn annotate(&self, root: String, error: bcs::Error) -> DeserializationError {
+        let mut path = root;
+        for segment in self.path.borrow().iter() {
+            match segment {
+                PathSegment::Field(name) => path = format!("{}.{}", path, name),
+                PathSegment::Index(i) => path = format!("{}.{}", path, i),
+            }
+        }
+        if let Some(layout) = self.layouts.borrow().last() {
+            path = format!("{} ({})", path, layout_name(layout));
+        }
+        DeserializationError::new(path, error, self.failure.take())
+    }
+
+    /// The error `message` for a failure of the check `failure`
+    fn fail<E: DeError>(&self, failure: Failure, message: String) -> E {
+        self.failure.set(Some(failure));
+        E::custom(message)
+    }
+
+    fn charge_node<E: DeError>(&self) -> Result<(), E> {
+        match self.remaining.get().checked_sub(1) {
+            Some(remaining) => {
+                self.remaining.set(remaining);
+                Ok(())
+            }
+            None => Err(self.fail(
+                Failure::NodeLimit,
+                format!("value exceeds the limit of {} nodes", self.limits.max_nodes),
+            )),
+        }
+    }
+
+    fn check_vector_length<E: DeError>(&self, len: usize) -> Result<(), E> {
+        if len > self.limits.max_vector_length {
+            return Err(E::custom(format!(
+                "vector length {} exceeds the limit of {}",
+                len, self.limits.max_vector_length
+            )));
+        }
+        Ok(())
+    }
+
+    /// The number of elements to reserve for a vector of `elem`s whose length prefix announces
+    /// `len`: no more than the blob could hold, so that a lying prefix cannot make us allocate
+    /// more than a small multiple of the blob's size. As the position in the blob is not
+    /// tracked, the whole blob bounds every vector.
+    fn vector_capacity(&self, len: usize, elem: &MoveTypeLayout) -> usize {
+        let blob_size = match self.blob_size.get() {
+            Some(blob_size) => blob_size,
+            None => return len.min(UNBOUNDED_PREALLOCATION),
+        };
+        match min_encoded_size(elem) {
+            // nothing bounds the number of empty elements but `max_vector_length`, so let such
+            // vectors grow as their elements are decoded
+            0 => 0,
+            elem_size => len.min(blob_size / elem_size),
+        }
+    }
+}
+
+/// The length of the shortest BCS encoding of a value of `layout`
+fn min_encoded_size(layout: &MoveTypeLayout) -> usize {
+    match layout {
+        MoveTypeLayout::Bool | MoveTypeLayout::U8 => 1,
+        MoveTypeLayout::U64 => 8,
+        MoveTypeLayout::U128 => 16,
+        MoveTypeLayout::Address | MoveTypeLayout::Signer => AccountAddress::LENGTH,
+        // the length prefix of an empty vector, or the tag of an enum
+        MoveTypeLayout::Vector(_) => 1,
+        MoveTypeLayout::Enum(e) => {
+            1 + e
+                .variants()
+                .iter()
+                .map(|(_, fields)| fields.iter().map(|f| min_encoded_size(&f.layout)).sum())
+                .min()
+                .unwrap_or(0)
+        }
+        MoveTypeLayout::Struct(s) => {
+            let (runtime, named) = s.split_fields();
+            runtime.iter().map(min_encoded_size).sum::<usize>()
+                + named
+                    .iter()
+                    .map(|f| min_encoded_size(&f.layout))
+                    .sum::<usize>()
+        }
+        MoveTypeLayout::TypeParameter(_) => 0,
+    }
+}
+
+/// A layout used as a seed within a deserialization tracked by `state`
+struct TrackedSeed<'a, L: ?Sized> {
+    layout: &'a L,
+    state: &'a DeserializerState<'a>,
+}
+
+impl<'a, L: ?Sized> TrackedSeed<'a, L> {
+    fn nested<M: ?Sized>(&self, layout: &'a M) -> TrackedSeed<'a, M> {
+        TrackedSeed {
+            layout,
+            state: self.state,
+        }
+    }
+}
+
+impl MoveTypeLayout {
+    /// Whether this is the layout of a primitive type, see `TypeTag::is_primitive`
+    pub fn is_primitive(&self) -> bool {
+        use MoveTypeLayout::*;
+        match self {
+            Bool | U8 | U64 | U128 | Address => true,
+            Signer | Vector(_) | Struct(_) | Enum(_) | TypeParameter(_) => false,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        use MoveTypeLayout::*;
+        match self {
+            U8 | U64 | U128 => true,
+            Bool | Address | Signer | Vector(_) | Struct(_) | Enum(_) | TypeParameter(_) => false,
+        }
+    }
+
+    /// Whether a transaction may pass a value of this layout as an argument, by the rule of
+    /// `TypeTag::is_valid_txn_arg_type`. Structs and enums are allowed if `allow_structs` and all
+    /// their fields are valid arguments in turn, so that no signer can be forged inside them.
+    /// Type parameters are not valid; instantiate the layout first.
+    pub fn is_valid_txn_arg_type(&self, allow_structs: bool) -> bool {
+        use MoveTypeLayout::*;
+        let valid_fields = |fields: &[MoveFieldLayout]| {
+            fields
+                .iter()
+                .all(|f| f.layout.is_valid_txn_arg_type(allow_structs))
+        };
+        match self {
+            Bool | U8 | U64 | U128 | Address => true,
+            Vector(elem) => elem.is_valid_txn_arg_type(allow_structs),
+            Struct(s) => {
+                allow_structs
+                    && match s {
+                        MoveStructLayout::Runtime(fields) => fields
+                            .iter()
+                            .all(|l| l.is_valid_txn_arg_type(allow_structs)),
+                        MoveStructLayout::WithFields(fields)
+                        | MoveStructLayout::WithTypes { fields, .. } => valid_fields(fields),
+                        MoveStructLayout::WithTypeTemplate { .. } => false,
+                    }
+            }
+            Enum(e) => allow_structs && e.variants.iter().all(|(_, fields)| valid_fields(fields)),
+            Signer | TypeParameter(_) => false,
+        }
+    }
+
+    /// Whether a value of this layout may hold a signer anywhere within it. Type parameters hold
+    /// none; instantiate the layout first to look into the types they stand for.
+    pub fn contains_signer(&self) -> bool {
+        use MoveTypeLayout::*;
+        match self {
+            Signer => true,
+            Bool | U8 | U64 | U128 | Address | TypeParameter(_) => false,
+            Vector(elem) => elem.contains_signer(),
+            Struct(s) => s
+                .field_layouts()
+                .into_iter()
+                .any(MoveTypeLayout::contains_signer),
+            Enum(e) => e
+                .variants
+                .iter()
+                .any(|(_, fields)| fields.iter().any(|f| f.layout.contains_signer())),
+        }
+    }
+
+    /// The depth of this layout tree: 1 for primitive types, plus one level for every enclosing
+    /// vector, struct, or enum
+    pub fn depth(&self) -> usize {
+        measure_layouts(vec![(self, 1)], 0, 0).0
+    }
+
+    /// The number of nodes in this layout tree, where a struct or enum node has its field layouts
+    /// as children and a vector node has its element layout as child
+    pub fn count_nodes(&self) -> usize {
+        measure_layouts(vec![(self, 1)], 0, 0).1
+    }
+
+    /// The type tag of this layout with its type parameters left in place, e.g. to be
+    /// substituted later with `TypeTagTemplate::substitute`. Fails where converting to a
+    /// `TypeTag` fails for any other reason than a type parameter or a `WithTypeTemplate` struct.
+    pub fn type_tag_template(&self) -> AResult<TypeTagTemplate> {
+        Ok(match self {
+            MoveTypeLayout::TypeParameter(idx) => TypeTagTemplate::TypeParameter(*idx),
+            MoveTypeLayout::Vector(elem) => {
+                TypeTagTemplate::Vector(Box::new(elem.type_tag_template()?))
+            }
+            MoveTypeLayout::Struct(MoveStructLayout::WithTypeTemplate { type_, .. }) => {
+                TypeTagTemplate::Struct(type_.clone())
             }
-            (v, _) => v, // already decorated
-        }
+            layout => {
+                let ty: TypeTag = layout.try_into()?;
+                ty.into()
+            }
+        })
     }
 
-    pub fn fields(&self) -> &[MoveValue] {
+    /// Convert every struct layout in this layout to the `Runtime` representation, dropping field
+    /// names and struct tags, so that it can be used where only `Runtime` layouts are accepted
+    /// (e.g. `MoveStructLayout::fields`). Values deserialized with the stripped layout contain
+    /// `MoveStruct::Runtime` structs only. Enum layouts have no undecorated representation and
+    /// keep their variant and field names, but the layouts of their fields are stripped.
+    pub fn strip_decoration(self) -> MoveTypeLayout {
         match self {
-            Self::Runtime(vals) => vals,
-            Self::WithFields(_) | Self::WithTypes { .. } => {
-                // It's not possible to implement this without changing the return type, and thus
-                // panicking is the best move
-                panic!("Getting fields for decorated representation")
+            MoveTypeLayout::Vector(elem) => {
+                MoveTypeLayout::Vector(Box::new(elem.strip_decoration()))
             }
+            MoveTypeLayout::Struct(s) => MoveTypeLayout::Struct(s.strip_decoration()),
+            MoveTypeLayout::Enum(e) => MoveTypeLayout::Enum(MoveEnumLayout::new(
+                e.variants
+                    .into_iter()
+                    .map(|(name, fields)| (name, strip_field_decorations(fields)))
+                    .collect(),
+            )),
+            l => l,
         }
     }
 
-    pub fn into_fields(self) -> Vec<MoveValue> {
+    /// Whether every struct layout in this layout uses the `Runtime` representation, as is the
+    /// case after `strip_decoration`
+    pub fn is_runtime_only(&self) -> bool {
         match self {
-            Self::Runtime(vals) => vals,
-            Self::WithFields(fields) | Self::WithTypes { fields, .. } => {
-                fields.into_iter().map(|(_, f)| f).collect()
-            }
+            MoveTypeLayout::Vector(elem) => elem.is_runtime_only(),
+            MoveTypeLayout::Struct(s) => s.is_runtime_only(),
+            MoveTypeLayout::Enum(e) => e
+                .variants
+                .iter()
+                .flat_map(|(_, fields)| fields)
+                .all(|f| f.layout.is_runtime_only()),
+            _ => true,
         }
     }
+
+    /// Substitute `ty_args[n]` for every `TypeParameter(n)` in this layout, including the type
+    /// parameters in the tags of `WithTypeTemplate` layouts, which become `WithTypes` layouts once
+    /// no type parameters are left in them. Fails if a parameter is out of range, or if it occurs
+    /// in a tag and its type argument has none (see `type_tag_template`).
+    pub fn instantiate(&self, ty_args: &[MoveTypeLayout]) -> AResult<MoveTypeLayout> {
+        Ok(match self {
+            MoveTypeLayout::TypeParameter(idx) => match ty_args.get(*idx as usize) {
+                Some(ty_arg) => ty_arg.clone(),
+                None => bail!(
+                    "type parameter {} is out of range for {} type arguments",
+                    idx,
+                    ty_args.len()
+                ),
+            },
+            MoveTypeLayout::Vector(elem) => {
+                MoveTypeLayout::Vector(Box::new(elem.instantiate(ty_args)?))
+            }
+            MoveTypeLayout::Struct(s) => MoveTypeLayout::Struct(s.instantiate(ty_args)?),
+            MoveTypeLayout::Enum(e) => MoveTypeLayout::Enum(MoveEnumLayout::new(
+                e.variants
+                    .iter()
+                    .map(|(name, fields)| Ok((name.clone(), instantiate_fields(fields, ty_args)?)))
+                    .collect::<AResult<_>>()?,
+            )),
+            MoveTypeLayout::Bool
+            | MoveTypeLayout::U8
+            | MoveTypeLayout::U64
+            | MoveTypeLayout::U128
+            | MoveTypeLayout::Address
+            | MoveTypeLayout::Signer => self.clone(),
+        })
+    }
+}
+
+fn instantiate_fields(
+    fields: &[MoveFieldLayout],
+    ty_args: &[MoveTypeLayout],
+) -> AResult<Vec<MoveFieldLayout>> {
+    fields
+        .iter()
+        .map(|f| {
+            Ok(MoveFieldLayout::new(
+                f.name.clone(),
+                f.layout.instantiate(ty_args)?,
+            ))
+        })
+        .collect()
 }
 
 impl MoveStructLayout {
-    pub fn new(types: Vec<MoveTypeLayout>) -> Self {
-        Self::Runtime(types)
+    /// Convert this layout and every struct layout nested in it to the `Runtime` representation,
+    /// see `MoveTypeLayout::strip_decoration`
+    pub fn strip_decoration(self) -> MoveStructLayout {
+        MoveStructLayout::Runtime(
+            self.into_fields()
+                .into_iter()
+                .map(MoveTypeLayout::strip_decoration)
+                .collect(),
+        )
     }
 
-    pub fn with_fields(types: Vec<MoveFieldLayout>) -> Self {
-        Self::WithFields(types)
+    /// Whether this layout and every struct layout nested in it use the `Runtime`
+    /// representation
+    pub fn is_runtime_only(&self) -> bool {
+        match self {
+            MoveStructLayout::Runtime(fields) => fields.iter().all(MoveTypeLayout::is_runtime_only),
+            MoveStructLayout::WithFields(_)
+            | MoveStructLayout::WithTypes { .. }
+            | MoveStructLayout::WithTypeTemplate { .. } => false,
+        }
     }
 
-    pub fn with_types(type_: StructTag, fields: Vec<MoveFieldLayout>) -> Self {
-        Self::WithTypes { type_, fields }
+    /// Substitute `ty_args` for the type parameters of this layout, see
+    /// `MoveTypeLayout::instantiate`
+    pub fn instantiate(&self, ty_args: &[MoveTypeLayout]) -> AResult<MoveStructLayout> {
+        Ok(match self {
+            MoveStructLayout::Runtime(fields) => MoveStructLayout::Runtime(
+                fields
+                    .iter()
+                    .map(|l| l.instantiate(ty_args))
+                    .collect::<AResult<_>>()?,
+            ),
+            MoveStructLayout::WithFields(fields) => {
+                MoveStructLayout::WithFields(instantiate_fields(fields, ty_args)?)
+            }
+            MoveStructLayout::WithTypes { type_, fields } => MoveStructLayout::WithTypes {
+                type_: type_.clone(),
+                fields: instantiate_fields(fields, ty_args)?,
+            },
+            MoveStructLayout::WithTypeTemplate { type_, fields } => {
+                let type_ = instantiate_struct_tag(type_, ty_args)?;
+                let fields = instantiate_fields(fields, ty_args)?;
+                if type_.is_concrete() {
+                    MoveStructLayout::WithTypes {
+                        type_: type_.substitute(&[])?,
+                        fields,
+                    }
+                } else {
+                    MoveStructLayout::WithTypeTemplate { type_, fields }
+                }
+            }
+        })
     }
+}
 
-    pub fn fields(&self) -> &[MoveTypeLayout] {
-        match self {
-            Self::Runtime(vals) => vals,
-            Self::WithFields(_) | Self::WithTypes { .. } => {
-                // It's not possible to implement this without changing the return type, and some
-                // performance-critical VM serialization code uses the Runtime case of this.
-                // panicking is the best move
-                panic!("Getting fields for decorated representation")
-            }
+/// Substitute the tags of `ty_args` for the type parameters in `template`, see
+/// `MoveTypeLayout::instantiate`
+fn instantiate_tag(
+    template: &TypeTagTemplate,
+    ty_args: &[MoveTypeLayout],
+) -> AResult<TypeTagTemplate> {
+    Ok(match template {
+        TypeTagTemplate::TypeParameter(idx) => match ty_args.get(*idx as usize) {
+            Some(ty_arg) => ty_arg.type_tag_template()?,
+            None => bail!(
+                "type parameter {} is out of range for {} type arguments",
+                idx,
+                ty_args.len()
+            ),
+        },
+        TypeTagTemplate::Vector(elem) => {
+            TypeTagTemplate::Vector(Box::new(instantiate_tag(elem, ty_args)?))
         }
-    }
+        TypeTagTemplate::Struct(tag) => {
+            TypeTagTemplate::Struct(instantiate_struct_tag(tag, ty_args)?)
+        }
+        TypeTagTemplate::Bool
+        | TypeTagTemplate::U8
+        | TypeTagTemplate::U64
+        | TypeTagTemplate::U128
+        | TypeTagTemplate::Address
+        | TypeTagTemplate::Signer => template.clone(),
+    })
+}
 
-    pub fn into_fields(self) -> Vec<MoveTypeLayout> {
-        match self {
-            Self::Runtime(vals) => vals,
-            Self::WithFields(fields) | Self::WithTypes { fields, .. } => {
-                fields.into_iter().map(|f| f.layout).collect()
+fn instantiate_struct_tag(
+    template: &StructTagTemplate,
+    ty_args: &[MoveTypeLayout],
+) -> AResult<StructTagTemplate> {
+    Ok(StructTagTemplate {
+        address: template.address,
+        module: template.module.clone(),
+        name: template.name.clone(),
+        type_params: template
+            .type_params
+            .iter()
+            .map(|ty| instantiate_tag(ty, ty_args))
+            .collect::<AResult<_>>()?,
+    })
+}
+
+fn strip_field_decorations(fields: Vec<MoveFieldLayout>) -> Vec<MoveFieldLayout> {
+    fields
+        .into_iter()
+        .map(|f| MoveFieldLayout::new(f.name, f.layout.strip_decoration()))
+        .collect()
+}
+
+/// Walk the layout trees in `pending`, each paired with its depth, and return the maximum depth
+/// and total number of nodes on top of the given ones. Iterative, so that pathologically deep
+/// layouts can be measured before recursive code (such as deserialization) rejects them.
+fn measure_layouts(
+    mut pending: Vec<(&MoveTypeLayout, usize)>,
+    mut depth: usize,
+    mut nodes: usize,
+) -> (usize, usize) {
+    while let Some((layout, d)) = pending.pop() {
+        depth = depth.max(d);
+        nodes += 1;
+        match layout {
+            MoveTypeLayout::Vector(elem) => pending.push((elem, d + 1)),
+            MoveTypeLayout::Struct(s) => {
+                pending.extend(s.field_layouts().into_iter().map(|l| (l, d + 1)))
             }
+            MoveTypeLayout::Enum(e) => pending.extend(
+                e.variants
+                    .iter()
+                    .flat_map(|(_, fields)| fields.iter().map(|f| (&f.layout, d + 1))),
+            ),
+            MoveTypeLayout::Bool
+            | MoveTypeLayout::U8
+            | MoveTypeLayout::U64
+            | MoveTypeLayout::U128
+            | MoveTypeLayout::Address
+            | MoveTypeLayout::Signer
+            | MoveTypeLayout::TypeParameter(_) => (),
         }
     }
+    (depth, nodes)
 }
 
 impl<'d> serde::de::DeserializeSeed<'d> for &MoveTypeLayout {
@@ -250,26 +2145,186 @@ impl<'d> serde::de::DeserializeSeed<'d> for &MoveTypeLayout {
         self,
         deserializer: D,
     ) -> Result<Self::Value, D::Error> {
-        match self {
+        let state = DeserializerState::new(DeserializationLimits::default());
+        TrackedSeed {
+            layout: self,
+            state: &state,
+        }
+        .deserialize(deserializer)
+    }
+}
+
+impl<'d, 'a> serde::de::DeserializeSeed<'d> for TrackedSeed<'a, MoveTypeLayout> {
+    type Value = MoveValue;
+    fn deserialize<D: serde::de::Deserializer<'d>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        self.state.charge_node()?;
+        self.state.layouts.borrow_mut().push(self.layout);
+        let value = self.deserialize_layout(deserializer)?;
+        self.state.layouts.borrow_mut().pop();
+        Ok(value)
+    }
+}
+
+impl<'a> TrackedSeed<'a, MoveTypeLayout> {
+    fn deserialize_layout<'d, D: serde::de::Deserializer<'d>>(
+        &self,
+        deserializer: D,
+    ) -> Result<MoveValue, D::Error> {
+        match self.layout {
             MoveTypeLayout::Bool => bool::deserialize(deserializer).map(MoveValue::Bool),
             MoveTypeLayout::U8 => u8::deserialize(deserializer).map(MoveValue::U8),
+            MoveTypeLayout::U64 if deserializer.is_human_readable() => deserializer
+                .deserialize_any(UnsignedVisitor(PhantomData))
+                .map(MoveValue::U64),
             MoveTypeLayout::U64 => u64::deserialize(deserializer).map(MoveValue::U64),
+            MoveTypeLayout::U128 if deserializer.is_human_readable() => deserializer
+                .deserialize_any(UnsignedVisitor(PhantomData))
+                .map(MoveValue::U128),
             MoveTypeLayout::U128 => u128::deserialize(deserializer).map(MoveValue::U128),
             MoveTypeLayout::Address => {
                 AccountAddress::deserialize(deserializer).map(MoveValue::Address)
             }
+            MoveTypeLayout::Signer if deserializer.is_human_readable() => {
+                deserializer.deserialize_any(SignerVisitor)
+            }
             MoveTypeLayout::Signer => {
                 AccountAddress::deserialize(deserializer).map(MoveValue::Signer)
             }
-            MoveTypeLayout::Struct(ty) => Ok(MoveValue::Struct(ty.deserialize(deserializer)?)),
+            MoveTypeLayout::Struct(ty) => Ok(MoveValue::Struct(
+                self.nested(ty).deserialize(deserializer)?,
+            )),
             MoveTypeLayout::Vector(layout) => Ok(MoveValue::Vector(
-                deserializer.deserialize_seq(VectorElementVisitor(layout))?,
+                deserializer.deserialize_seq(VectorElementVisitor(self.nested(layout.as_ref())))?,
             )),
+            MoveTypeLayout::Enum(layout) => {
+                deserializer.deserialize_enum(MOVE_ENUM_NAME, &[], EnumVisitor(self.nested(layout)))
+            }
+            MoveTypeLayout::TypeParameter(idx) => Err(D::Error::custom(format!(
+                "cannot deserialize a value of uninstantiated type parameter T{}",
+                idx
+            ))),
+        }
+    }
+}
+
+/// Accepts an integer as a number or as a decimal string, the form written with
+/// `SerdeOptions::integers_as_strings`. Note that JSON numbers above `u64::MAX` are parsed as
+/// floats by `serde_json`, so larger `u128`s must be strings.
+struct UnsignedVisitor<T>(PhantomData<T>);
+
+impl<'d, T: TryFrom<u128> + FromStr> serde::de::Visitor<'d> for UnsignedVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an unsigned integer, or a string holding one")
+    }
+
+    fn visit_u64<E: DeError>(self, v: u64) -> Result<Self::Value, E> {
+        self.visit_u128(v.into())
+    }
+
+    fn visit_u128<E: DeError>(self, v: u128) -> Result<Self::Value, E> {
+        T::try_from(v)
+            .map_err(|_| E::invalid_value(Unexpected::Other("out of range integer"), &self))
+    }
+
+    fn visit_i64<E: DeError>(self, v: i64) -> Result<Self::Value, E> {
+        match u128::try_from(v) {
+            Ok(v) => self.visit_u128(v),
+            Err(_) => Err(E::invalid_value(Unexpected::Signed(v), &self)),
+        }
+    }
+
+    fn visit_str<E: DeError>(self, v: &str) -> Result<Self::Value, E> {
+        // `FromStr` of integers also accepts a leading `+`
+        let parsed = if v.bytes().all(|b| b.is_ascii_digit()) {
+            v.parse().ok()
+        } else {
+            None
+        };
+        parsed.ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+}
+
+/// Accepts the `{"signer": address}` form of a signer, as well as a bare address
+struct SignerVisitor;
+
+impl<'d> serde::de::Visitor<'d> for SignerVisitor {
+    type Value = MoveValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("Signer")
+    }
+
+    /// A bare address, in any of the forms `AccountAddress` takes in human-readable formats
+    fn visit_str<E: DeError>(self, v: &str) -> Result<Self::Value, E> {
+        AccountAddress::from_str(v)
+            .map(MoveValue::Signer)
+            .map_err(E::custom)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'d>,
+    {
+        let address = match map.next_key::<String>()? {
+            Some(key) if key == MOVE_SIGNER_NAME => map.next_value::<AccountAddress>()?,
+            Some(key) => return Err(A::Error::unknown_field(&key, &[MOVE_SIGNER_NAME])),
+            None => return Err(A::Error::missing_field(MOVE_SIGNER_NAME)),
+        };
+        if let Some(key) = map.next_key::<String>()? {
+            return Err(A::Error::unknown_field(&key, &[MOVE_SIGNER_NAME]));
         }
+        Ok(MoveValue::Signer(address))
+    }
+}
+
+struct EnumVisitor<'a>(TrackedSeed<'a, MoveEnumLayout>);
+
+impl<'d, 'a> serde::de::Visitor<'d> for EnumVisitor<'a> {
+    type Value = MoveValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("Enum")
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::EnumAccess<'d>,
+    {
+        use serde::de::VariantAccess;
+
+        let (tag, variant) = data.variant::<u32>()?;
+        let (name, layouts) = match self.0.layout.variants.get(tag as usize) {
+            Some(variant) => variant,
+            None => {
+                return Err(self.0.state.fail(
+                    Failure::LayoutMismatch,
+                    format!(
+                        "variant tag {} is out of range for an enum with {} variants",
+                        tag,
+                        self.0.layout.variants.len()
+                    ),
+                ))
+            }
+        };
+        let fields = variant.tuple_variant(
+            layouts.len(),
+            DecoratedStructFieldVisitor(self.0.nested(layouts.as_slice())),
+        )?;
+        Ok(MoveValue::Variant {
+            // `variants` is indexed by tag, so any valid tag fits
+            tag: tag as u16,
+            name: Some(name.clone()),
+            fields: fields.into_iter().map(|(_, v)| v).collect(),
+        })
     }
 }
 
-struct VectorElementVisitor<'a>(&'a MoveTypeLayout);
+struct VectorElementVisitor<'a>(TrackedSeed<'a, MoveTypeLayout>);
 
 impl<'d, 'a> serde::de::Visitor<'d> for VectorElementVisitor<'a> {
     type Value = Vec<MoveValue>;
@@ -282,15 +2337,30 @@ impl<'d, 'a> serde::de::Visitor<'d> for VectorElementVisitor<'a> {
     where
         A: serde::de::SeqAccess<'d>,
     {
-        let mut vals = Vec::new();
-        while let Some(elem) = seq.next_element_seed(self.0)? {
-            vals.push(elem)
+        let state = self.0.state;
+        // reject oversized vectors up front when the length is announced
+        let mut vals = match seq.size_hint() {
+            Some(len) => {
+                state.check_vector_length(len)?;
+                Vec::with_capacity(state.vector_capacity(len, self.0.layout))
+            }
+            None => Vec::new(),
+        };
+        loop {
+            state.enter(PathSegment::Index(vals.len()));
+            match seq.next_element_seed(self.0.nested(self.0.layout))? {
+                Some(elem) => vals.push(elem),
+                None => break,
+            }
+            state.leave();
+            state.check_vector_length(vals.len())?;
         }
+        state.leave();
         Ok(vals)
     }
 }
 
-struct DecoratedStructFieldVisitor<'a>(&'a [MoveFieldLayout]);
+struct DecoratedStructFieldVisitor<'a>(TrackedSeed<'a, [MoveFieldLayout]>);
 
 impl<'d, 'a> serde::de::Visitor<'d> for DecoratedStructFieldVisitor<'a> {
     type Value = Vec<(Identifier, MoveValue)>;
@@ -304,17 +2374,86 @@ impl<'d, 'a> serde::de::Visitor<'d> for DecoratedStructFieldVisitor<'a> {
         A: serde::de::SeqAccess<'d>,
     {
         let mut vals = Vec::new();
-        for (i, layout) in self.0.iter().enumerate() {
-            match seq.next_element_seed(layout)? {
+        for (i, layout) in self.0.layout.iter().enumerate() {
+            match seq.next_element_seed(self.0.nested(layout))? {
                 Some(elem) => vals.push(elem),
                 None => return Err(A::Error::invalid_length(i, &self)),
             }
         }
         Ok(vals)
     }
+
+    /// Fields keyed by name may appear in any order, but each field of the layout must appear
+    /// exactly once
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'d>,
+    {
+        let layout = self.0.layout;
+        let mut vals: Vec<Option<(Identifier, MoveValue)>> = layout.iter().map(|_| None).collect();
+        while let Some(i) = map.next_key_seed(FieldIndexSeed(layout))? {
+            if vals[i].is_some() {
+                return Err(A::Error::custom(format!(
+                    "duplicate field `{}`",
+                    layout[i].name
+                )));
+            }
+            vals[i] = Some(map.next_value_seed(self.0.nested(&layout[i]))?);
+        }
+        vals.into_iter()
+            .zip(layout)
+            .map(|(val, field)| {
+                val.ok_or_else(|| A::Error::custom(format!("missing field `{}`", field.name)))
+            })
+            .collect()
+    }
+}
+
+/// Deserializes the name of a field of a decorated struct to the index of the field with that name
+/// in the layout, so that looking up the field never allocates the name
+struct FieldIndexSeed<'a>(&'a [MoveFieldLayout]);
+
+impl<'d, 'a> serde::de::DeserializeSeed<'d> for FieldIndexSeed<'a> {
+    type Value = usize;
+
+    fn deserialize<D: serde::de::Deserializer<'d>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'d, 'a> serde::de::Visitor<'d> for FieldIndexSeed<'a> {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a field name")
+    }
+
+    fn visit_str<E: DeError>(self, key: &str) -> Result<Self::Value, E> {
+        self.0
+            .iter()
+            .position(|f| f.name.as_str() == key)
+            .ok_or_else(|| {
+                let expected: Vec<_> = self.0.iter().map(|f| f.name.as_str()).collect();
+                E::custom(format!(
+                    "unknown field `{}`, expected one of `{}`",
+                    key,
+                    expected.join("`, `")
+                ))
+            })
+    }
+
+    fn visit_bytes<E: DeError>(self, key: &[u8]) -> Result<Self::Value, E> {
+        match std::str::from_utf8(key) {
+            Ok(key) => self.visit_str(key),
+            Err(_) => Err(E::invalid_value(Unexpected::Bytes(key), &self)),
+        }
+    }
 }
 
-struct StructFieldVisitor<'a>(&'a [MoveTypeLayout]);
+struct StructFieldVisitor<'a>(TrackedSeed<'a, [MoveTypeLayout]>);
 
 impl<'d, 'a> serde::de::Visitor<'d> for StructFieldVisitor<'a> {
     type Value = Vec<MoveValue>;
@@ -328,11 +2467,13 @@ impl<'d, 'a> serde::de::Visitor<'d> for StructFieldVisitor<'a> {
         A: serde::de::SeqAccess<'d>,
     {
         let mut val = Vec::new();
-        for (i, field_type) in self.0.iter().enumerate() {
-            match seq.next_element_seed(field_type)? {
+        for (i, field_type) in self.0.layout.iter().enumerate() {
+            self.0.state.enter(PathSegment::Index(i));
+            match seq.next_element_seed(self.0.nested(field_type))? {
                 Some(elem) => val.push(elem),
                 None => return Err(A::Error::invalid_length(i, &self)),
             }
+            self.0.state.leave();
         }
         Ok(val)
     }
@@ -345,7 +2486,27 @@ impl<'d> serde::de::DeserializeSeed<'d> for &MoveFieldLayout {
         self,
         deserializer: D,
     ) -> Result<Self::Value, D::Error> {
-        Ok((self.name.clone(), self.layout.deserialize(deserializer)?))
+        let state = DeserializerState::new(DeserializationLimits::default());
+        TrackedSeed {
+            layout: self,
+            state: &state,
+        }
+        .deserialize(deserializer)
+    }
+}
+
+impl<'d, 'a> serde::de::DeserializeSeed<'d> for TrackedSeed<'a, MoveFieldLayout> {
+    type Value = (Identifier, MoveValue);
+
+    fn deserialize<D: serde::de::Deserializer<'d>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        self.state.enter(PathSegment::Field(&self.layout.name));
+        let value = self.nested(&self.layout.layout).deserialize(deserializer)?;
+        self.state.leave();
+        // `Identifier`s share their string, so this clone does not allocate
+        Ok((self.layout.name.clone(), value))
     }
 }
 
@@ -356,60 +2517,180 @@ impl<'d> serde::de::DeserializeSeed<'d> for &MoveStructLayout {
         self,
         deserializer: D,
     ) -> Result<Self::Value, D::Error> {
-        match self {
+        let state = DeserializerState::new(DeserializationLimits::default());
+        TrackedSeed {
+            layout: self,
+            state: &state,
+        }
+        .deserialize(deserializer)
+    }
+}
+
+impl<'d, 'a> serde::de::DeserializeSeed<'d> for TrackedSeed<'a, MoveStructLayout> {
+    type Value = MoveStruct;
+
+    fn deserialize<D: serde::de::Deserializer<'d>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        match self.layout {
             MoveStructLayout::Runtime(layout) => {
-                let fields =
-                    deserializer.deserialize_tuple(layout.len(), StructFieldVisitor(layout))?;
+                let fields = deserializer.deserialize_tuple(
+                    layout.len(),
+                    StructFieldVisitor(self.nested(layout.as_slice())),
+                )?;
                 Ok(MoveStruct::Runtime(fields))
             }
             MoveStructLayout::WithFields(layout) => {
-                let fields = deserializer
-                    .deserialize_tuple(layout.len(), DecoratedStructFieldVisitor(layout))?;
+                let visitor = DecoratedStructFieldVisitor(self.nested(layout.as_slice()));
+                // self-describing formats may present the fields as a map keyed by name
+                let fields = if deserializer.is_human_readable() {
+                    deserializer.deserialize_any(visitor)?
+                } else {
+                    deserializer.deserialize_tuple(layout.len(), visitor)?
+                };
                 Ok(MoveStruct::WithFields(fields))
             }
             MoveStructLayout::WithTypes {
                 type_,
                 fields: layout,
             } => {
-                let fields = deserializer
-                    .deserialize_tuple(layout.len(), DecoratedStructFieldVisitor(layout))?;
+                let fields = deserializer.deserialize_tuple(
+                    layout.len(),
+                    DecoratedStructFieldVisitor(self.nested(layout.as_slice())),
+                )?;
                 Ok(MoveStruct::WithTypes {
-                    type_: type_.clone(),
+                    type_: Arc::new(type_.clone()),
                     fields,
                 })
             }
+            MoveStructLayout::WithTypeTemplate { type_, .. } => Err(D::Error::custom(format!(
+                "cannot deserialize a value of uninstantiated generic struct {}",
+                type_
+            ))),
         }
     }
 }
 
+/// Options controlling the serde representation of decorated values. The `Default` options
+/// produce the same output as the `Serialize` impls of `MoveValue` and `MoveStruct`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SerdeOptions {
+    /// Serialize the type of a `WithTypes` struct as a nested `StructTag` (with `address`,
+    /// `module`, `name` and `type_args` fields) instead of its string form
+    pub structured_type: bool,
+    /// For human-readable formats only, serialize `u64` and `u128` values as decimal strings,
+    /// since e.g. JavaScript readers of JSON lose precision on numbers above 2^53. Deserializing
+    /// from human-readable formats accepts both forms regardless.
+    pub integers_as_strings: bool,
+}
+
+/// A `MoveValue` or `MoveStruct` paired with the `SerdeOptions` used to serialize it
+pub struct WithSerdeOptions<'a, T> {
+    value: &'a T,
+    options: SerdeOptions,
+}
+
 impl serde::Serialize for MoveValue {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        match self {
-            MoveValue::Struct(s) => s.serialize(serializer),
+        self.with_serde_options(SerdeOptions::default())
+            .serialize(serializer)
+    }
+}
+
+impl<'a> serde::Serialize for WithSerdeOptions<'a, MoveValue> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            MoveValue::Struct(s) => s.with_serde_options(self.options).serialize(serializer),
             MoveValue::Bool(b) => serializer.serialize_bool(*b),
             MoveValue::U8(i) => serializer.serialize_u8(*i),
+            MoveValue::U64(i) if self.integers_as_strings(&serializer) => serializer.collect_str(i),
             MoveValue::U64(i) => serializer.serialize_u64(*i),
+            MoveValue::U128(i) if self.integers_as_strings(&serializer) => {
+                serializer.collect_str(i)
+            }
             MoveValue::U128(i) => serializer.serialize_u128(*i),
             MoveValue::Address(a) => a.serialize(serializer),
+            MoveValue::Signer(a) if serializer.is_human_readable() => {
+                let mut t = serializer.serialize_struct(MOVE_SIGNER_NAME, 1)?;
+                t.serialize_field(MOVE_SIGNER_NAME, a)?;
+                t.end()
+            }
             MoveValue::Signer(a) => a.serialize(serializer),
             MoveValue::Vector(v) => {
                 let mut t = serializer.serialize_seq(Some(v.len()))?;
                 for val in v {
-                    t.serialize_element(val)?;
+                    t.serialize_element(&val.with_serde_options(self.options))?;
                 }
                 t.end()
             }
+            MoveValue::Variant { tag, name, fields } => {
+                serialize_variant(serializer, *tag, name.as_ref(), fields, self.options)
+            }
+        }
+    }
+}
+
+impl<'a> WithSerdeOptions<'a, MoveValue> {
+    fn integers_as_strings<S: serde::Serializer>(&self, serializer: &S) -> bool {
+        self.options.integers_as_strings && serializer.is_human_readable()
+    }
+}
+
+/// Kept out of line so that the recursion through `WithSerdeOptions<MoveValue>` stays cheap on the
+/// stack for deeply nested values
+#[inline(never)]
+fn serialize_variant<S: serde::Serializer>(
+    serializer: S,
+    tag: u16,
+    name: Option<&Identifier>,
+    fields: &[MoveValue],
+    options: SerdeOptions,
+) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        let mut t = serializer.serialize_struct(MOVE_ENUM_NAME, 2)?;
+        match name {
+            Some(name) => t.serialize_field(MOVE_ENUM_VARIANT, name)?,
+            None => t.serialize_field(MOVE_ENUM_VARIANT, &tag)?,
+        }
+        t.serialize_field(MOVE_STRUCT_FIELDS, &VariantFields(fields, options))?;
+        t.end()
+    } else {
+        // serde's variant names must be `&'static str`s, but binary formats such as BCS only
+        // encode the variant index
+        let mut t =
+            serializer.serialize_tuple_variant(MOVE_ENUM_NAME, u32::from(tag), "", fields.len())?;
+        for val in fields {
+            t.serialize_field(&val.with_serde_options(options))?;
+        }
+        t.end()
+    }
+}
+
+struct VariantFields<'a>(&'a [MoveValue], SerdeOptions);
+
+impl<'a> serde::Serialize for VariantFields<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut t = serializer.serialize_seq(Some(self.0.len()))?;
+        for val in self.0 {
+            t.serialize_element(&val.with_serde_options(self.1))?;
         }
+        t.end()
     }
 }
 
-struct MoveFields<'a>(&'a [(Identifier, MoveValue)]);
+struct MoveFields<'a>(&'a [(Identifier, MoveValue)], SerdeOptions);
 
 impl<'a> serde::Serialize for MoveFields<'a> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // A map with duplicate keys would silently lose all but one of the fields
+        debug_assert!(
+            first_duplicate(self.0.iter().map(|(f, _)| f.as_ident_str())).is_none(),
+            "serializing a struct with duplicate field names"
+        );
         let mut t = serializer.serialize_map(Some(self.0.len()))?;
         for (f, v) in self.0.iter() {
-            t.serialize_entry(f, v)?;
+            t.serialize_entry(f, &v.with_serde_options(self.1))?;
         }
         t.end()
     }
@@ -417,25 +2698,38 @@ impl<'a> serde::Serialize for MoveFields<'a> {
 
 impl serde::Serialize for MoveStruct {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        match self {
-            Self::Runtime(s) => {
+        self.with_serde_options(SerdeOptions::default())
+            .serialize(serializer)
+    }
+}
+
+impl<'a> serde::Serialize for WithSerdeOptions<'a, MoveStruct> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            MoveStruct::Runtime(s) => {
                 let mut t = serializer.serialize_tuple(s.len())?;
                 for v in s.iter() {
-                    t.serialize_element(v)?;
+                    t.serialize_element(&v.with_serde_options(self.options))?;
                 }
                 t.end()
             }
-            Self::WithFields(fields) => MoveFields(fields).serialize(serializer),
-            Self::WithTypes { type_, fields } => {
+            MoveStruct::WithFields(fields) => {
+                MoveFields(fields, self.options).serialize(serializer)
+            }
+            MoveStruct::WithTypes { type_, fields } => {
                 // Serialize a Move struct as Serde struct type named `struct `with two fields named `type` and `fields`.
                 // `fields` will get serialized as a Serde map.
                 // Unfortunately, we can't serialize this in the logical way: as a Serde struct named `type` with a field for
                 // each of `fields` because serde insists that struct and field names be `'static &str`'s
                 let mut t = serializer.serialize_struct(MOVE_STRUCT_NAME, 2)?;
-                // serialize type as string (e.g., 0x0::ModuleName::StructName<TypeArg1,TypeArg2>) instead of (e.g.
-                // { address: 0x0...0, module: ModuleName, name: StructName, type_args: [TypeArg1, TypeArg2]})
-                t.serialize_field(MOVE_STRUCT_TYPE, &type_.to_string())?;
-                t.serialize_field(MOVE_STRUCT_FIELDS, &MoveFields(fields))?;
+                if self.options.structured_type {
+                    t.serialize_field(MOVE_STRUCT_TYPE, &Structured(&**type_))?;
+                } else {
+                    // serialize type as string (e.g., 0x0::ModuleName::StructName<TypeArg1,TypeArg2>) instead of (e.g.
+                    // { address: 0x0...0, module: ModuleName, name: StructName, type_args: [TypeArg1, TypeArg2]})
+                    t.serialize_field(MOVE_STRUCT_TYPE, &type_.to_string())?;
+                }
+                t.serialize_field(MOVE_STRUCT_FIELDS, &MoveFields(fields, self.options))?;
                 t.end()
             }
         }
@@ -460,6 +2754,8 @@ impl fmt::Display for MoveTypeLayout {
             Vector(typ) => write!(f, "vector<{}>", typ),
             Struct(s) => write!(f, "{}", s),
             Signer => write!(f, "signer"),
+            Enum(e) => write!(f, "{}", e),
+            TypeParameter(idx) => write!(f, "T{}", idx),
         }
     }
 }
@@ -485,6 +2781,27 @@ impl fmt::Display for MoveStructLayout {
                     write!(f, "{}, ", field)?
                 }
             }
+            Self::WithTypeTemplate { type_, fields } => {
+                write!(f, "Type: {}", type_)?;
+                write!(f, "Fields:")?;
+                for field in fields {
+                    write!(f, "{}, ", field)?
+                }
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for MoveEnumLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::fmt::Result {
+        write!(f, "enum {{ ")?;
+        for (name, fields) in &self.variants {
+            write!(f, "{} {{ ", name)?;
+            for field in fields {
+                write!(f, "{}, ", field)?
+            }
+            write!(f, "}}, ")?
         }
         write!(f, "}}")
     }
@@ -506,6 +2823,14 @@ impl TryInto<TypeTag> for &MoveTypeLayout {
                 TypeTag::Vector(Box::new(inner_type.try_into()?))
             }
             MoveTypeLayout::Struct(v) => TypeTag::Struct(v.try_into()?),
+            MoveTypeLayout::Enum(_) => {
+                bail!("Invalid MoveTypeLayout -> TypeTag conversion--enums have no TypeTag")
+            }
+            MoveTypeLayout::TypeParameter(idx) => bail!(
+                "Invalid MoveTypeLayout -> TypeTag conversion--type parameter T{} names no type, \
+                 see type_tag_template",
+                idx
+            ),
         })
     }
 }
@@ -520,6 +2845,59 @@ impl TryInto<StructTag> for &MoveStructLayout {
                 "Invalid MoveTypeLayout -> StructTag conversion--needed MoveLayoutType::WithTypes"
             ),
             WithTypes { type_, .. } => Ok(type_.clone()),
+            WithTypeTemplate { type_, .. } => bail!(
+                "Invalid MoveTypeLayout -> StructTag conversion--generic struct {} names no type, \
+                 see type_tag_template",
+                type_
+            ),
+        }
+    }
+}
+
+impl TryFrom<MoveTypeLayout> for TypeTag {
+    type Error = anyhow::Error;
+
+    fn try_from(layout: MoveTypeLayout) -> Result<TypeTag, Self::Error> {
+        Ok(match layout {
+            MoveTypeLayout::Vector(v) => TypeTag::Vector(Box::new(TypeTag::try_from(*v)?)),
+            MoveTypeLayout::Struct(s) => TypeTag::Struct(s.try_into()?),
+            layout => (&layout).try_into()?,
+        })
+    }
+}
+
+impl TryFrom<MoveStructLayout> for StructTag {
+    type Error = anyhow::Error;
+
+    fn try_from(layout: MoveStructLayout) -> Result<StructTag, Self::Error> {
+        match layout {
+            MoveStructLayout::WithTypes { type_, .. } => Ok(type_),
+            layout => (&layout).try_into(),
         }
     }
 }
+
+/// Only types without structs can be converted, since the layout of a struct depends on its
+/// declaration
+impl TryFrom<&TypeTag> for MoveTypeLayout {
+    type Error = anyhow::Error;
+
+    fn try_from(ty: &TypeTag) -> Result<MoveTypeLayout, Self::Error> {
+        Ok(match ty {
+            TypeTag::Bool => MoveTypeLayout::Bool,
+            TypeTag::U8 => MoveTypeLayout::U8,
+            TypeTag::U64 => MoveTypeLayout::U64,
+            TypeTag::U128 => MoveTypeLayout::U128,
+            TypeTag::Address => MoveTypeLayout::Address,
+            TypeTag::Signer => MoveTypeLayout::Signer,
+            TypeTag::Vector(elem) => {
+                MoveTypeLayout::Vector(Box::new(MoveTypeLayout::try_from(elem.as_ref())?))
+            }
+            TypeTag::Struct(tag) => bail!(
+                "Invalid TypeTag -> MoveTypeLayout conversion--the layout of struct {} must be \
+                 resolved from its module, e.g. with `MoveValueAnnotator::get_type_layout_with_types`",
+                tag
+            ),
+        })
+    }
+}
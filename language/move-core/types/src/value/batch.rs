@@ -0,0 +1,107 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deserialization of many blobs sharing one layout.
+
+use crate::{
+    account_address::AccountAddress,
+    value::{
+        layout_name, DeserializationError, DeserializationLimits, DeserializerState,
+        MoveStructLayout, MoveTypeLayout, MoveValue, TrackedSeed,
+    },
+};
+use anyhow::{Context, Result as AResult};
+
+impl MoveValue {
+    /// Deserialize each of `blobs` according to `layout`, each within the default
+    /// `DeserializationLimits`. Equivalent to calling `simple_deserialize` on every blob, but the
+    /// layout is inspected and the scratch state allocated only once. Failures name the index of
+    /// the failing blob and wrap its `DeserializationError`.
+    pub fn simple_deserialize_many<'a>(
+        blobs: impl IntoIterator<Item = &'a [u8]>,
+        layout: &MoveTypeLayout,
+    ) -> AResult<Vec<MoveValue>> {
+        let driver = LayoutDriver::new(layout);
+        let state = DeserializerState::new(DeserializationLimits::default());
+        let blobs = blobs.into_iter();
+        // collecting `Result`s loses the size hint, so reserve up front
+        let mut values = Vec::with_capacity(blobs.size_hint().0);
+        for (i, blob) in blobs.enumerate() {
+            values.push(
+                driver
+                    .deserialize(&state, blob)
+                    .with_context(|| format!("failed to deserialize blob #{}", i))?,
+            );
+        }
+        Ok(values)
+    }
+}
+
+/// A layout prepared for deserializing many values
+struct LayoutDriver<'a> {
+    layout: &'a MoveTypeLayout,
+    /// The length of every encoding of `layout`, if they all have the same length
+    fixed_size: Option<usize>,
+}
+
+impl<'a> LayoutDriver<'a> {
+    fn new(layout: &'a MoveTypeLayout) -> Self {
+        Self {
+            layout,
+            fixed_size: fixed_size(layout),
+        }
+    }
+
+    /// Deserialize `blob`, reusing `state` from previous calls
+    fn deserialize<'s>(
+        &'s self,
+        state: &'s DeserializerState<'s>,
+        blob: &[u8],
+    ) -> Result<MoveValue, DeserializationError> {
+        // blobs of the wrong length cannot decode, so reject them without walking the layout
+        if let Some(size) = self.fixed_size {
+            if blob.len() < size {
+                return Err(self.error(bcs::Error::Eof));
+            }
+            if blob.len() > size {
+                return Err(self.error(bcs::Error::RemainingInput));
+            }
+        }
+        state.reset();
+        state
+            .start_blob(blob.len())
+            .map_err(|e| state.annotate(layout_name(self.layout), e))?;
+        let seed = TrackedSeed {
+            layout: self.layout,
+            state,
+        };
+        bcs::from_bytes_seed(seed, blob).map_err(|e| state.annotate(layout_name(self.layout), e))
+    }
+
+    fn error(&self, source: bcs::Error) -> DeserializationError {
+        DeserializationError::new(layout_name(self.layout), source, None)
+    }
+}
+
+/// The length of the BCS encoding of every value of `layout`, if it is the same for all of them
+fn fixed_size(layout: &MoveTypeLayout) -> Option<usize> {
+    match layout {
+        MoveTypeLayout::Bool | MoveTypeLayout::U8 => Some(1),
+        MoveTypeLayout::U64 => Some(8),
+        MoveTypeLayout::U128 => Some(16),
+        MoveTypeLayout::Address | MoveTypeLayout::Signer => Some(AccountAddress::LENGTH),
+        MoveTypeLayout::Struct(s) => match s {
+            MoveStructLayout::Runtime(fields) => fields.iter().map(fixed_size).sum(),
+            MoveStructLayout::WithFields(fields)
+            | MoveStructLayout::WithTypes { fields, .. }
+            | MoveStructLayout::WithTypeTemplate { fields, .. } => {
+                fields.iter().map(|f| fixed_size(&f.layout)).sum()
+            }
+        },
+        // vectors and enums are prefixed with their length or tag, and type parameters do not
+        // decode at all
+        MoveTypeLayout::Vector(_) | MoveTypeLayout::Enum(_) | MoveTypeLayout::TypeParameter(_) => {
+            None
+        }
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Values that borrow the contents of their byte vectors from the blob they were decoded from.
+
+use crate::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::StructTag,
+    value::{
+        layout_name, DeserializationLimits, DeserializerState, Failure, MoveEnumLayout,
+        MoveFieldLayout, MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue, PathSegment,
+        TrackedSeed, MOVE_ENUM_NAME,
+    },
+};
+use anyhow::Result as AResult;
+use serde::de::{DeserializeSeed, Error as DeError};
+use std::{fmt, sync::Arc};
+
+/// A `MoveValue` whose `vector<u8>`s borrow from the blob it was deserialized from, see
+/// `MoveValue::simple_deserialize_borrowed`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BorrowedMoveValue<'a> {
+    U8(u8),
+    U64(u64),
+    U128(u128),
+    Bool(bool),
+    Address(AccountAddress),
+    /// A `vector<u8>`
+    Bytes(&'a [u8]),
+    /// A vector of any element type other than `u8`
+    Vector(Vec<BorrowedMoveValue<'a>>),
+    Struct(BorrowedMoveStruct<'a>),
+    Signer(AccountAddress),
+    Variant {
+        tag: u16,
+        name: Option<Identifier>,
+        fields: Vec<BorrowedMoveValue<'a>>,
+    },
+}
+
+/// The `MoveStruct` counterpart of `BorrowedMoveValue`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BorrowedMoveStruct<'a> {
+    Runtime(Vec<BorrowedMoveValue<'a>>),
+    WithFields(Vec<(Identifier, BorrowedMoveValue<'a>)>),
+    WithTypes {
+        type_: StructTag,
+        fields: Vec<(Identifier, BorrowedMoveValue<'a>)>,
+    },
+}
+
+impl MoveValue {
+    /// Like `simple_deserialize`, but the contents of every `vector<u8>` are borrowed from `blob`
+    /// rather than copied
+    pub fn simple_deserialize_borrowed<'a>(
+        blob: &'a [u8],
+        ty: &MoveTypeLayout,
+    ) -> AResult<BorrowedMoveValue<'a>> {
+        let state = DeserializerState::new(DeserializationLimits::default());
+        state
+            .start_blob(blob.len())
+            .map_err(|e| state.annotate(layout_name(ty), e))?;
+        let seed = Borrowed(TrackedSeed {
+            layout: ty,
+            state: &state,
+        });
+        bcs::from_bytes_seed(seed, blob).map_err(|e| state.annotate(layout_name(ty), e).into())
+    }
+}
+
+impl<'a> BorrowedMoveValue<'a> {
+    /// Copy this value into a `MoveValue`, which is equal to the one that `simple_deserialize`
+    /// produces from the same blob
+    pub fn to_owned(&self) -> MoveValue {
+        match self {
+            BorrowedMoveValue::U8(v) => MoveValue::U8(*v),
+            BorrowedMoveValue::U64(v) => MoveValue::U64(*v),
+            BorrowedMoveValue::U128(v) => MoveValue::U128(*v),
+            BorrowedMoveValue::Bool(v) => MoveValue::Bool(*v),
+            BorrowedMoveValue::Address(a) => MoveValue::Address(*a),
+            BorrowedMoveValue::Bytes(bytes) => MoveValue::vector_u8(bytes.to_vec()),
+            BorrowedMoveValue::Vector(vals) => {
+                MoveValue::Vector(vals.iter().map(BorrowedMoveValue::to_owned).collect())
+            }
+            BorrowedMoveValue::Struct(s) => MoveValue::Struct(s.to_owned()),
+            BorrowedMoveValue::Signer(a) => MoveValue::Signer(*a),
+            BorrowedMoveValue::Variant { tag, name, fields } => MoveValue::Variant {
+                tag: *tag,
+                name: name.clone(),
+                fields: fields.iter().map(BorrowedMoveValue::to_owned).collect(),
+            },
+        }
+    }
+}
+
+impl<'a> BorrowedMoveStruct<'a> {
+    pub fn to_owned(&self) -> MoveStruct {
+        let named = |fields: &[(Identifier, BorrowedMoveValue)]| {
+            fields
+                .iter()
+                .map(|(name, v)| (name.clone(), v.to_owned()))
+                .collect()
+        };
+        match self {
+            BorrowedMoveStruct::Runtime(vals) => {
+                MoveStruct::Runtime(vals.iter().map(BorrowedMoveValue::to_owned).collect())
+            }
+            BorrowedMoveStruct::WithFields(fields) => MoveStruct::WithFields(named(fields)),
+            BorrowedMoveStruct::WithTypes { type_, fields } => MoveStruct::WithTypes {
+                type_: Arc::new(type_.clone()),
+                fields: named(fields),
+            },
+        }
+    }
+}
+
+/// A seed producing `BorrowedMoveValue`s, tracked like the seeds producing `MoveValue`s
+struct Borrowed<'a, L: ?Sized>(TrackedSeed<'a, L>);
+
+impl<'a, L: ?Sized> Borrowed<'a, L> {
+    fn nested<M: ?Sized>(&self, layout: &'a M) -> Borrowed<'a, M> {
+        Borrowed(self.0.nested(layout))
+    }
+}
+
+impl<'d, 'a> DeserializeSeed<'d> for Borrowed<'a, MoveTypeLayout> {
+    type Value = BorrowedMoveValue<'d>;
+
+    fn deserialize<D: serde::de::Deserializer<'d>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        use serde::Deserialize;
+
+        let state = self.0.state;
+        state.charge_node()?;
+        state.layouts.borrow_mut().push(self.0.layout);
+        let value = match self.0.layout {
+            MoveTypeLayout::Bool => BorrowedMoveValue::Bool(bool::deserialize(deserializer)?),
+            MoveTypeLayout::U8 => BorrowedMoveValue::U8(u8::deserialize(deserializer)?),
+            MoveTypeLayout::U64 => BorrowedMoveValue::U64(u64::deserialize(deserializer)?),
+            MoveTypeLayout::U128 => BorrowedMoveValue::U128(u128::deserialize(deserializer)?),
+            MoveTypeLayout::Address => {
+                BorrowedMoveValue::Address(AccountAddress::deserialize(deserializer)?)
+            }
+            MoveTypeLayout::Signer => {
+                BorrowedMoveValue::Signer(AccountAddress::deserialize(deserializer)?)
+            }
+            MoveTypeLayout::Vector(elem) if **elem == MoveTypeLayout::U8 => {
+                let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+                state.check_vector_length(bytes.len())?;
+                BorrowedMoveValue::Bytes(bytes)
+            }
+            MoveTypeLayout::Vector(elem) => BorrowedMoveValue::Vector(
+                deserializer.deserialize_seq(VectorElementVisitor(self.nested(elem.as_ref())))?,
+            ),
+            MoveTypeLayout::Struct(s) => {
+                BorrowedMoveValue::Struct(self.nested(s).deserialize(deserializer)?)
+            }
+            MoveTypeLayout::Enum(e) => {
+                deserializer.deserialize_enum(MOVE_ENUM_NAME, &[], EnumVisitor(self.nested(e)))?
+            }
+            MoveTypeLayout::TypeParameter(idx) => {
+                return Err(D::Error::custom(format!(
+                    "cannot deserialize a value of uninstantiated type parameter T{}",
+                    idx
+                )))
+            }
+        };
+        state.layouts.borrow_mut().pop();
+        Ok(value)
+    }
+}
+
+impl<'d, 'a> DeserializeSeed<'d> for Borrowed<'a, MoveStructLayout> {
+    type Value = BorrowedMoveStruct<'d>;
+
+    fn deserialize<D: serde::de::Deserializer<'d>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        Ok(match self.0.layout {
+            MoveStructLayout::Runtime(layouts) => {
+                let visitor = StructFieldVisitor(self.nested(layouts.as_slice()));
+                BorrowedMoveStruct::Runtime(deserializer.deserialize_tuple(layouts.len(), visitor)?)
+            }
+            MoveStructLayout::WithFields(layouts) => {
+                let visitor = DecoratedStructFieldVisitor(self.nested(layouts.as_slice()));
+                BorrowedMoveStruct::WithFields(
+                    deserializer.deserialize_tuple(layouts.len(), visitor)?,
+                )
+            }
+            MoveStructLayout::WithTypes {
+                type_,
+                fields: layouts,
+            } => {
+                let visitor = DecoratedStructFieldVisitor(self.nested(layouts.as_slice()));
+                BorrowedMoveStruct::WithTypes {
+                    type_: type_.clone(),
+                    fields: deserializer.deserialize_tuple(layouts.len(), visitor)?,
+                }
+            }
+            MoveStructLayout::WithTypeTemplate { type_, .. } => {
+                return Err(D::Error::custom(format!(
+                    "cannot deserialize a value of uninstantiated generic struct {}",
+                    type_
+                )))
+            }
+        })
+    }
+}
+
+struct BytesVisitor;
+
+impl<'d> serde::de::Visitor<'d> for BytesVisitor {
+    type Value = &'d [u8];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("borrowed bytes")
+    }
+
+    fn visit_borrowed_bytes<E: DeError>(self, v: &'d [u8]) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+}
+
+struct VectorElementVisitor<'a>(Borrowed<'a, MoveTypeLayout>);
+
+impl<'d, 'a> serde::de::Visitor<'d> for VectorElementVisitor<'a> {
+    type Value = Vec<BorrowedMoveValue<'d>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("Vector")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'d>,
+    {
+        let state = self.0 .0.state;
+        let mut vals = match seq.size_hint() {
+            Some(len) => {
+                state.check_vector_length(len)?;
+                Vec::with_capacity(state.vector_capacity(len, self.0 .0.layout))
+            }
+            None => Vec::new(),
+        };
+        loop {
+            state.enter(PathSegment::Index(vals.len()));
+            match seq.next_element_seed(self.0.nested(self.0 .0.layout))? {
+                Some(elem) => vals.push(elem),
+                None => break,
+            }
+            state.leave();
+            state.check_vector_length(vals.len())?;
+        }
+        state.leave();
+        Ok(vals)
+    }
+}
+
+struct StructFieldVisitor<'a>(Borrowed<'a, [MoveTypeLayout]>);
+
+impl<'d, 'a> serde::de::Visitor<'d> for StructFieldVisitor<'a> {
+    type Value = Vec<BorrowedMoveValue<'d>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("Struct")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'d>,
+    {
+        let state = self.0 .0.state;
+        let mut vals = Vec::new();
+        for (i, layout) in self.0 .0.layout.iter().enumerate() {
+            state.enter(PathSegment::Index(i));
+            match seq.next_element_seed(self.0.nested(layout))? {
+                Some(elem) => vals.push(elem),
+                None => return Err(A::Error::invalid_length(i, &self)),
+            }
+            state.leave();
+        }
+        Ok(vals)
+    }
+}
+
+struct DecoratedStructFieldVisitor<'a>(Borrowed<'a, [MoveFieldLayout]>);
+
+impl<'d, 'a> serde::de::Visitor<'d> for DecoratedStructFieldVisitor<'a> {
+    type Value = Vec<(Identifier, BorrowedMoveValue<'d>)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("Struct")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'d>,
+    {
+        let state = self.0 .0.state;
+        let mut vals = Vec::new();
+        for (i, field) in self.0 .0.layout.iter().enumerate() {
+            state.enter(PathSegment::Field(&field.name));
+            match seq.next_element_seed(self.0.nested(&field.layout))? {
+                Some(elem) => vals.push((field.name.clone(), elem)),
+                None => return Err(A::Error::invalid_length(i, &self)),
+            }
+            state.leave();
+        }
+        Ok(vals)
+    }
+}
+
+struct EnumVisitor<'a>(Borrowed<'a, MoveEnumLayout>);
+
+impl<'d, 'a> serde::de::Visitor<'d> for EnumVisitor<'a> {
+    type Value = BorrowedMoveValue<'d>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("Enum")
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::EnumAccess<'d>,
+    {
+        use serde::de::VariantAccess;
+
+        let (tag, variant) = data.variant::<u32>()?;
+        let variants = &self.0 .0.layout.variants;
+        let (name, layouts) = match variants.get(tag as usize) {
+            Some(variant) => variant,
+            None => {
+                return Err(self.0 .0.state.fail(
+                    Failure::LayoutMismatch,
+                    format!(
+                        "variant tag {} is out of range for an enum with {} variants",
+                        tag,
+                        variants.len()
+                    ),
+                ))
+            }
+        };
+        let fields = variant.tuple_variant(
+            layouts.len(),
+            DecoratedStructFieldVisitor(self.0.nested(layouts.as_slice())),
+        )?;
+        Ok(BorrowedMoveValue::Variant {
+            tag: tag as u16,
+            name: Some(name.clone()),
+            fields: fields.into_iter().map(|(_, v)| v).collect(),
+        })
+    }
+}
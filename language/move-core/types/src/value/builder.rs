@@ -0,0 +1,403 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fluent construction of structs and vectors, e.g. for test fixtures:
+//!
+//! ```
+//! use move_core_types::value::{MoveStruct, StructBuilder};
+//!
+//! let store: MoveStruct = StructBuilder::for_tag("0x1::M::Store".parse().unwrap())
+//!     .field("value", 10u64)
+//!     .field_vec("holders", vec![1u8, 2])
+//!     .nested("coin", |b| b.field("value", 5u64))
+//!     .build_unchecked();
+//! ```
+
+use crate::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::StructTag,
+    value::{
+        layout_name, struct_layout_name, MoveFieldLayout, MoveStruct, MoveStructLayout,
+        MoveTypeLayout, MoveValue,
+    },
+};
+use anyhow::Result as AResult;
+use std::fmt;
+
+/// Builds a `MoveStruct` field by field. Nested structs are built with `nested` and take their
+/// type from the layout given to `build_with`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StructBuilder {
+    tag: Option<StructTag>,
+    fields: Vec<(Identifier, MoveValue)>,
+}
+
+impl StructBuilder {
+    /// A builder for a struct without a tag, which `build_unchecked` turns into a
+    /// `MoveStruct::WithFields`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A builder for a struct of type `tag`, which `build_unchecked` turns into a
+    /// `MoveStruct::WithTypes`
+    pub fn for_tag(tag: StructTag) -> Self {
+        Self {
+            tag: Some(tag),
+            fields: vec![],
+        }
+    }
+
+    /// Set field `name` to `value`, replacing any earlier value of the field. Panics if `name` is
+    /// not a valid identifier.
+    pub fn field(mut self, name: &str, value: impl Into<MoveValue>) -> Self {
+        let name = Identifier::new(name).unwrap();
+        let value = value.into();
+        match self.fields.iter_mut().find(|(n, _)| n == &name) {
+            Some((_, old)) => *old = value,
+            None => self.fields.push((name, value)),
+        }
+        self
+    }
+
+    /// Set field `name` to a vector of `values`
+    pub fn field_vec<T: Into<MoveValue>>(
+        self,
+        name: &str,
+        values: impl IntoIterator<Item = T>,
+    ) -> Self {
+        self.field(name, VectorBuilder::new().extend(values))
+    }
+
+    /// Set field `name` to the struct built by `build`
+    pub fn nested(self, name: &str, build: impl FnOnce(StructBuilder) -> StructBuilder) -> Self {
+        self.field(name, build(StructBuilder::new()))
+    }
+
+    /// The struct, with its fields in the order they were first set
+    pub fn build_unchecked(self) -> MoveStruct {
+        match self.tag {
+            Some(tag) => MoveStruct::with_types(tag, self.fields),
+            None => MoveStruct::with_fields(self.fields),
+        }
+    }
+
+    /// The struct, with its fields in declaration order and decorated like `layout`. Fails with a
+    /// `BuildError` if a field is missing, unknown, or of the wrong type, or if the tag of the
+    /// builder or of a nested struct differs from that in `layout`.
+    pub fn build_with(self, layout: &MoveStructLayout) -> AResult<MoveStruct> {
+        let mut path = struct_layout_name(layout);
+        Ok(check_struct(&mut path, self.build_unchecked(), layout)?)
+    }
+}
+
+/// Builds a vector of `MoveValue`s element by element
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VectorBuilder {
+    elements: Vec<MoveValue>,
+}
+
+impl VectorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(mut self, value: impl Into<MoveValue>) -> Self {
+        self.elements.push(value.into());
+        self
+    }
+
+    /// Append the struct built by `build`
+    pub fn push_struct(self, build: impl FnOnce(StructBuilder) -> StructBuilder) -> Self {
+        self.push(build(StructBuilder::new()))
+    }
+
+    pub fn extend<T: Into<MoveValue>>(mut self, values: impl IntoIterator<Item = T>) -> Self {
+        self.elements.extend(values.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn build_unchecked(self) -> MoveValue {
+        MoveValue::Vector(self.elements)
+    }
+
+    /// The vector, checked against `layout`, the layout of a vector, and with its elements
+    /// decorated like it. See `StructBuilder::build_with`.
+    pub fn build_with(self, layout: &MoveTypeLayout) -> AResult<MoveValue> {
+        let mut path = layout_name(layout);
+        Ok(check_value(&mut path, self.build_unchecked(), layout)?)
+    }
+}
+
+/// The ways in which a built value can fail to match its layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildErrorKind {
+    /// A struct lacks this field of its layout
+    MissingField(Identifier),
+    /// A struct has this field, which its layout lacks
+    UnknownField(Identifier),
+    /// A struct has this tag, which differs from that of its layout
+    WrongTag(StructTag),
+    /// A value is not of the type named here, that of its layout
+    Mismatch(String),
+    /// A struct has named fields, but its layout has none
+    RuntimeLayout,
+}
+
+/// A value that does not match its layout, along with the path to the offending struct or value,
+/// e.g. `0x1::M::Store.coin.value`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildError {
+    path: String,
+    kind: BuildErrorKind,
+}
+
+impl BuildError {
+    fn new(path: &str, kind: BuildErrorKind) -> Self {
+        Self {
+            path: path.to_string(),
+            kind,
+        }
+    }
+
+    /// The path to the struct (for field errors) or value (otherwise) that does not match
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> &BuildErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            BuildErrorKind::MissingField(name) => {
+                write!(f, "missing field `{}` in {}", name, self.path)
+            }
+            BuildErrorKind::UnknownField(name) => {
+                write!(f, "unknown field `{}` in {}", name, self.path)
+            }
+            BuildErrorKind::WrongTag(tag) => write!(f, "{} has tag {}", self.path, tag),
+            BuildErrorKind::Mismatch(expected) => {
+                write!(f, "{} is not of type {}", self.path, expected)
+            }
+            BuildErrorKind::RuntimeLayout => {
+                write!(f, "{} has named fields, but its layout has none", self.path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Check `value`, which is at `path`, against `layout`, and decorate the structs in it like
+/// `layout`. `path` is restored on success.
+fn check_value(
+    path: &mut String,
+    value: MoveValue,
+    layout: &MoveTypeLayout,
+) -> Result<MoveValue, BuildError> {
+    Ok(match (value, layout) {
+        (v @ MoveValue::Bool(_), MoveTypeLayout::Bool)
+        | (v @ MoveValue::U8(_), MoveTypeLayout::U8)
+        | (v @ MoveValue::U64(_), MoveTypeLayout::U64)
+        | (v @ MoveValue::U128(_), MoveTypeLayout::U128)
+        | (v @ MoveValue::Address(_), MoveTypeLayout::Address)
+        | (v @ MoveValue::Signer(_), MoveTypeLayout::Signer) => v,
+        (MoveValue::Vector(elems), MoveTypeLayout::Vector(elem)) => {
+            let mut checked = Vec::with_capacity(elems.len());
+            for (i, v) in elems.into_iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!(".{}", i));
+                checked.push(check_value(path, v, elem)?);
+                path.truncate(len);
+            }
+            MoveValue::Vector(checked)
+        }
+        (MoveValue::Struct(s), MoveTypeLayout::Struct(l)) => {
+            MoveValue::Struct(check_struct(path, s, l)?)
+        }
+        (MoveValue::Variant { tag, name, fields }, MoveTypeLayout::Enum(l)) => {
+            let (variant, layouts) = match l.variants().get(tag as usize) {
+                Some((variant, layouts))
+                    if name.as_ref().map_or(true, |n| n == variant)
+                        && fields.len() == layouts.len() =>
+                {
+                    (variant, layouts)
+                }
+                _ => {
+                    let expected = layout_name(layout);
+                    return Err(BuildError::new(path, BuildErrorKind::Mismatch(expected)));
+                }
+            };
+            let len = path.len();
+            path.push('.');
+            path.push_str(variant.as_str());
+            let fields = check_fields(path, fields, layouts)?;
+            path.truncate(len);
+            MoveValue::Variant {
+                tag,
+                name: Some(variant.clone()),
+                fields,
+            }
+        }
+        _ => {
+            let expected = layout_name(layout);
+            return Err(BuildError::new(path, BuildErrorKind::Mismatch(expected)));
+        }
+    })
+}
+
+/// Check the values of the fields in `layouts`, which are in the same order, positionally
+fn check_fields(
+    path: &mut String,
+    values: Vec<MoveValue>,
+    layouts: &[MoveFieldLayout],
+) -> Result<Vec<MoveValue>, BuildError> {
+    values
+        .into_iter()
+        .zip(layouts)
+        .map(|(v, l)| {
+            let len = path.len();
+            path.push('.');
+            path.push_str(l.name.as_str());
+            let v = check_value(path, v, &l.layout)?;
+            path.truncate(len);
+            Ok(v)
+        })
+        .collect()
+}
+
+fn check_struct(
+    path: &mut String,
+    value: MoveStruct,
+    layout: &MoveStructLayout,
+) -> Result<MoveStruct, BuildError> {
+    let (mut named, layouts) = match (value, layout) {
+        (MoveStruct::Runtime(values), MoveStructLayout::Runtime(layouts))
+            if values.len() == layouts.len() =>
+        {
+            let mut checked = Vec::with_capacity(values.len());
+            for (i, (v, l)) in values.into_iter().zip(layouts).enumerate() {
+                let len = path.len();
+                path.push_str(&format!(".{}", i));
+                checked.push(check_value(path, v, l)?);
+                path.truncate(len);
+            }
+            return Ok(MoveStruct::Runtime(checked));
+        }
+        (MoveStruct::Runtime(_), _) => {
+            let expected = struct_layout_name(layout);
+            return Err(BuildError::new(path, BuildErrorKind::Mismatch(expected)));
+        }
+        (_, MoveStructLayout::Runtime(_)) => {
+            return Err(BuildError::new(path, BuildErrorKind::RuntimeLayout))
+        }
+        // no value is of an uninstantiated generic struct
+        (_, MoveStructLayout::WithTypeTemplate { .. }) => {
+            let expected = struct_layout_name(layout);
+            return Err(BuildError::new(path, BuildErrorKind::Mismatch(expected)));
+        }
+        (MoveStruct::WithTypes { type_, .. }, MoveStructLayout::WithTypes { type_: t, .. })
+            if *type_ != *t =>
+        {
+            let tag = StructTag::clone(&type_);
+            return Err(BuildError::new(path, BuildErrorKind::WrongTag(tag)));
+        }
+        (MoveStruct::WithFields(fields), MoveStructLayout::WithFields(layouts))
+        | (
+            MoveStruct::WithFields(fields),
+            MoveStructLayout::WithTypes {
+                fields: layouts, ..
+            },
+        )
+        | (MoveStruct::WithTypes { fields, .. }, MoveStructLayout::WithFields(layouts))
+        | (
+            MoveStruct::WithTypes { fields, .. },
+            MoveStructLayout::WithTypes {
+                fields: layouts, ..
+            },
+        ) => (fields, layouts),
+    };
+
+    let mut values = Vec::with_capacity(layouts.len());
+    for l in layouts {
+        match named.iter().position(|(name, _)| name == &l.name) {
+            Some(i) => values.push(named.remove(i).1),
+            None => {
+                let kind = BuildErrorKind::MissingField(l.name.clone());
+                return Err(BuildError::new(path, kind));
+            }
+        }
+    }
+    if let Some((name, _)) = named.into_iter().next() {
+        return Err(BuildError::new(path, BuildErrorKind::UnknownField(name)));
+    }
+
+    let fields = layouts
+        .iter()
+        .map(|l| l.name.clone())
+        .zip(check_fields(path, values, layouts)?)
+        .collect();
+    Ok(match layout {
+        MoveStructLayout::WithTypes { type_, .. } => MoveStruct::with_types(type_.clone(), fields),
+        _ => MoveStruct::with_fields(fields),
+    })
+}
+
+impl From<bool> for MoveValue {
+    fn from(b: bool) -> Self {
+        MoveValue::Bool(b)
+    }
+}
+
+impl From<u8> for MoveValue {
+    fn from(n: u8) -> Self {
+        MoveValue::U8(n)
+    }
+}
+
+impl From<u64> for MoveValue {
+    fn from(n: u64) -> Self {
+        MoveValue::U64(n)
+    }
+}
+
+impl From<u128> for MoveValue {
+    fn from(n: u128) -> Self {
+        MoveValue::U128(n)
+    }
+}
+
+impl From<AccountAddress> for MoveValue {
+    fn from(a: AccountAddress) -> Self {
+        MoveValue::Address(a)
+    }
+}
+
+impl From<MoveStruct> for MoveValue {
+    fn from(s: MoveStruct) -> Self {
+        MoveValue::Struct(s)
+    }
+}
+
+impl<T: Into<MoveValue>> From<Vec<T>> for MoveValue {
+    fn from(v: Vec<T>) -> Self {
+        MoveValue::Vector(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<StructBuilder> for MoveValue {
+    fn from(b: StructBuilder) -> Self {
+        MoveValue::Struct(b.build_unchecked())
+    }
+}
+
+impl From<VectorBuilder> for MoveValue {
+    fn from(b: VectorBuilder) -> Self {
+        b.build_unchecked()
+    }
+}
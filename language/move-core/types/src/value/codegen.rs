@@ -0,0 +1,308 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Generation of Rust type definitions whose BCS encoding matches a decorated struct layout,
+//! e.g. for off-chain services that read resources without going through `MoveValue`.
+
+use crate::{
+    identifier,
+    language_storage::{StructTag, TypeTag},
+    value::{struct_layout_name, MoveFieldLayout, MoveStructLayout, MoveTypeLayout},
+};
+use anyhow::{bail, Result as AResult};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+/// Options controlling `generate_rust`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodegenOptions {
+    /// The name of the root struct. Required for `WithFields` layouts, which carry no struct tag;
+    /// `WithTypes` layouts are named after their tag by default.
+    pub root_name: Option<String>,
+    /// Derives added to every generated type after `Serialize` and `Deserialize`, e.g. `Debug`
+    pub extra_derives: Vec<String>,
+    /// The path `AccountAddress` is imported from
+    pub crate_path: String,
+    /// Start the output with the `use` declarations the generated types need
+    pub imports: bool,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        Self {
+            root_name: None,
+            extra_derives: vec![],
+            crate_path: "move_core_types".to_string(),
+            imports: true,
+        }
+    }
+}
+
+/// Generate Rust definitions for `layout` and the structs and enums nested in it, with fields in
+/// declaration order so that their BCS encoding is that of the corresponding `MoveValue`.
+/// Addresses and signers become `AccountAddress`es and vectors become `Vec`s. Structs with the
+/// same tag share a definition named after the tag and its type arguments, e.g. `CoinXUS` for
+/// `Coin<0x1::XUS::XUS>`, while nested `WithFields` structs and enums are named after the
+/// field holding them. The root comes first, followed by the types it uses.
+///
+/// Fails on `Runtime` struct layouts, which have no field names, and on type parameters.
+pub fn generate_rust(layout: &MoveStructLayout, opts: &CodegenOptions) -> AResult<String> {
+    let mut gen = Generator {
+        opts,
+        names: RESERVED_TYPE_NAMES.iter().map(|n| n.to_string()).collect(),
+        by_tag: BTreeMap::new(),
+        items: vec![],
+    };
+    if opts.root_name.is_none() && matches!(layout, MoveStructLayout::WithFields(_)) {
+        bail!("cannot name the root struct: it has no struct tag and no root name was given");
+    }
+    gen.struct_(
+        &mut struct_layout_name(layout),
+        layout,
+        opts.root_name.clone(),
+    )?;
+
+    let mut out = String::new();
+    if opts.imports {
+        writeln!(
+            out,
+            "use {}::account_address::AccountAddress;",
+            opts.crate_path
+        )?;
+        writeln!(out, "use serde::{{Deserialize, Serialize}};")?;
+    }
+    for item in &gen.items {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(item);
+    }
+    Ok(out)
+}
+
+/// Names generated types must not take, as the generated code refers to them
+const RESERVED_TYPE_NAMES: &[&str] = &["AccountAddress", "Deserialize", "Serialize", "Vec"];
+
+/// Strict and reserved keywords of the 2018 edition, which need escaping as identifiers
+const KEYWORDS: &[&str] = &[
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+struct Generator<'a> {
+    opts: &'a CodegenOptions,
+    /// The names taken by generated (or referenced) types
+    names: BTreeSet<String>,
+    /// The names of the types generated for tagged structs
+    by_tag: BTreeMap<StructTag, String>,
+    /// The generated definitions, in output order
+    items: Vec<String>,
+}
+
+impl<'a> Generator<'a> {
+    /// Generate a definition for `layout`, which is at `path`, unless one exists already, and
+    /// return its name. `name` names the definition, and is required unless `layout` has a tag.
+    /// `path` is restored on success.
+    fn struct_(
+        &mut self,
+        path: &mut String,
+        layout: &MoveStructLayout,
+        name: Option<String>,
+    ) -> AResult<String> {
+        let (name, fields) = match layout {
+            MoveStructLayout::Runtime(_) => bail!(
+                "cannot generate a definition for {}: runtime struct layouts have no field names",
+                path
+            ),
+            MoveStructLayout::WithFields(fields) => (
+                self.fresh_name(&name.expect("untagged structs are named")),
+                fields,
+            ),
+            MoveStructLayout::WithTypes { type_, fields } => {
+                if let Some(name) = self.by_tag.get(type_) {
+                    return Ok(name.clone());
+                }
+                let name = self.fresh_name(&name.unwrap_or_else(|| struct_type_name(type_)));
+                self.by_tag.insert(type_.clone(), name.clone());
+                (name, fields)
+            }
+            MoveStructLayout::WithTypeTemplate { type_, .. } => bail!(
+                "cannot generate a type for {}: uninstantiated generic struct {}",
+                path,
+                type_
+            ),
+        };
+
+        // Reserve the slot so that the definition precedes those of its fields' types
+        let slot = self.items.len();
+        self.items.push(String::new());
+        let mut item = self.derives();
+        writeln!(item, "pub struct {} {{", name)?;
+        for field in fields {
+            let ty = self.field_type(path, &name, field)?;
+            self.field(&mut item, "    ", "pub ", field.name.as_str(), &ty)?;
+        }
+        item.push_str("}\n");
+        self.items[slot] = item;
+        Ok(name)
+    }
+
+    /// The Rust type of `field`, a field of the type `parent` at `path`
+    fn field_type(
+        &mut self,
+        path: &mut String,
+        parent: &str,
+        field: &MoveFieldLayout,
+    ) -> AResult<String> {
+        let len = path.len();
+        path.push('.');
+        path.push_str(field.name.as_str());
+        let hint = format!("{}{}", parent, camel_case(field.name.as_str()));
+        let ty = self.type_(path, &field.layout, hint)?;
+        path.truncate(len);
+        Ok(ty)
+    }
+
+    /// The Rust type of `layout`, which is at `path`. `hint` names the definition generated for
+    /// `layout` if it is an untagged struct or an enum.
+    fn type_(
+        &mut self,
+        path: &mut String,
+        layout: &MoveTypeLayout,
+        hint: String,
+    ) -> AResult<String> {
+        Ok(match layout {
+            MoveTypeLayout::Bool => "bool".to_string(),
+            MoveTypeLayout::U8 => "u8".to_string(),
+            MoveTypeLayout::U64 => "u64".to_string(),
+            MoveTypeLayout::U128 => "u128".to_string(),
+            MoveTypeLayout::Address | MoveTypeLayout::Signer => "AccountAddress".to_string(),
+            MoveTypeLayout::Vector(elem) => {
+                format!(
+                    "Vec<{}>",
+                    self.type_(path, elem, format!("{}Element", hint))?
+                )
+            }
+            MoveTypeLayout::Struct(s @ MoveStructLayout::WithTypes { .. }) => {
+                self.struct_(path, s, None)?
+            }
+            MoveTypeLayout::Struct(s) => self.struct_(path, s, Some(hint))?,
+            MoveTypeLayout::Enum(e) => {
+                let name = self.fresh_name(&hint);
+                let slot = self.items.len();
+                self.items.push(String::new());
+                let mut item = self.derives();
+                writeln!(item, "pub enum {} {{", name)?;
+                for (variant, fields) in e.variants() {
+                    let (ident, rename) = rust_ident(variant.as_str());
+                    if let Some(rename) = rename {
+                        writeln!(item, "    #[serde(rename = \"{}\")]", rename)?;
+                    }
+                    if fields.is_empty() {
+                        writeln!(item, "    {},", ident)?;
+                        continue;
+                    }
+                    writeln!(item, "    {} {{", ident)?;
+                    let len = path.len();
+                    path.push('.');
+                    path.push_str(variant.as_str());
+                    let variant_name = format!("{}{}", name, camel_case(variant.as_str()));
+                    for field in fields {
+                        let ty = self.field_type(path, &variant_name, field)?;
+                        self.field(&mut item, "        ", "", field.name.as_str(), &ty)?;
+                    }
+                    path.truncate(len);
+                    writeln!(item, "    }},")?;
+                }
+                item.push_str("}\n");
+                self.items[slot] = item;
+                name
+            }
+            MoveTypeLayout::TypeParameter(idx) => bail!(
+                "cannot generate a type for {}: uninstantiated type parameter {}",
+                path,
+                idx
+            ),
+        })
+    }
+
+    fn field(
+        &self,
+        item: &mut String,
+        indent: &str,
+        visibility: &str,
+        name: &str,
+        ty: &str,
+    ) -> AResult<()> {
+        let (ident, rename) = rust_ident(name);
+        if let Some(rename) = rename {
+            writeln!(item, "{}#[serde(rename = \"{}\")]", indent, rename)?;
+        }
+        writeln!(item, "{}{}{}: {},", indent, visibility, ident, ty)?;
+        Ok(())
+    }
+
+    fn derives(&self) -> String {
+        let mut derives = vec!["Serialize", "Deserialize"];
+        derives.extend(self.opts.extra_derives.iter().map(String::as_str));
+        format!("#[derive({})]\n", derives.join(", "))
+    }
+
+    /// `name`, or `name` followed by the smallest number above 1 that makes it unused
+    fn fresh_name(&mut self, name: &str) -> String {
+        let mut fresh = name.to_string();
+        let mut i = 2;
+        while KEYWORDS.contains(&fresh.as_str()) || !self.names.insert(fresh.clone()) {
+            fresh = format!("{}{}", name, i);
+            i += 1;
+        }
+        fresh
+    }
+}
+
+/// `name` as a Rust identifier, along with the name serde should use for it if that differs
+fn rust_ident(name: &str) -> (String, Option<&str>) {
+    match name {
+        // These cannot be raw identifiers
+        "Self" | "crate" | "self" | "super" => (format!("{}_", name), Some(name)),
+        name if KEYWORDS.contains(&name) => (format!("r#{}", name), None),
+        name => (name.to_string(), None),
+    }
+}
+
+/// The name of the type generated for `tag`: its name followed by those of its type arguments
+fn struct_type_name(tag: &StructTag) -> String {
+    let mut name = tag.name.to_string();
+    for ty in &tag.type_params {
+        name.push_str(&type_tag_name(ty));
+    }
+    name
+}
+
+fn type_tag_name(ty: &TypeTag) -> String {
+    match ty {
+        TypeTag::Bool => "Bool".to_string(),
+        TypeTag::U8 => "U8".to_string(),
+        TypeTag::U64 => "U64".to_string(),
+        TypeTag::U128 => "U128".to_string(),
+        TypeTag::Address => "Address".to_string(),
+        TypeTag::Signer => "Signer".to_string(),
+        TypeTag::Vector(elem) => format!("Vector{}", type_tag_name(elem)),
+        TypeTag::Struct(s) => struct_type_name(s),
+    }
+}
+
+/// `coin_store` as `CoinStore`, without leading underscores
+fn camel_case(name: &str) -> String {
+    let camel = identifier::to_upper_camel_case(name.trim_start_matches('_'));
+    if camel.is_empty() {
+        "Field".to_string()
+    } else {
+        camel
+    }
+}
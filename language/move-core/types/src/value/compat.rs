@@ -0,0 +1,271 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compatibility checks between the layouts of a struct before and after a module upgrade.
+
+use crate::{
+    identifier::{IdentStr, Identifier},
+    value::{layout_name, struct_layout_name, MoveFieldLayout, MoveStructLayout, MoveTypeLayout},
+};
+use std::fmt;
+
+/// A difference between two layouts that keeps the new layout from deserializing data written
+/// under the old one, or from giving it the same meaning. Each carries the path to the
+/// offending field, e.g. `0x1::Account::Account.balance.value`; vector elements are written as
+/// `[]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// The new layout has a field the old one lacks
+    FieldAdded { path: String },
+    /// The old layout has a field the new one lacks
+    FieldRemoved { path: String },
+    /// A field of the old layout is at another position in the new one
+    FieldMoved {
+        path: String,
+        from: usize,
+        to: usize,
+    },
+    /// The type of a field (or vector element) changed, e.g. from `u64` to `u128`
+    TypeChanged {
+        path: String,
+        old: String,
+        new: String,
+    },
+    /// The old layout of an enum has a variant the new one lacks
+    VariantRemoved { path: String },
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Incompatibility::FieldAdded { path } => write!(f, "field {} was added", path),
+            Incompatibility::FieldRemoved { path } => write!(f, "field {} was removed", path),
+            Incompatibility::FieldMoved { path, from, to } => write!(
+                f,
+                "field {} moved from position {} to position {}",
+                path, from, to
+            ),
+            Incompatibility::TypeChanged { path, old, new } => {
+                write!(f, "{} changed from {} to {}", path, old, new)
+            }
+            Incompatibility::VariantRemoved { path } => write!(f, "variant {} was removed", path),
+        }
+    }
+}
+
+/// A field or enum variant that has a new name but is otherwise unchanged, which does not affect
+/// the encoding of values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldRename {
+    /// The path to the field or variant under its new name
+    pub path: String,
+    pub old: Identifier,
+    pub new: Identifier,
+}
+
+/// The outcome of `MoveStructLayout::compatible_with`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompatibilityResult {
+    incompatibilities: Vec<Incompatibility>,
+    renames: Vec<FieldRename>,
+}
+
+impl CompatibilityResult {
+    /// Whether data written under the old layout can be read with the new one. Renames do not
+    /// affect compatibility.
+    pub fn is_compatible(&self) -> bool {
+        self.incompatibilities.is_empty()
+    }
+
+    pub fn incompatibilities(&self) -> &[Incompatibility] {
+        &self.incompatibilities
+    }
+
+    pub fn renames(&self) -> &[FieldRename] {
+        &self.renames
+    }
+
+    pub fn has_renames(&self) -> bool {
+        !self.renames.is_empty()
+    }
+}
+
+impl fmt::Display for CompatibilityResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_compatible() {
+            write!(f, "compatible")?;
+        } else {
+            write!(f, "incompatible")?;
+        }
+        for incompatibility in &self.incompatibilities {
+            write!(f, "\n  {}", incompatibility)?;
+        }
+        for rename in &self.renames {
+            write!(
+                f,
+                "\n  {} was renamed from `{}` to `{}`",
+                rename.path, rename.old, rename.new
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl MoveStructLayout {
+    /// Compare this layout with `old`, a previous layout of the same struct, and report whether
+    /// data written under `old` can be deserialized with `self`. That requires the same number of
+    /// fields, in the same order, with the same structure; nested structs are compared field by
+    /// field, and their tags are ignored. Enums may gain but not lose variants. Fields and
+    /// variants may be renamed, which the result records separately.
+    pub fn compatible_with(&self, old: &MoveStructLayout) -> CompatibilityResult {
+        let mut result = CompatibilityResult::default();
+        let mut path = struct_layout_name(self);
+        Checker {
+            result: &mut result,
+        }
+        .struct_(&mut path, old, self);
+        result
+    }
+}
+
+struct Checker<'a> {
+    result: &'a mut CompatibilityResult,
+}
+
+impl<'a> Checker<'a> {
+    /// Compare the struct layouts at `path`. `path` is restored on return.
+    fn struct_(&mut self, path: &mut String, old: &MoveStructLayout, new: &MoveStructLayout) {
+        self.fields(path, &struct_fields(old), &struct_fields(new))
+    }
+
+    fn fields(
+        &mut self,
+        path: &mut String,
+        old: &[(Option<&IdentStr>, &MoveTypeLayout)],
+        new: &[(Option<&IdentStr>, &MoveTypeLayout)],
+    ) {
+        for i in 0..old.len().max(new.len()) {
+            let len = path.len();
+            path.push('.');
+            match (old.get(i), new.get(i)) {
+                (Some((old_name, old_layout)), Some((new_name, new_layout))) => {
+                    push_field_name(path, *new_name, i);
+                    if let (Some(old_name), Some(new_name)) = (old_name, new_name) {
+                        if old_name != new_name {
+                            self.renamed_field(path, old, i, old_name, new_name);
+                        }
+                    }
+                    self.layout(path, old_layout, new_layout);
+                }
+                (None, Some((name, _))) => {
+                    push_field_name(path, *name, i);
+                    self.incompatible(Incompatibility::FieldAdded { path: path.clone() });
+                }
+                (Some((name, _)), None) => {
+                    push_field_name(path, *name, i);
+                    self.incompatible(Incompatibility::FieldRemoved { path: path.clone() });
+                }
+                (None, None) => unreachable!("`i` is below the length of one of the lists"),
+            }
+            path.truncate(len);
+        }
+    }
+
+    /// Record that field `to` of the old fields is called `new_name` rather than `old_name`:
+    /// either the field at `to` was renamed, or the old field called `new_name` moved to `to`
+    fn renamed_field(
+        &mut self,
+        path: &str,
+        old: &[(Option<&IdentStr>, &MoveTypeLayout)],
+        to: usize,
+        old_name: &IdentStr,
+        new_name: &IdentStr,
+    ) {
+        match old.iter().position(|(name, _)| *name == Some(new_name)) {
+            Some(from) => self.incompatible(Incompatibility::FieldMoved {
+                path: path.to_string(),
+                from,
+                to,
+            }),
+            None => self.result.renames.push(FieldRename {
+                path: path.to_string(),
+                old: old_name.to_owned(),
+                new: new_name.to_owned(),
+            }),
+        }
+    }
+
+    /// Compare the layouts of the field (or vector elements) at `path`
+    fn layout(&mut self, path: &mut String, old: &MoveTypeLayout, new: &MoveTypeLayout) {
+        match (old, new) {
+            (MoveTypeLayout::Vector(old), MoveTypeLayout::Vector(new)) => {
+                let len = path.len();
+                path.push_str("[]");
+                self.layout(path, old, new);
+                path.truncate(len);
+            }
+            (MoveTypeLayout::Struct(old), MoveTypeLayout::Struct(new)) => {
+                self.struct_(path, old, new)
+            }
+            (MoveTypeLayout::Enum(old), MoveTypeLayout::Enum(new)) => {
+                for (i, (old_name, old_fields)) in old.variants().iter().enumerate() {
+                    let len = path.len();
+                    path.push('.');
+                    match new.variants().get(i) {
+                        Some((new_name, new_fields)) => {
+                            path.push_str(new_name.as_str());
+                            if old_name != new_name {
+                                self.result.renames.push(FieldRename {
+                                    path: path.clone(),
+                                    old: old_name.clone(),
+                                    new: new_name.clone(),
+                                });
+                            }
+                            self.fields(path, &named_fields(old_fields), &named_fields(new_fields));
+                        }
+                        None => {
+                            path.push_str(old_name.as_str());
+                            self.incompatible(Incompatibility::VariantRemoved {
+                                path: path.clone(),
+                            });
+                        }
+                    }
+                    path.truncate(len);
+                }
+            }
+            (old, new) if old == new => (),
+            (old, new) => self.incompatible(Incompatibility::TypeChanged {
+                path: path.clone(),
+                old: layout_name(old),
+                new: layout_name(new),
+            }),
+        }
+    }
+
+    fn incompatible(&mut self, incompatibility: Incompatibility) {
+        self.result.incompatibilities.push(incompatibility)
+    }
+}
+
+fn struct_fields(layout: &MoveStructLayout) -> Vec<(Option<&IdentStr>, &MoveTypeLayout)> {
+    match layout {
+        MoveStructLayout::Runtime(layouts) => layouts.iter().map(|l| (None, l)).collect(),
+        MoveStructLayout::WithFields(fields)
+        | MoveStructLayout::WithTypes { fields, .. }
+        | MoveStructLayout::WithTypeTemplate { fields, .. } => named_fields(fields),
+    }
+}
+
+fn named_fields(fields: &[MoveFieldLayout]) -> Vec<(Option<&IdentStr>, &MoveTypeLayout)> {
+    fields
+        .iter()
+        .map(|f| (Some(f.name.as_ident_str()), &f.layout))
+        .collect()
+}
+
+fn push_field_name(path: &mut String, name: Option<&IdentStr>, i: usize) {
+    match name {
+        Some(name) => path.push_str(name.as_str()),
+        None => path.push_str(&i.to_string()),
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A conformance harness for other implementations of the BCS encoding of Move values.
+//!
+//! `manifest` enumerates edge-case values of the given layouts and records, for each, the layout
+//! (in the versioned encoding of `value::encoding`, hex-encoded), the JSON form of the value and
+//! its BCS encoding:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "entries": [
+//!     { "layout": "vector<u8>", "layout_encoding": "01000601", "value": [], "bcs": "00" }
+//!   ]
+//! }
+//! ```
+//!
+//! `layout` is for humans only. Values are written
+//! with `SerdeOptions::integers_as_strings`, as JSON numbers cannot hold every `u64`. Another
+//! implementation conforms if it decodes each `bcs` into `value` and encodes `value` back into
+//! `bcs`; `verify_manifest` checks this one does.
+
+use crate::{
+    account_address::AccountAddress,
+    value::{MoveStruct, MoveTypeLayout, MoveValue, SerdeOptions},
+};
+use anyhow::{anyhow, bail, ensure, Context, Result as AResult};
+use serde_json::{json, Value};
+
+/// The version of the manifest format written by `manifest`
+pub const MANIFEST_VERSION: u64 = 1;
+
+/// The number of elements of the long vectors among the edge cases, the smallest length whose
+/// BCS prefix takes two bytes
+const LONG_VECTOR_LENGTH: usize = 128;
+
+const SERDE_OPTIONS: SerdeOptions = SerdeOptions {
+    structured_type: false,
+    integers_as_strings: true,
+};
+
+/// A deterministic set of edge-case values of `layout`: the bounds of integers and addresses,
+/// empty, singleton and long vectors, and structs (and enum variants) whose fields go through
+/// the edge cases of their types together. Values of layouts with field names are decorated.
+/// Fails on layouts with type parameters.
+pub fn edge_cases(layout: &MoveTypeLayout) -> AResult<Vec<MoveValue>> {
+    Ok(match layout {
+        MoveTypeLayout::Bool => vec![MoveValue::Bool(false), MoveValue::Bool(true)],
+        MoveTypeLayout::U8 => vec![MoveValue::U8(0), MoveValue::U8(1), MoveValue::U8(u8::MAX)],
+        MoveTypeLayout::U64 => vec![
+            MoveValue::U64(0),
+            MoveValue::U64(1),
+            MoveValue::U64(u64::MAX),
+        ],
+        MoveTypeLayout::U128 => vec![
+            MoveValue::U128(0),
+            MoveValue::U128(1),
+            MoveValue::U128(u128::from(u64::MAX) + 1),
+            MoveValue::U128(u128::MAX),
+        ],
+        MoveTypeLayout::Address => addresses().into_iter().map(MoveValue::Address).collect(),
+        MoveTypeLayout::Signer => addresses().into_iter().map(MoveValue::Signer).collect(),
+        MoveTypeLayout::Vector(elem) => {
+            let elems = edge_cases(elem)?;
+            let mut cases = vec![
+                MoveValue::Vector(vec![]),
+                MoveValue::Vector(vec![elems[0].clone()]),
+            ];
+            if elems.len() > 1 {
+                cases.push(MoveValue::Vector(elems.clone()));
+            }
+            cases.push(MoveValue::Vector(vec![
+                elems[0].clone();
+                LONG_VECTOR_LENGTH
+            ]));
+            cases
+        }
+        MoveTypeLayout::Struct(s) => {
+            let fields = combine(
+                s.field_layouts()
+                    .into_iter()
+                    .map(edge_cases)
+                    .collect::<AResult<_>>()?,
+            );
+            fields
+                .into_iter()
+                .map(|vals| MoveValue::Struct(MoveStruct::Runtime(vals)).decorate(layout))
+                .collect()
+        }
+        MoveTypeLayout::Enum(e) => {
+            let mut cases = vec![];
+            for (tag, (_, fields)) in e.variants().iter().enumerate() {
+                let fields = fields
+                    .iter()
+                    .map(|f| edge_cases(&f.layout))
+                    .collect::<AResult<_>>()?;
+                for vals in combine(fields) {
+                    let variant = MoveValue::Variant {
+                        tag: tag as u16,
+                        name: None,
+                        fields: vals,
+                    };
+                    cases.push(variant.decorate(layout));
+                }
+            }
+            cases
+        }
+        MoveTypeLayout::TypeParameter(idx) => bail!(
+            "cannot enumerate values of uninstantiated type parameter T{}",
+            idx
+        ),
+    })
+}
+
+/// The edge-case values of each of `layouts`, and their encodings, as a manifest described in
+/// `value::conformance`
+pub fn manifest(layouts: &[MoveTypeLayout]) -> AResult<Value> {
+    let mut entries = vec![];
+    for layout in layouts {
+        for value in edge_cases(layout)? {
+            entries.push(json!({
+                "layout": layout.to_string(),
+                "layout_encoding": hex::encode(layout.to_bytes()),
+                "value": serde_json::to_value(value.with_serde_options(SERDE_OPTIONS))?,
+                "bcs": hex::encode(encode(value)?),
+            }));
+        }
+    }
+    Ok(json!({ "version": MANIFEST_VERSION, "entries": entries }))
+}
+
+/// Check that this implementation agrees with every entry of `manifest`: that each `bcs`
+/// decodes into `value`, which encodes back into `bcs`
+pub fn verify_manifest(manifest: &Value) -> AResult<()> {
+    let version = manifest["version"].as_u64();
+    ensure!(
+        version == Some(MANIFEST_VERSION),
+        "unsupported manifest version {:?}",
+        version
+    );
+    let entries = manifest["entries"]
+        .as_array()
+        .ok_or_else(|| anyhow!("manifest has no entries"))?;
+    for (i, entry) in entries.iter().enumerate() {
+        verify_entry(entry).with_context(|| format!("entry {} ({})", i, entry["layout"]))?;
+    }
+    Ok(())
+}
+
+fn verify_entry(entry: &Value) -> AResult<()> {
+    let hex_field = |name: &str| -> AResult<Vec<u8>> {
+        let hex = entry[name]
+            .as_str()
+            .ok_or_else(|| anyhow!("missing `{}`", name))?;
+        hex::decode(hex).with_context(|| format!("malformed `{}`", name))
+    };
+    let layout = MoveTypeLayout::from_bytes(&hex_field("layout_encoding")?)?;
+    let bcs = hex_field("bcs")?;
+    let json = &entry["value"];
+
+    let decoded = MoveValue::simple_deserialize(&bcs, &layout)?;
+    let decoded_json = serde_json::to_value(decoded.with_serde_options(SERDE_OPTIONS))?;
+    ensure!(
+        &decoded_json == json,
+        "`bcs` decodes into {}, not the expected value",
+        decoded_json
+    );
+    let encoded = encode(decoded)?;
+    ensure!(
+        encoded == bcs,
+        "`value` encodes into {}, not the expected `bcs`",
+        hex::encode(encoded)
+    );
+    Ok(())
+}
+
+/// The BCS encoding of `value`, which is that of its undecorated form
+fn encode(value: MoveValue) -> AResult<Vec<u8>> {
+    value
+        .canonicalize()
+        .simple_serialize()
+        .ok_or_else(|| anyhow!("cannot serialize value"))
+}
+
+fn addresses() -> Vec<AccountAddress> {
+    vec![
+        AccountAddress::ZERO,
+        AccountAddress::ONE,
+        AccountAddress::new([u8::MAX; AccountAddress::LENGTH]),
+    ]
+}
+
+/// Combinations of the given cases of each field, such that every case of every field occurs:
+/// the i-th combination takes the i-th case of each field, wrapping around shorter lists. A
+/// struct without fields has one value.
+fn combine(fields: Vec<Vec<MoveValue>>) -> Vec<Vec<MoveValue>> {
+    let count = fields.iter().map(Vec::len).max().unwrap_or(1);
+    (0..count)
+        .map(|i| {
+            fields
+                .iter()
+                .map(|cases| cases[i % cases.len()].clone())
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,252 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deserialization of Rust types straight from decoded values, without encoding them first.
+
+use crate::{
+    identifier::Identifier,
+    value::{MoveStruct, MoveValue, ValueError},
+};
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer, StrDeserializer, U32Deserializer},
+        DeserializeSeed, EnumAccess, Error as DeError, IntoDeserializer, Unexpected, VariantAccess,
+        Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+use std::fmt;
+
+/// A `Deserializer` reading a `MoveValue`, so that any `Deserialize` type whose shape matches the
+/// value can be read from it, see `MoveValue::deserialize_into`. Integers and booleans are read
+/// as such, addresses and signers as their hex strings (the human-readable form of
+/// `AccountAddress`), vectors as sequences, or as bytes for `serde_bytes` types, structs as
+/// described for `MoveStructDeserializer`, and enum values as the variant with their name, or
+/// with their tag if they have no name.
+#[derive(Debug, Clone, Copy)]
+pub struct MoveValueDeserializer<'a>(pub &'a MoveValue);
+
+/// A `Deserializer` reading a `MoveStruct`. Decorated structs are maps from field names to
+/// fields, so that fields are matched by name, and runtime structs are sequences of their
+/// fields, matched by position.
+#[derive(Debug, Clone, Copy)]
+pub struct MoveStructDeserializer<'a>(pub &'a MoveStruct);
+
+impl MoveValue {
+    /// Read this value into a `T`, see `MoveValueDeserializer`
+    pub fn deserialize_into<'a, T: Deserialize<'a>>(&'a self) -> Result<T, ValueError> {
+        T::deserialize(MoveValueDeserializer(self))
+    }
+}
+
+impl MoveStruct {
+    /// Read this struct into a `T`, see `MoveStructDeserializer`
+    pub fn deserialize_into<'a, T: Deserialize<'a>>(&'a self) -> Result<T, ValueError> {
+        T::deserialize(MoveStructDeserializer(self))
+    }
+}
+
+impl DeError for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ValueError::Custom(msg.to_string())
+    }
+}
+
+impl<'de> Deserializer<'de> for MoveValueDeserializer<'de> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0 {
+            MoveValue::U8(v) => visitor.visit_u8(*v),
+            MoveValue::U64(v) => visitor.visit_u64(*v),
+            MoveValue::U128(v) => visitor.visit_u128(*v),
+            MoveValue::Bool(v) => visitor.visit_bool(*v),
+            MoveValue::Address(a) | MoveValue::Signer(a) => visitor.visit_string(a.to_hex()),
+            MoveValue::Vector(vals) => visit_values(vals, visitor),
+            MoveValue::Struct(s) => MoveStructDeserializer(s).deserialize_any(visitor),
+            MoveValue::Variant { tag, name, fields } => visitor.visit_enum(VariantDeserializer {
+                tag: *tag,
+                name: name.as_ref(),
+                fields,
+            }),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        if let MoveValue::Vector(vals) = self.0 {
+            let bytes: Option<Vec<u8>> = vals
+                .iter()
+                .map(|v| match v {
+                    MoveValue::U8(b) => Some(*b),
+                    _ => None,
+                })
+                .collect();
+            if let Some(bytes) = bytes {
+                return visitor.visit_byte_buf(bytes);
+            }
+        }
+        self.deserialize_any(visitor)
+    }
+
+    /// Move has no optional values, so every value is present
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        match self.0 {
+            MoveValue::Struct(s) => {
+                MoveStructDeserializer(s).deserialize_newtype_struct(name, visitor)
+            }
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string unit unit_struct seq
+        tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ValueError> for MoveValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for MoveStructDeserializer<'de> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0 {
+            MoveStruct::Runtime(vals) => visit_values(vals, visitor),
+            MoveStruct::WithFields(fields) | MoveStruct::WithTypes { fields, .. } => {
+                let mut map = MapDeserializer::new(
+                    fields
+                        .iter()
+                        .map(|(name, v)| (name.as_str(), MoveValueDeserializer(v))),
+                );
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// A Rust newtype struct stands for a Move struct with a single field, which is read by
+    /// position whether or not the struct is decorated
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        match self.0 {
+            MoveStruct::Runtime(vals) => visit_values(vals, visitor),
+            MoveStruct::WithFields(fields) | MoveStruct::WithTypes { fields, .. } => {
+                let mut seq =
+                    SeqDeserializer::new(fields.iter().map(|(_, v)| MoveValueDeserializer(v)));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Visit `vals` as a sequence, failing unless `visitor` takes all of them
+fn visit_values<'de, V: Visitor<'de>>(
+    vals: &'de [MoveValue],
+    visitor: V,
+) -> Result<V::Value, ValueError> {
+    let mut seq = SeqDeserializer::new(vals.iter().map(MoveValueDeserializer));
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+struct VariantDeserializer<'a> {
+    tag: u16,
+    name: Option<&'a Identifier>,
+    fields: &'a [MoveValue],
+}
+
+impl<'de> EnumAccess<'de> for VariantDeserializer<'de> {
+    type Error = ValueError;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self), ValueError> {
+        let variant = match self.name {
+            Some(name) => {
+                let name: StrDeserializer<ValueError> = name.as_str().into_deserializer();
+                seed.deserialize(name)?
+            }
+            None => {
+                let tag: U32Deserializer<ValueError> = u32::from(self.tag).into_deserializer();
+                seed.deserialize(tag)?
+            }
+        };
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer<'de> {
+    type Error = ValueError;
+
+    fn unit_variant(self) -> Result<(), ValueError> {
+        match self.fields {
+            [] => Ok(()),
+            _ => Err(ValueError::invalid_type(
+                Unexpected::TupleVariant,
+                &"a unit variant",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, ValueError> {
+        match self.fields {
+            [field] => seed.deserialize(MoveValueDeserializer(field)),
+            _ => Err(ValueError::invalid_length(
+                self.fields.len(),
+                &"a variant with one field",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visit_values(self.fields, visitor)
+    }
+
+    /// The fields of enum values have no names, so they are read by position
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visit_values(self.fields, visitor)
+    }
+}
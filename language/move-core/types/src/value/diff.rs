@@ -0,0 +1,178 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Structural diffs between two `MoveValue`s, e.g. two versions of the same resource.
+
+use crate::value::{MoveStruct, MoveValue, PrettyOptions};
+use std::fmt;
+
+/// A single difference between two values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueDiff {
+    /// Where the values differ, in the syntax accepted by `MoveValue::get_path`. Field names are
+    /// used for decorated structs and indices for everything else.
+    pub path: String,
+    /// The old value at `path`, or `None` if a vector grew
+    pub old: Option<MoveValue>,
+    /// The new value at `path`, or `None` if a vector shrank
+    pub new: Option<MoveValue>,
+}
+
+/// The differences between `old` and `new`, in field and element order. Structs and enum
+/// variants are descended into when they have the same shape (type, variant, and field names);
+/// vectors are compared positionally.
+pub fn diff(old: &MoveValue, new: &MoveValue) -> Vec<ValueDiff> {
+    Differ {
+        modulo_decoration: false,
+    }
+    .diff(old, new)
+}
+
+/// Like `diff`, but values that differ only in decoration are considered equal (see
+/// `MoveValue::equals_modulo_decoration`)
+pub fn diff_modulo_decoration(old: &MoveValue, new: &MoveValue) -> Vec<ValueDiff> {
+    Differ {
+        modulo_decoration: true,
+    }
+    .diff(old, new)
+}
+
+struct Differ {
+    modulo_decoration: bool,
+}
+
+impl Differ {
+    fn diff(&self, old: &MoveValue, new: &MoveValue) -> Vec<ValueDiff> {
+        let mut diffs = vec![];
+        self.diff_at(&mut String::new(), Some(old), Some(new), &mut diffs);
+        diffs
+    }
+
+    fn equal(&self, old: &MoveValue, new: &MoveValue) -> bool {
+        if self.modulo_decoration {
+            old.equals_modulo_decoration(new)
+        } else {
+            old == new
+        }
+    }
+
+    fn diff_at(
+        &self,
+        path: &mut String,
+        old: Option<&MoveValue>,
+        new: Option<&MoveValue>,
+        diffs: &mut Vec<ValueDiff>,
+    ) {
+        let (old_val, new_val) = match (old, new) {
+            (Some(o), Some(n)) => (o, n),
+            _ => return self.record(path, old, new, diffs),
+        };
+        if self.equal(old_val, new_val) {
+            return;
+        }
+        match (old_val, new_val) {
+            (MoveValue::Vector(o), MoveValue::Vector(n)) => {
+                for i in 0..o.len().max(n.len()) {
+                    self.descend(path, &i.to_string(), o.get(i), n.get(i), diffs)
+                }
+            }
+            (MoveValue::Struct(o), MoveValue::Struct(n)) if self.same_struct_shape(o, n) => {
+                for (i, ((o_name, o_val), (n_name, n_val))) in o.iter().zip(n.iter()).enumerate() {
+                    let segment = match o_name.or(n_name) {
+                        Some(name) => name.to_string(),
+                        None => i.to_string(),
+                    };
+                    self.descend(path, &segment, Some(o_val), Some(n_val), diffs)
+                }
+            }
+            (
+                MoveValue::Variant {
+                    tag: o_tag,
+                    name: o_name,
+                    fields: o,
+                },
+                MoveValue::Variant {
+                    tag: n_tag,
+                    name: n_name,
+                    fields: n,
+                },
+            ) if o_tag == n_tag
+                && o.len() == n.len()
+                && (self.modulo_decoration || o_name == n_name) =>
+            {
+                for (i, (o_val, n_val)) in o.iter().zip(n).enumerate() {
+                    self.descend(path, &i.to_string(), Some(o_val), Some(n_val), diffs)
+                }
+            }
+            _ => self.record(path, old, new, diffs),
+        }
+    }
+
+    fn same_struct_shape(&self, old: &MoveStruct, new: &MoveStruct) -> bool {
+        let (old_len, new_len) = (old.iter().len(), new.iter().len());
+        if self.modulo_decoration {
+            return old_len == new_len;
+        }
+        let same_type = match (old, new) {
+            (MoveStruct::Runtime(_), MoveStruct::Runtime(_))
+            | (MoveStruct::WithFields(_), MoveStruct::WithFields(_)) => true,
+            (MoveStruct::WithTypes { type_: o, .. }, MoveStruct::WithTypes { type_: n, .. }) => {
+                o == n
+            }
+            _ => false,
+        };
+        same_type && old_len == new_len && old.iter().zip(new.iter()).all(|((o, _), (n, _))| o == n)
+    }
+
+    fn descend(
+        &self,
+        path: &mut String,
+        segment: &str,
+        old: Option<&MoveValue>,
+        new: Option<&MoveValue>,
+        diffs: &mut Vec<ValueDiff>,
+    ) {
+        let len = path.len();
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(segment);
+        self.diff_at(path, old, new, diffs);
+        path.truncate(len);
+    }
+
+    fn record(
+        &self,
+        path: &str,
+        old: Option<&MoveValue>,
+        new: Option<&MoveValue>,
+        diffs: &mut Vec<ValueDiff>,
+    ) {
+        diffs.push(ValueDiff {
+            path: path.to_string(),
+            old: old.cloned(),
+            new: new.cloned(),
+        })
+    }
+}
+
+impl fmt::Display for ValueDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let opts = PrettyOptions {
+            max_inline_width: usize::MAX,
+            ..PrettyOptions::default()
+        };
+        let print = |f: &mut fmt::Formatter, value: &Option<MoveValue>| match value {
+            Some(value) => value.fmt_pretty(f, &opts),
+            None => write!(f, "<none>"),
+        };
+        if self.path.is_empty() {
+            write!(f, "<root>: ")?;
+        } else {
+            write!(f, "{}: ", self.path)?;
+        }
+        print(f, &self.old)?;
+        write!(f, " -> ")?;
+        print(f, &self.new)
+    }
+}
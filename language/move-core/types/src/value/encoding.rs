@@ -0,0 +1,476 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A compact, versioned binary encoding of `MoveTypeLayout`s, suitable for persisting layouts.
+//!
+//! Version 1 consists of
+//! - the version byte `0x01`,
+//! - a table of the identifiers used in the layout: a ULEB128 count, then each identifier as a
+//!   ULEB128 length followed by its UTF-8 bytes,
+//! - the layout itself, where every identifier is a ULEB128 index into the table.
+//!
+//! A layout is one tag byte (`LAYOUT_*` below) followed by its contents. Struct layouts add a
+//! byte for their kind (`STRUCT_*`), and every list of fields or type arguments is prefixed by a
+//! ULEB128 count, as is the index of a type parameter. Addresses are prefixed by their length,
+//! which depends on the `address20` and `address32` features. The tags are part of the format:
+//! never renumber them, only add new ones.
+
+use crate::{
+    account_address::AccountAddress,
+    identifier::{IdentStr, Identifier, IdentifierPool},
+    language_storage::{StructTag, StructTagTemplate, TypeTag, TypeTagTemplate},
+    value::{MoveEnumLayout, MoveFieldLayout, MoveStructLayout, MoveTypeLayout},
+};
+use anyhow::{anyhow, bail, Result as AResult};
+use std::{collections::BTreeMap, convert::TryFrom};
+
+/// The version written by `MoveTypeLayout::to_bytes`
+const VERSION: u8 = 1;
+
+/// Layouts nested more deeply than this are rejected when decoding
+const MAX_DECODING_DEPTH: usize = 1024;
+
+const LAYOUT_BOOL: u8 = 0;
+const LAYOUT_U8: u8 = 1;
+const LAYOUT_U64: u8 = 2;
+const LAYOUT_U128: u8 = 3;
+const LAYOUT_ADDRESS: u8 = 4;
+const LAYOUT_SIGNER: u8 = 5;
+const LAYOUT_VECTOR: u8 = 6;
+const LAYOUT_STRUCT: u8 = 7;
+const LAYOUT_ENUM: u8 = 8;
+const LAYOUT_TYPE_PARAMETER: u8 = 9;
+
+const STRUCT_RUNTIME: u8 = 0;
+const STRUCT_WITH_FIELDS: u8 = 1;
+const STRUCT_WITH_TYPES: u8 = 2;
+const STRUCT_WITH_TYPE_TEMPLATE: u8 = 3;
+
+impl MoveTypeLayout {
+    /// Encode this layout in the versioned format described in `value::encoding`. Layouts nested
+    /// more deeply than 1024 levels can be encoded, but not decoded again.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+        encoder.layout(self);
+
+        let mut bytes = vec![VERSION];
+        write_uleb(&mut bytes, encoder.identifiers.len());
+        for ident in encoder.identifiers {
+            write_uleb(&mut bytes, ident.len());
+            bytes.extend_from_slice(ident.as_bytes());
+        }
+        bytes.extend(encoder.body);
+        bytes
+    }
+
+    /// Decode a layout produced by `to_bytes`, rejecting versions this crate does not know and
+    /// trailing bytes
+    pub fn from_bytes(bytes: &[u8]) -> AResult<Self> {
+        Self::from_bytes_pooled(bytes, None)
+    }
+
+    /// Like `from_bytes`, but the identifiers of the layout are taken from `pool` if there is
+    /// one, so that layouts decoded through it share them
+    pub fn from_bytes_pooled(bytes: &[u8], pool: Option<&IdentifierPool>) -> AResult<Self> {
+        let mut decoder = Decoder {
+            bytes,
+            pos: 0,
+            identifiers: vec![],
+        };
+        let version = decoder.byte()?;
+        if version != VERSION {
+            bail!(
+                "unsupported layout encoding version {} (expected {})",
+                version,
+                VERSION
+            )
+        }
+        let count = decoder.count()?;
+        for _ in 0..count {
+            let len = decoder.uleb()?;
+            let ident = std::str::from_utf8(decoder.take(len)?)?;
+            decoder.identifiers.push(match pool {
+                Some(pool) => pool.get_or_intern(ident)?,
+                None => Identifier::new(ident)?,
+            });
+        }
+        let layout = decoder.layout(0)?;
+        if decoder.pos != bytes.len() {
+            bail!("{} trailing bytes after layout", bytes.len() - decoder.pos)
+        }
+        Ok(layout)
+    }
+}
+
+fn write_uleb(buf: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        buf.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+#[derive(Default)]
+struct Encoder<'a> {
+    identifiers: Vec<&'a IdentStr>,
+    indices: BTreeMap<&'a IdentStr, usize>,
+    body: Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    fn uleb(&mut self, n: usize) {
+        write_uleb(&mut self.body, n)
+    }
+
+    fn ident(&mut self, ident: &'a IdentStr) {
+        let next = self.identifiers.len();
+        let index = *self.indices.entry(ident).or_insert(next);
+        if index == next {
+            self.identifiers.push(ident);
+        }
+        self.uleb(index)
+    }
+
+    fn address(&mut self, address: &AccountAddress) {
+        self.uleb(AccountAddress::LENGTH);
+        self.body.extend_from_slice(address.as_ref());
+    }
+
+    fn layout(&mut self, layout: &'a MoveTypeLayout) {
+        match layout {
+            MoveTypeLayout::Bool => self.body.push(LAYOUT_BOOL),
+            MoveTypeLayout::U8 => self.body.push(LAYOUT_U8),
+            MoveTypeLayout::U64 => self.body.push(LAYOUT_U64),
+            MoveTypeLayout::U128 => self.body.push(LAYOUT_U128),
+            MoveTypeLayout::Address => self.body.push(LAYOUT_ADDRESS),
+            MoveTypeLayout::Signer => self.body.push(LAYOUT_SIGNER),
+            MoveTypeLayout::Vector(elem) => {
+                self.body.push(LAYOUT_VECTOR);
+                self.layout(elem)
+            }
+            MoveTypeLayout::Struct(s) => {
+                self.body.push(LAYOUT_STRUCT);
+                self.struct_layout(s)
+            }
+            MoveTypeLayout::Enum(e) => {
+                self.body.push(LAYOUT_ENUM);
+                self.uleb(e.variants.len());
+                for (name, fields) in &e.variants {
+                    self.ident(name);
+                    self.fields(fields);
+                }
+            }
+            MoveTypeLayout::TypeParameter(idx) => {
+                self.body.push(LAYOUT_TYPE_PARAMETER);
+                self.uleb(*idx as usize)
+            }
+        }
+    }
+
+    fn struct_layout(&mut self, layout: &'a MoveStructLayout) {
+        match layout {
+            MoveStructLayout::Runtime(fields) => {
+                self.body.push(STRUCT_RUNTIME);
+                self.uleb(fields.len());
+                for field in fields {
+                    self.layout(field);
+                }
+            }
+            MoveStructLayout::WithFields(fields) => {
+                self.body.push(STRUCT_WITH_FIELDS);
+                self.fields(fields);
+            }
+            MoveStructLayout::WithTypes { type_, fields } => {
+                self.body.push(STRUCT_WITH_TYPES);
+                self.struct_tag(type_);
+                self.fields(fields);
+            }
+            MoveStructLayout::WithTypeTemplate { type_, fields } => {
+                self.body.push(STRUCT_WITH_TYPE_TEMPLATE);
+                self.struct_tag_template(type_);
+                self.fields(fields);
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &'a [MoveFieldLayout]) {
+        self.uleb(fields.len());
+        for field in fields {
+            self.ident(&field.name);
+            self.layout(&field.layout);
+        }
+    }
+
+    fn struct_tag(&mut self, tag: &'a StructTag) {
+        self.address(&tag.address);
+        self.ident(&tag.module);
+        self.ident(&tag.name);
+        self.uleb(tag.type_params.len());
+        for ty in &tag.type_params {
+            self.type_tag(ty);
+        }
+    }
+
+    fn type_tag(&mut self, ty: &'a TypeTag) {
+        match ty {
+            TypeTag::Bool => self.body.push(LAYOUT_BOOL),
+            TypeTag::U8 => self.body.push(LAYOUT_U8),
+            TypeTag::U64 => self.body.push(LAYOUT_U64),
+            TypeTag::U128 => self.body.push(LAYOUT_U128),
+            TypeTag::Address => self.body.push(LAYOUT_ADDRESS),
+            TypeTag::Signer => self.body.push(LAYOUT_SIGNER),
+            TypeTag::Vector(elem) => {
+                self.body.push(LAYOUT_VECTOR);
+                self.type_tag(elem)
+            }
+            TypeTag::Struct(tag) => {
+                self.body.push(LAYOUT_STRUCT);
+                self.struct_tag(tag)
+            }
+        }
+    }
+
+    /// Like `struct_tag`, with type parameters encoded as in layouts
+    fn struct_tag_template(&mut self, tag: &'a StructTagTemplate) {
+        self.address(&tag.address);
+        self.ident(&tag.module);
+        self.ident(&tag.name);
+        self.uleb(tag.type_params.len());
+        for ty in &tag.type_params {
+            self.type_tag_template(ty);
+        }
+    }
+
+    fn type_tag_template(&mut self, ty: &'a TypeTagTemplate) {
+        match ty {
+            TypeTagTemplate::Bool => self.body.push(LAYOUT_BOOL),
+            TypeTagTemplate::U8 => self.body.push(LAYOUT_U8),
+            TypeTagTemplate::U64 => self.body.push(LAYOUT_U64),
+            TypeTagTemplate::U128 => self.body.push(LAYOUT_U128),
+            TypeTagTemplate::Address => self.body.push(LAYOUT_ADDRESS),
+            TypeTagTemplate::Signer => self.body.push(LAYOUT_SIGNER),
+            TypeTagTemplate::Vector(elem) => {
+                self.body.push(LAYOUT_VECTOR);
+                self.type_tag_template(elem)
+            }
+            TypeTagTemplate::Struct(tag) => {
+                self.body.push(LAYOUT_STRUCT);
+                self.struct_tag_template(tag)
+            }
+            TypeTagTemplate::TypeParameter(idx) => {
+                self.body.push(LAYOUT_TYPE_PARAMETER);
+                self.uleb(*idx as usize)
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    identifiers: Vec<Identifier>,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> AResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| anyhow!("unexpected end of layout at byte {}", self.bytes.len()))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> AResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn uleb(&mut self) -> AResult<usize> {
+        let start = self.pos;
+        let mut n: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return usize::try_from(n)
+                    .map_err(|_| anyhow!("length {} at byte {} is too large", n, start));
+            }
+        }
+        bail!("invalid ULEB128 integer at byte {}", start)
+    }
+
+    /// A collection count, which cannot exceed the remaining input since every element takes up
+    /// at least one byte
+    fn count(&mut self) -> AResult<usize> {
+        let start = self.pos;
+        let count = self.uleb()?;
+        if count > self.bytes.len() - self.pos {
+            bail!("count {} at byte {} exceeds the input", count, start)
+        }
+        Ok(count)
+    }
+
+    fn ident(&mut self) -> AResult<Identifier> {
+        let start = self.pos;
+        let index = self.uleb()?;
+        self.identifiers
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown identifier #{} at byte {}", index, start))
+    }
+
+    fn address(&mut self) -> AResult<AccountAddress> {
+        let start = self.pos;
+        let len = self.uleb()?;
+        if len != AccountAddress::LENGTH {
+            bail!(
+                "address of {} bytes at byte {}, expected {}",
+                len,
+                start,
+                AccountAddress::LENGTH
+            )
+        }
+        Ok(AccountAddress::try_from(self.take(len)?)?)
+    }
+
+    fn tag(&mut self, depth: usize) -> AResult<u8> {
+        if depth > MAX_DECODING_DEPTH {
+            bail!("layout is nested more than {} levels", MAX_DECODING_DEPTH)
+        }
+        self.byte()
+    }
+
+    fn layout(&mut self, depth: usize) -> AResult<MoveTypeLayout> {
+        let start = self.pos;
+        Ok(match self.tag(depth)? {
+            LAYOUT_BOOL => MoveTypeLayout::Bool,
+            LAYOUT_U8 => MoveTypeLayout::U8,
+            LAYOUT_U64 => MoveTypeLayout::U64,
+            LAYOUT_U128 => MoveTypeLayout::U128,
+            LAYOUT_ADDRESS => MoveTypeLayout::Address,
+            LAYOUT_SIGNER => MoveTypeLayout::Signer,
+            LAYOUT_VECTOR => MoveTypeLayout::Vector(Box::new(self.layout(depth + 1)?)),
+            LAYOUT_STRUCT => MoveTypeLayout::Struct(self.struct_layout(depth + 1)?),
+            LAYOUT_ENUM => {
+                let count = self.count()?;
+                let mut variants = Vec::with_capacity(count);
+                for _ in 0..count {
+                    variants.push((self.ident()?, self.fields(depth + 1)?));
+                }
+                MoveTypeLayout::Enum(MoveEnumLayout::new(variants))
+            }
+            LAYOUT_TYPE_PARAMETER => {
+                let idx = self.uleb()?;
+                MoveTypeLayout::TypeParameter(u16::try_from(idx).map_err(|_| {
+                    anyhow!("type parameter {} at byte {} is out of range", idx, start)
+                })?)
+            }
+            tag => bail!("unknown layout tag {} at byte {}", tag, start),
+        })
+    }
+
+    fn struct_layout(&mut self, depth: usize) -> AResult<MoveStructLayout> {
+        let start = self.pos;
+        Ok(match self.byte()? {
+            STRUCT_RUNTIME => {
+                let count = self.count()?;
+                let mut fields = Vec::with_capacity(count);
+                for _ in 0..count {
+                    fields.push(self.layout(depth)?);
+                }
+                MoveStructLayout::Runtime(fields)
+            }
+            STRUCT_WITH_FIELDS => MoveStructLayout::WithFields(self.fields(depth)?),
+            STRUCT_WITH_TYPES => MoveStructLayout::WithTypes {
+                type_: self.struct_tag(depth)?,
+                fields: self.fields(depth)?,
+            },
+            STRUCT_WITH_TYPE_TEMPLATE => MoveStructLayout::WithTypeTemplate {
+                type_: self.struct_tag_template(depth)?,
+                fields: self.fields(depth)?,
+            },
+            kind => bail!("unknown struct layout kind {} at byte {}", kind, start),
+        })
+    }
+
+    fn fields(&mut self, depth: usize) -> AResult<Vec<MoveFieldLayout>> {
+        let count = self.count()?;
+        let mut fields = Vec::with_capacity(count);
+        for _ in 0..count {
+            fields.push(MoveFieldLayout::new(self.ident()?, self.layout(depth)?));
+        }
+        Ok(fields)
+    }
+
+    fn struct_tag(&mut self, depth: usize) -> AResult<StructTag> {
+        let address = self.address()?;
+        let module = self.ident()?;
+        let name = self.ident()?;
+        let count = self.count()?;
+        let mut type_params = Vec::with_capacity(count);
+        for _ in 0..count {
+            type_params.push(self.type_tag(depth + 1)?);
+        }
+        Ok(StructTag {
+            address,
+            module,
+            name,
+            type_params,
+        })
+    }
+
+    fn type_tag(&mut self, depth: usize) -> AResult<TypeTag> {
+        let start = self.pos;
+        Ok(match self.tag(depth)? {
+            LAYOUT_BOOL => TypeTag::Bool,
+            LAYOUT_U8 => TypeTag::U8,
+            LAYOUT_U64 => TypeTag::U64,
+            LAYOUT_U128 => TypeTag::U128,
+            LAYOUT_ADDRESS => TypeTag::Address,
+            LAYOUT_SIGNER => TypeTag::Signer,
+            LAYOUT_VECTOR => TypeTag::Vector(Box::new(self.type_tag(depth + 1)?)),
+            LAYOUT_STRUCT => TypeTag::Struct(self.struct_tag(depth + 1)?),
+            tag => bail!("unknown type tag {} at byte {}", tag, start),
+        })
+    }
+
+    fn struct_tag_template(&mut self, depth: usize) -> AResult<StructTagTemplate> {
+        let address = self.address()?;
+        let module = self.ident()?;
+        let name = self.ident()?;
+        let count = self.count()?;
+        let mut type_params = Vec::with_capacity(count);
+        for _ in 0..count {
+            type_params.push(self.type_tag_template(depth + 1)?);
+        }
+        Ok(StructTagTemplate {
+            address,
+            module,
+            name,
+            type_params,
+        })
+    }
+
+    fn type_tag_template(&mut self, depth: usize) -> AResult<TypeTagTemplate> {
+        let start = self.pos;
+        Ok(match self.tag(depth)? {
+            LAYOUT_BOOL => TypeTagTemplate::Bool,
+            LAYOUT_U8 => TypeTagTemplate::U8,
+            LAYOUT_U64 => TypeTagTemplate::U64,
+            LAYOUT_U128 => TypeTagTemplate::U128,
+            LAYOUT_ADDRESS => TypeTagTemplate::Address,
+            LAYOUT_SIGNER => TypeTagTemplate::Signer,
+            LAYOUT_VECTOR => TypeTagTemplate::Vector(Box::new(self.type_tag_template(depth + 1)?)),
+            LAYOUT_STRUCT => TypeTagTemplate::Struct(self.struct_tag_template(depth + 1)?),
+            LAYOUT_TYPE_PARAMETER => {
+                let idx = self.uleb()?;
+                TypeTagTemplate::TypeParameter(u16::try_from(idx).map_err(|_| {
+                    anyhow!("type parameter {} at byte {} is out of range", idx, start)
+                })?)
+            }
+            tag => bail!("unknown type tag {} at byte {}", tag, start),
+        })
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Flattening of `MoveValue`s into key-path/leaf pairs, e.g. for exporting resources as columns.
+
+use crate::{
+    account_address::AccountAddress,
+    value::{runtime_field_name, MoveValue},
+};
+
+/// A primitive leaf of a flattened `MoveValue`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlatValue {
+    U8(u8),
+    U64(u64),
+    U128(u128),
+    Bool(bool),
+    /// An address, or the address of a signer
+    Address(AccountAddress),
+    /// A `vector<u8>`, which is always kept as a single leaf
+    Bytes(Vec<u8>),
+    /// A vector of primitives other than `u8`, kept as a single leaf when
+    /// `FlattenOptions::keep_primitive_vectors` is set
+    Vector(Vec<FlatValue>),
+}
+
+/// How the fields of `Runtime` structs, which have no names, appear in flattened paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeFieldNames {
+    /// The field's position, e.g. `0`
+    Index,
+    /// The name `MoveStruct::into_named_fields` synthesizes, e.g. `field_0`
+    Synthesized,
+}
+
+/// Options controlling `MoveValue::flatten_with_options`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlattenOptions {
+    /// Separates the field segments of a path. Vector indices are always written as `[i]`.
+    pub separator: String,
+    /// How the fields of `Runtime` structs and enum variants are named
+    pub runtime_fields: RuntimeFieldNames,
+    /// Keep vectors of primitives as a single `FlatValue::Vector` leaf rather than a leaf per
+    /// element
+    pub keep_primitive_vectors: bool,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        Self {
+            separator: ".".to_string(),
+            runtime_fields: RuntimeFieldNames::Index,
+            keep_primitive_vectors: false,
+        }
+    }
+}
+
+impl MoveValue {
+    /// Flatten this value with the default `FlattenOptions`, e.g. into `balance.coin.value` or
+    /// `holders[2].addr` paths
+    pub fn flatten(&self) -> Vec<(String, FlatValue)> {
+        self.flatten_with_options(&FlattenOptions::default())
+    }
+
+    /// Flatten this value into the paths of its primitive leaves, in declaration order. Struct
+    /// fields add a segment named after the field, vector elements add an `[i]` suffix, and enum
+    /// variants add a segment named after the variant (or its tag, if undecorated) followed by
+    /// segments for their fields. A primitive at the root has the empty path, and empty structs
+    /// and vectors have no leaves.
+    pub fn flatten_with_options(&self, opts: &FlattenOptions) -> Vec<(String, FlatValue)> {
+        let mut out = vec![];
+        Flattener { opts }.flatten(&mut String::new(), self, &mut out);
+        out
+    }
+}
+
+struct Flattener<'a> {
+    opts: &'a FlattenOptions,
+}
+
+impl<'a> Flattener<'a> {
+    /// Append the leaves of `value`, which is at `path`, to `out`. `path` is restored on return.
+    fn flatten(&self, path: &mut String, value: &MoveValue, out: &mut Vec<(String, FlatValue)>) {
+        if let Some(leaf) = self.leaf(value) {
+            out.push((path.clone(), leaf));
+            return;
+        }
+        match value {
+            MoveValue::Vector(vals) => {
+                for (i, val) in vals.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("[{}]", i));
+                    self.flatten(path, val, out);
+                    path.truncate(len);
+                }
+            }
+            MoveValue::Struct(s) => {
+                for (i, (name, val)) in s.iter().enumerate() {
+                    match name {
+                        Some(name) => self.child(path, name.as_str(), val, out),
+                        None => self.child(path, &self.runtime_field(i), val, out),
+                    }
+                }
+            }
+            MoveValue::Variant { tag, name, fields } => {
+                let len = path.len();
+                match name {
+                    Some(name) => self.push_segment(path, name.as_str()),
+                    None => self.push_segment(path, &tag.to_string()),
+                }
+                for (i, val) in fields.iter().enumerate() {
+                    self.child(path, &self.runtime_field(i), val, out);
+                }
+                path.truncate(len);
+            }
+            _ => unreachable!("primitives are leaves"),
+        }
+    }
+
+    fn child(
+        &self,
+        path: &mut String,
+        segment: &str,
+        value: &MoveValue,
+        out: &mut Vec<(String, FlatValue)>,
+    ) {
+        let len = path.len();
+        self.push_segment(path, segment);
+        self.flatten(path, value, out);
+        path.truncate(len);
+    }
+
+    fn push_segment(&self, path: &mut String, segment: &str) {
+        if !path.is_empty() {
+            path.push_str(&self.opts.separator);
+        }
+        path.push_str(segment);
+    }
+
+    fn runtime_field(&self, i: usize) -> String {
+        match self.opts.runtime_fields {
+            RuntimeFieldNames::Index => i.to_string(),
+            RuntimeFieldNames::Synthesized => runtime_field_name(i).into_string(),
+        }
+    }
+
+    /// `value` as a single leaf, if it is one
+    fn leaf(&self, value: &MoveValue) -> Option<FlatValue> {
+        Some(match value {
+            MoveValue::U8(v) => FlatValue::U8(*v),
+            MoveValue::U64(v) => FlatValue::U64(*v),
+            MoveValue::U128(v) => FlatValue::U128(*v),
+            MoveValue::Bool(v) => FlatValue::Bool(*v),
+            MoveValue::Address(a) | MoveValue::Signer(a) => FlatValue::Address(*a),
+            MoveValue::Vector(vals) if !vals.is_empty() => {
+                let bytes: Option<Vec<u8>> = vals
+                    .iter()
+                    .map(|v| match v {
+                        MoveValue::U8(b) => Some(*b),
+                        _ => None,
+                    })
+                    .collect();
+                if let Some(bytes) = bytes {
+                    return Some(FlatValue::Bytes(bytes));
+                }
+                if !self.opts.keep_primitive_vectors {
+                    return None;
+                }
+                FlatValue::Vector(
+                    vals.iter()
+                        .map(|v| match v {
+                            MoveValue::Vector(_)
+                            | MoveValue::Struct(_)
+                            | MoveValue::Variant { .. } => None,
+                            v => self.leaf(v),
+                        })
+                        .collect::<Option<_>>()?,
+                )
+            }
+            MoveValue::Vector(_) if self.opts.keep_primitive_vectors => FlatValue::Vector(vec![]),
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Interning of the struct tags of decorated values.
+
+use crate::language_storage::{StructTag, TypeTagCache};
+use std::{collections::BTreeSet, sync::Arc};
+
+/// A set of shared `StructTag`s. Decorating a value through a registry
+/// (`MoveValue::decorate_interned`) gives all its structs of the same type one copy of their tag,
+/// rather than a copy each; with a long vector of structs, the copies can take more memory than
+/// the values themselves. A registry can be kept across values to share tags between them too.
+///
+/// Interning does not change the meaning of values: structs compare and serialize the same
+/// either way.
+#[derive(Debug, Default, Clone)]
+pub struct StructTagRegistry {
+    tags: BTreeSet<Arc<StructTag>>,
+}
+
+impl StructTagRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared copy of `tag`, adding one if there is none
+    pub fn intern(&mut self, tag: &StructTag) -> Arc<StructTag> {
+        if let Some(shared) = self.tags.get(tag) {
+            return shared.clone();
+        }
+        let shared = Arc::new(tag.clone());
+        self.tags.insert(shared.clone());
+        shared
+    }
+
+    /// The number of distinct tags interned
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+/// Where decorating a value takes the struct tags of decorated structs from
+pub(crate) enum TagSource<'a> {
+    /// A copy of its own for each struct
+    Fresh,
+    Registry(&'a mut StructTagRegistry),
+    Cache(&'a TypeTagCache),
+}
+
+impl TagSource<'_> {
+    pub(crate) fn intern(&mut self, tag: &StructTag) -> Arc<StructTag> {
+        match self {
+            TagSource::Fresh => Arc::new(tag.clone()),
+            TagSource::Registry(registry) => registry.intern(tag),
+            TagSource::Cache(cache) => cache.intern_borrowed(tag),
+        }
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Access to parts of an encoded value without decoding the rest of it.
+
+use crate::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    value::{
+        layout_name, DeserializationError, DeserializationLimits, DeserializerState, Failure,
+        MoveFieldLayout, MoveTypeLayout, MoveValue,
+    },
+};
+use anyhow::{anyhow, Result as AResult};
+use std::convert::TryFrom;
+
+/// A value of `layout` that is still encoded, see `MoveValue::lazy`. Fields, elements and variant
+/// fields are located by skipping over the encodings of the values before them, which takes
+/// constant time for vectors of fixed-size elements such as `vector<u8>`, so only the parts
+/// that are `decode`d are materialized.
+///
+/// The values skipped on the way count against the same limits as in `simple_deserialize`: a
+/// part of a value can be read if decoding the value up to and including that part would stay
+/// within the limits. Bytes past the part that was read are not looked at, so a blob that is
+/// malformed further on, or that has trailing bytes, is only rejected by decoding all of it.
+#[derive(Debug, Clone)]
+pub struct LazyMoveValue<'a> {
+    /// The encoding of this value. For a root value, the whole blob it came from.
+    bytes: &'a [u8],
+    layout: &'a MoveTypeLayout,
+    limits: DeserializationLimits,
+    /// The node budget left for this value, after the nodes before it were charged
+    remaining_nodes: usize,
+    /// The number of containers that BCS counts towards its depth limit enclosing this value
+    container_depth: usize,
+    /// The path to this value from the root, for error messages
+    path: String,
+}
+
+impl MoveValue {
+    /// A view of the value of type `layout` encoded in `blob` whose parts can be decoded on
+    /// their own, within the default `DeserializationLimits`
+    pub fn lazy<'a>(blob: &'a [u8], layout: &'a MoveTypeLayout) -> AResult<LazyMoveValue<'a>> {
+        LazyMoveValue::with_limits(blob, layout, &DeserializationLimits::default())
+    }
+}
+
+impl<'a> LazyMoveValue<'a> {
+    /// `MoveValue::lazy`, failing if decoding would exceed `limits`
+    pub fn with_limits(
+        blob: &'a [u8],
+        layout: &'a MoveTypeLayout,
+        limits: &DeserializationLimits,
+    ) -> AResult<Self> {
+        let root = layout_name(layout);
+        let state = DeserializerState::new(*limits);
+        state
+            .start_blob(blob.len())
+            .map_err(|e| state.annotate(root.clone(), e))?;
+        Ok(Self {
+            bytes: blob,
+            layout,
+            limits: *limits,
+            remaining_nodes: limits.max_nodes,
+            container_depth: 0,
+            path: root,
+        })
+    }
+
+    pub fn layout(&self) -> &'a MoveTypeLayout {
+        self.layout
+    }
+
+    /// The bytes of this value. Those of a root value are the whole blob.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The part of this value at `segment`: a struct field by name (if the layout has names) or
+    /// by index, a vector element by index, or a field of an enum variant by index, as in
+    /// `MoveValue::get_path`. Only the encodings before the part and of the part itself are
+    /// read.
+    pub fn get_field(&self, segment: &str) -> AResult<LazyMoveValue<'a>> {
+        let path = format!("{}.{}", self.path, segment);
+        let mut skipper = Skipper {
+            input: self.bytes,
+            pos: 0,
+            limits: &self.limits,
+            remaining_nodes: self.remaining_nodes,
+            container_depth: self.container_depth,
+            failure: None,
+        };
+        skipper
+            .charge_nodes(1)
+            .map_err(|e| skipper.error(e, self))?;
+
+        let (preceding, layout): (Vec<&MoveTypeLayout>, _) = match self.layout {
+            MoveTypeLayout::Struct(s) => {
+                let layouts = s.field_layouts();
+                let pos = match segment.parse::<usize>() {
+                    Ok(pos) if pos < layouts.len() => pos,
+                    Ok(pos) => {
+                        return Err(anyhow!(
+                            "index {} is out of bounds for a struct with {} fields at `{}`",
+                            pos,
+                            layouts.len(),
+                            self.path
+                        ))
+                    }
+                    Err(_) => match s.split_fields() {
+                        (_, []) => {
+                            return Err(anyhow!(
+                                "fields of the undecorated struct at `{}` can only be accessed \
+                                 by index",
+                                self.path
+                            ))
+                        }
+                        (_, named) => named
+                            .iter()
+                            .position(|f| f.name.as_str() == segment)
+                            .ok_or_else(|| {
+                                anyhow!("no field named `{}` at `{}`", segment, self.path)
+                            })?,
+                    },
+                };
+                (layouts[..pos].to_vec(), layouts[pos])
+            }
+            MoveTypeLayout::Vector(elem) => {
+                let len = skipper.length().map_err(|e| skipper.error(e, self))?;
+                let index = match segment.parse::<usize>() {
+                    Ok(index) if index < len => index,
+                    _ => {
+                        return Err(anyhow!(
+                            "`{}` is not an index into the vector of length {} at `{}`",
+                            segment,
+                            len,
+                            self.path
+                        ))
+                    }
+                };
+                skipper
+                    .skip_elements(elem, index)
+                    .map_err(|e| skipper.error(e, self))?;
+                (vec![], elem.as_ref())
+            }
+            MoveTypeLayout::Enum(e) => {
+                skipper
+                    .enter_container("enum")
+                    .map_err(|e| skipper.error(e, self))?;
+                let fields = skipper
+                    .variant(e.variants())
+                    .map_err(|e| skipper.error(e, self))?;
+                let pos = match segment.parse::<usize>() {
+                    Ok(pos) if pos < fields.len() => pos,
+                    _ => {
+                        return Err(anyhow!(
+                            "`{}` is not an index into the {} variant fields at `{}`",
+                            segment,
+                            fields.len(),
+                            self.path
+                        ))
+                    }
+                };
+                (
+                    fields[..pos].iter().map(|f| &f.layout).collect(),
+                    &fields[pos].layout,
+                )
+            }
+            layout => {
+                return Err(anyhow!(
+                    "cannot descend into {} value at `{}`",
+                    layout_name(layout),
+                    self.path
+                ))
+            }
+        };
+        for layout in preceding {
+            skipper.skip(layout).map_err(|e| skipper.error(e, self))?;
+        }
+
+        // The part itself is charged when it is decoded or descended into, so skip it on a copy
+        // of the budget only to find where it ends
+        let start = skipper.pos;
+        let (remaining_nodes, container_depth) = (skipper.remaining_nodes, skipper.container_depth);
+        if let Err(e) = skipper.skip(layout) {
+            let path = format!("{} ({})", path, layout_name(layout));
+            return Err(DeserializationError::new(path, e, skipper.failure).into());
+        }
+        Ok(LazyMoveValue {
+            bytes: &self.bytes[start..skipper.pos],
+            layout,
+            limits: self.limits,
+            remaining_nodes,
+            container_depth,
+            path,
+        })
+    }
+
+    /// Decode this value, within what is left of the limits of the value it is part of
+    pub fn decode(&self) -> AResult<MoveValue> {
+        let limits = DeserializationLimits {
+            max_nodes: self.remaining_nodes,
+            ..self.limits
+        };
+        MoveValue::simple_deserialize_with_limits(self.bytes, self.layout, &limits)
+            .map_err(|e| e.context(format!("while decoding `{}`", self.path)))
+    }
+}
+
+/// Walks over encoded values, checking them as `simple_deserialize` would, without decoding them
+struct Skipper<'a, 'l> {
+    input: &'a [u8],
+    pos: usize,
+    limits: &'l DeserializationLimits,
+    remaining_nodes: usize,
+    container_depth: usize,
+    /// The check that failed, if skipping failed in one of ours
+    failure: Option<Failure>,
+}
+
+impl<'a, 'l> Skipper<'a, 'l> {
+    /// `error` as a failure to skip within `value`
+    fn error(&self, error: bcs::Error, value: &LazyMoveValue) -> DeserializationError {
+        let path = format!("{} ({})", value.path, layout_name(value.layout));
+        DeserializationError::new(path, error, self.failure)
+    }
+
+    fn skip(&mut self, layout: &MoveTypeLayout) -> Result<(), bcs::Error> {
+        if let Some((size, nodes)) = fixed_size(layout) {
+            self.charge_nodes(nodes)?;
+            if matches!(layout, MoveTypeLayout::Address | MoveTypeLayout::Signer) {
+                self.check_container_depth("AccountAddress")?;
+            }
+            return self.advance(size);
+        }
+        self.charge_nodes(1)?;
+        match layout {
+            MoveTypeLayout::Bool => match self.byte()? {
+                0 | 1 => Ok(()),
+                _ => Err(bcs::Error::ExpectedBoolean),
+            },
+            MoveTypeLayout::Vector(elem) => {
+                let len = self.length()?;
+                self.skip_elements(elem, len)
+            }
+            MoveTypeLayout::Struct(s) => {
+                for layout in s.field_layouts() {
+                    self.skip(layout)?;
+                }
+                Ok(())
+            }
+            MoveTypeLayout::Enum(e) => {
+                self.enter_container("enum")?;
+                for field in self.variant(e.variants())? {
+                    self.skip(&field.layout)?;
+                }
+                self.container_depth -= 1;
+                Ok(())
+            }
+            MoveTypeLayout::TypeParameter(idx) => Err(bcs::Error::Custom(format!(
+                "cannot deserialize a value of uninstantiated type parameter T{}",
+                idx
+            ))),
+            _ => unreachable!("other layouts have a fixed size"),
+        }
+    }
+
+    /// Skip the first `count` elements of a vector of `elem`s whose length was read
+    fn skip_elements(&mut self, elem: &MoveTypeLayout, count: usize) -> Result<(), bcs::Error> {
+        match fixed_size(elem) {
+            // Elements of a fixed size take constant time, but are still charged one by one
+            Some((size, nodes)) => {
+                let total_nodes = count.saturating_mul(nodes);
+                self.charge_nodes(total_nodes)?;
+                if count > 0 && matches!(elem, MoveTypeLayout::Address | MoveTypeLayout::Signer) {
+                    self.check_container_depth("AccountAddress")?;
+                }
+                self.advance(count.checked_mul(size).ok_or(bcs::Error::Eof)?)
+            }
+            None => {
+                for _ in 0..count {
+                    self.skip(elem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Read the tag of an enum value, returning the fields of its variant
+    fn variant<'v>(
+        &mut self,
+        variants: &'v [(Identifier, Vec<MoveFieldLayout>)],
+    ) -> Result<&'v [MoveFieldLayout], bcs::Error> {
+        let tag = self.uleb128()?;
+        match variants.get(tag as usize) {
+            Some((_, fields)) => Ok(fields),
+            None => Err(self.fail(
+                Failure::LayoutMismatch,
+                format!(
+                    "variant tag {} is out of range for an enum with {} variants",
+                    tag,
+                    variants.len()
+                ),
+            )),
+        }
+    }
+
+    fn charge_nodes(&mut self, nodes: usize) -> Result<(), bcs::Error> {
+        match self.remaining_nodes.checked_sub(nodes) {
+            Some(remaining) => {
+                self.remaining_nodes = remaining;
+                Ok(())
+            }
+            None => Err(self.fail(
+                Failure::NodeLimit,
+                format!("value exceeds the limit of {} nodes", self.limits.max_nodes),
+            )),
+        }
+    }
+
+    fn fail(&mut self, failure: Failure, message: String) -> bcs::Error {
+        self.failure = Some(failure);
+        bcs::Error::Custom(message)
+    }
+
+    /// BCS limits the nesting of enums, and of the containers that some types such as addresses
+    /// are wrapped in
+    fn check_container_depth(&self, name: &'static str) -> Result<(), bcs::Error> {
+        if self.container_depth >= bcs::MAX_CONTAINER_DEPTH {
+            return Err(bcs::Error::ExceededContainerDepthLimit(name));
+        }
+        Ok(())
+    }
+
+    fn enter_container(&mut self, name: &'static str) -> Result<(), bcs::Error> {
+        self.check_container_depth(name)?;
+        self.container_depth += 1;
+        Ok(())
+    }
+
+    fn advance(&mut self, len: usize) -> Result<(), bcs::Error> {
+        match self.pos.checked_add(len) {
+            Some(end) if end <= self.input.len() => {
+                self.pos = end;
+                Ok(())
+            }
+            _ => Err(bcs::Error::Eof),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, bcs::Error> {
+        let byte = *self.input.get(self.pos).ok_or(bcs::Error::Eof)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    /// A ULEB128-encoded `u32`, which BCS requires to be canonical
+    fn uleb128(&mut self) -> Result<u32, bcs::Error> {
+        let mut value: u64 = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = self.byte()?;
+            let digit = byte & 0x7f;
+            value |= u64::from(digit) << shift;
+            if digit == byte {
+                if shift > 0 && digit == 0 {
+                    return Err(bcs::Error::NonCanonicalUleb128Encoding);
+                }
+                return u32::try_from(value)
+                    .map_err(|_| bcs::Error::IntegerOverflowDuringUleb128Decoding);
+            }
+        }
+        Err(bcs::Error::IntegerOverflowDuringUleb128Decoding)
+    }
+
+    /// The length prefix of a vector, checked against the limits
+    fn length(&mut self) -> Result<usize, bcs::Error> {
+        let len = self.uleb128()? as usize;
+        if len > bcs::MAX_SEQUENCE_LENGTH {
+            return Err(bcs::Error::ExceededMaxLen(len));
+        }
+        if len > self.limits.max_vector_length {
+            return Err(bcs::Error::Custom(format!(
+                "vector length {} exceeds the limit of {}",
+                len, self.limits.max_vector_length
+            )));
+        }
+        Ok(len)
+    }
+}
+
+/// The size of the encoding of every value of `layout` and the number of nodes in it, if they do
+/// not depend on the value
+fn fixed_size(layout: &MoveTypeLayout) -> Option<(usize, usize)> {
+    match layout {
+        MoveTypeLayout::U8 => Some((1, 1)),
+        MoveTypeLayout::U64 => Some((8, 1)),
+        MoveTypeLayout::U128 => Some((16, 1)),
+        MoveTypeLayout::Address | MoveTypeLayout::Signer => Some((AccountAddress::LENGTH, 1)),
+        _ => None,
+    }
+}
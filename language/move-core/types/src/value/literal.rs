@@ -0,0 +1,87 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rendering of `MoveValue`s as Move source expressions, e.g. to paste decoded values into the
+//! expected results of Move unit tests. `parser::parse_move_value` reads the literals of
+//! primitives and vectors back.
+
+use crate::value::{MoveStruct, MoveValue};
+use anyhow::{bail, Result as AResult};
+use std::fmt::Write;
+
+/// Options controlling `MoveValue::to_move_literal_with`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveLiteralOptions {
+    /// Write structs as calls of a constructor taking the fields in order, e.g.
+    /// `0x1::M::S(1u64, true)`, and accept structs without a type, which become `S(...)`. The
+    /// calls are not valid Move unless such constructors exist.
+    pub constructor_calls: bool,
+}
+
+impl MoveValue {
+    /// Render this value as a Move expression: `1u64`, `@0x1`, `vector[1u8, 2u8]`, and
+    /// `0x1::M::S<u64> { f: 1u64 }` for structs, which need their type. Fails on signers and
+    /// enum values, which have no literal form, and on structs without a type.
+    pub fn to_move_literal(&self) -> AResult<String> {
+        self.to_move_literal_with(&MoveLiteralOptions::default())
+    }
+
+    /// `to_move_literal`, with options
+    pub fn to_move_literal_with(&self, opts: &MoveLiteralOptions) -> AResult<String> {
+        let mut out = String::new();
+        write_literal(&mut out, self, opts)?;
+        Ok(out)
+    }
+}
+
+fn write_literal(out: &mut String, value: &MoveValue, opts: &MoveLiteralOptions) -> AResult<()> {
+    match value {
+        MoveValue::U8(i) => write!(out, "{}u8", i)?,
+        MoveValue::U64(i) => write!(out, "{}u64", i)?,
+        MoveValue::U128(i) => write!(out, "{}u128", i)?,
+        MoveValue::Bool(b) => write!(out, "{}", b)?,
+        MoveValue::Address(a) => write!(out, "@{}", a.to_hex_literal())?,
+        MoveValue::Signer(a) => bail!("signer {} has no literal form in Move", a.to_hex_literal()),
+        MoveValue::Vector(vals) => {
+            out.push_str("vector[");
+            for (i, val) in vals.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_literal(out, val, opts)?;
+            }
+            out.push(']');
+        }
+        MoveValue::Struct(s) => {
+            match s {
+                MoveStruct::WithTypes { type_, .. } => write!(out, "{}", type_)?,
+                _ if opts.constructor_calls => out.push('S'),
+                _ => bail!("struct has no type to write it as a Move literal with"),
+            }
+            if opts.constructor_calls {
+                out.push('(');
+                for (i, (_, val)) in s.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    write_literal(out, val, opts)?;
+                }
+                out.push(')');
+            } else {
+                out.push_str(" {");
+                for (i, (name, val)) in s.iter().enumerate() {
+                    out.push_str(if i > 0 { ", " } else { " " });
+                    // Structs with a type have named fields
+                    write!(out, "{}: ", name.expect("decorated fields are named"))?;
+                    write_literal(out, val, opts)?;
+                }
+                out.push_str(if s.iter().len() > 0 { " }" } else { "}" });
+            }
+        }
+        MoveValue::Variant { tag, name, .. } => match name {
+            Some(name) => bail!("enum variant {} has no literal form in Move", name),
+            None => bail!("enum variant #{} has no literal form in Move", tag),
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,253 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A human-readable, indented rendering of `MoveValue`s for debugging large resources.
+
+use crate::{
+    language_storage::{StructTag, TypeTag},
+    value::{MoveStruct, MoveValue},
+};
+use std::fmt::{self, Write};
+
+/// Options controlling `MoveValue::pretty_print`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// Number of spaces per nesting level when a value is broken onto multiple lines
+    pub indent: usize,
+    /// Values that fit within this many columns are printed on a single line
+    pub max_inline_width: usize,
+    /// Vectors longer than this print only their first `max_vector_elements` elements, followed
+    /// by `... (N more)`
+    pub max_vector_elements: usize,
+    /// Print `vector<u8>` values as hex literals (`x"beef"`) rather than as lists of numbers
+    pub hex_bytes: bool,
+    /// Print struct types fully qualified (`0x1::XUS::XUS`) rather than by name only (`XUS`)
+    pub qualified_types: bool,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        Self {
+            indent: 4,
+            max_inline_width: 80,
+            max_vector_elements: 32,
+            hex_bytes: true,
+            qualified_types: false,
+        }
+    }
+}
+
+impl MoveValue {
+    /// Render this value for humans, breaking it onto multiple lines where it doesn't fit
+    pub fn pretty_print(&self, opts: &PrettyOptions) -> String {
+        let mut out = String::new();
+        self.fmt_pretty(&mut out, opts)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    /// Writer-based variant of `pretty_print`
+    pub fn fmt_pretty<W: Write>(&self, w: &mut W, opts: &PrettyOptions) -> fmt::Result {
+        Printer { opts }.write(w, self, 0, 0)
+    }
+}
+
+struct Printer<'a> {
+    opts: &'a PrettyOptions,
+}
+
+impl<'a> Printer<'a> {
+    /// Write `value`, which starts at `column` and is nested `level` deep
+    fn write<W: Write>(
+        &self,
+        w: &mut W,
+        value: &MoveValue,
+        level: usize,
+        column: usize,
+    ) -> fmt::Result {
+        let mut inline = String::new();
+        self.write_inline(&mut inline, value)?;
+        if column.saturating_add(inline.len()) <= self.opts.max_inline_width
+            || !self.is_breakable(value)
+        {
+            return w.write_str(&inline);
+        }
+
+        match value {
+            MoveValue::Vector(vals) => {
+                w.write_str("[\n")?;
+                let shown = vals.len().min(self.opts.max_vector_elements);
+                for val in &vals[..shown] {
+                    self.write_indent(w, level + 1)?;
+                    self.write(w, val, level + 1, (level + 1) * self.opts.indent)?;
+                    w.write_str(",\n")?;
+                }
+                if shown < vals.len() {
+                    self.write_indent(w, level + 1)?;
+                    writeln!(w, "... ({} more)", vals.len() - shown)?;
+                }
+                self.write_indent(w, level)?;
+                w.write_str("]")
+            }
+            MoveValue::Struct(s) => {
+                if let MoveStruct::WithTypes { type_, .. } = s {
+                    write!(w, "{} ", self.struct_type(type_))?;
+                }
+                self.write_fields_multiline(
+                    w,
+                    s.iter().map(|(name, v)| (name.map(|n| n.as_str()), v)),
+                    level,
+                )
+            }
+            MoveValue::Variant { tag, name, fields } => {
+                self.write_variant_name(w, *tag, name.as_ref().map(|n| n.as_str()))?;
+                w.write_str(" ")?;
+                self.write_fields_multiline(w, fields.iter().map(|v| (None, v)), level)
+            }
+            _ => unreachable!("only containers are broken onto multiple lines"),
+        }
+    }
+
+    fn write_fields_multiline<'v, W: Write>(
+        &self,
+        w: &mut W,
+        fields: impl Iterator<Item = (Option<&'v str>, &'v MoveValue)>,
+        level: usize,
+    ) -> fmt::Result {
+        w.write_str("{\n")?;
+        for (name, val) in fields {
+            self.write_indent(w, level + 1)?;
+            let mut column = (level + 1) * self.opts.indent;
+            if let Some(name) = name {
+                write!(w, "{}: ", name)?;
+                column += name.len() + 2;
+            }
+            self.write(w, val, level + 1, column)?;
+            w.write_str(",\n")?;
+        }
+        self.write_indent(w, level)?;
+        w.write_str("}")
+    }
+
+    /// Write `value` on a single line
+    fn write_inline<W: Write>(&self, w: &mut W, value: &MoveValue) -> fmt::Result {
+        match value {
+            MoveValue::U8(i) => write!(w, "{}", i),
+            MoveValue::U64(i) => write!(w, "{}", i),
+            MoveValue::U128(i) => write!(w, "{}", i),
+            MoveValue::Bool(b) => write!(w, "{}", b),
+            MoveValue::Address(a) => write!(w, "0x{}", a.short_str_lossless()),
+            MoveValue::Signer(a) => write!(w, "signer(0x{})", a.short_str_lossless()),
+            MoveValue::Vector(vals) => {
+                let shown = vals.len().min(self.opts.max_vector_elements);
+                let hidden = vals.len() - shown;
+                if let Some(bytes) = self.as_bytes(vals) {
+                    write!(w, "x\"{}\"", hex::encode(&bytes[..shown]))?;
+                    if hidden > 0 {
+                        write!(w, " ... ({} more)", hidden)?;
+                    }
+                    return Ok(());
+                }
+                w.write_str("[")?;
+                for (i, val) in vals[..shown].iter().enumerate() {
+                    if i > 0 {
+                        w.write_str(", ")?;
+                    }
+                    self.write_inline(w, val)?;
+                }
+                if hidden > 0 {
+                    if shown > 0 {
+                        w.write_str(", ")?;
+                    }
+                    write!(w, "... ({} more)", hidden)?;
+                }
+                w.write_str("]")
+            }
+            MoveValue::Struct(s) => {
+                if let MoveStruct::WithTypes { type_, .. } = s {
+                    write!(w, "{} ", self.struct_type(type_))?;
+                }
+                self.write_fields_inline(w, s.iter().map(|(name, v)| (name.map(|n| n.as_str()), v)))
+            }
+            MoveValue::Variant { tag, name, fields } => {
+                self.write_variant_name(w, *tag, name.as_ref().map(|n| n.as_str()))?;
+                if fields.is_empty() {
+                    return Ok(());
+                }
+                w.write_str(" ")?;
+                self.write_fields_inline(w, fields.iter().map(|v| (None, v)))
+            }
+        }
+    }
+
+    fn write_fields_inline<'v, W: Write>(
+        &self,
+        w: &mut W,
+        fields: impl Iterator<Item = (Option<&'v str>, &'v MoveValue)>,
+    ) -> fmt::Result {
+        w.write_str("{")?;
+        let mut empty = true;
+        for (name, val) in fields {
+            w.write_str(if empty { " " } else { ", " })?;
+            empty = false;
+            if let Some(name) = name {
+                write!(w, "{}: ", name)?;
+            }
+            self.write_inline(w, val)?;
+        }
+        w.write_str(if empty { "}" } else { " }" })
+    }
+
+    fn write_variant_name<W: Write>(&self, w: &mut W, tag: u16, name: Option<&str>) -> fmt::Result {
+        match name {
+            Some(name) => w.write_str(name),
+            None => write!(w, "#{}", tag),
+        }
+    }
+
+    fn write_indent<W: Write>(&self, w: &mut W, level: usize) -> fmt::Result {
+        write!(w, "{:width$}", "", width = level * self.opts.indent)
+    }
+
+    fn is_breakable(&self, value: &MoveValue) -> bool {
+        match value {
+            MoveValue::Vector(vals) => self.as_bytes(vals).is_none() && !vals.is_empty(),
+            MoveValue::Struct(s) => s.iter().len() > 0,
+            MoveValue::Variant { fields, .. } => !fields.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// The contents of `vals` if it should be printed as a byte string
+    fn as_bytes(&self, vals: &[MoveValue]) -> Option<Vec<u8>> {
+        if !self.opts.hex_bytes || vals.is_empty() {
+            return None;
+        }
+        vals.iter()
+            .map(|v| match v {
+                MoveValue::U8(b) => Some(*b),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn struct_type(&self, tag: &StructTag) -> String {
+        if self.opts.qualified_types {
+            return tag.to_string();
+        }
+        let mut out = tag.name.to_string();
+        if !tag.type_params.is_empty() {
+            let args: Vec<_> = tag.type_params.iter().map(|t| self.type_tag(t)).collect();
+            out = format!("{}<{}>", out, args.join(", "));
+        }
+        out
+    }
+
+    fn type_tag(&self, tag: &TypeTag) -> String {
+        match tag {
+            TypeTag::Struct(s) => self.struct_type(s),
+            TypeTag::Vector(t) => format!("vector<{}>", self.type_tag(t)),
+            t => t.to_string(),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! JSON Schemas for the human-readable serde representation of decorated values.
+
+use crate::{
+    account_address::AccountAddress,
+    language_storage::StructTag,
+    value::{
+        MoveFieldLayout, MoveStructLayout, MoveTypeLayout, SerdeOptions, MOVE_ENUM_VARIANT,
+        MOVE_SIGNER_NAME, MOVE_STRUCT_FIELDS, MOVE_STRUCT_TYPE,
+    },
+};
+use serde_json::{json, Map, Number, Value};
+
+/// The version of JSON Schema the schemas are written in
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl MoveTypeLayout {
+    /// A JSON Schema describing the JSON that values of this layout serialize to, see
+    /// `to_json_schema_with`
+    pub fn to_json_schema(&self) -> Value {
+        self.to_json_schema_with(SerdeOptions::default())
+    }
+
+    /// A JSON Schema describing the JSON that values of this layout serialize to with `options`.
+    /// Integers are bounded by the range of their type, which for `u128` is approximate, as
+    /// JSON Schema bounds are floats; with `integers_as_strings`, `u64`s and `u128`s are decimal
+    /// strings instead. The schemas of structs with a tag are written once each, under `$defs`
+    /// keyed by the tag (e.g. `0x1::Diem::Diem<0x1::XUS::XUS>`). Type parameters are
+    /// unconstrained.
+    pub fn to_json_schema_with(&self, options: SerdeOptions) -> Value {
+        let mut gen = SchemaGenerator {
+            options,
+            defs: Map::new(),
+        };
+        let mut schema = match gen.type_(self) {
+            Value::Object(schema) => schema,
+            _ => unreachable!("schemas are objects"),
+        };
+        schema.insert("$schema".to_string(), JSON_SCHEMA_DIALECT.into());
+        if !gen.defs.is_empty() {
+            schema.insert("$defs".to_string(), Value::Object(gen.defs));
+        }
+        Value::Object(schema)
+    }
+}
+
+struct SchemaGenerator {
+    options: SerdeOptions,
+    /// The schemas of the tagged structs seen so far, keyed by tag
+    defs: Map<String, Value>,
+}
+
+impl SchemaGenerator {
+    fn type_(&mut self, layout: &MoveTypeLayout) -> Value {
+        match layout {
+            MoveTypeLayout::Bool => json!({ "type": "boolean" }),
+            MoveTypeLayout::U8 => unsigned(u8::MAX.into()),
+            MoveTypeLayout::U64 if self.options.integers_as_strings => decimal_string(u64::MAX),
+            MoveTypeLayout::U64 => unsigned(u64::MAX.into()),
+            MoveTypeLayout::U128 if self.options.integers_as_strings => decimal_string(u128::MAX),
+            MoveTypeLayout::U128 => {
+                unsigned(Number::from_f64(u128::MAX as f64).expect("u128::MAX is a finite float"))
+            }
+            MoveTypeLayout::Address => address(),
+            MoveTypeLayout::Signer => object(vec![(MOVE_SIGNER_NAME.to_string(), address())]),
+            MoveTypeLayout::Vector(elem) => json!({ "type": "array", "items": self.type_(elem) }),
+            MoveTypeLayout::Struct(s) => self.struct_(s),
+            MoveTypeLayout::Enum(e) => {
+                let variants: Vec<_> = e
+                    .variants()
+                    .iter()
+                    .map(|(name, fields)| {
+                        let fields = fields.iter().map(|f| self.type_(&f.layout)).collect();
+                        object(vec![
+                            (
+                                MOVE_ENUM_VARIANT.to_string(),
+                                json!({ "const": name.as_str() }),
+                            ),
+                            (MOVE_STRUCT_FIELDS.to_string(), tuple(fields)),
+                        ])
+                    })
+                    .collect();
+                json!({ "oneOf": variants })
+            }
+            MoveTypeLayout::TypeParameter(idx) => {
+                json!({ "description": format!("uninstantiated type parameter T{}", idx) })
+            }
+        }
+    }
+
+    fn struct_(&mut self, layout: &MoveStructLayout) -> Value {
+        match layout {
+            MoveStructLayout::Runtime(layouts) => {
+                tuple(layouts.iter().map(|l| self.type_(l)).collect())
+            }
+            MoveStructLayout::WithFields(fields) => self.fields(fields),
+            // the tag of a generic struct names no single type, so it has no definition
+            MoveStructLayout::WithTypeTemplate { fields, .. } => self.fields(fields),
+            MoveStructLayout::WithTypes { type_, fields } => {
+                let key = type_.to_string();
+                if !self.defs.contains_key(&key) {
+                    // Reserve the entry first, so that the key is taken while the fields are
+                    // generated
+                    self.defs.insert(key.clone(), Value::Null);
+                    let schema = object(vec![
+                        (MOVE_STRUCT_TYPE.to_string(), self.struct_tag(type_)),
+                        (MOVE_STRUCT_FIELDS.to_string(), self.fields(fields)),
+                    ]);
+                    self.defs.insert(key.clone(), schema);
+                }
+                json!({ "$ref": def_ref(&key) })
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &[MoveFieldLayout]) -> Value {
+        object(
+            fields
+                .iter()
+                .map(|f| (f.name.to_string(), self.type_(&f.layout)))
+                .collect(),
+        )
+    }
+
+    fn struct_tag(&self, tag: &StructTag) -> Value {
+        if self.options.structured_type {
+            json!({ "const": serde_json::to_value(tag).expect("struct tags serialize to JSON") })
+        } else {
+            json!({ "const": tag.to_string() })
+        }
+    }
+}
+
+fn unsigned(max: Number) -> Value {
+    json!({ "type": "integer", "minimum": 0, "maximum": max })
+}
+
+/// A decimal string of at most as many digits as `max`
+fn decimal_string(max: impl ToString) -> Value {
+    json!({
+        "type": "string",
+        "pattern": "^(0|[1-9][0-9]*)$",
+        "maxLength": max.to_string().len(),
+    })
+}
+
+fn address() -> Value {
+    json!({
+        "type": "string",
+        "pattern": format!("^[0-9a-f]{{{}}}$", AccountAddress::LENGTH * 2),
+    })
+}
+
+/// An object with exactly `properties`, all of them required
+fn object(properties: Vec<(String, Value)>) -> Value {
+    let required: Vec<_> = properties.iter().map(|(name, _)| name.clone()).collect();
+    json!({
+        "type": "object",
+        "properties": properties.into_iter().collect::<Map<_, _>>(),
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// An array with an element of each of `items`, in order
+fn tuple(items: Vec<Value>) -> Value {
+    let len = items.len();
+    json!({
+        "type": "array",
+        "prefixItems": items,
+        "items": false,
+        "minItems": len,
+    })
+}
+
+/// A reference to the definition at `key` in `$defs`. The key goes in a URI fragment, which
+/// cannot hold the spaces and angle brackets of type arguments.
+fn def_ref(key: &str) -> String {
+    let mut out = "#/$defs/".to_string();
+    for c in key.chars() {
+        match c {
+            ' ' => out.push_str("%20"),
+            '<' => out.push_str("%3C"),
+            '>' => out.push_str("%3E"),
+            c => out.push(c),
+        }
+    }
+    out
+}
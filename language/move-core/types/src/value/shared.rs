@@ -0,0 +1,81 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A `MoveValue` that is shared between its clones until one of them is mutated.
+
+use crate::value::{MoveTypeLayout, MoveValue};
+use std::{ops::Deref, sync::Arc};
+
+/// A `MoveValue` behind an `Arc`, so that cloning it is O(1) regardless of its size, e.g. to keep
+/// a snapshot of a resource holding a large vector. `make_mut` copies the value on the first
+/// mutation of a clone, leaving the other clones untouched. The value is shared as a whole, so
+/// that first mutation copies all of it.
+///
+/// Equality and serialization are those of the underlying `MoveValue`.
+#[derive(Debug, Clone, Eq)]
+pub struct SharedMoveValue(Arc<MoveValue>);
+
+impl SharedMoveValue {
+    pub fn new(value: MoveValue) -> Self {
+        Self(Arc::new(value))
+    }
+
+    /// A mutable reference to the value, copying it first if it is shared with other clones
+    pub fn make_mut(&mut self) -> &mut MoveValue {
+        Arc::make_mut(&mut self.0)
+    }
+
+    /// The value, copied only if it is shared with other clones
+    pub fn into_owned(self) -> MoveValue {
+        Arc::try_unwrap(self.0).unwrap_or_else(|shared| (*shared).clone())
+    }
+
+    /// Whether `self` and `other` share the same value, i.e. one is an unmutated clone of the
+    /// other
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// See `MoveValue::decorate`
+    pub fn decorate(self, layout: &MoveTypeLayout) -> Self {
+        Self::new(self.into_owned().decorate(layout))
+    }
+}
+
+impl Deref for SharedMoveValue {
+    type Target = MoveValue;
+
+    fn deref(&self) -> &MoveValue {
+        &self.0
+    }
+}
+
+impl AsRef<MoveValue> for SharedMoveValue {
+    fn as_ref(&self) -> &MoveValue {
+        &self.0
+    }
+}
+
+impl From<MoveValue> for SharedMoveValue {
+    fn from(value: MoveValue) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<SharedMoveValue> for MoveValue {
+    fn from(value: SharedMoveValue) -> Self {
+        value.into_owned()
+    }
+}
+
+impl PartialEq for SharedMoveValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.0 == other.0
+    }
+}
+
+impl serde::Serialize for SharedMoveValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
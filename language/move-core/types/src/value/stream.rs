@@ -0,0 +1,105 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deserialization of values from a `Read` source, without buffering the whole blob first.
+
+use crate::value::{
+    layout_name, DeserializationError, DeserializationLimits, DeserializerState, MoveTypeLayout,
+    MoveValue, TrackedSeed,
+};
+use anyhow::Result as AResult;
+use std::{
+    fmt,
+    io::{self, Read},
+};
+
+/// A failure of `MoveValue::deserialize_from`: either the source failed, or the bytes it produced
+/// do not encode a value of the layout (including when the source ends early)
+#[derive(Debug)]
+pub enum ReadValueError {
+    Io(io::Error),
+    Malformed(DeserializationError),
+}
+
+impl fmt::Display for ReadValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadValueError::Io(e) => write!(f, "failed to read value: {}", e),
+            ReadValueError::Malformed(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReadValueError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadValueError::Io(e) => Some(e),
+            ReadValueError::Malformed(e) => Some(e),
+        }
+    }
+}
+
+impl MoveValue {
+    /// Deserialize a value of type `layout` from `reader`, pulling bytes as they are needed and
+    /// failing if the result would exceed `limits`. `reader` must end right after the value, and
+    /// no more than `limits.max_blob_size` bytes are read from it. Failures are reported as a
+    /// `ReadValueError`.
+    pub fn deserialize_from<R: Read>(
+        reader: R,
+        layout: &MoveTypeLayout,
+        limits: &DeserializationLimits,
+    ) -> AResult<MoveValue> {
+        let mut reader = CapturingReader {
+            inner: reader,
+            error: None,
+            // one byte past the limit tells a blob that is too long from one that fits exactly
+            budget: limits.max_blob_size.saturating_add(1),
+        };
+        let state = DeserializerState::new(*limits);
+        let seed = TrackedSeed {
+            layout,
+            state: &state,
+        };
+        let result = bcs::from_reader_seed(seed, &mut reader);
+        if reader.budget == 0 {
+            let e = bcs::Error::Custom(format!(
+                "blob exceeds the limit of {} bytes",
+                limits.max_blob_size
+            ));
+            return Err(ReadValueError::Malformed(state.annotate(layout_name(layout), e)).into());
+        }
+        result.map_err(|e| {
+            match reader.error.take() {
+                Some(io_error) => ReadValueError::Io(io_error),
+                None => ReadValueError::Malformed(state.annotate(layout_name(layout), e)),
+            }
+            .into()
+        })
+    }
+}
+
+/// Keeps the first error of `inner` other than an early end, which BCS would only keep as a
+/// message, and ends the input once `budget` bytes were read
+struct CapturingReader<R> {
+    inner: R,
+    error: Option<io::Error>,
+    budget: usize,
+}
+
+impl<R: Read> Read for CapturingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.budget);
+        let read = self.inner.read(&mut buf[..len]);
+        if let Ok(n) = read {
+            self.budget -= n;
+        }
+        read.map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof | io::ErrorKind::Interrupted => e,
+            kind => {
+                let message = e.to_string();
+                self.error.get_or_insert(e);
+                io::Error::new(kind, message)
+            }
+        })
+    }
+}
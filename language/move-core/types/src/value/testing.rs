@@ -0,0 +1,183 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Random layouts and values conforming to them, for tests. Enabled by the `testing` feature,
+//! which only exposes this module: `rand` is a dependency of the crate either way, for
+//! `AccountAddress::random`.
+
+use crate::{
+    account_address::AccountAddress,
+    ident_str,
+    identifier::Identifier,
+    language_storage::StructTag,
+    value::{
+        MoveEnumLayout, MoveFieldLayout, MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue,
+    },
+};
+use rand::Rng;
+use std::ops::Range;
+
+/// Options for `random_layout` and `random_value`
+#[derive(Debug, Clone)]
+pub struct GenConfig {
+    /// The range that vector lengths are drawn from
+    pub vector_len: Range<usize>,
+    /// The addresses that address and signer values are drawn from. If empty, addresses are
+    /// generated at random.
+    pub addresses: Vec<AccountAddress>,
+    /// Whether layouts may contain signers
+    pub allow_signers: bool,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        Self {
+            vector_len: 0..4,
+            addresses: vec![],
+            allow_signers: true,
+        }
+    }
+}
+
+/// A random value of type `layout`. Decorated struct layouts produce decorated structs, and enum
+/// layouts produce variants with their names.
+///
+/// Panics if `layout` contains a signer while `config` does not allow them, an enum without
+/// variants, or a type parameter.
+pub fn random_value(layout: &MoveTypeLayout, rng: &mut impl Rng, config: &GenConfig) -> MoveValue {
+    match layout {
+        MoveTypeLayout::Bool => MoveValue::Bool(rng.gen()),
+        MoveTypeLayout::U8 => MoveValue::U8(rng.gen()),
+        MoveTypeLayout::U64 => MoveValue::U64(rng.gen()),
+        MoveTypeLayout::U128 => MoveValue::U128(rng.gen()),
+        MoveTypeLayout::Address => MoveValue::Address(random_address(rng, config)),
+        MoveTypeLayout::Signer => {
+            assert!(config.allow_signers, "signers are not allowed");
+            MoveValue::Signer(random_address(rng, config))
+        }
+        MoveTypeLayout::Vector(elem) => {
+            let len = if config.vector_len.is_empty() {
+                config.vector_len.start
+            } else {
+                rng.gen_range(config.vector_len.clone())
+            };
+            MoveValue::Vector((0..len).map(|_| random_value(elem, rng, config)).collect())
+        }
+        MoveTypeLayout::Struct(s) => MoveValue::Struct(random_struct(s, rng, config)),
+        MoveTypeLayout::Enum(e) => {
+            assert!(
+                !e.variants.is_empty(),
+                "enums without variants have no values"
+            );
+            let tag = rng.gen_range(0..e.variants.len());
+            let (name, fields) = &e.variants[tag];
+            MoveValue::Variant {
+                tag: tag as u16,
+                name: Some(name.clone()),
+                fields: fields
+                    .iter()
+                    .map(|f| random_value(&f.layout, rng, config))
+                    .collect(),
+            }
+        }
+        MoveTypeLayout::TypeParameter(idx) => {
+            panic!("type parameter T{} has no values", idx)
+        }
+    }
+}
+
+fn random_struct(layout: &MoveStructLayout, rng: &mut impl Rng, config: &GenConfig) -> MoveStruct {
+    match layout {
+        MoveStructLayout::Runtime(layouts) => MoveStruct::Runtime(
+            layouts
+                .iter()
+                .map(|l| random_value(l, rng, config))
+                .collect(),
+        ),
+        MoveStructLayout::WithFields(layouts) => {
+            MoveStruct::WithFields(random_field_values(layouts, rng, config))
+        }
+        MoveStructLayout::WithTypes {
+            type_,
+            fields: layouts,
+        } => MoveStruct::with_types(type_.clone(), random_field_values(layouts, rng, config)),
+        MoveStructLayout::WithTypeTemplate { type_, .. } => {
+            panic!("uninstantiated generic struct {} has no values", type_)
+        }
+    }
+}
+
+fn random_field_values(
+    layouts: &[MoveFieldLayout],
+    rng: &mut impl Rng,
+    config: &GenConfig,
+) -> Vec<(Identifier, MoveValue)> {
+    layouts
+        .iter()
+        .map(|f| (f.name.clone(), random_value(&f.layout, rng, config)))
+        .collect()
+}
+
+fn random_address(rng: &mut impl Rng, config: &GenConfig) -> AccountAddress {
+    if config.addresses.is_empty() {
+        AccountAddress::new(rng.gen())
+    } else {
+        config.addresses[rng.gen_range(0..config.addresses.len())]
+    }
+}
+
+/// A random layout nested at most `depth` levels below its root, mixing all three struct
+/// representations. Enums have at least one variant, there are no type parameters, and signers
+/// only if `config` allows them, so `random_value` accepts every layout generated here with the
+/// same `config`.
+pub fn random_layout(rng: &mut impl Rng, depth: usize, config: &GenConfig) -> MoveTypeLayout {
+    let signers = config.allow_signers as usize;
+    let choices = if depth == 0 { 5 + signers } else { 8 + signers };
+    let mut choice = rng.gen_range(0..choices);
+    if signers == 0 && choice >= 5 {
+        // skip the signer
+        choice += 1;
+    }
+    match choice {
+        0 => MoveTypeLayout::Bool,
+        1 => MoveTypeLayout::U8,
+        2 => MoveTypeLayout::U64,
+        3 => MoveTypeLayout::U128,
+        4 => MoveTypeLayout::Address,
+        5 => MoveTypeLayout::Signer,
+        6 => MoveTypeLayout::Vector(Box::new(random_layout(rng, depth - 1, config))),
+        7 => {
+            let fields = random_fields(rng, depth - 1, config);
+            MoveTypeLayout::Struct(match rng.gen_range(0..3) {
+                0 => MoveStructLayout::new(fields.into_iter().map(|f| f.layout).collect()),
+                1 => MoveStructLayout::with_fields(fields),
+                _ => MoveStructLayout::with_types(
+                    StructTag {
+                        address: AccountAddress::ONE,
+                        module: ident_str!("Testing").to_owned(),
+                        name: Identifier::new(format!("S{}", rng.gen_range(0..4))).unwrap(),
+                        type_params: vec![],
+                    },
+                    fields,
+                ),
+            })
+        }
+        _ => MoveTypeLayout::Enum(MoveEnumLayout::new(
+            (0..rng.gen_range(1..4))
+                .map(|i| {
+                    let name = Identifier::new(format!("V{}", i)).unwrap();
+                    (name, random_fields(rng, depth - 1, config))
+                })
+                .collect(),
+        )),
+    }
+}
+
+fn random_fields(rng: &mut impl Rng, depth: usize, config: &GenConfig) -> Vec<MoveFieldLayout> {
+    (0..rng.gen_range(0..4))
+        .map(|i| {
+            let name = Identifier::new(format!("f{}", i)).unwrap();
+            MoveFieldLayout::new(name, random_layout(rng, depth, config))
+        })
+        .collect()
+}
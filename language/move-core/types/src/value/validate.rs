@@ -0,0 +1,210 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Consistency checks for struct layouts that did not come straight from a module, e.g. ones
+//! read back from a cache.
+
+use crate::{
+    identifier::{IdentStr, Identifier},
+    language_storage::StructTag,
+    value::{struct_layout_name, MoveFieldLayout, MoveStructLayout, MoveTypeLayout},
+};
+use anyhow::Result as AResult;
+use std::{collections::BTreeSet, fmt};
+
+/// Options for `MoveStructLayout::validate_with`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationOptions {
+    /// Accept fields and vector elements of type `signer`
+    pub allow_signers: bool,
+}
+
+/// The ways in which a struct layout can be inconsistent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutViolation {
+    /// A decorated struct or an enum variant has two fields with this name
+    DuplicateField(Identifier),
+    /// A field name is not a valid identifier
+    InvalidFieldName(Identifier),
+    /// The module or name of the tag of a `WithTypes` layout is not a valid identifier
+    InvalidStructTag(StructTag),
+    /// A field is a signer
+    SignerField,
+    /// A vector has signer elements
+    SignerInVector,
+}
+
+/// A violation found by `MoveStructLayout::validate`, along with the path to the offending struct
+/// or field, e.g. `0x1::Account::Account.balance.coin`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutValidationError {
+    path: String,
+    violation: LayoutViolation,
+}
+
+impl LayoutValidationError {
+    /// The path to the struct (for field name and tag violations) or field (for signer
+    /// violations) that is inconsistent
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn violation(&self) -> &LayoutViolation {
+        &self.violation
+    }
+}
+
+impl fmt::Display for LayoutValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.violation {
+            LayoutViolation::DuplicateField(name) => {
+                write!(f, "duplicate field `{}` in {}", name, self.path)
+            }
+            LayoutViolation::InvalidFieldName(name) => {
+                write!(f, "invalid field name `{}` in {}", name, self.path)
+            }
+            LayoutViolation::InvalidStructTag(tag) => {
+                write!(f, "implausible struct tag {} at {}", tag, self.path)
+            }
+            LayoutViolation::SignerField => write!(f, "field {} is a signer", self.path),
+            LayoutViolation::SignerInVector => {
+                write!(f, "{} is a vector of signers", self.path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutValidationError {}
+
+impl MoveStructLayout {
+    /// Check this layout with the default `ValidationOptions`, see `validate_with`
+    pub fn validate(&self) -> AResult<()> {
+        self.validate_with(&ValidationOptions::default())
+    }
+
+    /// Check that this layout and the layouts nested in it are consistent: the fields of
+    /// decorated structs and enum variants have unique names that are valid identifiers, the tags
+    /// of `WithTypes` layouts have valid module and struct names, and, unless `opts` allows them,
+    /// no field or vector element is a signer. Failures are reported as a
+    /// `LayoutValidationError` naming the offending struct or field.
+    pub fn validate_with(&self, opts: &ValidationOptions) -> AResult<()> {
+        let mut path = struct_layout_name(self);
+        Validator { opts }.struct_(&mut path, self)?;
+        Ok(())
+    }
+}
+
+struct Validator<'a> {
+    opts: &'a ValidationOptions,
+}
+
+impl<'a> Validator<'a> {
+    /// Check `layout`, which is at `path`. `path` is restored on success.
+    fn struct_(
+        &self,
+        path: &mut String,
+        layout: &MoveStructLayout,
+    ) -> Result<(), LayoutValidationError> {
+        match layout {
+            MoveStructLayout::Runtime(fields) => {
+                for (i, field) in fields.iter().enumerate() {
+                    self.field(path, &i.to_string(), field)?;
+                }
+                Ok(())
+            }
+            MoveStructLayout::WithFields(fields)
+            | MoveStructLayout::WithTypeTemplate { fields, .. } => self.named_fields(path, fields),
+            MoveStructLayout::WithTypes { type_, fields } => {
+                if !Identifier::is_valid(type_.module.as_str())
+                    || !Identifier::is_valid(type_.name.as_str())
+                {
+                    return Err(error(
+                        path,
+                        LayoutViolation::InvalidStructTag(type_.clone()),
+                    ));
+                }
+                self.named_fields(path, fields)
+            }
+        }
+    }
+
+    fn named_fields(
+        &self,
+        path: &mut String,
+        fields: &[MoveFieldLayout],
+    ) -> Result<(), LayoutValidationError> {
+        let mut seen: BTreeSet<&IdentStr> = BTreeSet::new();
+        for field in fields {
+            if !Identifier::is_valid(field.name.as_str()) {
+                return Err(error(
+                    path,
+                    LayoutViolation::InvalidFieldName(field.name.clone()),
+                ));
+            }
+            if !seen.insert(&field.name) {
+                return Err(error(
+                    path,
+                    LayoutViolation::DuplicateField(field.name.clone()),
+                ));
+            }
+        }
+        for field in fields {
+            self.field(path, field.name.as_str(), &field.layout)?;
+        }
+        Ok(())
+    }
+
+    fn field(
+        &self,
+        path: &mut String,
+        name: &str,
+        layout: &MoveTypeLayout,
+    ) -> Result<(), LayoutValidationError> {
+        let len = path.len();
+        path.push('.');
+        path.push_str(name);
+        self.layout(path, layout, false)?;
+        path.truncate(len);
+        Ok(())
+    }
+
+    /// Check `layout`, the type of the field at `path` or, if `in_vector` is set, of the elements
+    /// of the vector at `path`
+    fn layout(
+        &self,
+        path: &mut String,
+        layout: &MoveTypeLayout,
+        in_vector: bool,
+    ) -> Result<(), LayoutValidationError> {
+        match layout {
+            MoveTypeLayout::Signer if !self.opts.allow_signers => Err(error(
+                path,
+                if in_vector {
+                    LayoutViolation::SignerInVector
+                } else {
+                    LayoutViolation::SignerField
+                },
+            )),
+            MoveTypeLayout::Vector(elem) => self.layout(path, elem, true),
+            MoveTypeLayout::Struct(s) => self.struct_(path, s),
+            MoveTypeLayout::Enum(e) => {
+                for (name, fields) in &e.variants {
+                    let len = path.len();
+                    path.push('.');
+                    path.push_str(name.as_str());
+                    self.named_fields(path, fields)?;
+                    path.truncate(len);
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn error(path: &str, violation: LayoutViolation) -> LayoutValidationError {
+    LayoutValidationError {
+        path: path.to_string(),
+        violation,
+    }
+}
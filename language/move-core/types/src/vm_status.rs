@@ -751,6 +751,8 @@ pub mod sub_status {
     pub const NFE_VECTOR_ERROR_BASE: u64 = 0;
     // Failure in BCS deserialization
     pub const NFE_BCS_SERIALIZATION_FAILURE: u64 = 0x1C5;
+    // Attempt to hash a value containing a signer
+    pub const NFE_HASH_SIGNER_VALUE: u64 = 0x1C6;
 }
 
 /// The `Arbitrary` impl only generates validation statuses since the full enum is too large.
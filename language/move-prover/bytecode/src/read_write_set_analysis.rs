@@ -681,7 +681,10 @@ fn call_native_function(
         }
         ("Vector", "empty") | ("Vector", "destroy_empty") | ("Vector", "reverse") => (),
         ("Event", "write_to_event_store") => (),
-        ("Hash", "sha3_256") | ("Hash", "sha2_256") => (),
+        ("Hash", "sha3_256")
+        | ("Hash", "sha2_256")
+        | ("Hash", "hash128")
+        | ("Hash", "hash_value") => (),
         ("Signature", "ed25519_validate_pubkey") | ("Signature", "ed25519_verify") => (),
         (m, f) => {
             panic!("Unsupported native function {:?}::{:?}", m, f)
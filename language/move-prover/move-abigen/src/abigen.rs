@@ -5,12 +5,11 @@
 use log::{debug, info, warn};
 
 use anyhow::bail;
-use heck::SnakeCase;
 use move_bytecode_verifier::script_signature;
 use move_command_line_common::files::MOVE_COMPILED_EXTENSION;
 use move_core_types::{
     abi::{ArgumentABI, ScriptABI, ScriptFunctionABI, TransactionScriptABI, TypeArgumentABI},
-    identifier::IdentStr,
+    identifier::{self, IdentStr},
     language_storage::TypeTag,
 };
 use move_model::{
@@ -156,7 +155,7 @@ impl<'env> Abigen<'env> {
             .get_named_type_parameters()
             .iter()
             .map(|ty_param| {
-                TypeArgumentABI::new(symbol_pool.string(ty_param.0).to_string().to_snake_case())
+                TypeArgumentABI::new(identifier::to_snake_case(&symbol_pool.string(ty_param.0)))
             })
             .collect();
         let args = func
@@ -5,4 +5,13 @@
 module Std::Hash {
     native public fun sha2_256(data: vector<u8>): vector<u8>;
     native public fun sha3_256(data: vector<u8>): vector<u8>;
+
+    /// Returns the 128-bit MurmurHash3 (x64 variant) of `data` with the given `seed` as the pair
+    /// `(h1, h2)`. The 16-byte digest of the reference implementation is `h1` followed by `h2`,
+    /// each in little-endian byte order. This is not a cryptographic hash.
+    native public fun hash128(data: vector<u8>, seed: u64): (u64, u64);
+
+    /// Returns `h1` of `hash128(BCS::to_bytes(v), seed)`, i.e. the first 8 bytes of the digest
+    /// of the BCS encoding of `v` as a little-endian integer. Aborts if `v` contains a signer.
+    native public fun hash_value<T>(v: &T, seed: u64): u64;
 }
@@ -2,13 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use move_binary_format::errors::PartialVMResult;
+use move_core_types::{
+    gas_schedule::GasAlgebra,
+    vm_status::sub_status::{NFE_BCS_SERIALIZATION_FAILURE, NFE_HASH_SIGNER_VALUE},
+};
 use move_vm_runtime::native_functions::NativeContext;
 use move_vm_types::{
     gas_schedule::NativeCostIndex,
     loaded_data::runtime_types::Type,
     natives::function::{native_gas, NativeResult},
     pop_arg,
-    values::Value,
+    values::{values_impl::Reference, Value},
 };
 use sha2::{Digest, Sha256};
 use sha3::Sha3_256;
@@ -60,3 +64,150 @@ pub fn native_sha3_256(
         smallvec![Value::vector_u8(hash_vec)],
     ))
 }
+
+pub fn native_hash128(
+    context: &mut NativeContext,
+    _ty_args: Vec<Type>,
+    mut arguments: VecDeque<Value>,
+) -> PartialVMResult<NativeResult> {
+    debug_assert!(_ty_args.is_empty());
+    debug_assert!(arguments.len() == 2);
+
+    let seed = pop_arg!(arguments, u64);
+    let hash_arg = pop_arg!(arguments, Vec<u8>);
+
+    let cost = native_gas(
+        context.cost_table(),
+        NativeCostIndex::HASH128,
+        hash_arg.len(),
+    );
+
+    let (h1, h2) = murmur3_x64_128(hash_arg.as_slice(), seed);
+    Ok(NativeResult::ok(
+        cost,
+        smallvec![Value::u64(h1), Value::u64(h2)],
+    ))
+}
+
+/// Rust implementation of Move's `native public fun hash_value<T>(v: &T, seed: u64): u64`, the
+/// first half of `murmur3_x64_128` of the BCS encoding of `v`
+pub fn native_hash_value(
+    context: &mut NativeContext,
+    mut ty_args: Vec<Type>,
+    mut arguments: VecDeque<Value>,
+) -> PartialVMResult<NativeResult> {
+    debug_assert!(ty_args.len() == 1);
+    debug_assert!(arguments.len() == 2);
+
+    let seed = pop_arg!(arguments, u64);
+    let ref_to_val = pop_arg!(arguments, Reference);
+
+    let arg_type = ty_args.pop().unwrap();
+    let failure_cost = native_gas(context.cost_table(), NativeCostIndex::BCS_TO_BYTES, 1);
+    // references have no layout, and asking the loader for one is an invariant violation
+    let layout = match arg_type {
+        Type::Reference(_) | Type::MutableReference(_) => None,
+        _ => context.type_to_type_layout(&arg_type)?,
+    };
+    let serialized_value_opt = match layout {
+        None => None,
+        Some(layout) if layout.contains_signer() => {
+            return Ok(NativeResult::err(failure_cost, NFE_HASH_SIGNER_VALUE));
+        }
+        Some(layout) => ref_to_val.read_ref()?.simple_serialize(&layout),
+    };
+    let serialized_value = match serialized_value_opt {
+        None => {
+            return Ok(NativeResult::err(
+                failure_cost,
+                NFE_BCS_SERIALIZATION_FAILURE,
+            ));
+        }
+        Some(serialized_value) => serialized_value,
+    };
+
+    // cost covers both serializing the value and hashing its bytes
+    let cost = native_gas(
+        context.cost_table(),
+        NativeCostIndex::BCS_TO_BYTES,
+        serialized_value.len(),
+    )
+    .add(native_gas(
+        context.cost_table(),
+        NativeCostIndex::HASH128,
+        serialized_value.len(),
+    ));
+
+    let (h1, _) = murmur3_x64_128(serialized_value.as_slice(), seed);
+    Ok(NativeResult::ok(cost, smallvec![Value::u64(h1)]))
+}
+
+/// MurmurHash3 x64_128 of `data`, with both halves of the state initialized to `seed`.
+///
+/// The reference implementation writes the digest as the two `u64` halves `(h1, h2)` in native
+/// byte order, i.e. on little-endian machines the 16-byte digest is `h1` followed by `h2`, each
+/// in little-endian byte order. The digest usually printed for seed 0 and "The quick
+/// brown fox jumps over the lazy dog", `6c1b07bc7bbc4be347939ac4a93c437a`, is thus returned as
+/// `(0xe34bbc7bbc071b6c, 0x7a433ca9c49a9347)`.
+pub fn murmur3_x64_128(data: &[u8], seed: u64) -> (u64, u64) {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+    fn fmix64(mut k: u64) -> u64 {
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        k ^= k >> 33;
+        k
+    }
+
+    fn read_u64(bytes: &[u8]) -> u64 {
+        let mut buf = [0u8; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        u64::from_le_bytes(buf)
+    }
+
+    let mut h1 = seed;
+    let mut h2 = seed;
+
+    let mut blocks = data.chunks_exact(16);
+    for block in &mut blocks {
+        let k1 = read_u64(&block[..8]);
+        let k2 = read_u64(&block[8..]);
+
+        h1 ^= k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+
+        h2 ^= k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+
+    let tail = blocks.remainder();
+    if tail.len() > 8 {
+        let k2 = read_u64(&tail[8..]);
+        h2 ^= k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+    }
+    if !tail.is_empty() {
+        let k1 = read_u64(&tail[..std::cmp::min(tail.len(), 8)]);
+        h1 ^= k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix64(h1);
+    h2 = fmix64(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    (h1, h2)
+}
@@ -22,6 +22,8 @@ pub fn all_natives(move_std_addr: AccountAddress) -> NativeFunctionTable {
         ("Event", "write_to_event_store", event::write_to_event_store),
         ("Hash", "sha2_256", hash::native_sha2_256),
         ("Hash", "sha3_256", hash::native_sha3_256),
+        ("Hash", "hash128", hash::native_hash128),
+        ("Hash", "hash_value", hash::native_hash_value),
         ("Signer", "borrow_address", signer::native_borrow_address),
         ("Vector", "length", vector::native_length),
         ("Vector", "empty", vector::native_empty),
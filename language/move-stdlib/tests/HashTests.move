@@ -1,6 +1,12 @@
 #[test_only]
 module Std::HashTests {
+    use Std::BCS;
     use Std::Hash;
+    use Std::Vector;
+
+    struct Coin has drop { value: u64, owner: address, memo: vector<u8> }
+    struct Wallet has drop { coins: vector<Coin>, locked: bool }
+    struct SignerBox has drop { s: signer }
 
     #[test]
     fun sha2_256_expected_hash() {
@@ -15,4 +21,58 @@ module Std::HashTests {
         let expected_output = x"3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532";
         assert!(Hash::sha3_256(input) == expected_output, 0);
     }
+
+    #[test]
+    fun hash128_expected_hash() {
+        let (h1, h2) = Hash::hash128(x"", 0);
+        assert!(h1 == 0 && h2 == 0, 0);
+
+        let (h1, h2) = Hash::hash128(x"", 1);
+        assert!(h1 == 0x4610abe56eff5cb5 && h2 == 0x51622daa78f83583, 1);
+
+        // "The quick brown fox jumps over the lazy dog", digest 6c1b07bc7bbc4be347939ac4a93c437a
+        let input = x"54686520717569636b2062726f776e20666f78206a756d7073206f76657220746865206c617a7920646f67";
+        let (h1, h2) = Hash::hash128(copy input, 0);
+        assert!(h1 == 0xe34bbc7bbc071b6c && h2 == 0x7a433ca9c49a9347, 2);
+
+        let (h1, h2) = Hash::hash128(input, 42);
+        assert!(h1 == 0x740dcf93fe0bd5d7 && h2 == 0xc4546cf4ec705c8f, 3);
+
+        // A tail of at most 8 bytes
+        let (h1, h2) = Hash::hash128(x"616263", 0);
+        assert!(h1 == 0xb4963f3f3fad7867 && h2 == 0x3ba2744126ca2d52, 4);
+
+        // A tail of more than 8 bytes
+        let (h1, h2) = Hash::hash128(x"68656c6c6f2c20776f726c64", 0);
+        assert!(h1 == 0x342fac623a5ebc8e && h2 == 0x4cdcbc079642414d, 5);
+    }
+
+    #[test]
+    fun hash_value_is_hash_of_bcs_bytes() {
+        let coin = Coin { value: 10, owner: @0x1, memo: x"68656c6c6f" };
+        let (h1, _) = Hash::hash128(BCS::to_bytes(&coin), 7);
+        assert!(Hash::hash_value(&coin, 7) == h1, 0);
+
+        let coins = Vector::singleton(coin);
+        Vector::push_back(&mut coins, Coin { value: 0, owner: @0x2, memo: x"" });
+        let wallet = Wallet { coins, locked: true };
+        let (h1, _) = Hash::hash128(BCS::to_bytes(&wallet), 0);
+        assert!(Hash::hash_value(&wallet, 0) == h1, 1);
+
+        let (h1, _) = Hash::hash128(x"0100000000000000", 0);
+        assert!(Hash::hash_value(&1, 0) == h1, 2);
+        assert!(Hash::hash_value(&1, 0) != Hash::hash_value(&1, 1), 3);
+    }
+
+    #[test(s = @0x1)]
+    #[expected_failure(abort_code = 454)]
+    fun hash_value_signer(s: signer) {
+        Hash::hash_value(&s, 0);
+    }
+
+    #[test(s = @0x1)]
+    #[expected_failure(abort_code = 454)]
+    fun hash_value_struct_with_signer(s: signer) {
+        Hash::hash_value(&SignerBox { s }, 0);
+    }
 }
@@ -281,9 +281,9 @@ pub fn zero_cost_schedule(num_of_native_funcs: usize) -> CostTable {
         (VecUnpack(SignatureIndex::new(0), 0), GasCost::new(0, 0)),
         (VecSwap(SignatureIndex::new(0)), GasCost::new(0, 0)),
     ];
-    // length of native_table vector should be at least 18 due to the fact that there's a
-    // builtin native function cost EMIT_EVENT which indexed 17 in the vector
-    let num_of_native_funcs = max(num_of_native_funcs, 18);
+    // length of native_table vector should be at least 19 due to the fact that there's a
+    // builtin native function cost HASH128 which indexed 18 in the vector
+    let num_of_native_funcs = max(num_of_native_funcs, 19);
     let native_table = (0..num_of_native_funcs)
         .map(|_| GasCost::new(0, 0))
         .collect::<Vec<GasCost>>();
@@ -432,6 +432,7 @@ pub static INITIAL_COST_SCHEDULE: Lazy<CostTable> = Lazy::new(|| {
         (N::CREATE_SIGNER, GasCost::new(24, 1)),
         (N::DESTROY_SIGNER, GasCost::new(212, 1)),
         (N::EMIT_EVENT, GasCost::new(52, 1)),
+        (N::HASH128, GasCost::new(4, 1)),
     ];
     native_table.sort_by_key(|cost| cost.0 as u64);
     let raw_native_table = native_table
@@ -479,6 +480,7 @@ pub enum NativeCostIndex {
     CREATE_SIGNER = 15,
     DESTROY_SIGNER = 16,
     EMIT_EVENT = 17,
+    HASH128 = 18,
 }
 
 impl From<NativeCostIndex> for u8 {
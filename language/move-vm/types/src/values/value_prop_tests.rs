@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::values::{prop::layout_and_value_strategy, Value};
-use move_core_types::value::MoveValue;
+use move_core_types::{gas_schedule::GasAlgebra, value::MoveValue};
 use proptest::prelude::*;
 
 proptest! {
@@ -12,7 +12,9 @@ proptest! {
         let value_deserialized = Value::simple_deserialize(&blob, &layout).expect("must deserialize");
         assert!(value.equals(&value_deserialized).unwrap());
 
-        let move_value = value.as_move_value(&layout);
+        let move_value = value.as_move_value(&layout).expect("must convert");
+        let value_converted = Value::from_move_value_with_layout(&move_value, &layout).expect("must convert");
+        assert!(value.equals(&value_converted).unwrap());
 
         let blob2 = move_value.simple_serialize().expect("must serialize");
         assert_eq!(blob, blob2);
@@ -20,4 +22,10 @@ proptest! {
         let move_value_deserialized = MoveValue::simple_deserialize(&blob2, &layout).expect("must deserialize.");
         assert_eq!(move_value, move_value_deserialized);
     }
+
+    #[test]
+    fn abstract_memory_size_agrees((layout, value) in layout_and_value_strategy()) {
+        let move_value = value.as_move_value(&layout).expect("must convert");
+        prop_assert_eq!(value.size().get(), move_value.abstract_memory_size_with_layout(&layout));
+    }
 }
@@ -3,6 +3,13 @@
 
 use crate::values::*;
 use move_binary_format::errors::*;
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::StructTag,
+    value::{MoveFieldLayout, MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue},
+    vm_status::StatusCode,
+};
 
 #[test]
 fn locals() -> PartialVMResult<()> {
@@ -120,3 +127,101 @@ fn global_value_non_struct() -> PartialVMResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn move_value_conversion() -> PartialVMResult<()> {
+    use MoveTypeLayout as L;
+
+    let coin = |value: u64, memo: Vec<u8>| {
+        Value::struct_(Struct::pack(vec![
+            Value::u64(value),
+            Value::vector_u8(memo),
+        ]))
+    };
+    let coin_value = |value: u64, memo: Vec<u8>| {
+        MoveValue::Struct(MoveStruct::new(vec![
+            MoveValue::U64(value),
+            MoveValue::vector_u8(memo),
+        ]))
+    };
+    let coin_layout = L::Struct(MoveStructLayout::new(vec![
+        L::U64,
+        L::Vector(Box::new(L::U8)),
+    ]));
+    let coins_layout = L::Vector(Box::new(coin_layout));
+
+    // vectors of structs
+    let coins = Value::vector_for_testing_only(vec![coin(1, vec![]), coin(2, b"tip".to_vec())]);
+    let coins_value =
+        MoveValue::Vector(vec![coin_value(1, vec![]), coin_value(2, b"tip".to_vec())]);
+    assert_eq!(coins.as_move_value(&coins_layout)?, coins_value);
+    assert!(Value::from_move_value_with_layout(&coins_value, &coins_layout)?.equals(&coins)?);
+    // the empty memo needs the layout to tell that it is a `vector<u8>`
+    assert!(Value::from_move_value(&coins_value).is_err());
+    let coins_value = MoveValue::Vector(vec![coin_value(3, vec![4])]);
+    assert!(Value::from_move_value(&coins_value)?
+        .equals(&Value::vector_for_testing_only(vec![coin(3, vec![4])]))?);
+
+    // decorated layouts keep their decoration
+    let coin_tag = StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new("Coin").unwrap(),
+        name: Identifier::new("Coin").unwrap(),
+        type_params: vec![],
+    };
+    let coin_typed_layout = L::Struct(MoveStructLayout::with_types(
+        coin_tag.clone(),
+        vec![
+            MoveFieldLayout::new(Identifier::new("value").unwrap(), L::U64),
+            MoveFieldLayout::new(Identifier::new("memo").unwrap(), L::Vector(Box::new(L::U8))),
+        ],
+    ));
+    let coins_typed_layout = L::Vector(Box::new(coin_typed_layout));
+    let coin_typed_value = |value: u64, memo: Vec<u8>| {
+        MoveValue::Struct(MoveStruct::with_types(
+            coin_tag.clone(),
+            vec![
+                (Identifier::new("value").unwrap(), MoveValue::U64(value)),
+                (Identifier::new("memo").unwrap(), MoveValue::vector_u8(memo)),
+            ],
+        ))
+    };
+    let coins_typed_value = MoveValue::Vector(vec![
+        coin_typed_value(1, vec![]),
+        coin_typed_value(2, b"tip".to_vec()),
+    ]);
+    assert_eq!(coins.as_move_value(&coins_typed_layout)?, coins_typed_value);
+    assert!(
+        Value::from_move_value_with_layout(&coins_typed_value, &coins_typed_layout)?
+            .equals(&coins)?
+    );
+
+    // signers
+    let addr = AccountAddress::random();
+    assert_eq!(
+        Value::signer(addr).as_move_value(&L::Signer)?,
+        MoveValue::Signer(addr)
+    );
+    assert!(Value::from_move_value(&MoveValue::Signer(addr))?.equals(&Value::signer(addr))?);
+
+    // references are rejected
+    let err = Value::signer_reference(addr)
+        .as_move_value(&L::Signer)
+        .unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::INTERNAL_TYPE_ERROR);
+    let mut locals = Locals::new(1);
+    locals.store_loc(0, Value::u64(5))?;
+    assert!(locals.borrow_loc(0)?.as_move_value(&L::U64).is_err());
+
+    // so are values that do not match the layout
+    assert!(Value::u64(5).as_move_value(&L::U8).is_err());
+    assert!(coins.as_move_value(&L::Vector(Box::new(L::U64))).is_err());
+    assert!(Value::from_move_value_with_layout(&MoveValue::U8(1), &L::U64).is_err());
+    assert!(Value::from_move_value(&MoveValue::Vector(vec![
+        MoveValue::U8(1),
+        MoveValue::U64(2)
+    ]))
+    .is_err());
+
+    Ok(())
+}
@@ -8,17 +8,17 @@ use move_binary_format::{
 use move_core_types::{
     account_address::AccountAddress,
     gas_schedule::{
-        AbstractMemorySize, GasAlgebra, GasCarrier, CONST_SIZE, MIN_EXISTS_DATA_SIZE,
-        REFERENCE_SIZE, STRUCT_SIZE,
+        AbstractMemorySize, GasAlgebra, GasCarrier, ADDRESS_SIZE, CONST_SIZE, MIN_EXISTS_DATA_SIZE,
+        REFERENCE_SIZE, STRUCT_SIZE, VEC_ADDRESS_ELEMENT_SIZE, VEC_BOOL_ELEMENT_SIZE,
+        VEC_U128_ELEMENT_SIZE, VEC_U64_ELEMENT_SIZE, VEC_U8_ELEMENT_SIZE,
     },
-    value::{MoveStructLayout, MoveTypeLayout},
+    value::{MoveFieldLayout, MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue},
     vm_status::{sub_status::NFE_VECTOR_ERROR_BASE, StatusCode},
 };
 use std::{
     cell::RefCell,
     fmt::{self, Debug, Display},
     iter,
-    mem::size_of,
     ops::Add,
     rc::Rc,
 };
@@ -1786,21 +1786,16 @@ impl Vector {
 
 impl Container {
     fn size(&self) -> AbstractMemorySize<GasCarrier> {
+        let elements = |len: usize, element_size: AbstractMemorySize<GasCarrier>| {
+            element_size.map(|size| len as GasCarrier * size)
+        };
         match self {
             Self::Locals(r) | Self::Vec(r) | Self::Struct(r) => Struct::size_impl(&*r.borrow()),
-            Self::VecU8(r) => AbstractMemorySize::new((r.borrow().len() * size_of::<u8>()) as u64),
-            Self::VecU64(r) => {
-                AbstractMemorySize::new((r.borrow().len() * size_of::<u64>()) as u64)
-            }
-            Self::VecU128(r) => {
-                AbstractMemorySize::new((r.borrow().len() * size_of::<u128>()) as u64)
-            }
-            Self::VecBool(r) => {
-                AbstractMemorySize::new((r.borrow().len() * size_of::<bool>()) as u64)
-            }
-            Self::VecAddress(r) => {
-                AbstractMemorySize::new((r.borrow().len() * size_of::<AccountAddress>()) as u64)
-            }
+            Self::VecU8(r) => elements(r.borrow().len(), VEC_U8_ELEMENT_SIZE),
+            Self::VecU64(r) => elements(r.borrow().len(), VEC_U64_ELEMENT_SIZE),
+            Self::VecU128(r) => elements(r.borrow().len(), VEC_U128_ELEMENT_SIZE),
+            Self::VecBool(r) => elements(r.borrow().len(), VEC_BOOL_ELEMENT_SIZE),
+            Self::VecAddress(r) => elements(r.borrow().len(), VEC_ADDRESS_ELEMENT_SIZE),
         }
     }
 }
@@ -1823,7 +1818,7 @@ impl ValueImpl {
 
         match self {
             Invalid | U8(_) | U64(_) | U128(_) | Bool(_) => CONST_SIZE,
-            Address(_) => AbstractMemorySize::new(AccountAddress::LENGTH as u64),
+            Address(_) => ADDRESS_SIZE,
             ContainerRef(r) => r.size(),
             IndexedRef(r) => r.size(),
             // TODO: in case the borrow fails the VM will panic.
@@ -2515,6 +2510,11 @@ impl<'d> serde::de::DeserializeSeed<'d> for SeedWrapper<&MoveTypeLayout> {
                 };
                 Ok(Value(ValueImpl::Container(container)))
             }
+
+            L::Enum(_) => Err(D::Error::custom("enum values are not supported by the VM")),
+            L::TypeParameter(_) => Err(D::Error::custom(
+                "cannot deserialize a value of an uninstantiated type parameter",
+            )),
         }
     }
 }
@@ -2615,6 +2615,278 @@ impl Value {
     }
 }
 
+/***************************************************************************************
+ *
+ * Conversion to and from MoveValue
+ *
+ *   Converts between VM values and `MoveValue`s without going through BCS. As with
+ *   serialization, a layout is required to tell what a value in a general container
+ *   stands for. References, whose targets are not owned by the value, cannot be
+ *   converted.
+ *
+ **************************************************************************************/
+
+fn conversion_error(message: String) -> PartialVMError {
+    PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR).with_message(message)
+}
+
+impl ValueImpl {
+    fn as_move_value(&self, layout: &MoveTypeLayout) -> PartialVMResult<MoveValue> {
+        use MoveTypeLayout as L;
+
+        Ok(match (layout, self) {
+            (L::U8, ValueImpl::U8(x)) => MoveValue::U8(*x),
+            (L::U64, ValueImpl::U64(x)) => MoveValue::U64(*x),
+            (L::U128, ValueImpl::U128(x)) => MoveValue::U128(*x),
+            (L::Bool, ValueImpl::Bool(x)) => MoveValue::Bool(*x),
+            (L::Address, ValueImpl::Address(x)) => MoveValue::Address(*x),
+
+            (L::Struct(struct_layout), ValueImpl::Container(Container::Struct(r))) => {
+                let v = r.borrow();
+                let field_layouts = struct_layout.field_layouts();
+                if v.len() != field_layouts.len() {
+                    return Err(conversion_error(format!(
+                        "cannot convert struct with {} fields as {:?}",
+                        v.len(),
+                        struct_layout
+                    )));
+                }
+                let fields = v
+                    .iter()
+                    .zip(field_layouts)
+                    .map(|(v, field_layout)| v.as_move_value(field_layout))
+                    .collect::<PartialVMResult<Vec<_>>>()?;
+                // Keep the decoration of the layout
+                let named = |layouts: &[MoveFieldLayout], fields: Vec<MoveValue>| {
+                    layouts
+                        .iter()
+                        .map(|l| l.name().to_owned())
+                        .zip(fields)
+                        .collect()
+                };
+                MoveValue::Struct(match struct_layout {
+                    MoveStructLayout::Runtime(_) => MoveStruct::new(fields),
+                    MoveStructLayout::WithFields(layouts) => {
+                        MoveStruct::with_fields(named(layouts, fields))
+                    }
+                    MoveStructLayout::WithTypes {
+                        type_,
+                        fields: layouts,
+                    } => MoveStruct::with_types(type_.clone(), named(layouts, fields)),
+                    MoveStructLayout::WithTypeTemplate {
+                        fields: layouts, ..
+                    } => MoveStruct::with_fields(named(layouts, fields)),
+                })
+            }
+
+            (L::Vector(inner_layout), ValueImpl::Container(c)) => {
+                let inner_layout = &**inner_layout;
+                MoveValue::Vector(match (inner_layout, c) {
+                    (L::U8, Container::VecU8(r)) => {
+                        r.borrow().iter().map(|u| MoveValue::U8(*u)).collect()
+                    }
+                    (L::U64, Container::VecU64(r)) => {
+                        r.borrow().iter().map(|u| MoveValue::U64(*u)).collect()
+                    }
+                    (L::U128, Container::VecU128(r)) => {
+                        r.borrow().iter().map(|u| MoveValue::U128(*u)).collect()
+                    }
+                    (L::Bool, Container::VecBool(r)) => {
+                        r.borrow().iter().map(|u| MoveValue::Bool(*u)).collect()
+                    }
+                    (L::Address, Container::VecAddress(r)) => {
+                        r.borrow().iter().map(|u| MoveValue::Address(*u)).collect()
+                    }
+                    (_, Container::Vec(r)) => r
+                        .borrow()
+                        .iter()
+                        .map(|v| v.as_move_value(inner_layout))
+                        .collect::<PartialVMResult<_>>()?,
+                    (layout, container) => {
+                        return Err(conversion_error(format!(
+                            "cannot convert container {:?} as a vector of {:?}",
+                            container, layout
+                        )))
+                    }
+                })
+            }
+
+            (L::Signer, ValueImpl::Container(Container::Struct(r))) => {
+                let v = r.borrow();
+                match v.as_slice() {
+                    [ValueImpl::Address(a)] => MoveValue::Signer(*a),
+                    v => {
+                        return Err(conversion_error(format!(
+                            "cannot convert container as a signer -- expected 1 address field \
+                             got {:?}",
+                            v
+                        )))
+                    }
+                }
+            }
+
+            (_, ValueImpl::ContainerRef(_)) | (_, ValueImpl::IndexedRef(_)) => {
+                return Err(conversion_error(format!(
+                    "cannot convert reference {:?} to a MoveValue",
+                    self
+                )))
+            }
+
+            (layout, val) => {
+                return Err(conversion_error(format!(
+                    "cannot convert value {:?} as {:?}",
+                    val, layout
+                )))
+            }
+        })
+    }
+
+    /// Convert `v`, which has `layout` if one is given. Without a layout, the element type of a
+    /// vector is that of its first element.
+    fn from_move_value(v: &MoveValue, layout: Option<&MoveTypeLayout>) -> PartialVMResult<Self> {
+        use MoveTypeLayout as L;
+
+        let mismatch = || conversion_error(format!("cannot convert {:?} as {:?}", v, layout));
+        let matches_layout = match (v, layout) {
+            (MoveValue::U8(_), Some(l)) => l == &L::U8,
+            (MoveValue::U64(_), Some(l)) => l == &L::U64,
+            (MoveValue::U128(_), Some(l)) => l == &L::U128,
+            (MoveValue::Bool(_), Some(l)) => l == &L::Bool,
+            (MoveValue::Address(_), Some(l)) => l == &L::Address,
+            (MoveValue::Signer(_), Some(l)) => l == &L::Signer,
+            _ => true,
+        };
+        if !matches_layout {
+            return Err(mismatch());
+        }
+        Ok(match v {
+            MoveValue::U8(x) => ValueImpl::U8(*x),
+            MoveValue::U64(x) => ValueImpl::U64(*x),
+            MoveValue::U128(x) => ValueImpl::U128(*x),
+            MoveValue::Bool(x) => ValueImpl::Bool(*x),
+            MoveValue::Address(x) => ValueImpl::Address(*x),
+            MoveValue::Signer(x) => ValueImpl::Container(Container::signer(*x)),
+
+            MoveValue::Struct(s) => {
+                let vals: Vec<&MoveValue> = match s {
+                    MoveStruct::Runtime(vals) => vals.iter().collect(),
+                    MoveStruct::WithFields(fields) | MoveStruct::WithTypes { fields, .. } => {
+                        fields.iter().map(|(_, v)| v).collect()
+                    }
+                };
+                let fields = match layout {
+                    Some(L::Struct(struct_layout)) => {
+                        let field_layouts = struct_layout.field_layouts();
+                        if vals.len() != field_layouts.len() {
+                            return Err(mismatch());
+                        }
+                        vals.into_iter()
+                            .zip(field_layouts)
+                            .map(|(v, l)| Self::from_move_value(v, Some(l)))
+                            .collect::<PartialVMResult<_>>()?
+                    }
+                    None => vals
+                        .into_iter()
+                        .map(|v| Self::from_move_value(v, None))
+                        .collect::<PartialVMResult<_>>()?,
+                    Some(_) => return Err(mismatch()),
+                };
+                ValueImpl::Container(Container::Struct(Rc::new(RefCell::new(fields))))
+            }
+
+            MoveValue::Vector(vals) => {
+                let elem = match layout {
+                    Some(L::Vector(elem)) => Some(&**elem),
+                    None => None,
+                    Some(_) => return Err(mismatch()),
+                };
+                ValueImpl::Container(Self::vector_from_move_values(vals, elem)?)
+            }
+
+            MoveValue::Variant { .. } => {
+                return Err(conversion_error(format!(
+                    "VM values have no enums, cannot convert {:?}",
+                    v
+                )))
+            }
+        })
+    }
+
+    fn vector_from_move_values(
+        vals: &[MoveValue],
+        elem: Option<&MoveTypeLayout>,
+    ) -> PartialVMResult<Container> {
+        use MoveTypeLayout as L;
+
+        macro_rules! specialized {
+            ($vals:expr, $variant:ident, $container:ident) => {{
+                let elems = $vals
+                    .iter()
+                    .map(|v| match v {
+                        MoveValue::$variant(x) => Ok(*x),
+                        v => Err(conversion_error(format!(
+                            "cannot convert {:?} as an element of a vector of {}",
+                            v,
+                            stringify!($variant)
+                        ))),
+                    })
+                    .collect::<PartialVMResult<_>>()?;
+                Container::$container(Rc::new(RefCell::new(elems)))
+            }};
+        }
+
+        let is_elem = |layout: &MoveTypeLayout, v: fn(&MoveValue) -> bool| match elem {
+            Some(elem) => elem == layout,
+            None => matches!(vals.first(), Some(first) if v(first)),
+        };
+        Ok(if is_elem(&L::U8, |v| matches!(v, MoveValue::U8(_))) {
+            specialized!(vals, U8, VecU8)
+        } else if is_elem(&L::U64, |v| matches!(v, MoveValue::U64(_))) {
+            specialized!(vals, U64, VecU64)
+        } else if is_elem(&L::U128, |v| matches!(v, MoveValue::U128(_))) {
+            specialized!(vals, U128, VecU128)
+        } else if is_elem(&L::Bool, |v| matches!(v, MoveValue::Bool(_))) {
+            specialized!(vals, Bool, VecBool)
+        } else if is_elem(&L::Address, |v| matches!(v, MoveValue::Address(_))) {
+            specialized!(vals, Address, VecAddress)
+        } else if elem.is_none() && vals.is_empty() {
+            return Err(conversion_error(
+                "cannot tell the element type of an empty vector without a layout".to_string(),
+            ));
+        } else {
+            let elems = vals
+                .iter()
+                .map(|v| Self::from_move_value(v, elem))
+                .collect::<PartialVMResult<_>>()?;
+            Container::Vec(Rc::new(RefCell::new(elems)))
+        })
+    }
+}
+
+impl Value {
+    /// Convert this value, which has `layout`, into a `MoveValue`. Fails on references and on
+    /// values that do not match `layout`.
+    pub fn as_move_value(&self, layout: &MoveTypeLayout) -> PartialVMResult<MoveValue> {
+        self.0.as_move_value(layout)
+    }
+
+    /// Convert `v` into a VM value. The element type of a vector is taken from its first
+    /// element, so empty vectors, whose representation depends on their element type, are
+    /// rejected; use `from_move_value_with_layout` for values that may contain them. Enum
+    /// values, which the VM does not support, are rejected too.
+    pub fn from_move_value(v: &MoveValue) -> PartialVMResult<Value> {
+        Ok(Value(ValueImpl::from_move_value(v, None)?))
+    }
+
+    /// Convert `v`, which has `layout`, into a VM value
+    pub fn from_move_value_with_layout(
+        v: &MoveValue,
+        layout: &MoveTypeLayout,
+    ) -> PartialVMResult<Value> {
+        Ok(Value(ValueImpl::from_move_value(v, Some(layout))?))
+    }
+}
+
 /***************************************************************************************
  *
  * Prop Testing
@@ -2625,7 +2897,6 @@ impl Value {
 #[cfg(feature = "fuzzing")]
 pub mod prop {
     use super::*;
-    use move_core_types::value::{MoveStruct, MoveValue};
     use proptest::{collection::vec, prelude::*};
 
     pub fn value_strategy_with_layout(layout: &MoveTypeLayout) -> impl Strategy<Value = Value> {
@@ -2691,6 +2962,13 @@ pub mod prop {
                 .collect::<Vec<_>>()
                 .prop_map(move |vals| Value::struct_(Struct::pack(vals)))
                 .boxed(),
+
+            L::Enum(_) | L::TypeParameter(_) => {
+                panic!(
+                    "VM values have no enum or type parameter layouts: {}",
+                    layout
+                )
+            }
         }
     }
 
@@ -2721,68 +2999,4 @@ pub mod prop {
             (Just(layout), value_strategy)
         })
     }
-
-    impl ValueImpl {
-        pub fn as_move_value(&self, layout: &MoveTypeLayout) -> MoveValue {
-            use MoveTypeLayout as L;
-
-            match (layout, &self) {
-                (L::U8, ValueImpl::U8(x)) => MoveValue::U8(*x),
-                (L::U64, ValueImpl::U64(x)) => MoveValue::U64(*x),
-                (L::U128, ValueImpl::U128(x)) => MoveValue::U128(*x),
-                (L::Bool, ValueImpl::Bool(x)) => MoveValue::Bool(*x),
-                (L::Address, ValueImpl::Address(x)) => MoveValue::Address(*x),
-
-                (L::Struct(struct_layout), ValueImpl::Container(Container::Struct(r))) => {
-                    let mut fields = vec![];
-                    for (v, field_layout) in r.borrow().iter().zip(struct_layout.fields().iter()) {
-                        fields.push(v.as_move_value(field_layout));
-                    }
-                    MoveValue::Struct(MoveStruct::new(fields))
-                }
-
-                (L::Vector(inner_layout), ValueImpl::Container(c)) => MoveValue::Vector(match c {
-                    Container::VecU8(r) => r.borrow().iter().map(|u| MoveValue::U8(*u)).collect(),
-                    Container::VecU64(r) => r.borrow().iter().map(|u| MoveValue::U64(*u)).collect(),
-                    Container::VecU128(r) => {
-                        r.borrow().iter().map(|u| MoveValue::U128(*u)).collect()
-                    }
-                    Container::VecBool(r) => {
-                        r.borrow().iter().map(|u| MoveValue::Bool(*u)).collect()
-                    }
-                    Container::VecAddress(r) => {
-                        r.borrow().iter().map(|u| MoveValue::Address(*u)).collect()
-                    }
-                    Container::Vec(r) => r
-                        .borrow()
-                        .iter()
-                        .map(|v| v.as_move_value(inner_layout.as_ref()))
-                        .collect(),
-                    Container::Struct(_) => {
-                        panic!("got struct container when converting vec")
-                    }
-                    Container::Locals(_) => panic!("got locals container when converting vec"),
-                }),
-
-                (L::Signer, ValueImpl::Container(Container::Struct(r))) => {
-                    let v = r.borrow();
-                    if v.len() != 1 {
-                        panic!("Unexpected signer layout: {:?}", v);
-                    }
-                    match &v[0] {
-                        ValueImpl::Address(a) => MoveValue::Signer(*a),
-                        v => panic!("Unexpected non-address while converting signer: {:?}", v),
-                    }
-                }
-
-                (layout, val) => panic!("Cannot convert value {:?} as {:?}", val, layout),
-            }
-        }
-    }
-
-    impl Value {
-        pub fn as_move_value(&self, layout: &MoveTypeLayout) -> MoveValue {
-            self.0.as_move_value(layout)
-        }
-    }
 }
@@ -0,0 +1,194 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A one-call facade for evaluating a single Move function call against a state snapshot, e.g.
+//! `evaluator.eval("0x1::Oracle::get_price<0x1::XUS::XUS>()")`.
+
+use anyhow::{anyhow, bail, Result};
+use move_binary_format::errors::VMError;
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, GasUnits},
+    identifier::Identifier,
+    language_storage::{ModuleId, TypeTag, CORE_CODE_ADDRESS},
+    parser,
+    resolver::MoveResolver,
+    value::{DeserializationLimits, MoveValue},
+};
+use move_resource_viewer::MoveValueAnnotator;
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_types::gas_schedule::{GasStatus, INITIAL_COST_SCHEDULE};
+use std::fmt;
+
+/// Gas budget used for evaluation unless overridden with `MoveEvaluator::with_gas_budget`.
+pub const DEFAULT_EVAL_GAS_BUDGET: u64 = 1_000_000;
+
+/// Limits on the return values of an evaluation, far below the defaults for stored values:
+/// a getter has no business returning more than this.
+pub const EVAL_DESERIALIZATION_LIMITS: DeserializationLimits = DeserializationLimits {
+    max_nodes: 1 << 16,
+    max_vector_length: 1 << 16,
+    max_blob_size: 1 << 20,
+};
+
+/// The stage at which evaluating a call failed.
+#[derive(Debug)]
+pub enum EvaluationError {
+    /// The call string is not of the form `<address>::<module>::<function><ty_args>(<args>)`
+    Parse(anyhow::Error),
+    /// The function, its type arguments or its return types could not be resolved in the state
+    Resolution(anyhow::Error),
+    /// The VM failed to execute the call
+    Execution(VMError),
+    /// The return values of the call do not decode as its return types, or exceed
+    /// `EVAL_DESERIALIZATION_LIMITS`
+    Decoding(anyhow::Error),
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "failed to parse call: {}", e),
+            Self::Resolution(e) => write!(f, "failed to resolve call: {}", e),
+            Self::Execution(e) => write!(f, "failed to execute call: {:?}", e),
+            Self::Decoding(e) => write!(f, "failed to decode return values: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EvaluationError {}
+
+/// Evaluates read-only function calls against `state`. Effects of the call are never committed:
+/// every evaluation runs in a fresh view session that is dropped once the return values are
+/// read, within a gas budget and `EVAL_DESERIALIZATION_LIMITS`.
+pub struct MoveEvaluator<S> {
+    state: S,
+    vm: MoveVM,
+    gas_budget: u64,
+}
+
+impl<S: MoveResolver> MoveEvaluator<S> {
+    /// Create an evaluator over `state` with the Move standard library natives published at
+    /// `0x1`.
+    pub fn new(state: S) -> Self {
+        let vm = MoveVM::new(move_stdlib::natives::all_natives(CORE_CODE_ADDRESS))
+            .expect("standard library natives must be well-formed");
+        Self {
+            state,
+            vm,
+            gas_budget: DEFAULT_EVAL_GAS_BUDGET,
+        }
+    }
+
+    /// Limit each evaluation to `gas_budget` units of gas.
+    pub fn with_gas_budget(mut self, gas_budget: u64) -> Self {
+        self.gas_budget = gas_budget;
+        self
+    }
+
+    /// Evaluate `call`, e.g. `0x1::M::f<u8, 0x1::M::S>(1u64, @0x42, vector[x"beef"])`, and return its
+    /// results as `WithTypes`-decorated values. Failures are reported as an `EvaluationError`
+    /// that callers can recover with `downcast_ref`.
+    pub fn eval(&self, call: &str) -> Result<Vec<MoveValue>> {
+        let (module_id, function, ty_args, args) =
+            parse_call(call).map_err(EvaluationError::Parse)?;
+
+        let annotator = MoveValueAnnotator::new(&self.state);
+        let layouts = annotator
+            .get_function_return_types(&module_id, &function, &ty_args)
+            .and_then(|tys| {
+                tys.iter()
+                    .map(|ty| annotator.get_type_layout_with_types(ty))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .map_err(EvaluationError::Resolution)?;
+
+        let return_values = self
+            .execute_view(&module_id, &function, ty_args, args)
+            .map_err(EvaluationError::Execution)?;
+        if return_values.len() != layouts.len() {
+            return Err(EvaluationError::Decoding(anyhow!(
+                "expected {} return values, got {}",
+                layouts.len(),
+                return_values.len()
+            ))
+            .into());
+        }
+
+        return_values
+            .iter()
+            .zip(layouts.iter())
+            .map(|(blob, layout)| {
+                MoveValue::simple_deserialize_with_limits(
+                    blob,
+                    layout,
+                    &EVAL_DESERIALIZATION_LIMITS,
+                )
+            })
+            .collect::<Result<_>>()
+            .map_err(|e| EvaluationError::Decoding(e).into())
+    }
+
+    /// Execute the call in a session that is never finished, so that none of its writes reach
+    /// `state`, and return its serialized return values
+    fn execute_view(
+        &self,
+        module_id: &ModuleId,
+        function: &Identifier,
+        ty_args: Vec<TypeTag>,
+        args: Vec<Vec<u8>>,
+    ) -> Result<Vec<Vec<u8>>, VMError> {
+        let mut gas_status = GasStatus::new(&INITIAL_COST_SCHEDULE, GasUnits::new(self.gas_budget));
+        let mut session = self.vm.new_session(&self.state);
+        session.execute_function(module_id, function, ty_args, args, &mut gas_status)
+    }
+}
+
+/// Split `call` into the function it targets, its type arguments and its arguments.
+/// Arguments are value literals, see `parser::parse_move_value`, and are returned serialized.
+fn parse_call(call: &str) -> Result<(ModuleId, Identifier, Vec<TypeTag>, Vec<Vec<u8>>)> {
+    let call = call.trim();
+    let open = call
+        .find('(')
+        .ok_or_else(|| anyhow!("expected `(` after the function name in `{}`", call))?;
+    if !call.ends_with(')') {
+        bail!("expected `)` at the end of `{}`", call)
+    }
+    let (target, args) = (&call[..open], &call[open + 1..call.len() - 1]);
+
+    let (path, ty_args) = match target.find('<') {
+        Some(lt) => {
+            if !target.trim_end().ends_with('>') {
+                bail!("unbalanced type arguments in `{}`", target)
+            }
+            let ty_args = target.trim_end();
+            (
+                &target[..lt],
+                parser::parse_type_tags(&ty_args[lt + 1..ty_args.len() - 1])?,
+            )
+        }
+        None => (target, vec![]),
+    };
+
+    let parts: Vec<_> = path.split("::").map(str::trim).collect();
+    if parts.len() != 3 {
+        bail!(
+            "expected a function of the form `<address>::<module>::<function>`, got `{}`",
+            path.trim()
+        )
+    }
+    let address = AccountAddress::from_hex_literal(parts[0])
+        .map_err(|_| anyhow!("invalid address `{}`", parts[0]))?;
+    let module_id = ModuleId::new(address, Identifier::new(parts[1])?);
+    let function = Identifier::new(parts[2])?;
+
+    let args = parser::parse_move_values(args)?
+        .iter()
+        .map(|arg| {
+            arg.simple_serialize()
+                .ok_or_else(|| anyhow!("cannot serialize argument {:?}", arg))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok((module_id, function, ty_args, args))
+}
@@ -37,6 +37,7 @@ use std::{
     path::Path,
 };
 
+pub mod evaluator;
 pub mod on_disk_state_view;
 pub mod package_context;
 
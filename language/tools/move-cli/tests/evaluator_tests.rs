@@ -0,0 +1,174 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_cli::sandbox::utils::{
+    evaluator::{EvaluationError, MoveEvaluator},
+    on_disk_state_view::OnDiskStateView,
+};
+use move_compiler::{compiled_unit::AnnotatedCompiledUnit, Compiler, Flags};
+use move_core_types::{
+    account_address::AccountAddress,
+    ident_str,
+    identifier::IdentStr,
+    language_storage::TypeTag,
+    move_resource::MoveStructType,
+    value::{MoveStruct, MoveValue},
+};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+const ORACLE_MODULES: &str = r#"
+address 0x2 {
+module XUS {
+    struct XUS {}
+}
+
+module Oracle {
+    struct Price<phantom C> has key, copy, drop {
+        value: u64,
+    }
+
+    public fun get_price<C>(): u64 acquires Price {
+        borrow_global<Price<C>>(@0x2).value
+    }
+
+    public fun get_price_info<C>(): Price<C> acquires Price {
+        *borrow_global<Price<C>>(@0x2)
+    }
+
+    public fun scale(x: u64, by: u64): u64 {
+        x * by
+    }
+
+    public fun echo(owner: address, blobs: vector<vector<u8>>): (address, vector<vector<u8>>) {
+        (owner, blobs)
+    }
+}
+}
+"#;
+
+/// `0x2::XUS::XUS`
+struct Xus;
+
+impl MoveStructType for Xus {
+    const ADDRESS: AccountAddress = AccountAddress::TWO;
+    const MODULE_NAME: &'static IdentStr = ident_str!("XUS");
+    const STRUCT_NAME: &'static IdentStr = ident_str!("XUS");
+}
+
+/// `0x2::Oracle::Price<0x2::XUS::XUS>`
+struct XusPrice;
+
+impl MoveStructType for XusPrice {
+    const ADDRESS: AccountAddress = AccountAddress::TWO;
+    const MODULE_NAME: &'static IdentStr = ident_str!("Oracle");
+    const STRUCT_NAME: &'static IdentStr = ident_str!("Price");
+
+    fn type_params() -> Vec<TypeTag> {
+        vec![TypeTag::Struct(Xus::struct_tag())]
+    }
+}
+
+/// Build a fixture state holding the Oracle modules and a `Price<XUS>` of 42 under 0x2.
+fn fixture_state(dir: &Path) -> OnDiskStateView {
+    let source = dir.join("oracle.move");
+    fs::write(&source, ORACLE_MODULES).unwrap();
+    let (_, units) = Compiler::new(&[source.to_str().unwrap().to_string()], &[])
+        .set_flags(Flags::empty().set_sources_shadow_deps(false))
+        .build_and_report()
+        .unwrap();
+
+    let state = OnDiskStateView::create(dir.join("build"), dir.join("storage")).unwrap();
+    for unit in units {
+        match unit {
+            AnnotatedCompiledUnit::Module(annot_module) => {
+                let module = annot_module.named_module.module;
+                let mut bytes = vec![];
+                module.serialize(&mut bytes).unwrap();
+                state.save_module(&module.self_id(), &bytes).unwrap();
+            }
+            AnnotatedCompiledUnit::Script(_) => panic!("expected modules only"),
+        }
+    }
+    let price = MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::U64(42)]));
+    state
+        .save_resource(
+            AccountAddress::from_hex_literal("0x2").unwrap(),
+            XusPrice::struct_tag(),
+            &price.simple_serialize().unwrap(),
+        )
+        .unwrap();
+    state
+}
+
+#[test]
+fn eval_getter_against_fixture_state() {
+    let dir = TempDir::new().unwrap();
+    let evaluator = MoveEvaluator::new(fixture_state(dir.path()));
+
+    assert_eq!(
+        evaluator
+            .eval("0x2::Oracle::get_price<0x2::XUS::XUS>()")
+            .unwrap(),
+        vec![MoveValue::U64(42)]
+    );
+    assert_eq!(
+        evaluator.eval("0x2::Oracle::scale(6, 7u64)").unwrap(),
+        vec![MoveValue::U64(42)]
+    );
+    assert_eq!(
+        evaluator
+            .eval("0x2::Oracle::get_price_info<0x2::XUS::XUS>()")
+            .unwrap(),
+        vec![MoveValue::Struct(MoveStruct::with_types(
+            XusPrice::struct_tag(),
+            vec![(ident_str!("value").to_owned(), MoveValue::U64(42))]
+        ))]
+    );
+    assert_eq!(
+        evaluator
+            .eval(r#"0x2::Oracle::echo(@0x2, vector[x"beef", b"hi", vector[]])"#)
+            .unwrap(),
+        vec![
+            MoveValue::Address(AccountAddress::from_hex_literal("0x2").unwrap()),
+            MoveValue::Vector(vec![
+                MoveValue::vector_u8(vec![0xbe, 0xef]),
+                MoveValue::vector_u8(b"hi".to_vec()),
+                MoveValue::vector_u8(vec![]),
+            ]),
+        ]
+    );
+}
+
+#[test]
+fn eval_reports_failure_stage() {
+    let dir = TempDir::new().unwrap();
+    let evaluator = MoveEvaluator::new(fixture_state(dir.path()));
+    let stage = |call: &str| match evaluator
+        .eval(call)
+        .unwrap_err()
+        .downcast_ref::<EvaluationError>()
+        .unwrap()
+    {
+        EvaluationError::Parse(_) => "parse",
+        EvaluationError::Resolution(_) => "resolution",
+        EvaluationError::Execution(_) => "execution",
+        EvaluationError::Decoding(_) => "decoding",
+    };
+
+    for malformed in &[
+        "0x2::Oracle::get_price<0x2::XUS::XUS>",
+        "0x2::Oracle::get_price<0x2::XUS::XUS()",
+        "0x2::get_price()",
+        "0x2::Oracle::scale(1, u8)",
+        "Oracle::Oracle::scale(1, 2)",
+        "0x2::Oracle::scale(vector[1, 2)",
+        "0x2::Oracle::echo(@, vector[])",
+    ] {
+        assert_eq!(stage(malformed), "parse", "{}", malformed);
+    }
+    assert_eq!(stage("0x2::Oracle::missing()"), "resolution");
+    assert_eq!(stage("0x2::Oracle::get_price()"), "resolution");
+    // there is no `Price<u8>` published under 0x2
+    assert_eq!(stage("0x2::Oracle::get_price<u8>()"), "execution");
+}
@@ -0,0 +1,130 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::MoveValueAnnotator;
+use anyhow::{anyhow, bail, Result};
+use move_core_types::{
+    account_address::AccountAddress,
+    language_storage::{ModuleId, StructTag, TypeTag},
+    resolver::{ModuleResolver, MoveResolver, ResourceResolver},
+    value::{MoveStruct, MoveValue},
+};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::BTreeMap};
+
+/// Everything `MoveValueAnnotator::view_decorated_value` needs to annotate a resource: the blob,
+/// its struct tag, and the module blobs the annotator fetched to resolve that tag, exactly as the
+/// resolver returned them. A bundle serializes to a single JSON or BCS document, so that an
+/// annotation can be reproduced offline with `replay`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotationBundle {
+    pub tag: StructTag,
+    pub blob: Vec<u8>,
+    /// The modules fetched while resolving `tag`, ordered by id
+    pub modules: Vec<(ModuleId, Vec<u8>)>,
+}
+
+impl AnnotationBundle {
+    /// Annotate the blob with a `MoveValueAnnotator` that resolves modules from the bundle only,
+    /// producing the `MoveStruct::WithTypes` the recorded annotator would have. Fails if a module
+    /// the annotation needs is missing from the bundle.
+    pub fn replay(&self) -> Result<MoveStruct> {
+        let resolver = BundleResolver {
+            bundle: self,
+            missing: RefCell::new(None),
+        };
+        let value = MoveValueAnnotator::new(&resolver)
+            .view_decorated_value(&TypeTag::Struct(self.tag.clone()), &self.blob)
+            .map_err(|e| match resolver.missing.borrow().as_ref() {
+                Some(module_id) => anyhow!(
+                    "module {} is missing from the bundle",
+                    module_id.short_str_lossless()
+                ),
+                None => e,
+            })?;
+        match value {
+            MoveValue::Struct(s) => Ok(s),
+            v => bail!("expected a struct, got {:?}", v),
+        }
+    }
+}
+
+/// Serves the modules of a bundle, and no resources, remembering the first module asked for
+/// that the bundle lacks
+struct BundleResolver<'a> {
+    bundle: &'a AnnotationBundle,
+    missing: RefCell<Option<ModuleId>>,
+}
+
+impl ModuleResolver for BundleResolver<'_> {
+    type Error = ();
+
+    fn get_module(&self, id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        let module = self
+            .bundle
+            .modules
+            .iter()
+            .find(|(module_id, _)| module_id == id)
+            .map(|(_, blob)| blob.clone());
+        if module.is_none() {
+            self.missing.borrow_mut().get_or_insert_with(|| id.clone());
+        }
+        Ok(module)
+    }
+}
+
+impl ResourceResolver for BundleResolver<'_> {
+    type Error = ();
+
+    fn get_resource(
+        &self,
+        _address: &AccountAddress,
+        _tag: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(None)
+    }
+}
+
+/// Passes requests through to `state`, keeping every module it returns
+pub(crate) struct RecordingResolver<'a, T: ?Sized> {
+    state: &'a T,
+    modules: RefCell<BTreeMap<ModuleId, Vec<u8>>>,
+}
+
+impl<'a, T: MoveResolver + ?Sized> RecordingResolver<'a, T> {
+    pub fn new(state: &'a T) -> Self {
+        Self {
+            state,
+            modules: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// The modules fetched, ordered by id
+    pub fn into_modules(self) -> Vec<(ModuleId, Vec<u8>)> {
+        self.modules.into_inner().into_iter().collect()
+    }
+}
+
+impl<T: MoveResolver + ?Sized> ModuleResolver for RecordingResolver<'_, T> {
+    type Error = T::Err;
+
+    fn get_module(&self, id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        let module = self.state.get_module(id)?;
+        if let Some(blob) = &module {
+            self.modules.borrow_mut().insert(id.clone(), blob.clone());
+        }
+        Ok(module)
+    }
+}
+
+impl<T: MoveResolver + ?Sized> ResourceResolver for RecordingResolver<'_, T> {
+    type Error = T::Err;
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        tag: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.state.get_resource(address, tag)
+    }
+}
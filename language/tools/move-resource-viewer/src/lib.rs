@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    bundle::RecordingResolver,
     fat_type::{FatStructType, FatType},
     resolver::Resolver,
 };
@@ -14,9 +15,9 @@ use move_binary_format::{
 use move_core_types::{
     account_address::AccountAddress,
     identifier::{IdentStr, Identifier},
-    language_storage::{ModuleId, StructTag, TypeTag},
+    language_storage::{ModuleId, StructTag, TypeTag, TypeTagCache},
     resolver::MoveResolver,
-    value::{MoveStruct, MoveValue},
+    value::{MoveFieldLayout, MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue},
     vm_status::VMStatus,
 };
 use serde::ser::{SerializeMap, SerializeSeq};
@@ -26,9 +27,14 @@ use std::{
     rc::Rc,
 };
 
+mod bundle;
 mod fat_type;
 mod module_cache;
 mod resolver;
+#[cfg(test)]
+mod unit_tests;
+
+pub use bundle::AnnotationBundle;
 
 #[derive(Clone, Debug)]
 pub struct AnnotatedMoveStruct {
@@ -71,15 +77,24 @@ impl AnnotatedMoveValue {
 
 pub struct MoveValueAnnotator<'a, T: ?Sized> {
     cache: Resolver<'a, T>,
+    tag_cache: Option<&'a TypeTagCache>,
 }
 
 impl<'a, T: MoveResolver + ?Sized> MoveValueAnnotator<'a, T> {
     pub fn new(view: &'a T) -> Self {
         Self {
             cache: Resolver::new(view),
+            tag_cache: None,
         }
     }
 
+    /// Share the struct tags of the values decorated by `view_decorated_value` through
+    /// `tag_cache`
+    pub fn with_tag_cache(mut self, tag_cache: &'a TypeTagCache) -> Self {
+        self.tag_cache = Some(tag_cache);
+        self
+    }
+
     pub fn get_resource_bytes(&self, addr: &AccountAddress, tag: &StructTag) -> Option<Vec<u8>> {
         self.cache.state.get_resource(addr, tag).ok()?
     }
@@ -143,6 +158,72 @@ impl<'a, T: MoveResolver + ?Sized> MoveValueAnnotator<'a, T> {
         self.view_value_by_fat_type(&ty, blob)
     }
 
+    /// Deserialize `blob` as a value of type `ty_tag`, with every struct decorated with its
+    /// `StructTag` and field names. The struct tags are taken from the tag cache, if any.
+    pub fn view_decorated_value(&self, ty_tag: &TypeTag, blob: &[u8]) -> Result<MoveValue> {
+        let ty = self.cache.resolve_type(ty_tag)?;
+        let layout = (&ty).try_into().map_err(into_vm_status)?;
+        let value = MoveValue::simple_deserialize(blob, &layout)?;
+        Ok(value.decorate_cached(&self.decorated_layout(&ty)?, self.tag_cache))
+    }
+
+    /// Resolve the return types of `module::function` instantiated with `ty_args`.
+    pub fn get_function_return_types(
+        &self,
+        module: &ModuleId,
+        function: &IdentStr,
+        ty_args: &[TypeTag],
+    ) -> Result<Vec<TypeTag>> {
+        let ty_args = ty_args
+            .iter()
+            .map(|ty| self.cache.resolve_type(ty))
+            .collect::<Result<Vec<_>>>()?;
+        self.cache
+            .resolve_function_returns(module, function, &ty_args)?
+            .iter()
+            .map(|ty| Ok(ty.type_tag().map_err(into_vm_status)?))
+            .collect()
+    }
+
+    /// Build the layout of `ty_tag` where every struct is decorated with its `StructTag` and field
+    /// names, so that values deserialized with it come out as `MoveStruct::WithTypes`.
+    pub fn get_type_layout_with_types(&self, ty_tag: &TypeTag) -> Result<MoveTypeLayout> {
+        let ty = self.cache.resolve_type(ty_tag)?;
+        self.decorated_layout(&ty)
+    }
+
+    /// Capture `tag`, `blob`, and the modules fetched to resolve `tag`, so that the annotation can
+    /// be reproduced without the state with `AnnotationBundle::replay`. The blob is not
+    /// deserialized, so blobs that fail to annotate can be recorded too.
+    pub fn record_bundle(&self, tag: &StructTag, blob: &[u8]) -> Result<AnnotationBundle> {
+        let recorder = RecordingResolver::new(self.cache.state);
+        MoveValueAnnotator::new(&recorder)
+            .get_type_layout_with_types(&TypeTag::Struct(tag.clone()))?;
+        Ok(AnnotationBundle {
+            tag: tag.clone(),
+            blob: blob.to_vec(),
+            modules: recorder.into_modules(),
+        })
+    }
+
+    fn decorated_layout(&self, ty: &FatType) -> Result<MoveTypeLayout> {
+        Ok(match ty {
+            FatType::Vector(ty) => MoveTypeLayout::Vector(Box::new(self.decorated_layout(ty)?)),
+            FatType::Struct(ty) => {
+                let type_ = ty.struct_tag().map_err(into_vm_status)?;
+                let fields = self
+                    .cache
+                    .get_field_names(ty)?
+                    .into_iter()
+                    .zip(ty.layout.iter())
+                    .map(|(name, ty)| Ok(MoveFieldLayout::new(name, self.decorated_layout(ty)?)))
+                    .collect::<Result<_>>()?;
+                MoveTypeLayout::Struct(MoveStructLayout::try_with_types(type_, fields)?)
+            }
+            ty => ty.try_into().map_err(into_vm_status)?,
+        })
+    }
+
     fn view_value_by_fat_type(&self, ty: &FatType, blob: &[u8]) -> Result<AnnotatedMoveValue> {
         let layout = ty.try_into().map_err(into_vm_status)?;
         let move_value = MoveValue::simple_deserialize(blob, &layout)?;
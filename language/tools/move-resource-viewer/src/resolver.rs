@@ -5,7 +5,7 @@ use crate::{
     fat_type::{FatStructType, FatType, WrappedAbilitySet},
     module_cache::ModuleCache,
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use move_binary_format::{
     access::ModuleAccess,
     errors::PartialVMError,
@@ -81,6 +81,40 @@ impl<'a, T: MoveResolver + ?Sized> Resolver<'a, T> {
         Err(anyhow!("Function {:?} not found in {:?}", function, module))
     }
 
+    pub fn resolve_function_returns(
+        &self,
+        module: &ModuleId,
+        function: &IdentStr,
+        ty_args: &[FatType],
+    ) -> Result<Vec<FatType>> {
+        let m = self.get_module_by_id(module)?;
+        for def in m.function_defs.iter() {
+            let fhandle = m.function_handle_at(def.function);
+            let fhandle_view = FunctionHandleView::new(m.as_ref(), fhandle);
+            if fhandle_view.name() == function {
+                if fhandle.type_parameters.len() != ty_args.len() {
+                    bail!(
+                        "Function {:?} expects {} type argument(s), got {}",
+                        function,
+                        fhandle.type_parameters.len(),
+                        ty_args.len()
+                    )
+                }
+                return fhandle_view
+                    .return_()
+                    .0
+                    .iter()
+                    .map(|signature| {
+                        self.resolve_signature(m.clone(), signature)?
+                            .subst(ty_args)
+                            .map_err(|status| anyhow!("Substitution failure: {:?}", status))
+                    })
+                    .collect::<Result<_>>();
+            }
+        }
+        Err(anyhow!("Function {:?} not found in {:?}", function, module))
+    }
+
     pub fn resolve_type(&self, type_tag: &TypeTag) -> Result<FatType> {
         Ok(match type_tag {
             TypeTag::Address => FatType::Address,
@@ -0,0 +1,129 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{AnnotationBundle, MoveValueAnnotator};
+use move_binary_format::file_format::{
+    basic_test_module, AbilitySet, FieldDefinition, IdentifierIndex, ModuleHandleIndex,
+    SignatureToken, StructDefinition, StructFieldInformation, StructHandle, StructHandleIndex,
+    TypeSignature,
+};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag, TypeTag},
+    resolver::ModuleResolver,
+    value::MoveValue,
+};
+use move_vm_test_utils::InMemoryStorage;
+
+/// Storage holding the module
+/// // module 0x0::M {
+/// //     struct Bar { x: u64 }
+/// //     struct Baz { bar: Bar, bars: vector<Bar>, flag: bool }
+/// // }
+fn storage() -> InMemoryStorage {
+    let mut m = basic_test_module();
+    m.identifiers[0] = Identifier::new("M").unwrap();
+
+    m.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex(0),
+        name: IdentifierIndex(m.identifiers.len() as u16),
+        abilities: AbilitySet::EMPTY,
+        type_parameters: vec![],
+    });
+    m.identifiers.push(Identifier::new("Baz").unwrap());
+
+    let mut fields = vec![];
+    for (name, ty) in [
+        ("bar", SignatureToken::Struct(StructHandleIndex(0))),
+        (
+            "bars",
+            SignatureToken::Vector(Box::new(SignatureToken::Struct(StructHandleIndex(0)))),
+        ),
+        ("flag", SignatureToken::Bool),
+    ] {
+        fields.push(FieldDefinition {
+            name: IdentifierIndex(m.identifiers.len() as u16),
+            signature: TypeSignature(ty),
+        });
+        m.identifiers.push(Identifier::new(name).unwrap());
+    }
+    m.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex(1),
+        field_information: StructFieldInformation::Declared(fields),
+    });
+
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module_id(), blob);
+    storage
+}
+
+fn module_id() -> ModuleId {
+    ModuleId::new(AccountAddress::ZERO, Identifier::new("M").unwrap())
+}
+
+fn tag(name: &str) -> StructTag {
+    StructTag {
+        address: AccountAddress::ZERO,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params: vec![],
+    }
+}
+
+fn recorded_bundle() -> AnnotationBundle {
+    let storage = storage();
+    let blob = bcs::to_bytes(&(7u64, vec![1u64, 2], true)).unwrap();
+    MoveValueAnnotator::new(&storage)
+        .record_bundle(&tag("Baz"), &blob)
+        .unwrap()
+}
+
+#[test]
+fn replay_bundle() {
+    let storage = storage();
+    let bundle = recorded_bundle();
+    // the bundle holds the module as storage returned it
+    assert_eq!(
+        bundle.modules,
+        vec![(
+            module_id(),
+            storage.get_module(&module_id()).unwrap().unwrap()
+        )]
+    );
+
+    let live = match MoveValueAnnotator::new(&storage)
+        .view_decorated_value(&TypeTag::Struct(tag("Baz")), &bundle.blob)
+        .unwrap()
+    {
+        MoveValue::Struct(s) => s,
+        v => panic!("expected a struct, got {:?}", v),
+    };
+    assert_eq!(bundle.replay().unwrap(), live);
+
+    let json = serde_json::to_string(&bundle).unwrap();
+    let from_json: AnnotationBundle = serde_json::from_str(&json).unwrap();
+    assert_eq!(from_json, bundle);
+    assert_eq!(from_json.replay().unwrap(), live);
+
+    let from_bcs: AnnotationBundle = bcs::from_bytes(&bcs::to_bytes(&bundle).unwrap()).unwrap();
+    assert_eq!(from_bcs, bundle);
+    assert_eq!(from_bcs.replay().unwrap(), live);
+}
+
+#[test]
+fn replay_bundle_missing_module() {
+    let mut bundle = recorded_bundle();
+    bundle.modules.clear();
+    let err = bundle.replay().unwrap_err();
+    assert_eq!(err.to_string(), "module 0x0::M is missing from the bundle");
+
+    // A bundle can be recorded for a blob that does not match its type, and replays the failure
+    let storage = storage();
+    let bundle = MoveValueAnnotator::new(&storage)
+        .record_bundle(&tag("Baz"), &[7])
+        .unwrap();
+    assert!(bundle.replay().is_err());
+}
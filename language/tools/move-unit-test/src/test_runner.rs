@@ -7,7 +7,10 @@ use crate::{
 };
 use anyhow::Result;
 use colored::*;
-use move_binary_format::{errors::VMResult, file_format::CompiledModule};
+use move_binary_format::{
+    errors::{Location, PartialVMError, VMResult},
+    file_format::CompiledModule,
+};
 use move_bytecode_utils::Modules;
 use move_compiler::{
     shared::{Flags, NumericalAddress},
@@ -18,7 +21,7 @@ use move_core_types::{
     effects::ChangeSet,
     gas_schedule::{CostTable, GasAlgebra, GasCost, GasUnits},
     identifier::IdentStr,
-    value::serialize_values,
+    value::try_serialize_values,
     vm_status::StatusCode,
 };
 use move_model::{
@@ -195,13 +198,21 @@ impl SharedTestingConfig {
         // TODO: collect VM logs if the verbose flag (i.e, `self.verbose`) is set
 
         let now = Instant::now();
-        let return_result = session.execute_function(
-            &test_plan.module_id,
-            IdentStr::new(function_name).unwrap(),
-            vec![], // no ty args, at least for now
-            serialize_values(test_info.arguments.iter()),
-            &mut gas_meter,
-        );
+        let return_result = try_serialize_values(test_info.arguments.iter())
+            .map_err(|(idx, err)| {
+                PartialVMError::new(StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT)
+                    .with_message(format!("invalid transaction argument #{}: {}", idx, err))
+                    .finish(Location::Undefined)
+            })
+            .and_then(|args| {
+                session.execute_function(
+                    &test_plan.module_id,
+                    IdentStr::new(function_name).unwrap(),
+                    vec![], // no ty args, at least for now
+                    args,
+                    &mut gas_meter,
+                )
+            });
         let test_run_info = TestRunInfo::new(
             function_name.to_string(),
             now.elapsed(),